@@ -7,6 +7,7 @@ fn main() {
         |event| match event {
             RawInputEvent::Keyboard(kbd) => println!("Keyboard: {kbd:?}"),
             RawInputEvent::Mouse(mouse) => println!("Mouse: {mouse:?}"),
+            RawInputEvent::Hid(hid) => println!("HID: {hid:?}"),
         },
         Some(|err| eprintln!("Raw input runtime error: {err}")),
     )
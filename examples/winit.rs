@@ -0,0 +1,110 @@
+//! Example: Receive raw input inside a winit event loop using mkb-raw-input's attach mode
+//!
+//! Run with `cargo run --example winit --features raw-window-handle`.
+
+// winit's message hook and the Win32 message types only exist on Windows
+#[cfg(windows)]
+mod app {
+    use mkb_raw_input::{AttachedListener, ListenerBuilder, RawInputEvent};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use windows::Win32::UI::WindowsAndMessaging::{MSG, WM_INPUT, WM_INPUT_DEVICE_CHANGE};
+    use winit::application::ApplicationHandler;
+    use winit::event::WindowEvent;
+    use winit::event_loop::{ActiveEventLoop, EventLoop};
+    use winit::platform::windows::EventLoopBuilderExtWindows;
+    use winit::raw_window_handle::HasWindowHandle;
+    use winit::window::{Window, WindowId};
+
+    /// Shared between the application (which attaches once the window exists) and the
+    /// message hook (which feeds it WM_INPUT messages).
+    type SharedListener = Rc<RefCell<Option<AttachedListener>>>;
+
+    struct App {
+        window: Option<Window>,
+        listener: SharedListener,
+    }
+
+    impl ApplicationHandler for App {
+        fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+            if self.window.is_some() {
+                return;
+            }
+            let window = event_loop
+                .create_window(Window::default_attributes().with_title("mkb-raw-input + winit"))
+                .expect("Failed to create window");
+            let handle = window
+                .window_handle()
+                .expect("Failed to get window handle")
+                .as_raw();
+            let listener = ListenerBuilder::new()
+                .error_callback(|err| eprintln!("Raw input runtime error: {err}"))
+                .attach_window_handle(handle)
+                .expect("Failed to attach raw input");
+            *self.listener.borrow_mut() = Some(listener);
+            self.window = Some(window);
+        }
+
+        fn window_event(
+            &mut self,
+            event_loop: &ActiveEventLoop,
+            _id: WindowId,
+            event: WindowEvent,
+        ) {
+            match event {
+                WindowEvent::CloseRequested => event_loop.exit(),
+                WindowEvent::Focused(false) => {
+                    if let Some(listener) = self.listener.borrow().as_ref() {
+                        listener.reset_state();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn main() {
+        let listener = SharedListener::default();
+        let hook_listener = listener.clone();
+
+        let event_loop = EventLoop::builder()
+            .with_msg_hook(move |msg| {
+                let msg = unsafe { &*(msg as *const MSG) };
+                if msg.message == WM_INPUT
+                    && let Some(listener) = hook_listener.borrow_mut().as_mut()
+                    && let Some(event) = listener.handle_raw_event(msg.lParam.0)
+                {
+                    match event {
+                        RawInputEvent::Keyboard(kbd) => println!("Keyboard: {kbd:?}"),
+                        RawInputEvent::Mouse(mouse) => println!("Mouse: {mouse:?}"),
+                        RawInputEvent::Hid(hid) => println!("HID: {hid:?}"),
+                    }
+                } else if msg.message == WM_INPUT_DEVICE_CHANGE
+                    && let Some(listener) = hook_listener.borrow().as_ref()
+                {
+                    // Releases the keys held on a removed keyboard
+                    listener.handle_device_change(msg.wParam.0, msg.lParam.0);
+                }
+                // Never consume the message: winit and DefWindowProcW still need to see it
+                false
+            })
+            .build()
+            .expect("Failed to create event loop");
+
+        let mut app = App {
+            window: None,
+            listener,
+        };
+        event_loop.run_app(&mut app).expect("Event loop failed");
+    }
+}
+
+#[cfg(windows)]
+fn main() {
+    app::main();
+}
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("This example requires Windows");
+}
@@ -0,0 +1,314 @@
+//! Attach mode: raw input delivered to an application's own window and message loop
+
+use crate::builder::ListenerConfig;
+use crate::dispatch::{Dispatcher, ErrorReporter, ListenerCallbacks};
+use crate::listener::{acquire_singleton, release_singleton};
+use crate::reader::PacketReader;
+use crate::state::{RecentEvents, ScrollAccumulator, SharedInputState};
+use crate::timing::TimingCollector;
+use crate::{
+    CaptureMode, ListenerBuilder, MouseButton, RawInputDeviceHandle, RawInputError, RawInputEvent,
+    TimingStats, UsageRegistration, VirtualKey, ffi,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use windows::Win32::Foundation::{HWND, LPARAM};
+
+/// Raw input registered against an existing application window.
+///
+/// Instead of running a hidden window on a background thread, raw input is delivered as
+/// `WM_INPUT` messages to your window, and your message loop hands each one to
+/// [`handle_raw_event`](Self::handle_raw_event), and `WM_INPUT_DEVICE_CHANGE` messages to
+/// [`handle_device_change`](Self::handle_device_change). This fits frameworks that own the message
+/// loop and require windows to be created on the main thread, such as `winit` (call it
+/// from the hook installed with `EventLoopBuilderExtWindows::with_msg_hook`).
+///
+/// Keyboard and mouse are registered with `RIDEV_INPUTSINK` (input is received even when
+/// the window is not focused) but without `RIDEV_NOLEGACY`, so the window keeps receiving
+/// its normal keyboard and mouse messages. `WM_INPUT` must still reach `DefWindowProcW`,
+/// which frees the raw input buffer.
+///
+/// Created with [`ListenerBuilder::attach`](crate::ListenerBuilder::attach), or without
+/// `RIDEV_INPUTSINK` by [`start_foreground_listener`] and
+/// [`ListenerBuilder::attach_foreground`](crate::ListenerBuilder::attach_foreground). Counts
+/// as the process's single listener; dropping it unregisters raw input.
+pub struct AttachedListener {
+    dispatcher: Dispatcher<fn(RawInputEvent)>,
+    reader: PacketReader,
+    /// Registered usages, keyboards and mice included
+    usages: Vec<UsageRegistration>,
+    capture_mode: CaptureMode,
+    last_error: Arc<Mutex<Option<RawInputError>>>,
+    state: SharedInputState,
+    paused: Arc<AtomicBool>,
+    scroll: Arc<ScrollAccumulator>,
+    recent: Arc<RecentEvents>,
+    timing: Arc<TimingCollector>,
+}
+
+impl AttachedListener {
+    /// Parses the raw input of a `WM_INPUT` message received by the attached window.
+    ///
+    /// # Arguments
+    /// * `lparam` - The message's LPARAM (`MSG::lParam.0`)
+    ///
+    /// # Returns
+    /// The event, or None if the packet could not be read (the error is reported through
+    /// the error callback), went to the [`hid_callback`](crate::ListenerBuilder::hid_callback)
+    /// or is of an unsupported type.
+    pub fn handle_raw_event(&mut self, lparam: isize) -> Option<RawInputEvent> {
+        match self.reader.read(LPARAM(lparam))? {
+            Ok(event) => self.dispatcher.process(event),
+            Err(e) => {
+                self.dispatcher.report(e);
+                None
+            }
+        }
+    }
+
+    /// Handles a `WM_INPUT_DEVICE_CHANGE` message received by the attached window,
+    /// releasing the keys held on a keyboard that was removed.
+    ///
+    /// Keyboards are registered with `RIDEV_DEVNOTIFY`, so the window receives this message
+    /// whenever one is added or removed.
+    ///
+    /// # Arguments
+    /// * `wparam` - The message's WPARAM (`MSG::wParam.0`), `GIDC_ARRIVAL` or `GIDC_REMOVAL`
+    /// * `lparam` - The message's LPARAM (`MSG::lParam.0`), the device handle
+    pub fn handle_device_change(&self, wparam: usize, lparam: isize) {
+        if let Ok(mut state) = self.state.lock() {
+            state.device_changed(wparam, lparam);
+        }
+    }
+
+    /// Pauses event delivery: [`handle_raw_event`](Self::handle_raw_event) returns None
+    /// while paused, but still tracks held keys and buttons.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resumes event delivery after [`pause`](Self::pause).
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns true if event delivery is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Returns the raw wheel delta accumulated since the last call as `(vertical,
+    /// horizontal)`, and resets it to zero.
+    ///
+    /// See [`ListenerHandle::take_scroll`](crate::ListenerHandle::take_scroll).
+    pub fn take_scroll(&self) -> (i32, i32) {
+        self.scroll.take()
+    }
+
+    /// Returns the events retained by the
+    /// [`ring_buffer`](crate::ListenerBuilder::ring_buffer), oldest first.
+    ///
+    /// See [`ListenerHandle::recent_events`](crate::ListenerHandle::recent_events).
+    pub fn recent_events(&self) -> Vec<RawInputEvent> {
+        self.recent.snapshot()
+    }
+
+    /// Returns statistics of the intervals between the events so far.
+    ///
+    /// See [`ListenerHandle::timing_stats`](crate::ListenerHandle::timing_stats).
+    pub fn timing_stats(&self) -> TimingStats {
+        self.timing.stats()
+    }
+
+    /// Returns the most recent error reported by the listener, if any.
+    pub fn last_error(&self) -> Option<RawInputError> {
+        self.last_error.lock().ok().and_then(|last| last.clone())
+    }
+
+    /// Returns true if the listener has seen the key go down and not yet come back up.
+    pub fn is_key_down(&self, key: VirtualKey) -> bool {
+        self.state.lock().is_ok_and(|state| state.is_key_down(key))
+    }
+
+    /// Returns the keys currently held down, in no particular order.
+    pub fn pressed_keys(&self) -> Vec<VirtualKey> {
+        self.state
+            .lock()
+            .map(|state| state.pressed_keys())
+            .unwrap_or_default()
+    }
+
+    /// Returns the keys currently held down on one keyboard, in no particular order.
+    ///
+    /// See [`ListenerHandle::pressed_keys_for_device`](crate::ListenerHandle::pressed_keys_for_device).
+    /// With several keyboards attached, e.g. one per player in a local multiplayer game,
+    /// this tells their keys apart where [`pressed_keys`](Self::pressed_keys) merges
+    /// them. Keys are attributed to the [`device`](crate::RawKeyboardEvent::device) of
+    /// their events; injected input has the default handle, and keys already held when
+    /// the listener started belong to no keyboard. A key stays down in
+    /// [`pressed_keys`](Self::pressed_keys) while any keyboard holds it. A keyboard is
+    /// only kept while it holds keys, so memory does not grow with devices that come and
+    /// go, and the keys of one unplugged mid-press are released once its removal is
+    /// passed to [`handle_device_change`](Self::handle_device_change).
+    pub fn pressed_keys_for_device(&self, device: RawInputDeviceHandle) -> Vec<VirtualKey> {
+        self.state
+            .lock()
+            .map(|state| state.pressed_keys_for_device(device))
+            .unwrap_or_default()
+    }
+
+    /// Forgets the keys held on one keyboard; keys also held on another keyboard stay down.
+    ///
+    /// See [`ListenerHandle::forget_device`](crate::ListenerHandle::forget_device).
+    pub fn forget_device(&self, device: RawInputDeviceHandle) {
+        if let Ok(mut state) = self.state.lock() {
+            state.forget_device(device);
+        }
+    }
+
+    /// Returns true if the listener has seen the mouse button go down and not yet come back up.
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.state
+            .lock()
+            .is_ok_and(|state| state.is_mouse_button_down(button))
+    }
+
+    /// Returns the mouse buttons currently held down, in no particular order.
+    pub fn pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        self.state
+            .lock()
+            .map(|state| state.pressed_mouse_buttons())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the tracked state of `key` agrees with the physical state Windows
+    /// reports.
+    ///
+    /// See [`ListenerHandle::verify_key_state`](crate::ListenerHandle::verify_key_state).
+    pub fn verify_key_state(&self, key: VirtualKey) -> bool {
+        self.is_key_down(key) == ffi::async_key_down(u16::from(key))
+    }
+
+    /// Returns how the listener captures input: in the background or only while the
+    /// window has focus, and which usages it registered.
+    pub fn capture_mode(&self) -> CaptureMode {
+        self.capture_mode.clone()
+    }
+
+    /// Clears all tracked key and mouse button down-states.
+    ///
+    /// See [`ListenerHandle::reset_state`](crate::ListenerHandle::reset_state); an attached
+    /// window sees focus changes itself, so call this on `WM_ACTIVATEAPP` / focus loss.
+    pub fn reset_state(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.reset();
+        }
+    }
+}
+
+impl Drop for AttachedListener {
+    fn drop(&mut self) {
+        // Failures are ignored: the registrations may already be gone with the window
+        for usage in &self.usages {
+            let _ = ffi::unregister_usage(usage.usage_page, usage.usage, usage.page_only);
+        }
+
+        // Allow another listener to be created
+        release_singleton();
+    }
+}
+
+/// Start a foreground-only listener on an application window with default settings.
+///
+/// Unlike [`start_listener`](crate::start_listener), this does not capture input globally:
+/// raw input is delivered to `hwnd` only while it is in the foreground. See
+/// [`ListenerBuilder::attach_foreground`](crate::ListenerBuilder::attach_foreground) for
+/// the details, and [`AttachedListener`] for how to hand `WM_INPUT` messages over.
+///
+/// # Arguments
+/// * `hwnd` - The window handle (`HWND`) as an integer
+///
+/// # Returns
+/// * `Ok(AttachedListener)` - The attached listener (unregister by dropping)
+/// * `Err(RawInputError)` - If the handle is null, registration fails or a listener is
+///   already running
+pub fn start_foreground_listener(hwnd: isize) -> Result<AttachedListener, RawInputError> {
+    ListenerBuilder::new().attach_foreground(hwnd)
+}
+
+/// Registers raw input against `hwnd` and creates the attached listener.
+///
+/// This is the implementation behind [`ListenerBuilder::attach`](crate::ListenerBuilder::attach).
+pub(crate) fn attach_listener(
+    hwnd: isize,
+    config: ListenerConfig,
+    callbacks: ListenerCallbacks,
+) -> Result<AttachedListener, RawInputError> {
+    if hwnd == 0 {
+        return Err(RawInputError::Other(
+            "Cannot attach to a null window handle".to_string(),
+        ));
+    }
+
+    // Singleton enforcement
+    acquire_singleton()?;
+
+    let last_error = Arc::new(Mutex::new(None));
+    let state = SharedInputState::default();
+    let reporter = ErrorReporter::new(callbacks.on_error, config.silent, last_error.clone())
+        .with_repeat_interval(config.error_repeat_interval());
+    // Events are returned from `handle_raw_event` rather than passed to a callback
+    let mut dispatcher =
+        Dispatcher::<fn(RawInputEvent)>::new(|_| {}, &config, reporter, state.clone())
+            .with_panic_callback(callbacks.on_panic_hotkey)
+            .with_wheel_callback(callbacks.on_wheel)
+            .with_key_set_callback(callbacks.on_key_set)
+            .with_hotkeys(callbacks.hotkeys)
+            .with_taps(callbacks.taps)
+            .with_first_event_callback(callbacks.on_first_event)
+            .with_log(callbacks.log);
+
+    // The listener is usable as long as one device type registered
+    let hwnd = HWND(hwnd as *mut _);
+    let capture_mode =
+        match crate::register_raw_input(Some(hwnd), &config, config.flags(ffi::ATTACHED_FLAGS)) {
+            Ok(result) => {
+                result
+                    .errors()
+                    .into_iter()
+                    .for_each(|e| dispatcher.report(e));
+                CaptureMode::requested(&config, true).registered(&result)
+            }
+            Err(e) => {
+                release_singleton();
+                return Err(e);
+            }
+        };
+    // Taken once registered, so that later transitions queue up behind the snapshot
+    if let Ok(mut state) = state.lock() {
+        state.seed_from_system();
+    }
+
+    let paused = dispatcher.paused();
+    let scroll = dispatcher.scroll();
+    let recent = dispatcher.recent();
+    let timing = dispatcher.timing();
+    Ok(AttachedListener {
+        dispatcher,
+        reader: PacketReader::new(
+            config.capture_system_time,
+            config.include_raw,
+            callbacks.on_hid,
+            paused.clone(),
+        )
+        .with_foreground_process(config.capture_foreground_process),
+        paused,
+        usages: config.registered_usages(),
+        capture_mode,
+        last_error,
+        state,
+        scroll,
+        recent,
+        timing,
+    })
+}
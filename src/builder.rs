@@ -0,0 +1,1359 @@
+//! Builder for configuring and starting a raw input listener
+
+#[cfg(windows)]
+use crate::attach::attach_listener;
+use crate::dispatch::{Dispatcher, ErrorCallback, ErrorReporter, ListenerCallbacks};
+use crate::export::EventLog;
+use crate::hotkey::HotkeyBinding;
+#[cfg(windows)]
+use crate::listener::spawn_listener;
+use crate::source::EventSource;
+use crate::tap::TapDetector;
+#[cfg(not(windows))]
+use crate::unsupported::{attach_listener, spawn_listener};
+use crate::{
+    AttachedListener, Hotkey, InputRecorder, KeySetChange, KeyTap, ListenerHandle, Modifiers,
+    RawHidEventRef, RawInputError, RawInputEvent, RawKeyboardEvent, RegistrationResult,
+    SessionChange, UsageRegistration, VirtualKey, WheelAxis,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+#[cfg(windows)]
+use windows::Win32::System::Threading::{
+    THREAD_PRIORITY, THREAD_PRIORITY_ABOVE_NORMAL, THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_NORMAL,
+    THREAD_PRIORITY_TIME_CRITICAL,
+};
+#[cfg(windows)]
+use windows::Win32::UI::Input::RAWINPUTDEVICE_FLAGS;
+
+/// Scheduling priority of the listener thread.
+///
+/// Raising the priority reduces the delay between input arriving and the callback running
+/// when the system is busy, at the risk of starving other threads: a high-priority thread
+/// that does a lot of work in its callback can make the rest of the system (including
+/// your own application) unresponsive. Keep callbacks short when raising the priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThreadPriority {
+    /// Normal priority (`THREAD_PRIORITY_NORMAL`), the default
+    #[default]
+    Normal,
+    /// `THREAD_PRIORITY_ABOVE_NORMAL`
+    AboveNormal,
+    /// `THREAD_PRIORITY_HIGHEST`
+    Highest,
+    /// `THREAD_PRIORITY_TIME_CRITICAL`; use with great care
+    TimeCritical,
+}
+
+#[cfg(windows)]
+impl ThreadPriority {
+    pub(crate) fn to_win32(self) -> THREAD_PRIORITY {
+        match self {
+            Self::Normal => THREAD_PRIORITY_NORMAL,
+            Self::AboveNormal => THREAD_PRIORITY_ABOVE_NORMAL,
+            Self::Highest => THREAD_PRIORITY_HIGHEST,
+            Self::TimeCritical => THREAD_PRIORITY_TIME_CRITICAL,
+        }
+    }
+}
+
+/// Receiving end of the channel returned by [`ListenerBuilder::start_channel`], yielding
+/// events and runtime errors in the order they occurred.
+pub type EventReceiver = mpsc::Receiver<Result<RawInputEvent, RawInputError>>;
+
+/// Longest delay before an automatic restart, however often the listener restarted
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// Automatic restarts after fatal listener errors, as set by
+/// [`ListenerBuilder::auto_restart`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RestartPolicy {
+    /// Restarts allowed over the lifetime of the listener
+    pub max_retries: u32,
+    /// Delay before the first restart
+    pub backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// Returns the delay before restart number `restart` (counting from 1): the backoff,
+    /// doubled for each restart before it, at most a minute.
+    pub(crate) fn delay(&self, restart: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(restart.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.backoff
+            .saturating_mul(factor)
+            .min(MAX_RESTART_DELAY.max(self.backoff))
+    }
+}
+
+/// Options controlling how the listener behaves, collected by [`ListenerBuilder`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ListenerConfig {
+    /// Suppress all direct stderr output, even when no error callback is set
+    pub silent: bool,
+    /// Attach a wall-clock timestamp to every event
+    pub capture_system_time: bool,
+    /// Attach the foreground window's process name to every event
+    pub capture_foreground_process: bool,
+    /// Attach the verbatim Win32 structure fields to keyboard and mouse events
+    pub include_raw: bool,
+    /// Additional usage page / usage registrations
+    pub usages: Vec<UsageRegistration>,
+    /// Scheduling priority of the listener thread
+    pub thread_priority: ThreadPriority,
+    /// Processors the listener thread may run on, as an affinity mask (None to leave it
+    /// unchanged)
+    pub cpu_affinity: Option<usize>,
+    /// Drop keyboard-synthesized "fake shift" events before they reach the callback
+    pub filter_fake_shift: bool,
+    /// Drop auto-repeated key presses before they reach the callback
+    pub ignore_auto_repeat: bool,
+    /// Drop mouse button presses and releases that do not change the button's state
+    pub dedup_mouse_buttons: bool,
+    /// Report when an elevated foreground window withholds input
+    pub detect_uipi_blocking: bool,
+    /// Report when no input arrived this long after registering
+    pub no_input_warning: Option<Duration>,
+    /// Keep the display on while input arrives
+    pub keep_awake: bool,
+    /// Hotkey that toggles the paused state
+    pub panic_hotkey: Option<Hotkey>,
+    /// Modifiers that must be held for keyboard events to be delivered
+    pub required_modifiers: Modifiers,
+    /// Keys whose events are processed (None for every key)
+    pub allowed_keys: Option<Vec<VirtualKey>>,
+    /// Keys whose events are dropped
+    pub denied_keys: Vec<VirtualKey>,
+    /// Register with `RIDEV_EXINPUTSINK` instead of `RIDEV_INPUTSINK`
+    pub extended_background: bool,
+    /// Register keyboard and mouse with `RIDEV_NOLEGACY`
+    pub no_legacy: bool,
+    /// Create the hidden window as a top-level window instead of a message-only window
+    pub top_level_window: bool,
+    /// Register without a background capture flag, receiving input only while the window
+    /// is in the foreground
+    pub foreground_only: bool,
+    /// Queue delivered events for polling instead of calling the event callback
+    pub poll: bool,
+    /// Maximum number of events queued for polling (None for unbounded)
+    pub poll_capacity: Option<usize>,
+    /// Number of recently delivered events to retain (0 disables the ring buffer)
+    pub ring_buffer: usize,
+    /// Also pass errors that prevent the listener from starting to the error callback
+    pub report_init_errors: bool,
+    /// Collect statistics of the intervals between events
+    pub timing_stats: bool,
+    /// File the delivered events are logged to as CSV, created when the listener starts
+    pub log_path: Option<PathBuf>,
+    /// Attach the previous movement of the same device to mouse movement events
+    pub track_velocity: bool,
+    /// Interval of the listener thread's self-wake timer (None for the default, zero to
+    /// disable it)
+    pub watchdog_interval: Option<Duration>,
+    /// Interval within which repeats of the same error are coalesced (None for the
+    /// default, zero to report every error)
+    pub error_repeat_interval: Option<Duration>,
+    /// Restart the listener after fatal errors
+    pub auto_restart: Option<RestartPolicy>,
+    /// Do not register keyboards
+    pub exclude_keyboard: bool,
+    /// Do not register mice
+    pub exclude_mouse: bool,
+}
+
+/// Error coalescing interval unless set with [`ListenerBuilder::error_repeat_interval`]
+const DEFAULT_ERROR_REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+impl ListenerConfig {
+    /// Checks the configuration for invalid option combinations.
+    pub(crate) fn validate(&self) -> Result<(), RawInputError> {
+        self.usages
+            .iter()
+            .try_for_each(UsageRegistration::validate)?;
+        if self.exclude_keyboard && self.exclude_mouse && self.usages.is_empty() {
+            return Err(RawInputError::Other(
+                "Nothing to capture: keyboard and mouse capture are disabled and no usage is registered"
+                    .to_string(),
+            ));
+        }
+        if self.exclude_keyboard
+            && let Some(usage) = self.usages.iter().find(|usage| usage.covers_keyboard())
+        {
+            return Err(RawInputError::Other(format!(
+                "Keyboard capture is disabled, but usage page {:#06x} usage {:#06x} would capture keystrokes",
+                usage.usage_page, usage.usage
+            )));
+        }
+        if let Some(mask) = self.cpu_affinity {
+            let processors = std::thread::available_parallelism().map_or(1, |n| n.get());
+            validate_affinity(mask, processors)?;
+        }
+        if self.poll_capacity == Some(0) {
+            return Err(RawInputError::Other(
+                "The poll queue capacity must be at least 1".to_string(),
+            ));
+        }
+        if self.foreground_only && self.extended_background {
+            return Err(RawInputError::Other(
+                "Foreground-only capture cannot be combined with extended background capture"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the interval within which repeats of the same error are coalesced.
+    pub(crate) fn error_repeat_interval(&self) -> Duration {
+        self.error_repeat_interval
+            .unwrap_or(DEFAULT_ERROR_REPEAT_INTERVAL)
+    }
+
+    /// Returns every usage the listener registers, keyboards and mice included.
+    pub(crate) fn registered_usages(&self) -> Vec<UsageRegistration> {
+        let keyboard = (!self.exclude_keyboard).then(UsageRegistration::keyboard);
+        let mouse = (!self.exclude_mouse).then(UsageRegistration::mouse);
+        mouse
+            .into_iter()
+            .chain(keyboard)
+            .chain(self.usages.iter().copied())
+            .collect()
+    }
+
+    /// Applies the background capture and legacy message options to the keyboard/mouse
+    /// registration flags.
+    #[cfg(windows)]
+    pub(crate) fn flags(&self, flags: RAWINPUTDEVICE_FLAGS) -> RAWINPUTDEVICE_FLAGS {
+        let flags = if self.no_legacy {
+            crate::ffi::no_legacy(flags)
+        } else {
+            flags
+        };
+        if self.foreground_only {
+            crate::ffi::without_sink(flags)
+        } else if self.extended_background {
+            crate::ffi::extended_sink(flags)
+        } else {
+            flags
+        }
+    }
+}
+
+/// Checks that an affinity mask selects at least one processor and only processors among
+/// the first `processors`.
+pub(crate) fn validate_affinity(mask: usize, processors: usize) -> Result<(), RawInputError> {
+    let available = if processors >= usize::BITS as usize {
+        usize::MAX
+    } else {
+        (1 << processors) - 1
+    };
+    if mask == 0 || mask & !available != 0 {
+        return Err(RawInputError::Other(format!(
+            "Invalid CPU affinity mask {mask:#x}: the system has {processors} processors"
+        )));
+    }
+    Ok(())
+}
+
+/// Builder for configuring a raw input listener.
+///
+/// [`start_listener`](crate::start_listener) is a thin wrapper over this builder; use the
+/// builder directly when you need options beyond an event callback and an error callback.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{ListenerBuilder, RawInputEvent};
+///
+/// let listener = ListenerBuilder::new()
+///     .silent()
+///     .start(|event: RawInputEvent| println!("{event:?}"))
+///     .expect("Failed to start listener");
+///
+/// // Errors are not printed; inspect the most recent one instead
+/// if let Some(err) = listener.last_error() {
+///     println!("Last error: {err}");
+/// }
+/// ```
+#[derive(Default)]
+pub struct ListenerBuilder {
+    config: ListenerConfig,
+    callbacks: ListenerCallbacks,
+}
+
+impl ListenerBuilder {
+    /// Creates a builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder that captures pointing devices only, the least privileged
+    /// configuration for mouse-driven applications.
+    ///
+    /// Equivalent to `ListenerBuilder::new().capture_keyboard(false)`: the keyboard usage is
+    /// never registered, so the process cannot receive keystrokes through raw input, as
+    /// [`registered_devices`](crate::registered_devices) can confirm.
+    pub fn mouse_only() -> Self {
+        Self::new().capture_keyboard(false)
+    }
+
+    /// Sets whether keyboards are registered (default: true).
+    ///
+    /// When disabled, the keyboard usage is not registered at all, rather than keyboard
+    /// events being dropped, and starting fails if a
+    /// [`register_usage`](Self::register_usage) registration would deliver keystrokes
+    /// anyway (the keyboard or keypad usage, or the whole Generic Desktop page).
+    pub fn capture_keyboard(mut self, enabled: bool) -> Self {
+        self.config.exclude_keyboard = !enabled;
+        self
+    }
+
+    /// Sets whether mice are registered (default: true).
+    ///
+    /// Starting fails if neither keyboards, mice nor any
+    /// [`register_usage`](Self::register_usage) registration are to be captured.
+    pub fn capture_mouse(mut self, enabled: bool) -> Self {
+        self.config.exclude_mouse = !enabled;
+        self
+    }
+
+    /// Sets the callback invoked for runtime errors.
+    ///
+    /// When an error callback is set, the listener never prints errors to stderr itself.
+    pub fn error_callback<E>(mut self, on_error: E) -> Self
+    where
+        E: FnMut(RawInputError) + Send + 'static,
+    {
+        self.callbacks.on_error = Some(Box::new(on_error));
+        self
+    }
+
+    /// Also passes errors that prevent the listener from starting to the
+    /// [`error_callback`](Self::error_callback) (default: false).
+    ///
+    /// Starting still returns the error as well, but with this enabled all errors can be
+    /// handled in the error callback alone, from an invalid configuration or a listener
+    /// that is already running to a failing window creation or registration. Has no effect
+    /// without an error callback.
+    pub fn report_init_errors(mut self, enabled: bool) -> Self {
+        self.config.report_init_errors = enabled;
+        self
+    }
+
+    /// Shares the error callback between the listener and the caller if initialization
+    /// errors are to be reported to it, returning the caller's end.
+    fn init_error_callback(&mut self) -> Option<Arc<Mutex<ErrorCallback>>> {
+        if !self.config.report_init_errors {
+            return None;
+        }
+        let on_error = Arc::new(Mutex::new(self.callbacks.on_error.take()?));
+        let listener_on_error = on_error.clone();
+        self.callbacks.on_error = Some(Box::new(move |err| {
+            if let Ok(mut on_error) = listener_on_error.lock() {
+                on_error(err);
+            }
+        }));
+        Some(on_error)
+    }
+
+    /// Returns the configuration, dropping the callbacks.
+    pub(crate) fn into_config(self) -> ListenerConfig {
+        self.config
+    }
+
+    /// Delivers HID events to `on_hid` as borrowed [`RawHidEventRef`]s instead of passing
+    /// them to the event callback as [`RawInputEvent::Hid`].
+    ///
+    /// The report bytes are only valid for the duration of the call, but no memory is
+    /// allocated per event, which matters for devices with high report rates. Use
+    /// [`RawHidEventRef::to_event`] to keep an owned copy.
+    pub fn hid_callback<H>(mut self, on_hid: H) -> Self
+    where
+        H: FnMut(RawHidEventRef<'_>) + Send + 'static,
+    {
+        self.callbacks.on_hid = Some(Box::new(on_hid));
+        self
+    }
+
+    /// Sets a callback invoked for every mouse wheel event with its axis and the scrolled
+    /// distance in notches (see [`RawMouseEvent::wheel_notches`](crate::RawMouseEvent::wheel_notches)).
+    ///
+    /// The callback sees nothing but wheel input, already decoded into fractional notches,
+    /// which suits scroll-driven views (maps, timelines) that sum the notches per frame.
+    /// Wheel events are still delivered to the event callback as well.
+    pub fn wheel_callback<W>(mut self, on_wheel: W) -> Self
+    where
+        W: FnMut(WheelAxis, f32) + Send + 'static,
+    {
+        self.callbacks.on_wheel = Some(Box::new(on_wheel));
+        self
+    }
+
+    /// Sets a callback invoked with a [`KeySetChange`] whenever a keyboard event changes
+    /// the set of held keys, as an alternative to tracking presses and releases from the
+    /// event callback.
+    ///
+    /// The change lists the keys that went down or up and every key held afterwards, from
+    /// the same down-state as [`ListenerHandle::pressed_keys`], which suits combo detection
+    /// and on-screen keyboards highlighting the held keys. Auto-repeat changes nothing and
+    /// is not reported, nor are changes while the listener is paused; neither is clearing
+    /// the state with [`ListenerHandle::reset_state`]. Keyboard events are still delivered
+    /// to the event callback as well.
+    pub fn key_set_callback<K>(mut self, on_key_set: K) -> Self
+    where
+        K: FnMut(KeySetChange) + Send + 'static,
+    {
+        self.callbacks.on_key_set = Some(Box::new(on_key_set));
+        self
+    }
+
+    /// Sets a callback invoked with a [`KeyTap`] whenever a key is pressed and released
+    /// within `threshold` without auto-repeating in between, e.g. to tell a tap of Space
+    /// from holding it, or to detect double taps from the taps' timestamps.
+    ///
+    /// The durations are measured between the events' monotonic timestamps. Taps are
+    /// reported on the listener thread when the key is released, before the release is
+    /// delivered to the event callback (which still receives both events), and not while
+    /// the listener is paused.
+    pub fn tap_callback<T>(mut self, threshold: Duration, on_tap: T) -> Self
+    where
+        T: FnMut(KeyTap) + Send + 'static,
+    {
+        self.callbacks.taps = Some(TapDetector::new(threshold, Box::new(on_tap)));
+        self
+    }
+
+    /// Sets a callback invoked once, with the first event delivered after the listener
+    /// starts, and dropped afterwards, e.g. for a "press any key to begin" prompt.
+    ///
+    /// Unlike a successful start, which only means that registration succeeded, the
+    /// callback confirms that input actually arrives. It runs on the listener thread just
+    /// before the event is passed to the event callback (or returned by
+    /// [`AttachedListener::handle_raw_event`](crate::AttachedListener::handle_raw_event)), so
+    /// events that are not delivered (while paused, filtered, gated by
+    /// [`require_modifiers`](Self::require_modifiers) or handled by a
+    /// [`hid_callback`](Self::hid_callback)) do not count.
+    pub fn on_first_event<C>(mut self, on_first_event: C) -> Self
+    where
+        C: FnOnce(&RawInputEvent) + Send + 'static,
+    {
+        self.callbacks.on_first_event = Some(Box::new(on_first_event));
+        self
+    }
+
+    /// Sets a callback invoked when the session is locked, unlocked, or connected to or
+    /// disconnected from the console or a Remote Desktop client.
+    ///
+    /// No input is captured while the session is locked (Win+L) or disconnected:
+    /// [`SessionChange::suspends_capture`] tells the callback when that starts. Held keys
+    /// are then forgotten, and raw input is registered again once the session returns,
+    /// with failures reported to the error callback.
+    ///
+    /// Some transitions are not signaled at all: the secure desktop of UAC prompts and of
+    /// Ctrl+Alt+Del switches away from the session's desktop without a session change,
+    /// and Windows deliberately isolates it from other applications, so its input can
+    /// never be captured. Input to elevated windows is withheld as well, see
+    /// [`detect_uipi_blocking`](Self::detect_uipi_blocking). Not available in attach mode.
+    pub fn session_callback<S>(mut self, on_session: S) -> Self
+    where
+        S: FnMut(SessionChange) + Send + 'static,
+    {
+        self.callbacks.on_session = Some(Box::new(on_session));
+        self
+    }
+
+    /// Suppresses all direct stderr output from the listener.
+    ///
+    /// Without an error callback, runtime errors are then only observable through
+    /// [`ListenerHandle::last_error`].
+    pub fn silent(mut self) -> Self {
+        self.config.silent = true;
+        self
+    }
+
+    /// Sets the interval within which repeats of the same error are coalesced (default: 1
+    /// second; [`Duration::ZERO`] reports every error).
+    ///
+    /// A persistent failure, e.g. every read of a flood of input failing, would otherwise
+    /// call the error callback (or print to stderr) thousands of times per second. Each
+    /// distinct error, as told apart by its message, is instead passed on at most once per
+    /// interval: its repeats in between are counted, and its next occurrence after the
+    /// interval is passed on with the count appended to the message, e.g. "Windows API
+    /// error: GetRawInputData failed: ... (x1523 in the last 1.0s)". If the error does not
+    /// occur again, the count is passed on at the first
+    /// [watchdog](Self::watchdog_interval) wake-up after the interval instead, and repeats
+    /// still pending when the listener stops are passed on then.
+    /// [`ListenerHandle::last_error`] is updated on every occurrence regardless.
+    pub fn error_repeat_interval(mut self, interval: Duration) -> Self {
+        self.config.error_repeat_interval = Some(interval);
+        self
+    }
+
+    /// Attaches the wall-clock time (`SystemTime`, UTC) at which each event was dequeued.
+    ///
+    /// Every event always carries a monotonic `timestamp`; enable this in addition when
+    /// you need absolute times, e.g. for audit logs. `SystemTime::now` uses
+    /// `GetSystemTimePreciseAsFileTime` on Windows 8 and later. Note that the wall clock can
+    /// jump backward on NTP or manual adjustments, whereas the monotonic timestamp cannot.
+    pub fn capture_system_time(mut self, enabled: bool) -> Self {
+        self.config.capture_system_time = enabled;
+        self
+    }
+
+    /// Attaches the executable file name of the foreground window's process (e.g.
+    /// "notepad.exe") to every event, as read by
+    /// [`RawInputEvent::foreground_process`], e.g. to switch between per-application input
+    /// profiles.
+    ///
+    /// The name is cached: every event only costs a `GetForegroundWindow` call, and the
+    /// process is opened and its image name queried (`QueryFullProcessImageNameW`) only
+    /// when the foreground window changes. The name is shared between events, so they do
+    /// not allocate either. A `WinEventHook` for `EVENT_SYSTEM_FOREGROUND` would save even
+    /// that call, but its notifications are queued like any other message, so events read
+    /// right after a switch would still be attributed to the previous application;
+    /// comparing the window handle per event is both exact and cheap. The name is None
+    /// when there is no foreground window (e.g. on the secure desktop) or its process
+    /// cannot be queried, such as some protected system processes.
+    pub fn capture_foreground_process(mut self, enabled: bool) -> Self {
+        self.config.capture_foreground_process = enabled;
+        self
+    }
+
+    /// Attaches the verbatim `RAWKEYBOARD` / `RAWMOUSE` fields to every keyboard and mouse
+    /// event as [`RawInputRaw`](crate::RawInputRaw), in the events' `raw` field.
+    ///
+    /// Intended for diagnostics: it shows exactly what Windows reported next to the parsed
+    /// fields, e.g. to check a surprising `vkey` or button action before filing a bug.
+    pub fn include_raw(mut self) -> Self {
+        self.config.include_raw = true;
+        self
+    }
+
+    /// Receives background input only while the foreground application does not read raw
+    /// input itself (`RIDEV_EXINPUTSINK`, Windows Vista and later; default: false).
+    ///
+    /// By default keyboard and mouse are registered with `RIDEV_INPUTSINK`, which delivers
+    /// input in the background unconditionally. With this option the listener instead
+    /// steps aside while the foreground application (typically a game) is itself registered
+    /// for raw input, and receives input again once focus moves elsewhere. Input is always
+    /// received while the listener's own window is in the foreground. As the two flags ask
+    /// for contradictory behavior, this replaces `RIDEV_INPUTSINK` rather than adding to it;
+    /// it also applies to [`register_usage`](Self::register_usage) registrations.
+    pub fn extended_background(mut self, enabled: bool) -> Self {
+        self.config.extended_background = enabled;
+        self
+    }
+
+    /// Creates the listener's hidden window as a message-only window (default: true).
+    ///
+    /// A message-only window (a child of `HWND_MESSAGE`) receives raw input and every
+    /// message sent or posted to it, but it is never shown, has no taskbar or Alt+Tab
+    /// entry and is not enumerated by other applications. Disabling this creates an
+    /// invisible top-level window instead, which also receives broadcast messages; the
+    /// listener itself needs none of them.
+    pub fn message_only_window(mut self, enabled: bool) -> Self {
+        self.config.top_level_window = !enabled;
+        self
+    }
+
+    /// Suppresses the legacy keyboard and mouse messages (`WM_KEYDOWN`, `WM_CHAR`,
+    /// `WM_MOUSEMOVE`, ...) of this process's windows (`RIDEV_NOLEGACY`; default: false).
+    ///
+    /// By default the listener only observes: input keeps reaching the focused application
+    /// unchanged, and this process's own windows keep receiving their usual messages. Raw
+    /// input registrations apply to the whole process, so with this option every window
+    /// of the process (a GUI next to the listener, a console's text input, an
+    /// [`attach`](Self::attach)ed window) stops receiving keyboard and mouse messages.
+    /// Other applications are never affected. Enable it only in processes without windows
+    /// of their own that need these messages, to save the message traffic.
+    pub fn no_legacy(mut self, enabled: bool) -> Self {
+        self.config.no_legacy = enabled;
+        self
+    }
+
+    /// Registers an additional HID usage page / usage for raw input.
+    ///
+    /// Keyboard and mouse are registered unless disabled with
+    /// [`capture_keyboard`](Self::capture_keyboard) or [`capture_mouse`](Self::capture_mouse).
+    /// Events from other devices matched by this registration are delivered as
+    /// [`RawInputEvent::Hid`]. Page-only registrations (`RIDEV_PAGEONLY`) must have a usage
+    /// of 0, and while keyboard capture is disabled a usage that covers keyboards is
+    /// rejected, otherwise [`start`](Self::start) fails.
+    pub fn register_usage(mut self, registration: UsageRegistration) -> Self {
+        self.config.usages.push(registration);
+        self
+    }
+
+    /// Sets the scheduling priority of the listener thread (default: normal).
+    ///
+    /// This is a latency knob for input-critical applications; see [`ThreadPriority`] for
+    /// the risks. If the priority cannot be applied, the error is reported through the
+    /// error callback and the listener keeps running at normal priority.
+    pub fn thread_priority(mut self, priority: ThreadPriority) -> Self {
+        self.config.thread_priority = priority;
+        self
+    }
+
+    /// Configures the listener to deliver every packet as early as possible, trading CPU
+    /// time for latency.
+    ///
+    /// The listener never buffers or batches input: each `WM_INPUT` message is read as
+    /// `GetMessageW` returns it and passed to the callback inline on the listener thread,
+    /// before the next message is retrieved. This preset makes that an explicit guarantee
+    /// of the configuration, which stays in place should options that coalesce or
+    /// throttle input be added later. It also turns off
+    /// [`ignore_auto_repeat`](Self::ignore_auto_repeat) and
+    /// [`dedup_mouse_buttons`](Self::dedup_mouse_buttons), so redundant packets are
+    /// delivered too.
+    ///
+    /// The thread priority is left as it is. To also have the listener thread scheduled
+    /// ahead of normal threads as soon as input arrives, add
+    /// [`thread_priority(ThreadPriority::Highest)`](Self::thread_priority) after weighing
+    /// the risks described at [`ThreadPriority`].
+    ///
+    /// Options set after this one take precedence. Start the listener with
+    /// [`start`](Self::start): [`start_polling`](Self::start_polling) queues events until
+    /// they are polled, which adds latency however often you poll.
+    pub fn low_latency(mut self) -> Self {
+        self.config.ignore_auto_repeat = false;
+        self.config.dedup_mouse_buttons = false;
+        self
+    }
+
+    /// Pins the listener thread to the processors in `mask` (bit 0 for the first
+    /// processor) with `SetThreadAffinityMask` (default: no affinity change).
+    ///
+    /// This is an advanced latency knob: keeping the thread on one core avoids the jitter
+    /// of migrating between cores, but the thread then waits whenever that core is busy,
+    /// even with others idle, so it only pays off with a core kept free of other load.
+    /// Starting fails if the mask is zero or selects processors the system does not have
+    /// (on systems with more than 64 processors, those of the listener thread's processor
+    /// group). If the mask cannot be applied, e.g. because it lies outside the process
+    /// affinity, the error is reported through the error callback and the listener keeps
+    /// running on any processor. Not applicable in attach mode, as no listener thread is
+    /// created.
+    pub fn cpu_affinity(mut self, mask: usize) -> Self {
+        self.config.cpu_affinity = Some(mask);
+        self
+    }
+
+    /// Restarts the listener up to `max_retries` times when a fatal error ends it
+    /// (default: no restarts).
+    ///
+    /// Without restarts, a fatal error stops the listener for good: the handle stays
+    /// valid, but no more events arrive and [`ListenerHandle::join`] returns the error. With
+    /// restarts, the listener thread instead waits, creates a new hidden window and
+    /// registers raw input again with the current configuration (including
+    /// [`reconfigure`](ListenerHandle::reconfigure) changes). The callbacks, the tracked key
+    /// and button states and the handle are kept, so capture continues with the same
+    /// callback. The first restart waits `backoff`, and each further one twice as long as
+    /// the one before, up to a minute; dropping the handle during the wait stops the
+    /// listener right away.
+    ///
+    /// Restartable errors are those that end a running listener:
+    /// * a failing message loop (`GetMessageW` returning an error)
+    /// * a panic in the event callback or another callback, which is caught on the listener
+    ///   thread
+    ///
+    /// Every restart is reported through the error callback, after the error that caused
+    /// it. A restart whose window or registrations cannot be created counts as an attempt
+    /// as well. Stopping the listener is never undone: dropping the handle or posting
+    /// `WM_QUIT` ends it, and errors at [`start`](Self::start) are returned rather than
+    /// retried. [`attach`](Self::attach) ignores this option, as the window belongs to
+    /// the application.
+    pub fn auto_restart(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.config.auto_restart = Some(RestartPolicy {
+            max_retries,
+            backoff,
+        });
+        self
+    }
+
+    /// Keeps "fake shift" events from the event callback (default: false).
+    ///
+    /// With NumLock on, numpad keys pressed while Shift is held are surrounded by
+    /// synthetic Shift release/press events; see [`RawKeyboardEvent::is_fake_shift`]
+    /// for the details. Held-key tracking ignores these events either way.
+    ///
+    /// [`RawKeyboardEvent::is_fake_shift`]: crate::RawKeyboardEvent::is_fake_shift
+    pub fn filter_fake_shift(mut self, enabled: bool) -> Self {
+        self.config.filter_fake_shift = enabled;
+        self
+    }
+
+    /// Keeps auto-repeated key presses from the event callback (default: false).
+    ///
+    /// A held key repeats its key-down event at the keyboard repeat rate; with this
+    /// option only the first press and the release are delivered, as menus and hotkeys
+    /// usually want. A press counts as an auto-repeat when the key is already held, the
+    /// same rule hotkeys and the key set callback use. Held-key tracking sees the
+    /// repeats either way.
+    pub fn ignore_auto_repeat(mut self, enabled: bool) -> Self {
+        self.config.ignore_auto_repeat = enabled;
+        self
+    }
+
+    /// Keeps redundant mouse button events from the event callback (default: false).
+    ///
+    /// Some mice and drivers repeat a button's down packet while it is held, or send a
+    /// release for a button that is not held. With this option a button event is only
+    /// delivered when it changes the button's held state, as tracked for
+    /// [`ListenerHandle::is_mouse_button_down`]; this is the mouse counterpart of
+    /// [`ignore_auto_repeat`](Self::ignore_auto_repeat). A redundant packet that also
+    /// moved the mouse is still delivered, as a movement with
+    /// [`MouseButtonAction::None`](crate::MouseButtonAction::None).
+    pub fn dedup_mouse_buttons(mut self, enabled: bool) -> Self {
+        self.config.dedup_mouse_buttons = enabled;
+        self
+    }
+
+    /// Sets how often the listener thread wakes up when no input arrives (default: 1
+    /// second; [`Duration::ZERO`] disables it).
+    ///
+    /// The wake-up is a timer message that lets the message loop re-check whether the
+    /// listener was stopped and handle pending [`reconfigure`](ListenerHandle::reconfigure)
+    /// requests, so that shutdown and reconfiguration do not depend on a single posted
+    /// message getting through, and pass on the counts of coalesced errors (see
+    /// [`error_repeat_interval`](Self::error_repeat_interval)). It costs one message per
+    /// interval; intervals below 10 ms are raised to 10 ms by Windows.
+    pub fn watchdog_interval(mut self, interval: Duration) -> Self {
+        self.config.watchdog_interval = Some(interval);
+        self
+    }
+
+    /// Reports when input stops because an elevated window is in the foreground
+    /// (default: false).
+    ///
+    /// Windows does not deliver input to a process while a window of a higher integrity
+    /// level (an elevated application, Task Manager, a UAC prompt) has focus, so capture
+    /// silently goes dark. With this option the listener checks the foreground window once
+    /// per second and reports a [`RawInputError::AccessDenied`] through the error callback
+    /// each time input becomes blocked. See [`is_foreground_elevated`](crate::is_foreground_elevated)
+    /// for ways to capture input over elevated windows. Not available in attach mode.
+    pub fn detect_uipi_blocking(mut self, enabled: bool) -> Self {
+        self.config.detect_uipi_blocking = enabled;
+        self
+    }
+
+    /// Reports when no input at all arrived within `grace` of registering (default: off).
+    ///
+    /// Registration can succeed and still deliver nothing: raw input registrations are
+    /// per process and the last one wins, so another component of the process may have
+    /// taken the devices over, and some drivers keep their device to themselves. With
+    /// this option the listener checks once, `grace` after registering (and after each
+    /// restart), whether it received any input, and if not reports a
+    /// [`RawInputError::Other`] through the error callback hinting that the registration
+    /// may be ineffective. Check [`registered_devices`](crate::registered_devices) to find
+    /// out. Choose a grace period in which input is expected, as an idle user is
+    /// indistinguishable from blocked input. Not available in attach mode.
+    pub fn no_input_warning(mut self, grace: Duration) -> Self {
+        self.config.no_input_warning = Some(grace);
+        self
+    }
+
+    /// Keeps the display on and the system awake while the listener receives input
+    /// (default: false).
+    ///
+    /// Input from keyboards and mice already resets the idle timers, but input from
+    /// other devices often does not: a presentation driven by a gamepad or a kiosk
+    /// controlled by a custom HID panel lets the display turn off mid-use. With this
+    /// option every raw input the listener receives (including
+    /// [`register_usage`](Self::register_usage) devices and input while paused) resets
+    /// the display and system idle timers with `SetThreadExecutionState`, at most every
+    /// 10 seconds.
+    ///
+    /// The power settings still apply from the last input: the display turns off and the
+    /// system sleeps after their usual timeout once input stops. Only the timers are
+    /// reset and no continuous request is made, so nothing is held after the listener
+    /// stops and there is nothing to undo. Windows does not let `SetThreadExecutionState`
+    /// hold off the screensaver, which keeps its own timeout. Not available in attach
+    /// mode.
+    pub fn keep_awake(mut self, enabled: bool) -> Self {
+        self.config.keep_awake = enabled;
+        self
+    }
+
+    /// Sets an emergency-stop hotkey that toggles the listener's paused state.
+    ///
+    /// Pressing the hotkey pauses event delivery, as
+    /// [`ListenerHandle::pause`](crate::ListenerHandle::pause) does, and pressing it again
+    /// resumes it; this works while paused because keys are still tracked. The key press
+    /// that pauses the listener is itself delivered to the event callback, the one that
+    /// resumes it is not. Raw input cannot intercept keys, so the hotkey also still reaches
+    /// other applications.
+    pub fn panic_hotkey(mut self, hotkey: Hotkey) -> Self {
+        self.config.panic_hotkey = Some(hotkey);
+        self
+    }
+
+    /// Only delivers the events of `keys` (default: every key). Calling it again adds to
+    /// the keys.
+    ///
+    /// Events of other keys are dropped as soon as they are read, before any processing:
+    /// besides the event callback, they never reach the tracked key state, hotkeys, tap
+    /// and key set callbacks, the ring buffer or the [`log_to`](Self::log_to) log. A tool
+    /// that must only ever handle, say, the media keys can thereby guarantee it at the
+    /// API level. This is a delivery filter, not a capture restriction: raw input
+    /// registers whole device classes, so Windows still delivers every keystroke to the
+    /// process, and another listener configuration (or
+    /// [`register_usage`](Self::register_usage)) could receive them.
+    ///
+    /// Keys are matched by [`RawKeyboardEvent::vkey`](crate::RawKeyboardEvent::vkey). Raw
+    /// input reports the modifiers as [`VirtualKey::Shift`], [`VirtualKey::Control`] and
+    /// [`VirtualKey::Alt`] rather than their left and right variants, so list those to
+    /// allow modifiers. Mouse and HID events are not affected.
+    pub fn allow_keys(mut self, keys: &[VirtualKey]) -> Self {
+        self.config
+            .allowed_keys
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(keys);
+        self
+    }
+
+    /// Drops the events of `keys`, which are never delivered (default: none). Calling it
+    /// again adds to the keys.
+    ///
+    /// Like [`allow_keys`](Self::allow_keys), this is a delivery filter rather than a
+    /// capture restriction, applied before any processing; see there. If both are set, a
+    /// key must be allowed and not denied.
+    pub fn deny_keys(mut self, keys: &[VirtualKey]) -> Self {
+        self.config.denied_keys.extend_from_slice(keys);
+        self
+    }
+
+    /// Only delivers keyboard events while all of `modifiers` are held.
+    ///
+    /// Unlike a [`Hotkey`], which fires once for a specific chord, this is a gate: every
+    /// key event passes while the modifiers are held (e.g. "only while Ctrl is down"), and
+    /// all others are dropped before reaching the event callback. The modifier state is
+    /// taken after applying the event, so the press of the last required modifier is
+    /// delivered and its release is not. Mouse and HID events are not affected.
+    pub fn require_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.config.required_modifiers = modifiers;
+        self
+    }
+
+    /// Attaches the previous movement of the same device to every mouse movement event
+    /// (default: false), so that [`RawMouseEvent::velocity`](crate::RawMouseEvent::velocity)
+    /// can compute its velocity without the caller keeping track of the last position.
+    ///
+    /// See [`RawMouseEvent::previous_move`](crate::RawMouseEvent::previous_move). Movements
+    /// are tracked per device, including while the listener is paused.
+    pub fn track_velocity(mut self, enabled: bool) -> Self {
+        self.config.track_velocity = enabled;
+        self
+    }
+
+    /// Retains the last `capacity` delivered events in a ring buffer (default: 0, disabled).
+    ///
+    /// The events are retrieved with
+    /// [`ListenerHandle::recent_events`](crate::ListenerHandle::recent_events), e.g. to dump
+    /// what the user pressed right before a crash. They are recorded in addition to being
+    /// passed to the event callback.
+    ///
+    /// The buffer is allocated up front and takes about
+    /// `capacity * size_of::<RawInputEvent>()` bytes (plus the report data of HID events,
+    /// which are only recorded if no [`hid_callback`](Self::hid_callback) is set). Each
+    /// event is cloned into it, and a snapshot briefly blocks recording while it copies the
+    /// buffer, so keep the capacity modest (a few thousand events at most).
+    pub fn ring_buffer(mut self, capacity: usize) -> Self {
+        self.config.ring_buffer = capacity;
+        self
+    }
+
+    /// Caps the queue of [`start_polling`](Self::start_polling) at `capacity` events
+    /// (default: unbounded).
+    ///
+    /// When the queue is full, each new event discards the oldest one, and the first
+    /// discarded event after each poll is reported through the error callback. Size it
+    /// for a few frames of input: a high polling rate mouse alone can produce a thousand
+    /// events per second. A capacity of 0 is rejected when the listener starts.
+    pub fn poll_capacity(mut self, capacity: usize) -> Self {
+        self.config.poll_capacity = Some(capacity);
+        self
+    }
+
+    /// Collects statistics of the intervals between events (default: false).
+    ///
+    /// The statistics are read with
+    /// [`ListenerHandle::timing_stats`](crate::ListenerHandle::timing_stats), to tell
+    /// whether irregular input comes from the device or from a listener thread held up
+    /// by its callbacks. See [`TimingStats`](crate::TimingStats) for what is measured:
+    /// the intervals between the times events were dequeued, not their latency from the
+    /// hardware. Each event costs a short lock and a histogram update of a few
+    /// kilobytes allocated up front. HID reports handled by a
+    /// [`hid_callback`](Self::hid_callback) are not counted.
+    pub fn timing_stats(mut self, enabled: bool) -> Self {
+        self.config.timing_stats = enabled;
+        self
+    }
+
+    /// Logs every delivered event to the file at `path` as CSV, in the format of
+    /// [`write_events_csv`](crate::write_events_csv), in addition to passing it to the
+    /// event callback.
+    ///
+    /// The file is created (or truncated) when the listener starts, which fails if it
+    /// cannot be created. Each event is written on the listener thread before the callback
+    /// runs, so it is logged even if the callback is slow or panics. Rows are buffered and
+    /// flushed at least once a second while events arrive, and when the listener stops.
+    /// A failing write is reported through the error callback and stops the logging, but
+    /// not the listener. Events handled by a [`hid_callback`](Self::hid_callback) are not
+    /// logged, nor are events that are not delivered (see
+    /// [`on_first_event`](Self::on_first_event)).
+    pub fn log_to<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config.log_path = Some(path.as_ref().to_path_buf());
+        self.callbacks.log = None;
+        self
+    }
+
+    /// Like [`log_to`](Self::log_to), writing the log to `writer` instead of a file, e.g.
+    /// a file opened for appending or a network stream.
+    pub fn log_to_writer<W>(mut self, writer: W) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        self.config.log_path = None;
+        self.callbacks.log = Some(EventLog::new(Box::new(writer)));
+        self
+    }
+
+    /// Creates the [`log_to`](Self::log_to) file, if any.
+    fn open_log(&mut self) -> Result<(), RawInputError> {
+        if let Some(path) = self.config.log_path.take() {
+            let file = std::fs::File::create(&path).map_err(|e| {
+                RawInputError::Other(format!(
+                    "Failed to create the event log {}: {e}",
+                    path.display()
+                ))
+            })?;
+            self.callbacks.log = Some(EventLog::new(Box::new(file)));
+        }
+        Ok(())
+    }
+
+    /// Invokes `on_press` whenever `hotkey` is pressed.
+    ///
+    /// The hotkey is pressed when the last of its keys goes down while the others are held
+    /// (see [`Hotkey`]); auto-repeats do not press it again. Callbacks run on the listener
+    /// thread before the event is delivered, and not while the listener is paused. Several
+    /// hotkeys can be registered, including overlapping ones. Raw input cannot intercept
+    /// keys, so the keys are still delivered to the event callback and to other
+    /// applications.
+    pub fn hotkey<P>(self, hotkey: Hotkey, on_press: P) -> Self
+    where
+        P: FnMut() + Send + 'static,
+    {
+        self.push_hotkey(HotkeyBinding::new(hotkey, Box::new(on_press), None))
+    }
+
+    /// Invokes `on_press` whenever `hotkey` is pressed and `on_release` when it is released
+    /// again, e.g. to start and stop push-to-talk.
+    ///
+    /// The hotkey is released as soon as any of its keys goes up, whatever the order in
+    /// which they are released, and each press is followed by exactly one release. A
+    /// hotkey pressed before the listener is paused is still released while paused. See
+    /// [`hotkey`](Self::hotkey) for when it is pressed.
+    pub fn hotkey_with_release<P, R>(self, hotkey: Hotkey, on_press: P, mut on_release: R) -> Self
+    where
+        P: FnMut() + Send + 'static,
+        R: FnMut() + Send + 'static,
+    {
+        self.hotkey_with_hold(hotkey, on_press, move |_| on_release())
+    }
+
+    /// Like [`hotkey_with_release`](Self::hotkey_with_release), passing `on_release` how
+    /// long the hotkey was held, e.g. for hold-to-activate features.
+    ///
+    /// The duration runs from the event that completed the chord to the event that broke
+    /// it, by their [timestamps](crate::RawKeyboardEvent::timestamp). Releasing any key of
+    /// the chord ends the hold, even if the key is pressed again while the others are
+    /// still held: that press starts a new hold with a new duration, as the chord was
+    /// incomplete in between. Auto-repeats do not restart it.
+    pub fn hotkey_with_hold<P, R>(self, hotkey: Hotkey, on_press: P, on_release: R) -> Self
+    where
+        P: FnMut() + Send + 'static,
+        R: FnMut(Duration) + Send + 'static,
+    {
+        self.push_hotkey(HotkeyBinding::new(
+            hotkey,
+            Box::new(on_press),
+            Some(Box::new(on_release)),
+        ))
+    }
+
+    fn push_hotkey(mut self, binding: HotkeyBinding) -> Self {
+        self.callbacks.hotkeys.push(binding);
+        self
+    }
+
+    /// Sets a callback invoked with the new paused state whenever the
+    /// [`panic_hotkey`](Self::panic_hotkey) toggles it.
+    pub fn panic_hotkey_callback<P>(mut self, on_panic_hotkey: P) -> Self
+    where
+        P: FnMut(bool) + Send + 'static,
+    {
+        self.callbacks.on_panic_hotkey = Some(Box::new(on_panic_hotkey));
+        self
+    }
+
+    /// Starts the listener, delivering every raw input event to `callback`.
+    ///
+    /// # Returns
+    /// * `Ok(ListenerHandle)` - Handle to the running listener (stop by dropping)
+    /// * `Err(RawInputError)` - If the configuration is invalid, initialization fails or a
+    ///   listener is already running
+    pub fn start<F>(self, callback: F) -> Result<ListenerHandle, RawInputError>
+    where
+        F: FnMut(RawInputEvent) + Send + 'static,
+    {
+        self.start_partial(callback).map(|(handle, _)| handle)
+    }
+
+    /// Starts the listener, passing `context` mutably to `callback` along with every event.
+    ///
+    /// The context is moved to the listener thread and dropped there when the listener
+    /// stops, so stateful handlers can keep their state in it instead of capturing an
+    /// `Arc<Mutex<...>>`. A plain function pointer as callback also maps directly to C
+    /// callbacks taking a user data pointer.
+    ///
+    /// # Returns
+    /// * `Ok(ListenerHandle)` - Handle to the running listener (stop by dropping)
+    /// * `Err(RawInputError)` - If the configuration is invalid, initialization fails or a
+    ///   listener is already running
+    pub fn start_with_context<T>(
+        self,
+        mut context: T,
+        callback: fn(&mut T, RawInputEvent),
+    ) -> Result<ListenerHandle, RawInputError>
+    where
+        T: Send + 'static,
+    {
+        self.start(move |event| callback(&mut context, event))
+    }
+
+    /// Like [`start`](Self::start), also returning which raw input registrations succeeded.
+    ///
+    /// The listener starts as long as keyboard, mouse or one of the
+    /// [`register_usage`](Self::register_usage) registrations succeeded; the failed ones
+    /// are reported through the error callback either way. Inspect the
+    /// [`RegistrationResult`] to decide whether partial capture is acceptable, and drop the
+    /// handle if it is not.
+    ///
+    /// # Returns
+    /// * `Ok((ListenerHandle, RegistrationResult))` - Handle to the running listener and the
+    ///   outcome of each registration
+    /// * `Err(RawInputError)` - If the configuration is invalid, initialization fails, no
+    ///   registration succeeded or a listener is already running
+    pub fn start_partial<F>(
+        mut self,
+        callback: F,
+    ) -> Result<(ListenerHandle, RegistrationResult), RawInputError>
+    where
+        F: FnMut(RawInputEvent) + Send + 'static,
+    {
+        let on_init_error = self.init_error_callback();
+        let result = self
+            .config
+            .validate()
+            .and_then(|()| self.open_log())
+            .and_then(|()| spawn_listener(callback, self.config, self.callbacks));
+        report_init_error(result, on_init_error)
+    }
+
+    /// Starts the listener, collects every event for `duration`, stops it and returns the
+    /// events in the order they arrived.
+    ///
+    /// Blocks the calling thread for the whole duration. As with [`start`](Self::start),
+    /// this fails if another listener is running. Every event is kept in memory until the
+    /// call returns: mouse movement alone can produce a thousand events per second with a
+    /// high polling rate mouse, so prefer [`start`](Self::start) for long captures.
+    pub fn capture_for(self, duration: Duration) -> Result<Vec<RawInputEvent>, RawInputError> {
+        let (tx, rx) = mpsc::channel();
+        let listener = self.start(move |event| {
+            let _ = tx.send(event);
+        })?;
+        std::thread::sleep(duration);
+        // Dropping the handle joins the listener thread, so every event has been sent
+        drop(listener);
+        Ok(rx.try_iter().collect())
+    }
+
+    /// Starts the listener, waits up to `timeout` for the next key press, stops it and
+    /// returns the press, or None if no key was pressed in time.
+    ///
+    /// Made for "Press a key..." prompts of binding UIs: the returned event carries the
+    /// [`vkey`](RawKeyboardEvent::vkey), the scan code and the
+    /// [`modifiers`](RawKeyboardEvent::modifiers) held with it. Keys already held when
+    /// the listener starts, such as the Enter that opened the prompt, are only reported
+    /// once pressed again: their auto-repeats are ignored (see
+    /// [`ignore_auto_repeat`](Self::ignore_auto_repeat)), as are fake shifts. Mouse
+    /// buttons are not captured; use [`RawInputEvent::button`] with
+    /// [`start`](Self::start) for prompts that accept them.
+    ///
+    /// Blocks the calling thread until the key press or the timeout. As with
+    /// [`start`](Self::start), this fails if another listener is running.
+    pub fn capture_next_key(
+        self,
+        timeout: Duration,
+    ) -> Result<Option<RawKeyboardEvent>, RawInputError> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let listener = self
+            .ignore_auto_repeat(true)
+            .filter_fake_shift(true)
+            .start(move |event| {
+                if let RawInputEvent::Keyboard(kbd) = event
+                    && !kbd.key_up
+                {
+                    let _ = tx.try_send(kbd);
+                }
+            })?;
+        let key = rx.recv_timeout(timeout).ok();
+        drop(listener);
+        Ok(key)
+    }
+
+    /// Starts the listener without an event callback, queueing the events for
+    /// [`ListenerHandle::poll_events`].
+    ///
+    /// This suits game loops and immediate-mode GUIs (egui, imgui) that pull input once
+    /// per frame. The queue is unbounded unless capped with
+    /// [`poll_capacity`](Self::poll_capacity), so an application that stops polling
+    /// (e.g. while minimized) keeps accumulating events; poll or drop the handle
+    /// regularly, or set a capacity.
+    ///
+    /// # Returns
+    /// * `Ok(ListenerHandle)` - Handle to the running listener (stop by dropping)
+    /// * `Err(RawInputError)` - As for [`start`](Self::start)
+    pub fn start_polling(mut self) -> Result<ListenerHandle, RawInputError> {
+        self.config.poll = true;
+        self.start(|_| {})
+    }
+
+    /// Starts the listener, sending its events and runtime errors into a channel instead of
+    /// calling back.
+    ///
+    /// This suits event loops that pull input from several sources: receive with
+    /// [`recv`](mpsc::Receiver::recv), [`try_recv`](mpsc::Receiver::try_recv) or
+    /// [`recv_timeout`](mpsc::Receiver::recv_timeout) on any thread. Runtime errors
+    /// arrive on the same channel as `Err`, in order with the events; they replace an
+    /// error callback set on the builder. The channel is unbounded, so events pile up in
+    /// memory while nobody receives them. Dropping the receiver does not disturb the
+    /// listener, which then discards its events until stopped; once the listener stops,
+    /// the receiver reports the channel as disconnected after the last event.
+    ///
+    /// # Returns
+    /// * `Ok((ListenerHandle, Receiver))` - Handle to the running listener (stop by
+    ///   dropping) and the receiving end of the channel
+    /// * `Err(RawInputError)` - As for [`start`](Self::start)
+    pub fn start_channel(self) -> Result<(ListenerHandle, EventReceiver), RawInputError> {
+        let (tx, rx) = mpsc::channel();
+        let errors = tx.clone();
+        let listener = self
+            .error_callback(move |err| {
+                let _ = errors.send(Err(err));
+            })
+            .start(move |event| {
+                let _ = tx.send(Ok(event));
+            })?;
+        Ok((listener, rx))
+    }
+
+    /// Starts the listener and records its events into an [`InputRecorder`], which can
+    /// pause and resume recording and export the timeline.
+    ///
+    /// # Returns
+    /// * `Ok(InputRecorder)` - The recording recorder (stop by dropping or with
+    ///   [`InputRecorder::stop`])
+    /// * `Err(RawInputError)` - As for [`start`](Self::start)
+    pub fn start_recorder(self) -> Result<InputRecorder, RawInputError> {
+        InputRecorder::from_builder(self)
+    }
+
+    /// Registers raw input against an existing window instead of starting a background
+    /// listener.
+    ///
+    /// Your message loop must pass every `WM_INPUT` message received by that window to
+    /// [`AttachedListener::handle_raw_event`]. The thread priority option does not apply,
+    /// as no listener thread is created.
+    ///
+    /// # Arguments
+    /// * `hwnd` - The window handle (`HWND`) as an integer
+    ///
+    /// # Returns
+    /// * `Ok(AttachedListener)` - The attached listener (unregister by dropping)
+    /// * `Err(RawInputError)` - If the configuration is invalid, the handle is null,
+    ///   registration fails or a listener is already running
+    pub fn attach(mut self, hwnd: isize) -> Result<AttachedListener, RawInputError> {
+        let on_init_error = self.init_error_callback();
+        let result = self
+            .config
+            .validate()
+            .and_then(|()| self.open_log())
+            .and_then(|()| attach_listener(hwnd, self.config, self.callbacks));
+        report_init_error(result, on_init_error)
+    }
+
+    /// Like [`attach`](Self::attach), but receives input only while the window is in the
+    /// foreground: keyboard, mouse and [`register_usage`](Self::register_usage)
+    /// registrations omit `RIDEV_INPUTSINK`.
+    ///
+    /// This is not global capture. Windows delivers raw input to the window only while it
+    /// (or one of its child windows) has the focus, just like its regular keyboard and
+    /// mouse messages. Nothing is captured while the user types in other applications,
+    /// which suits applications that must not observe input meant for others. Another
+    /// process registered for background capture keeps receiving input regardless.
+    ///
+    /// # Returns
+    /// * `Ok(AttachedListener)` - The attached listener (unregister by dropping)
+    /// * `Err(RawInputError)` - As for [`attach`](Self::attach), and if
+    ///   [`extended_background`](Self::extended_background) is enabled
+    pub fn attach_foreground(mut self, hwnd: isize) -> Result<AttachedListener, RawInputError> {
+        self.config.foreground_only = true;
+        self.attach(hwnd)
+    }
+
+    /// Like [`attach`](Self::attach), taking the window from a `raw-window-handle` handle,
+    /// as returned by e.g. `winit`'s `Window::window_handle`.
+    ///
+    /// Fails for handles other than `RawWindowHandle::Win32`.
+    #[cfg(feature = "raw-window-handle")]
+    pub fn attach_window_handle(
+        self,
+        handle: raw_window_handle::RawWindowHandle,
+    ) -> Result<AttachedListener, RawInputError> {
+        match handle {
+            raw_window_handle::RawWindowHandle::Win32(win32) => self.attach(win32.hwnd.get()),
+            other => Err(RawInputError::Other(format!(
+                "Unsupported window handle: {other:?}"
+            ))),
+        }
+    }
+
+    /// Runs the listener's event pipeline synchronously over an arbitrary [`EventSource`].
+    ///
+    /// No window, thread or Raw Input registration is involved: events from `source` are
+    /// processed exactly as the background listener would process Win32 input, until the
+    /// source is exhausted. This is intended for testing code built on the listener
+    /// (e.g. with a [`VecEventSource`](crate::VecEventSource)) on any machine.
+    pub fn run_with_source<S, F>(mut self, mut source: S, callback: F)
+    where
+        S: EventSource,
+        F: FnMut(RawInputEvent),
+    {
+        let opened = self.open_log();
+        let callbacks = self.callbacks;
+        let mut reporter = ErrorReporter::new(
+            callbacks.on_error,
+            self.config.silent,
+            Arc::new(Mutex::new(None)),
+        )
+        .with_repeat_interval(self.config.error_repeat_interval());
+        if let Err(e) = opened {
+            reporter.report(e);
+        }
+        Dispatcher::new(callback, &self.config, reporter, Default::default())
+            .with_hid_callback(callbacks.on_hid)
+            .with_panic_callback(callbacks.on_panic_hotkey)
+            .with_wheel_callback(callbacks.on_wheel)
+            .with_key_set_callback(callbacks.on_key_set)
+            .with_hotkeys(callbacks.hotkeys)
+            .with_taps(callbacks.taps)
+            .with_first_event_callback(callbacks.on_first_event)
+            .with_log(callbacks.log)
+            .run(&mut source);
+    }
+}
+
+/// Passes the error of a failed start to the shared error callback, if any.
+fn report_init_error<T>(
+    result: Result<T, RawInputError>,
+    on_init_error: Option<Arc<Mutex<ErrorCallback>>>,
+) -> Result<T, RawInputError> {
+    if let (Err(e), Some(on_error)) = (&result, on_init_error)
+        && let Ok(mut on_error) = on_error.lock()
+    {
+        on_error(e.clone());
+    }
+    result
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+    use crate::ffi;
+
+    /// Test that an affinity mask must select available processors only
+    #[test]
+    fn test_cpu_affinity_validation() {
+        assert!(validate_affinity(0b0001, 4).is_ok());
+        assert!(validate_affinity(0b1111, 4).is_ok());
+        assert!(validate_affinity(0, 4).is_err());
+        assert!(validate_affinity(0b1_0000, 4).is_err());
+        assert!(validate_affinity(usize::MAX, usize::BITS as usize).is_ok());
+        assert!(validate_affinity(usize::MAX, 200).is_ok());
+    }
+
+    /// Test that foreground-only capture drops the sink flags and excludes extended background
+    #[test]
+    fn test_foreground_only_drops_sink() {
+        use windows::Win32::UI::Input::{RIDEV_EXINPUTSINK, RIDEV_INPUTSINK};
+
+        let config = ListenerConfig {
+            foreground_only: true,
+            ..Default::default()
+        };
+        let flags = config.flags(ffi::ATTACHED_FLAGS);
+        assert_eq!(flags.0 & (RIDEV_INPUTSINK.0 | RIDEV_EXINPUTSINK.0), 0);
+        assert!(config.validate().is_ok());
+
+        let extended = ListenerConfig {
+            extended_background: true,
+            ..config
+        };
+        assert!(extended.validate().is_err());
+    }
+
+    /// Test that legacy messages are kept unless no_legacy is set
+    #[test]
+    fn test_legacy_messages_kept_by_default() {
+        use windows::Win32::UI::Input::{RIDEV_INPUTSINK, RIDEV_NOLEGACY};
+
+        let config = ListenerConfig::default();
+        let flags = config.flags(ffi::LISTENER_FLAGS);
+        assert_eq!(flags.0 & RIDEV_NOLEGACY.0, 0);
+        assert_ne!(flags.0 & RIDEV_INPUTSINK.0, 0);
+
+        let no_legacy = ListenerConfig {
+            no_legacy: true,
+            ..config
+        };
+        assert_ne!(no_legacy.flags(ffi::LISTENER_FLAGS).0 & RIDEV_NOLEGACY.0, 0);
+    }
+
+    /// Test that extended background capture swaps the sink flag and keeps the others
+    #[test]
+    fn test_extended_sink_replaces_inputsink() {
+        use windows::Win32::UI::Input::{RIDEV_EXINPUTSINK, RIDEV_INPUTSINK, RIDEV_NOLEGACY};
+
+        for flags in [ffi::LISTENER_FLAGS, ffi::ATTACHED_FLAGS] {
+            let extended = ffi::extended_sink(flags);
+            assert_ne!(extended.0 & RIDEV_EXINPUTSINK.0, 0);
+            assert_eq!(extended.0 & RIDEV_INPUTSINK.0, 0);
+            assert_eq!(extended.0 & RIDEV_NOLEGACY.0, flags.0 & RIDEV_NOLEGACY.0);
+        }
+    }
+
+    /// Test that a poll capacity of zero is rejected
+    #[test]
+    fn test_poll_capacity_validation() {
+        let capacity = |capacity| {
+            ListenerBuilder::new()
+                .poll_capacity(capacity)
+                .into_config()
+                .validate()
+        };
+        assert!(capacity(1).is_ok());
+        assert!(capacity(0).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Keyboard keys and mouse buttons under a single identity, for bindings
+
+use crate::{MouseButton, RawInputError, RawInputEvent, VirtualKey};
+use std::fmt;
+use std::str::FromStr;
+
+/// A keyboard key or mouse button, regardless of whether it is pressed or released.
+///
+/// Bindings that accept "any input" can be keyed by `Button`, e.g. in a
+/// `HashMap<Button, Action>`, and filled by a "press any button to bind" prompt using
+/// [`RawInputEvent::button`]. A button displays as its English name (see
+/// [`VirtualKey::name`]; mouse buttons are "Left Button", "Right Button", "Middle Button",
+/// "X1 Button" and "X2 Button") and parses back from it, ignoring case, so bindings can be
+/// saved as text.
+///
+/// Keys are identified by virtual key, so the two Enter keys are the same button (the
+/// two Plus keys are not: [`VirtualKey::OEMPlus`] and [`VirtualKey::NumpadAdd`]). Bind by
+/// [`RawKeyboardEvent::key_code`](crate::RawKeyboardEvent::key_code) instead where
+/// Numpad Enter must be told apart.
+///
+/// # Example
+/// ```
+/// use mkb_raw_input::{Button, MouseButton, VirtualKey};
+///
+/// assert_eq!("page up".parse::<Button>().unwrap(), Button::Key(VirtualKey::PageUp));
+/// assert_eq!(Button::Mouse(MouseButton::X1).to_string(), "X1 Button");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    /// A keyboard key
+    Key(VirtualKey),
+    /// A mouse button
+    Mouse(MouseButton),
+}
+
+/// Names of the mouse buttons, as displayed and parsed
+const MOUSE_BUTTON_NAMES: [(MouseButton, &str); 5] = [
+    (MouseButton::Left, "Left Button"),
+    (MouseButton::Right, "Right Button"),
+    (MouseButton::Middle, "Middle Button"),
+    (MouseButton::X1, "X1 Button"),
+    (MouseButton::X2, "X2 Button"),
+];
+
+impl From<VirtualKey> for Button {
+    fn from(key: VirtualKey) -> Self {
+        Self::Key(key)
+    }
+}
+
+impl From<MouseButton> for Button {
+    fn from(button: MouseButton) -> Self {
+        Self::Mouse(button)
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => f.write_str(&key.name()),
+            Self::Mouse(button) => {
+                let (_, name) = MOUSE_BUTTON_NAMES
+                    .iter()
+                    .find(|(b, _)| b == button)
+                    .expect("every mouse button has a name");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl FromStr for Button {
+    type Err = RawInputError;
+
+    /// Parses a button from its [`Display`](fmt::Display) name, ignoring case and
+    /// surrounding whitespace. Keys without a name parse from their code, e.g. "VK 0xFF".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+        if let Some(&(button, _)) = MOUSE_BUTTON_NAMES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        {
+            return Ok(Self::Mouse(button));
+        }
+        (0x01..=0xFF)
+            .map(VirtualKey::from)
+            .find(|key| key.name().eq_ignore_ascii_case(name))
+            .map(Self::Key)
+            .ok_or_else(|| RawInputError::Other(format!("Unknown button name: {s:?}")))
+    }
+}
+
+impl RawInputEvent {
+    /// Returns the key or mouse button this event pressed or released, or None for
+    /// movement, wheel and HID events.
+    ///
+    /// Auto-repeated key presses report their key as well; see
+    /// [`ListenerBuilder::ignore_auto_repeat`](crate::ListenerBuilder::ignore_auto_repeat)
+    /// to drop them.
+    pub fn button(&self) -> Option<Button> {
+        match self {
+            Self::Keyboard(kbd) => Some(Button::Key(kbd.vkey)),
+            Self::Mouse(mouse) => mouse
+                .button_action
+                .button_transition()
+                .map(|(button, _)| Button::Mouse(button)),
+            Self::Hid(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that every key and mouse button parses back from its name
+    #[test]
+    fn test_name_round_trip() {
+        let keys = (0x01..=0xFF).map(|code| Button::Key(VirtualKey::from(code)));
+        let mice = MOUSE_BUTTON_NAMES.iter().map(|&(b, _)| Button::Mouse(b));
+        for button in keys.chain(mice) {
+            assert_eq!(button.to_string().parse::<Button>().unwrap(), button);
+        }
+
+        assert_eq!(
+            " left button ".parse::<Button>().unwrap(),
+            Button::Mouse(MouseButton::Left)
+        );
+        assert_eq!("Left".parse::<Button>().unwrap(), VirtualKey::Left.into());
+        assert!("Hyper".parse::<Button>().is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Raw input device handles and device information queries
+
+use crate::RawInputEvent;
+#[cfg(windows)]
+use crate::{RawInputError, ffi};
+#[cfg(windows)]
+use windows::Win32::Foundation::HANDLE;
+#[cfg(windows)]
+use windows::Win32::UI::Input::{
+    RAWINPUTHEADER, RID_DEVICE_INFO_TYPE, RIM_TYPEHID, RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::RIM_INPUTSINK;
+
+/// Opaque handle identifying a physical raw input device.
+///
+/// Wraps the Win32 device `HANDLE` (from `RAWINPUTHEADER.hDevice`) as an integer so it is
+/// `Send`, `Copy` and usable as a `HashMap` key. The default (0) handle stands for no
+/// device, as reported for input injected with `SendInput`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RawInputDeviceHandle(isize);
+
+impl RawInputDeviceHandle {
+    /// Creates a handle from a raw Win32 device handle value.
+    pub fn from_raw(raw: isize) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the raw Win32 device handle value.
+    pub fn as_raw(self) -> isize {
+        self.0
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn as_handle(self) -> HANDLE {
+        HANDLE(self.0 as *mut _)
+    }
+}
+
+/// Returns the distinct devices that produced `events`, in order of first appearance.
+///
+/// Use this to pick the device to keep with [`filter_device`] before replaying a recording
+/// of several devices.
+pub fn recorded_devices(events: &[RawInputEvent]) -> Vec<RawInputDeviceHandle> {
+    let mut devices = Vec::new();
+    for device in events.iter().map(RawInputEvent::device) {
+        if !devices.contains(&device) {
+            devices.push(device);
+        }
+    }
+    devices
+}
+
+/// Returns the events produced by `device`, in their original order.
+///
+/// Replaying with [`send_events`](crate::send_events) injects every event as coming from
+/// the same virtual device (`SendInput` cannot target a device), so a recording of two
+/// mice moving at once would replay as one cursor moved by both. Filtering the recording to
+/// a single device first keeps the playback coherent.
+pub fn filter_device(events: &[RawInputEvent], device: RawInputDeviceHandle) -> Vec<RawInputEvent> {
+    events
+        .iter()
+        .filter(|event| event.device() == device)
+        .cloned()
+        .collect()
+}
+
+/// Type of a raw input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawInputDeviceType {
+    Mouse,
+    Keyboard,
+    /// Any other HID device
+    Hid,
+}
+
+/// Header of a raw input packet, as read by [`read_raw_input_header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawInputHeader {
+    /// Type of the device that produced the input
+    pub device_type: RawInputDeviceType,
+    /// Device that produced the input (the default handle for injected input)
+    pub device: RawInputDeviceHandle,
+    /// Size in bytes of the complete packet, header and data
+    pub size: u32,
+    /// Whether the input arrived while the receiving window was in the background
+    /// (`RIM_INPUTSINK`)
+    pub background: bool,
+}
+
+#[cfg(windows)]
+impl TryFrom<&RAWINPUTHEADER> for RawInputHeader {
+    type Error = RawInputError;
+
+    fn try_from(header: &RAWINPUTHEADER) -> Result<Self, Self::Error> {
+        let device_type = match RID_DEVICE_INFO_TYPE(header.dwType) {
+            RIM_TYPEMOUSE => RawInputDeviceType::Mouse,
+            RIM_TYPEKEYBOARD => RawInputDeviceType::Keyboard,
+            RIM_TYPEHID => RawInputDeviceType::Hid,
+            other => {
+                return Err(RawInputError::Other(format!(
+                    "Unknown raw input type {}",
+                    other.0
+                )));
+            }
+        };
+        Ok(Self {
+            device_type,
+            device: RawInputDeviceHandle::from_raw(header.hDevice.0 as isize),
+            size: header.dwSize,
+            background: header.wParam.0 == RIM_INPUTSINK as usize,
+        })
+    }
+}
+
+/// Reads only the header of the raw input of a `WM_INPUT` message, for deciding cheaply
+/// whether to read the rest.
+///
+/// Reading a packet in full copies its data (many bytes for some HID devices) and parses
+/// it; the header alone tells the device and its type. In attach mode, filter by device
+/// here and pass only the wanted messages to
+/// [`AttachedListener::handle_raw_event`](crate::AttachedListener::handle_raw_event):
+/// the packet stays readable until the message reaches `DefWindowProcW`, so reading the
+/// header first does not consume it.
+///
+/// # Arguments
+/// * `lparam` - The message's LPARAM (`MSG::lParam.0`), the packet's `HRAWINPUT`
+///
+/// # Returns
+/// * `Ok(RawInputHeader)` - The packet's device, type and size
+/// * `Err(RawInputError)` - If `GetRawInputData` fails (e.g. the handle is not a valid
+///   packet) or the packet is of an unknown type
+#[cfg(windows)]
+pub fn read_raw_input_header(lparam: isize) -> Result<RawInputHeader, RawInputError> {
+    let header = ffi::read_raw_input_header(windows::Win32::Foundation::LPARAM(lparam))
+        .map_err(|e| RawInputError::WinApiError(format!("GetRawInputData failed: {e}")))?;
+    RawInputHeader::try_from(&header)
+}
+
+/// Capabilities of a mouse device, from `RID_DEVICE_INFO_MOUSE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseDeviceInfo {
+    /// Bitfield identifying the mouse device's properties
+    pub id: u32,
+    /// Number of buttons on the mouse
+    pub number_of_buttons: u32,
+    /// Number of data points reported per second (0 if the device does not report it)
+    pub sample_rate: u32,
+    /// Whether the mouse has a horizontal wheel
+    pub has_horizontal_wheel: bool,
+}
+
+/// Queries the capabilities of a mouse device.
+///
+/// # Arguments
+/// * `handle` - Handle of the mouse device
+///
+/// # Returns
+/// * `Ok(MouseDeviceInfo)` - The device's button count, sample rate and wheel support
+/// * `Err(RawInputError)` - If the query fails or the device is not a mouse
+#[cfg(windows)]
+pub fn mouse_device_info(handle: RawInputDeviceHandle) -> Result<MouseDeviceInfo, RawInputError> {
+    let info = ffi::device_info(handle.as_handle())
+        .map_err(|e| RawInputError::WinApiError(format!("GetRawInputDeviceInfoW failed: {e}")))?;
+
+    if info.dwType != RIM_TYPEMOUSE {
+        return Err(RawInputError::Other(format!(
+            "Device {:#x} is not a mouse (type {})",
+            handle.as_raw(),
+            info.dwType.0
+        )));
+    }
+
+    let mouse = unsafe { info.Anonymous.mouse };
+    Ok(MouseDeviceInfo {
+        id: mouse.dwId,
+        number_of_buttons: mouse.dwNumberOfButtons,
+        sample_rate: mouse.dwSampleRate,
+        has_horizontal_wheel: mouse.fHasHorizontalWheel.as_bool(),
+    })
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+    use crate::RawMouseEvent;
+    use windows::Win32::UI::Input::RAWMOUSE;
+
+    fn moved_by(device: isize, last_x: i32) -> RawInputEvent {
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        raw.lLastX = last_x;
+        let mut event = RawMouseEvent::from(&raw);
+        event.device = RawInputDeviceHandle::from_raw(device);
+        RawInputEvent::Mouse(event)
+    }
+
+    /// Test that a recording of two interleaved mice can be split by device
+    #[test]
+    fn test_filter_device() {
+        let events = [
+            moved_by(2, 1),
+            moved_by(1, 10),
+            moved_by(2, 3),
+            moved_by(1, 20),
+        ];
+        let (first, second) = (
+            RawInputDeviceHandle::from_raw(2),
+            RawInputDeviceHandle::from_raw(1),
+        );
+        assert_eq!(recorded_devices(&events), vec![first, second]);
+
+        let moves: Vec<_> = filter_device(&events, second)
+            .into_iter()
+            .map(|event| match event {
+                RawInputEvent::Mouse(mouse) => mouse.last_x,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(moves, vec![10, 20]);
+        assert!(filter_device(&events, RawInputDeviceHandle::default()).is_empty());
+    }
+
+    /// Test the conversion of packet headers and the failure on an invalid packet handle
+    #[test]
+    fn test_raw_input_header() {
+        use windows::Win32::Foundation::{HANDLE, WPARAM};
+
+        let header = RAWINPUTHEADER {
+            dwType: RIM_TYPEHID.0,
+            dwSize: 64,
+            hDevice: HANDLE(5 as *mut _),
+            wParam: WPARAM(RIM_INPUTSINK as usize),
+        };
+        assert_eq!(
+            RawInputHeader::try_from(&header).unwrap(),
+            RawInputHeader {
+                device_type: RawInputDeviceType::Hid,
+                device: RawInputDeviceHandle::from_raw(5),
+                size: 64,
+                background: true,
+            }
+        );
+        let unknown = RAWINPUTHEADER {
+            dwType: 7,
+            ..header
+        };
+        assert!(RawInputHeader::try_from(&unknown).is_err());
+
+        assert!(read_raw_input_header(0).is_err());
+    }
+}
@@ -0,0 +1,1314 @@
+//! Platform-independent event dispatch pipeline shared by all event sources
+
+use crate::builder::ListenerConfig;
+use crate::export::EventLog;
+use crate::hotkey::HotkeyBinding;
+use crate::mouse::MoveTracker;
+use crate::source::EventSource;
+use crate::state::{KeySetChange, PolledEvents, RecentEvents, ScrollAccumulator, SharedInputState};
+use crate::tap::TapDetector;
+use crate::timing::TimingCollector;
+use crate::{
+    EventKind, Hotkey, Modifiers, MouseButtonAction, MouseMoveMode, RawHidEventRef, RawInputError,
+    RawInputEvent, SessionChange, VirtualKey, WheelAxis,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Boxed error callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type ErrorCallback = Box<dyn FnMut(RawInputError) + Send + 'static>;
+
+/// Boxed HID callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type HidCallback = Box<dyn FnMut(RawHidEventRef<'_>) + Send + 'static>;
+
+/// Boxed panic hotkey callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type PanicCallback = Box<dyn FnMut(bool) + Send + 'static>;
+
+/// Boxed wheel callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type WheelCallback = Box<dyn FnMut(WheelAxis, f32) + Send + 'static>;
+
+/// Boxed key set callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type KeySetCallback = Box<dyn FnMut(KeySetChange) + Send + 'static>;
+
+/// Boxed session callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type SessionCallback = Box<dyn FnMut(SessionChange) + Send + 'static>;
+
+/// Boxed first event callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type FirstEventCallback = Box<dyn FnOnce(&RawInputEvent) + Send + 'static>;
+
+/// Optional callbacks collected by [`ListenerBuilder`](crate::ListenerBuilder).
+#[derive(Default)]
+pub(crate) struct ListenerCallbacks {
+    pub on_error: Option<ErrorCallback>,
+    pub on_hid: Option<HidCallback>,
+    pub on_panic_hotkey: Option<PanicCallback>,
+    pub on_wheel: Option<WheelCallback>,
+    pub on_key_set: Option<KeySetCallback>,
+    pub on_session: Option<SessionCallback>,
+    pub hotkeys: Vec<HotkeyBinding>,
+    pub taps: Option<TapDetector>,
+    pub on_first_event: Option<FirstEventCallback>,
+    pub log: Option<EventLog>,
+}
+
+/// Routes listener errors to the user's error callback.
+///
+/// This is the only place the listener writes to stderr: errors are printed only when
+/// no error callback is set and the listener was not configured as silent. Every
+/// reported error is also recorded so it can be retrieved via
+/// [`ListenerHandle::last_error`](crate::ListenerHandle::last_error).
+///
+/// With a repeat interval, each distinct error (by message) is passed on at most once per
+/// interval; its repeats in between are counted and passed on with the next occurrence
+/// after the interval, at the next [`flush_expired`](Self::flush_expired) after it, or
+/// when the reporter is dropped.
+pub(crate) struct ErrorReporter {
+    on_error: Option<ErrorCallback>,
+    silent: bool,
+    last_error: Arc<Mutex<Option<RawInputError>>>,
+    repeat_interval: Duration,
+    repeats: HashMap<String, RepeatedError>,
+}
+
+/// An error passed on within the repeat interval, and how often it recurred since
+struct RepeatedError {
+    error: RawInputError,
+    since: Instant,
+    count: u64,
+}
+
+impl RepeatedError {
+    /// Returns the error with the number of occurrences since it was last passed on
+    /// appended to its message, counting `occurrences` beyond the repeats.
+    fn summary(&self, error: RawInputError, occurrences: u64, now: Instant) -> RawInputError {
+        let count = self.count + occurrences;
+        let elapsed = now.saturating_duration_since(self.since);
+        let suffix = format!(" (x{count} in the last {elapsed:.1?})");
+        match error {
+            RawInputError::WinApiError(msg) => RawInputError::WinApiError(msg + &suffix),
+            RawInputError::AccessDenied(msg) => RawInputError::AccessDenied(msg + &suffix),
+            RawInputError::Other(msg) => RawInputError::Other(msg + &suffix),
+        }
+    }
+}
+
+impl ErrorReporter {
+    pub(crate) fn new(
+        on_error: Option<ErrorCallback>,
+        silent: bool,
+        last_error: Arc<Mutex<Option<RawInputError>>>,
+    ) -> Self {
+        Self {
+            on_error,
+            silent,
+            last_error,
+            repeat_interval: Duration::ZERO,
+            repeats: HashMap::new(),
+        }
+    }
+
+    /// Coalesces repeats of the same error within `interval` (zero passes on every error).
+    pub(crate) fn with_repeat_interval(mut self, interval: Duration) -> Self {
+        self.repeat_interval = interval;
+        self
+    }
+
+    /// Records the error and forwards it to the error callback (or stderr, unless silent).
+    pub(crate) fn report(&mut self, err: RawInputError) {
+        self.report_at(err, Instant::now());
+    }
+
+    fn report_at(&mut self, err: RawInputError, now: Instant) {
+        if let Ok(mut last) = self.last_error.lock() {
+            *last = Some(err.clone());
+        }
+        if self.repeat_interval.is_zero() {
+            self.forward(err);
+            return;
+        }
+
+        let key = err.to_string();
+        let interval = self.repeat_interval;
+        // Other errors whose interval is over are passed on with their repeats, if any
+        self.forward_expired(Some(&key), now);
+
+        let forwarded = match self.repeats.get_mut(&key) {
+            Some(repeated) if now.saturating_duration_since(repeated.since) < interval => {
+                repeated.count += 1;
+                return;
+            }
+            Some(repeated) => {
+                let forwarded = if repeated.count > 0 {
+                    repeated.summary(err, 1, now)
+                } else {
+                    err
+                };
+                repeated.since = now;
+                repeated.count = 0;
+                forwarded
+            }
+            None => {
+                let repeated = RepeatedError {
+                    error: err.clone(),
+                    since: now,
+                    count: 0,
+                };
+                self.repeats.insert(key, repeated);
+                err
+            }
+        };
+        self.forward(forwarded);
+    }
+
+    /// Passes on the repeats of errors whose interval is over, so that the count of a burst
+    /// that stopped is not held back until the error occurs again.
+    pub(crate) fn flush_expired(&mut self) {
+        self.flush_expired_at(Instant::now());
+    }
+
+    fn flush_expired_at(&mut self, now: Instant) {
+        self.forward_expired(None, now);
+    }
+
+    /// Forgets the errors whose interval is over, except `keep`, passing on their repeats.
+    fn forward_expired(&mut self, keep: Option<&str>, now: Instant) {
+        let interval = self.repeat_interval;
+        let expired: Vec<_> = self
+            .repeats
+            .extract_if(|k, r| {
+                Some(k.as_str()) != keep && now.saturating_duration_since(r.since) >= interval
+            })
+            .filter(|(_, r)| r.count > 0)
+            .map(|(_, r)| r.summary(r.error.clone(), 0, now))
+            .collect();
+        for summary in expired {
+            self.forward(summary);
+        }
+    }
+
+    fn forward(&mut self, err: RawInputError) {
+        if let Some(ref mut err_cb) = self.on_error {
+            err_cb(err);
+        } else if !self.silent {
+            eprintln!("Raw input error: {err}");
+        }
+    }
+}
+
+impl Drop for ErrorReporter {
+    /// Passes on the repeats not reported yet.
+    fn drop(&mut self) {
+        let now = Instant::now();
+        let pending: Vec<_> = self
+            .repeats
+            .drain()
+            .filter(|(_, r)| r.count > 0)
+            .map(|(_, r)| r.summary(r.error.clone(), 0, now))
+            .collect();
+        for summary in pending {
+            self.forward(summary);
+        }
+    }
+}
+
+/// Processes events from an [`EventSource`] and delivers them to the user's callbacks.
+///
+/// All event-processing behavior lives here rather than in the Win32 message loop, so
+/// that it behaves identically for the real listener and for test sources.
+pub(crate) struct Dispatcher<F> {
+    callback: F,
+    pub(crate) reporter: ErrorReporter,
+    state: SharedInputState,
+    filter_fake_shift: bool,
+    ignore_auto_repeat: bool,
+    dedup_mouse_buttons: bool,
+    on_hid: Option<HidCallback>,
+    paused: Arc<AtomicBool>,
+    panic_hotkey: Option<Hotkey>,
+    on_panic_hotkey: Option<PanicCallback>,
+    hotkeys: Vec<HotkeyBinding>,
+    required_modifiers: Modifiers,
+    allowed_keys: Option<Vec<VirtualKey>>,
+    denied_keys: Vec<VirtualKey>,
+    on_wheel: Option<WheelCallback>,
+    on_key_set: Option<KeySetCallback>,
+    taps: Option<TapDetector>,
+    moves: Option<MoveTracker>,
+    on_first_event: Option<FirstEventCallback>,
+    log: Option<EventLog>,
+    scroll: Arc<ScrollAccumulator>,
+    recent: Arc<RecentEvents>,
+    polled: Arc<PolledEvents>,
+    timing: Arc<TimingCollector>,
+}
+
+impl<F> Dispatcher<F>
+where
+    F: FnMut(RawInputEvent),
+{
+    pub(crate) fn new(
+        callback: F,
+        config: &ListenerConfig,
+        reporter: ErrorReporter,
+        state: SharedInputState,
+    ) -> Self {
+        Self {
+            callback,
+            reporter,
+            state,
+            filter_fake_shift: config.filter_fake_shift,
+            ignore_auto_repeat: config.ignore_auto_repeat,
+            dedup_mouse_buttons: config.dedup_mouse_buttons,
+            on_hid: None,
+            paused: Arc::new(AtomicBool::new(false)),
+            panic_hotkey: config.panic_hotkey.clone(),
+            on_panic_hotkey: None,
+            hotkeys: Vec::new(),
+            required_modifiers: config.required_modifiers,
+            allowed_keys: config.allowed_keys.clone(),
+            denied_keys: config.denied_keys.clone(),
+            on_wheel: None,
+            on_key_set: None,
+            taps: None,
+            moves: config.track_velocity.then(MoveTracker::default),
+            on_first_event: None,
+            log: None,
+            scroll: Arc::default(),
+            recent: Arc::new(RecentEvents::new(config.ring_buffer)),
+            polled: Arc::new(PolledEvents::new(config.poll, config.poll_capacity)),
+            timing: Arc::new(TimingCollector::new(config.timing_stats)),
+        }
+    }
+
+    /// Routes owned HID events to `on_hid` instead of the event callback.
+    ///
+    /// Sources reading Win32 input pass HID packets to the callback directly (see
+    /// `PacketReader`); this covers sources that yield owned events.
+    pub(crate) fn with_hid_callback(mut self, on_hid: Option<HidCallback>) -> Self {
+        self.on_hid = on_hid;
+        self
+    }
+
+    /// Sets the hotkeys whose press and release callbacks are invoked.
+    pub(crate) fn with_hotkeys(mut self, hotkeys: Vec<HotkeyBinding>) -> Self {
+        self.hotkeys = hotkeys;
+        self
+    }
+
+    /// Sets the callback invoked with the new paused state when the panic hotkey fires.
+    pub(crate) fn with_panic_callback(mut self, on_panic_hotkey: Option<PanicCallback>) -> Self {
+        self.on_panic_hotkey = on_panic_hotkey;
+        self
+    }
+
+    /// Sets the callback invoked with the axis and notches of every wheel event.
+    pub(crate) fn with_wheel_callback(mut self, on_wheel: Option<WheelCallback>) -> Self {
+        self.on_wheel = on_wheel;
+        self
+    }
+
+    /// Sets the callback invoked with every change in the set of held keys.
+    pub(crate) fn with_key_set_callback(mut self, on_key_set: Option<KeySetCallback>) -> Self {
+        self.on_key_set = on_key_set;
+        self
+    }
+
+    /// Sets the detector whose callback is invoked for every key tap.
+    pub(crate) fn with_taps(mut self, taps: Option<TapDetector>) -> Self {
+        self.taps = taps;
+        self
+    }
+
+    /// Sets the callback invoked once with the first delivered event.
+    pub(crate) fn with_first_event_callback(
+        mut self,
+        on_first_event: Option<FirstEventCallback>,
+    ) -> Self {
+        self.on_first_event = on_first_event;
+        self
+    }
+
+    /// Sets the CSV log every delivered event is written to.
+    pub(crate) fn with_log(mut self, log: Option<EventLog>) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Returns the flag that pauses event delivery, for sharing with a handle.
+    pub(crate) fn paused(&self) -> Arc<AtomicBool> {
+        self.paused.clone()
+    }
+
+    /// Returns the accumulated scrolling, for sharing with a handle.
+    pub(crate) fn scroll(&self) -> Arc<ScrollAccumulator> {
+        self.scroll.clone()
+    }
+
+    /// Returns the tracked key and button state, for sharing with a session monitor.
+    pub(crate) fn state(&self) -> SharedInputState {
+        self.state.clone()
+    }
+
+    /// Returns the ring buffer of recently delivered events, for sharing with a handle.
+    pub(crate) fn recent(&self) -> Arc<RecentEvents> {
+        self.recent.clone()
+    }
+
+    /// Returns the queue of events waiting to be polled, for sharing with a handle.
+    pub(crate) fn polled(&self) -> Arc<PolledEvents> {
+        self.polled.clone()
+    }
+
+    /// Returns the event interval statistics, for sharing with a handle.
+    pub(crate) fn timing(&self) -> Arc<TimingCollector> {
+        self.timing.clone()
+    }
+
+    /// Returns true unless the allowed and denied keys filter out `key`.
+    fn is_key_delivered(&self, key: VirtualKey) -> bool {
+        self.allowed_keys
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(&key))
+            && !self.denied_keys.contains(&key)
+    }
+
+    /// Runs a single event through the pipeline (timing statistics, Pause coalescing,
+    /// filters, tap detection, movement tracking, wheel callback and scroll accumulation,
+    /// state tracking, key set callback, hotkeys, panic hotkey, modifier gate and
+    /// auto-repeat filter, ring buffer, event log, first event callback), returning the
+    /// event to deliver, if any.
+    ///
+    /// While paused, state is still tracked and the panic hotkey still detected, but no
+    /// event is delivered and no hotkey pressed (pressed ones are still released).
+    pub(crate) fn process(&mut self, mut event: RawInputEvent) -> Option<RawInputEvent> {
+        // HID reports routed to the HID callback are not counted
+        if !(matches!(event, RawInputEvent::Hid(_)) && self.on_hid.is_some()) {
+            self.timing.record(event.timestamp());
+        }
+
+        if let RawInputEvent::Keyboard(kbd) = &event
+            && kbd.is_pause_continuation()
+        {
+            return None;
+        }
+
+        if self.filter_fake_shift
+            && let RawInputEvent::Keyboard(kbd) = &event
+            && kbd.is_fake_shift()
+        {
+            return None;
+        }
+
+        if let RawInputEvent::Keyboard(kbd) = &event
+            && !self.is_key_delivered(kbd.vkey)
+        {
+            return None;
+        }
+
+        let was_paused = self.paused.load(Ordering::SeqCst);
+
+        if let RawInputEvent::Keyboard(kbd) = &event
+            && let Some(taps) = self.taps.as_mut()
+        {
+            taps.process(kbd, was_paused);
+        }
+
+        if let RawInputEvent::Mouse(mouse) = &mut event
+            && let Some(moves) = self.moves.as_mut()
+        {
+            moves.track(mouse);
+        }
+
+        if let RawInputEvent::Hid(hid) = &event
+            && let Some(on_hid) = self.on_hid.as_mut()
+        {
+            if !was_paused {
+                on_hid(hid.as_event_ref());
+            }
+            return None;
+        }
+
+        if !was_paused
+            && let RawInputEvent::Mouse(mouse) = &event
+            && let Some((axis, notches)) = mouse.wheel_notches()
+        {
+            self.scroll.add(axis, i32::from(mouse.wheel_delta));
+            if let Some(on_wheel) = self.on_wheel.as_mut() {
+                on_wheel(axis, notches);
+            }
+        }
+
+        let mut gated = false;
+        let mut key_set_change = None;
+        if !matches!(event.kind(), EventKind::MouseMove | EventKind::MouseWheel)
+            && let Ok(mut state) = self.state.lock()
+        {
+            let pressed = match &event {
+                RawInputEvent::Keyboard(kbd) if !kbd.key_up => Some(kbd.vkey),
+                _ => None,
+            };
+            let repeat = pressed.is_some_and(|key| state.is_key_down(key));
+            let was_down =
+                matches!(&event, RawInputEvent::Keyboard(kbd) if state.is_key_down(kbd.vkey));
+            let redundant_button = self.dedup_mouse_buttons
+                && matches!(&event, RawInputEvent::Mouse(mouse) if mouse
+                    .button_action
+                    .button_transition()
+                    .is_some_and(|(button, down)| state.is_mouse_button_down(button) == down));
+            state.apply(&event);
+            // A redundant button packet that also moved still delivers the movement
+            if redundant_button && let RawInputEvent::Mouse(mouse) = &mut event {
+                let moved = mouse.move_mode != MouseMoveMode::Relative
+                    || mouse.last_x != 0
+                    || mouse.last_y != 0;
+                mouse.button_action = MouseButtonAction::None;
+                gated = !moved;
+            }
+            if let RawInputEvent::Keyboard(kbd) = &mut event {
+                if !was_paused && self.on_key_set.is_some() {
+                    key_set_change = state.key_set_change(kbd.vkey, was_down);
+                }
+                kbd.modifiers = state.modifiers();
+                gated = !kbd.modifiers.contains(self.required_modifiers)
+                    || (self.ignore_auto_repeat && repeat);
+                let pressed = pressed.filter(|_| !repeat);
+                for binding in &mut self.hotkeys {
+                    binding.observe(pressed, &state, was_paused, kbd.timestamp);
+                }
+            }
+
+            if let Some(key) = pressed
+                && !repeat
+                && self
+                    .panic_hotkey
+                    .as_ref()
+                    .is_some_and(|hotkey| hotkey.is_triggered_by(key, &state))
+            {
+                drop(state);
+                let paused = !was_paused;
+                self.paused.store(paused, Ordering::SeqCst);
+                if let Some(ref mut on_panic) = self.on_panic_hotkey {
+                    on_panic(paused);
+                }
+            }
+        }
+
+        // Invoked after the state lock is released, so the callback may query the handle
+        if let Some(change) = key_set_change
+            && let Some(on_key_set) = self.on_key_set.as_mut()
+        {
+            on_key_set(change);
+        }
+        self.hotkeys.iter_mut().for_each(HotkeyBinding::fire);
+
+        let deliver = !was_paused && !gated;
+        if deliver {
+            self.recent.push(&event);
+            if let Some(log) = self.log.as_mut()
+                && let Err(e) = log.write(&event)
+            {
+                self.log = None;
+                self.reporter.report(RawInputError::Other(format!(
+                    "Failed to write the event log, logging stopped: {e}"
+                )));
+            }
+            if let Some(on_first_event) = self.on_first_event.take() {
+                on_first_event(&event);
+            }
+        }
+        deliver.then_some(event)
+    }
+
+    /// Processes a single event and delivers it to the callback, or to the poll queue if
+    /// polling.
+    pub(crate) fn dispatch(&mut self, event: RawInputEvent) {
+        let Some(event) = self.process(event) else {
+            return;
+        };
+        if !self.polled.is_enabled() {
+            (self.callback)(event);
+        } else if self.polled.push(event) {
+            let dropped = self.polled.dropped();
+            self.report(RawInputError::Other(format!(
+                "The poll queue is full: discarding the oldest events until the next poll \
+                 ({dropped} discarded since the listener started)"
+            )));
+        }
+    }
+
+    /// Reports a single error.
+    pub(crate) fn report(&mut self, err: RawInputError) {
+        self.reporter.report(err);
+    }
+
+    /// Passes on coalesced error repeats whose interval is over.
+    pub(crate) fn flush_expired_errors(&mut self) {
+        self.reporter.flush_expired();
+    }
+
+    /// Processes events until the source is exhausted.
+    pub(crate) fn run<S: EventSource>(&mut self, source: &mut S) {
+        while let Some(item) = source.next_event() {
+            match item {
+                Ok(event) => self.dispatch(event),
+                Err(e) => self.report(e),
+            }
+        }
+    }
+}
+
+impl<F> Drop for Dispatcher<F> {
+    fn drop(&mut self) {
+        if let Some(log) = self.log.as_mut()
+            && let Err(e) = log.flush()
+        {
+            self.reporter.report(RawInputError::Other(format!(
+                "Failed to flush the event log: {e}"
+            )));
+        }
+    }
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+    use crate::mouse::{MOUSE_BUTTON_LEFT_DOWN, MOUSE_BUTTON_LEFT_UP};
+    use crate::source::VecEventSource;
+    use crate::{
+        Hotkey, ListenerBuilder, RawHidEvent, RawKeyboardEvent, RawMouseEvent, VirtualKey,
+    };
+    use std::sync::mpsc;
+    use windows::Win32::UI::Input::{RAWKEYBOARD, RAWMOUSE};
+
+    fn mouse_event(last_x: i32) -> RawInputEvent {
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        raw.lLastX = last_x;
+        RawInputEvent::Mouse(RawMouseEvent::from(&raw))
+    }
+
+    fn button_event(button_flags: u16, last_x: i32) -> RawInputEvent {
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        raw.Anonymous.Anonymous.usButtonFlags = button_flags;
+        raw.lLastX = last_x;
+        RawInputEvent::Mouse(RawMouseEvent::from(&raw))
+    }
+
+    fn wheel_event(button_flags: u16, delta: i16) -> RawInputEvent {
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        raw.Anonymous.Anonymous.usButtonFlags = button_flags;
+        raw.Anonymous.Anonymous.usButtonData = delta as u16;
+        RawInputEvent::Mouse(RawMouseEvent::from(&raw))
+    }
+
+    fn key_event(make_code: u16, flags: u16, vkey: u16) -> RawInputEvent {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.MakeCode = make_code;
+        raw.Flags = flags;
+        raw.VKey = vkey;
+        RawInputEvent::Keyboard(RawKeyboardEvent::from(&raw))
+    }
+
+    fn vkey_event(vkey: u16, key_up: bool) -> RawInputEvent {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.VKey = vkey;
+        raw.Flags = if key_up { 1 } else { 0 };
+        RawInputEvent::Keyboard(RawKeyboardEvent::from(&raw))
+    }
+
+    /// Test that reported errors are recorded and forwarded to the error callback
+    #[test]
+    fn test_error_reporter_forwards_and_records() {
+        let (tx, rx) = mpsc::channel();
+        let last_error = Arc::new(Mutex::new(None));
+        let mut reporter = ErrorReporter::new(
+            Some(Box::new(move |err| {
+                let _ = tx.send(err);
+            })),
+            false,
+            last_error.clone(),
+        );
+
+        reporter.report(RawInputError::Other("first".to_string()));
+        reporter.report(RawInputError::Other("second".to_string()));
+
+        assert_eq!(rx.try_iter().count(), 2);
+        let last = last_error.lock().unwrap().clone();
+        assert!(
+            matches!(last, Some(RawInputError::Other(ref msg)) if msg == "second"),
+            "Last error should be the most recent one, got: {last:?}"
+        );
+    }
+
+    /// Test that a silent reporter without callback still records the error
+    #[test]
+    fn test_error_reporter_silent_records() {
+        let last_error = Arc::new(Mutex::new(None));
+        let mut reporter = ErrorReporter::new(None, true, last_error.clone());
+
+        reporter.report(RawInputError::WinApiError("failure".to_string()));
+
+        assert!(last_error.lock().unwrap().is_some());
+    }
+
+    /// Test that repeats of an error within the interval are passed on with a count
+    #[test]
+    fn test_error_reporter_coalesces_repeats() {
+        let (tx, rx) = mpsc::channel();
+        let last_error = Arc::new(Mutex::new(None));
+        let mut reporter = ErrorReporter::new(
+            Some(Box::new(move |err: RawInputError| {
+                let _ = tx.send(err.to_string());
+            })),
+            false,
+            last_error.clone(),
+        )
+        .with_repeat_interval(Duration::from_secs(1));
+        let read_failed = || RawInputError::WinApiError("read failed".to_string());
+        let start = Instant::now();
+
+        reporter.report_at(read_failed(), start);
+        for ms in 1..=3 {
+            reporter.report_at(read_failed(), start + Duration::from_millis(ms));
+        }
+        reporter.report_at(RawInputError::Other("other".to_string()), start);
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            ["Windows API error: read failed", "Other error: other"]
+        );
+        assert!(matches!(
+            *last_error.lock().unwrap(),
+            Some(RawInputError::Other(_))
+        ));
+
+        // The next occurrence after the interval carries the repeats before it
+        reporter.report_at(read_failed(), start + Duration::from_secs(1));
+        reporter.report_at(read_failed(), start + Duration::from_millis(1500));
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            ["Windows API error: read failed (x4 in the last 1.0s)"]
+        );
+
+        drop(reporter);
+        let pending = rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(pending.len(), 1);
+        assert!(pending[0].starts_with("Windows API error: read failed (x1 in the last "));
+    }
+
+    /// Test that flushing passes on the repeats of expired errors without a new occurrence
+    #[test]
+    fn test_error_reporter_flushes_expired_repeats() {
+        let (tx, rx) = mpsc::channel();
+        let mut reporter = ErrorReporter::new(
+            Some(Box::new(move |err: RawInputError| {
+                let _ = tx.send(err.to_string());
+            })),
+            false,
+            Default::default(),
+        )
+        .with_repeat_interval(Duration::from_secs(1));
+        let start = Instant::now();
+
+        for ms in 0..3 {
+            let err = RawInputError::Other("read failed".to_string());
+            reporter.report_at(err, start + Duration::from_millis(ms));
+        }
+        reporter.report_at(RawInputError::Other("once".to_string()), start);
+        assert_eq!(rx.try_iter().count(), 2);
+
+        // Nothing is due within the interval
+        reporter.flush_expired_at(start + Duration::from_millis(500));
+        assert_eq!(rx.try_iter().count(), 0);
+
+        // Only the error that recurred has a summary to pass on
+        reporter.flush_expired_at(start + Duration::from_secs(1));
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            ["Other error: read failed (x2 in the last 1.0s)"]
+        );
+
+        // Flushed repeats are not passed on again when the reporter is dropped
+        drop(reporter);
+        assert_eq!(rx.try_iter().count(), 0);
+    }
+
+    /// Test that a vec source drives events and errors through the pipeline in order
+    #[test]
+    fn test_run_with_vec_source() {
+        let (err_tx, err_rx) = mpsc::channel();
+        let source = VecEventSource::from_results(vec![
+            Ok(mouse_event(1)),
+            Err(RawInputError::Other("bad packet".to_string())),
+            Ok(mouse_event(2)),
+        ]);
+
+        let mut seen = Vec::new();
+        ListenerBuilder::new()
+            .error_callback(move |err| {
+                let _ = err_tx.send(err);
+            })
+            .run_with_source(source, |event| {
+                if let RawInputEvent::Mouse(mouse) = event {
+                    seen.push(mouse.last_x);
+                }
+            });
+
+        assert_eq!(seen, vec![1, 2]);
+        assert_eq!(err_rx.try_iter().count(), 1);
+    }
+
+    /// Test that fake shift events are dropped only when filtering is enabled
+    #[test]
+    fn test_filter_fake_shift() {
+        // Shift+Numpad4 with NumLock on: fake Shift up, Left down/up, fake Shift down
+        let events = || {
+            vec![
+                key_event(0x2A, 0x02 | 0x01, 0x10),
+                key_event(0x4B, 0, 0x25),
+                key_event(0x4B, 0x01, 0x25),
+                key_event(0x2A, 0x02, 0x10),
+            ]
+        };
+        let count = |builder: ListenerBuilder| {
+            let mut seen = 0;
+            builder.run_with_source(VecEventSource::new(events()), |_| seen += 1);
+            seen
+        };
+
+        assert_eq!(count(ListenerBuilder::new()), 4);
+        assert_eq!(count(ListenerBuilder::new().filter_fake_shift(true)), 2);
+    }
+
+    /// Test that the trailing event of the Pause key's E1 sequence is dropped, while
+    /// Ctrl+NumLock (also reported as VK_PAUSE) is delivered
+    #[test]
+    fn test_pause_sequence_coalesced() {
+        let events = vec![
+            key_event(0x1D, 0x04, 0x13),
+            key_event(0x45, 0, 0xFF),
+            key_event(0x1D, 0x04 | 0x01, 0x13),
+            key_event(0x45, 0x01, 0xFF),
+            key_event(0x45, 0, 0x13),
+        ];
+
+        let mut seen = Vec::new();
+        ListenerBuilder::new().run_with_source(VecEventSource::new(events), |event| {
+            if let RawInputEvent::Keyboard(kbd) = event {
+                seen.push((kbd.is_pause_key(), kbd.is_ctrl_num_lock(), kbd.key_up));
+            }
+        });
+
+        assert_eq!(
+            seen,
+            vec![
+                (true, false, false),
+                (true, false, true),
+                (false, true, false)
+            ]
+        );
+    }
+
+    /// Test that HID events go to the HID callback instead of the event callback
+    #[test]
+    fn test_hid_callback_receives_hid_events() {
+        let (hid_tx, hid_rx) = mpsc::channel();
+        let hid = RawHidEvent {
+            report_size: 2,
+            report_count: 1,
+            data: vec![0xAB, 0xCD],
+            device: Default::default(),
+            timestamp: std::time::Instant::now(),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        };
+
+        let mut seen = 0;
+        ListenerBuilder::new()
+            .hid_callback(move |hid| {
+                let _ = hid_tx.send(hid.data.to_vec());
+            })
+            .run_with_source(
+                VecEventSource::new(vec![mouse_event(1), RawInputEvent::Hid(hid)]),
+                |_| seen += 1,
+            );
+
+        assert_eq!(seen, 1);
+        assert_eq!(
+            hid_rx.try_iter().collect::<Vec<_>>(),
+            vec![vec![0xAB, 0xCD]]
+        );
+    }
+
+    /// Test that timing statistics leave out HID reports routed to the HID callback
+    #[test]
+    fn test_timing_stats_skip_hid_callback_reports() {
+        let config = ListenerConfig {
+            timing_stats: true,
+            ..Default::default()
+        };
+        let reporter = ErrorReporter::new(None, true, Arc::new(Mutex::new(None)));
+        let mut dispatcher = Dispatcher::new(|_| {}, &config, reporter, Default::default())
+            .with_hid_callback(Some(Box::new(|_| {})));
+        let timing = dispatcher.timing();
+        let hid = RawHidEvent {
+            report_size: 1,
+            report_count: 1,
+            data: vec![0xAB],
+            device: Default::default(),
+            timestamp: std::time::Instant::now(),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        };
+
+        dispatcher.dispatch(mouse_event(1));
+        dispatcher.dispatch(RawInputEvent::Hid(hid));
+        dispatcher.dispatch(mouse_event(2));
+
+        assert_eq!(timing.stats().count, 1);
+    }
+
+    /// Test that the wheel callback receives only wheel events, in fractional notches
+    #[test]
+    fn test_wheel_callback_reports_notches() {
+        let (wheel_tx, wheel_rx) = mpsc::channel();
+        let source = VecEventSource::new(vec![
+            wheel_event(0x0400, 120), // one notch up
+            mouse_event(5),           // movement
+            vkey_event(0x41, false),
+            wheel_event(0x0400, -30), // a quarter notch down (trackpad)
+            wheel_event(0x0800, 60),  // half a notch right
+        ]);
+
+        let mut seen = 0;
+        ListenerBuilder::new()
+            .wheel_callback(move |axis, notches| {
+                let _ = wheel_tx.send((axis, notches));
+            })
+            .run_with_source(source, |_| seen += 1);
+
+        assert_eq!(seen, 5);
+        assert_eq!(
+            wheel_rx.try_iter().collect::<Vec<_>>(),
+            vec![
+                (WheelAxis::Vertical, 1.0),
+                (WheelAxis::Vertical, -0.25),
+                (WheelAxis::Horizontal, 0.5)
+            ]
+        );
+    }
+
+    /// Test that the key set callback reports held set changes but not auto-repeat
+    #[test]
+    fn test_key_set_callback_reports_changes() {
+        let (tx, rx) = mpsc::channel();
+        let source = VecEventSource::new(vec![
+            vkey_event(0x11, false), // Ctrl down
+            vkey_event(0x41, false), // A down
+            vkey_event(0x41, false), // A auto-repeat
+            vkey_event(0x11, true),  // Ctrl up
+            vkey_event(0x42, true),  // B up, never seen down
+        ]);
+
+        ListenerBuilder::new()
+            .key_set_callback(move |change| {
+                let _ = tx.send(change);
+            })
+            .run_with_source(source, |_| {});
+
+        let changes: Vec<_> = rx.try_iter().collect();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].newly_pressed, vec![VirtualKey::Control]);
+        assert_eq!(changes[1].newly_pressed, vec![VirtualKey::A]);
+        let held = &changes[1].currently_held;
+        assert!(held.len() == 2 && held.contains(&VirtualKey::Control));
+        assert_eq!(
+            changes[2],
+            KeySetChange {
+                newly_pressed: vec![],
+                newly_released: vec![VirtualKey::Control],
+                currently_held: vec![VirtualKey::A],
+            }
+        );
+    }
+
+    /// Writer failing every write, like a full disk
+    struct FailingWriter;
+
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Err(std::io::Error::other("disk full"))
+        }
+    }
+
+    /// Test that a failing event log is reported once and stops logging, while events are
+    /// still delivered
+    #[test]
+    fn test_event_log_failure_keeps_delivering() {
+        let (tx, rx) = mpsc::channel();
+        let events = vec![mouse_event(1), mouse_event(2)];
+        let mut delivered = 0;
+        ListenerBuilder::new()
+            .log_to_writer(FailingWriter)
+            .error_callback(move |err| {
+                let _ = tx.send(err);
+            })
+            .run_with_source(VecEventSource::new(events), |_| delivered += 1);
+
+        assert_eq!(delivered, 2);
+        assert_eq!(rx.try_iter().count(), 1);
+    }
+
+    /// Test that keys outside the allowed keys or among the denied keys are dropped before
+    /// the state is tracked
+    #[test]
+    fn test_allowed_and_denied_keys() {
+        let mut delivered = Vec::new();
+        let reporter = ErrorReporter::new(None, true, Default::default());
+        let config = ListenerConfig {
+            allowed_keys: Some(vec![VirtualKey::A, VirtualKey::B]),
+            denied_keys: vec![VirtualKey::B],
+            ..Default::default()
+        };
+        let mut dispatcher = Dispatcher::new(
+            |event: RawInputEvent| delivered.push(event.button()),
+            &config,
+            reporter,
+            Default::default(),
+        );
+        let state = dispatcher.state();
+        for vkey in [0x41, 0x42, 0x43] {
+            dispatcher.dispatch(vkey_event(vkey, false));
+        }
+        dispatcher.dispatch(mouse_event(1));
+
+        assert!(!state.lock().unwrap().is_key_down(VirtualKey::C));
+        drop(dispatcher);
+        assert_eq!(delivered, vec![Some(VirtualKey::A.into()), None]);
+    }
+
+    /// Test that a polling dispatcher queues events instead of calling the callback and
+    /// reports when the queue overflows
+    #[test]
+    fn test_polling_queues_events() {
+        let config = ListenerConfig {
+            poll: true,
+            poll_capacity: Some(1),
+            ..Default::default()
+        };
+        let last_error = Arc::new(Mutex::new(None));
+        let reporter = ErrorReporter::new(None, true, last_error.clone());
+        let mut dispatcher = Dispatcher::new(
+            |_| panic!("Polled events must not reach the callback"),
+            &config,
+            reporter,
+            Default::default(),
+        );
+        let polled = dispatcher.polled();
+
+        dispatcher.dispatch(mouse_event(1));
+        assert!(last_error.lock().unwrap().is_none());
+        dispatcher.dispatch(mouse_event(2));
+        let error = last_error.lock().unwrap().clone().unwrap();
+        assert!(error.to_string().contains("(1 discarded"));
+        assert_eq!(polled.dropped(), 1);
+
+        let events = polled.drain();
+        assert!(matches!(&events[..], [RawInputEvent::Mouse(mouse)] if mouse.last_x == 2));
+    }
+
+    /// Test that ignoring auto-repeat delivers only the first press and the release
+    #[test]
+    fn test_ignore_auto_repeat() {
+        let burst = || {
+            VecEventSource::new(vec![
+                vkey_event(0x41, false), // A down
+                vkey_event(0x41, false), // A auto-repeat
+                vkey_event(0x41, false), // A auto-repeat
+                vkey_event(0x41, true),  // A up
+                vkey_event(0x41, false), // A down again
+            ])
+        };
+        let presses = |builder: ListenerBuilder| {
+            let mut delivered = Vec::new();
+            builder.run_with_source(burst(), |event| {
+                if let RawInputEvent::Keyboard(kbd) = event {
+                    delivered.push(kbd.key_up);
+                }
+            });
+            delivered
+        };
+
+        assert_eq!(presses(ListenerBuilder::new()).len(), 5);
+        assert_eq!(
+            presses(ListenerBuilder::new().ignore_auto_repeat(true)),
+            vec![false, true, false]
+        );
+    }
+
+    /// Test that deduplicating mouse buttons delivers only the transitions
+    #[test]
+    fn test_dedup_mouse_buttons() {
+        let burst = || {
+            VecEventSource::new(vec![
+                button_event(MOUSE_BUTTON_LEFT_DOWN, 0),
+                button_event(MOUSE_BUTTON_LEFT_DOWN, 0), // Duplicate
+                button_event(MOUSE_BUTTON_LEFT_DOWN, 5), // Duplicate that moved
+                button_event(MOUSE_BUTTON_LEFT_UP, 0),
+                button_event(MOUSE_BUTTON_LEFT_UP, 0), // Duplicate
+            ])
+        };
+        let actions = |builder: ListenerBuilder| {
+            let mut delivered = Vec::new();
+            builder.run_with_source(burst(), |event| {
+                if let RawInputEvent::Mouse(mouse) = event {
+                    delivered.push((mouse.button_action, mouse.last_x));
+                }
+            });
+            delivered
+        };
+
+        assert_eq!(actions(ListenerBuilder::new()).len(), 5);
+        let low_latency = ListenerBuilder::new()
+            .dedup_mouse_buttons(true)
+            .low_latency();
+        assert_eq!(actions(low_latency).len(), 5);
+        assert_eq!(
+            actions(ListenerBuilder::new().dedup_mouse_buttons(true)),
+            vec![
+                (MouseButtonAction::LeftDown, 0),
+                (MouseButtonAction::None, 5),
+                (MouseButtonAction::LeftUp, 0),
+            ]
+        );
+    }
+
+    /// Test that wheel deltas accumulate per axis, except while paused, until taken
+    #[test]
+    fn test_scroll_accumulates_until_taken() {
+        let reporter = ErrorReporter::new(None, true, Arc::new(Mutex::new(None)));
+        let mut dispatcher =
+            Dispatcher::new(|_| {}, &Default::default(), reporter, Default::default());
+        let scroll = dispatcher.scroll();
+
+        dispatcher.process(wheel_event(0x0400, 120));
+        dispatcher.process(wheel_event(0x0400, -30));
+        dispatcher.process(wheel_event(0x0800, 240));
+        dispatcher.process(mouse_event(7));
+        assert_eq!(scroll.take(), (90, 240));
+        assert_eq!(scroll.take(), (0, 0));
+
+        dispatcher.paused().store(true, Ordering::SeqCst);
+        dispatcher.process(wheel_event(0x0400, 120));
+        assert_eq!(scroll.take(), (0, 0));
+    }
+
+    /// Test that the ring buffer records delivered events only
+    #[test]
+    fn test_ring_buffer_records_delivered_events() {
+        let config = ListenerConfig {
+            ring_buffer: 8,
+            ..Default::default()
+        };
+        let reporter = ErrorReporter::new(None, true, Arc::new(Mutex::new(None)));
+        let mut dispatcher = Dispatcher::new(|_| {}, &config, reporter, Default::default());
+        let recent = dispatcher.recent();
+
+        dispatcher.dispatch(mouse_event(1));
+        dispatcher.paused().store(true, Ordering::SeqCst);
+        dispatcher.dispatch(mouse_event(2));
+        dispatcher.paused().store(false, Ordering::SeqCst);
+        dispatcher.dispatch(vkey_event(0x41, false));
+
+        let kinds: Vec<_> = recent.snapshot().iter().map(RawInputEvent::kind).collect();
+        assert_eq!(kinds, vec![EventKind::MouseMove, EventKind::KeyDown]);
+    }
+
+    /// Test that keyboard events carry the modifiers held after them, told apart by side
+    #[test]
+    fn test_keyboard_events_carry_modifiers() {
+        let source = VecEventSource::new(vec![
+            key_event(0x1D, 0, 0x11),           // Left Ctrl down
+            key_event(0x1D, 0x02, 0x11),        // Right Ctrl down
+            key_event(0x1D, 0x01, 0x11),        // Left Ctrl up
+            key_event(0x1E, 0, 0x41),           // A down
+            key_event(0x1D, 0x02 | 0x01, 0x11), // Right Ctrl up
+        ]);
+
+        let mut seen = Vec::new();
+        ListenerBuilder::new().run_with_source(source, |event| {
+            if let RawInputEvent::Keyboard(kbd) = event {
+                seen.push(kbd.modifiers);
+            }
+        });
+
+        let right = Modifiers::CTRL | Modifiers::RIGHT_CTRL;
+        assert_eq!(
+            seen,
+            vec![
+                Modifiers::CTRL | Modifiers::LEFT_CTRL,
+                right | Modifiers::LEFT_CTRL,
+                right,
+                right,
+                Modifiers::NONE
+            ]
+        );
+    }
+
+    /// Test that key events only pass while the required modifiers are held
+    #[test]
+    fn test_require_modifiers_gates_keys() {
+        let source = VecEventSource::new(vec![
+            vkey_event(0x41, false), // A: dropped
+            vkey_event(0x11, false), // Ctrl down: passes
+            vkey_event(0x41, false), // Ctrl+A: passes
+            mouse_event(3),          // mouse: always passes
+            vkey_event(0x11, true),  // Ctrl up: dropped
+            vkey_event(0x42, false), // B: dropped
+        ]);
+
+        let mut seen = Vec::new();
+        ListenerBuilder::new()
+            .require_modifiers(Modifiers::CTRL)
+            .run_with_source(source, |event| seen.push(event.kind()));
+
+        assert_eq!(
+            seen,
+            vec![EventKind::KeyDown, EventKind::KeyDown, EventKind::MouseMove]
+        );
+    }
+
+    /// Test that the first event callback fires once, with the first delivered event
+    #[test]
+    fn test_first_event_callback_fires_once() {
+        let (first_tx, first_rx) = mpsc::channel();
+        let source = VecEventSource::new(vec![
+            vkey_event(0x41, false), // A: gated, so not the first event
+            vkey_event(0x11, false), // Ctrl down: first delivered
+            mouse_event(3),
+        ]);
+
+        let mut seen = 0;
+        ListenerBuilder::new()
+            .require_modifiers(Modifiers::CTRL)
+            .on_first_event(move |event| {
+                let _ = first_tx.send(event.kind());
+            })
+            .run_with_source(source, |_| seen += 1);
+
+        assert_eq!(seen, 2);
+        assert_eq!(
+            first_rx.try_iter().collect::<Vec<_>>(),
+            vec![EventKind::KeyDown]
+        );
+    }
+
+    /// Test that the panic hotkey toggles pausing and that paused events are not delivered
+    #[test]
+    fn test_panic_hotkey_toggles_pause() {
+        let (panic_tx, panic_rx) = mpsc::channel();
+        let source = VecEventSource::new(vec![
+            vkey_event(0x41, false), // A down
+            vkey_event(0x13, false), // Pause down: pauses, still delivered
+            vkey_event(0x13, false), // auto-repeat: ignored
+            vkey_event(0x13, true),
+            vkey_event(0x41, true),  // A up: not delivered
+            vkey_event(0x13, false), // Pause down: resumes, not delivered
+            vkey_event(0x42, false), // B down
+        ]);
+
+        let mut seen = Vec::new();
+        ListenerBuilder::new()
+            .panic_hotkey(Hotkey::new([VirtualKey::Pause]))
+            .panic_hotkey_callback(move |paused| {
+                let _ = panic_tx.send(paused);
+            })
+            .run_with_source(source, |event| {
+                if let RawInputEvent::Keyboard(kbd) = event {
+                    seen.push((kbd.vkey, kbd.key_up));
+                }
+            });
+
+        assert_eq!(
+            seen,
+            vec![
+                (VirtualKey::A, false),
+                (VirtualKey::Pause, false),
+                (VirtualKey::B, false)
+            ]
+        );
+        assert_eq!(panic_rx.try_iter().collect::<Vec<_>>(), vec![true, false]);
+    }
+
+    /// Test that a hotkey is pressed and released exactly once per cycle, whatever the
+    /// order of the key presses and releases
+    #[test]
+    fn test_hotkey_press_and_release() {
+        let (tx, rx) = mpsc::channel();
+        let release_tx = tx.clone();
+        let source = VecEventSource::new(vec![
+            vkey_event(0x11, false), // Ctrl down
+            vkey_event(0x54, false), // T down: pressed
+            vkey_event(0x54, false), // auto-repeat: ignored
+            vkey_event(0x11, true),  // Ctrl up: released
+            vkey_event(0x11, false), // Ctrl down while T is held: pressed
+            vkey_event(0x54, true),  // T up: released
+            vkey_event(0x11, true),  // Ctrl up: nothing left to release
+        ]);
+
+        ListenerBuilder::new()
+            .hotkey_with_release(
+                Hotkey::new([VirtualKey::Control, VirtualKey::T]),
+                move || {
+                    let _ = tx.send(true);
+                },
+                move || {
+                    let _ = release_tx.send(false);
+                },
+            )
+            .run_with_source(source, |_| {});
+
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![true, false, true, false]
+        );
+    }
+
+    /// Test that a hotkey release reports how long the whole chord was held
+    #[test]
+    fn test_hotkey_held_for() {
+        let start = std::time::Instant::now();
+        let at = |vkey, key_up, millis| {
+            let mut event = vkey_event(vkey, key_up);
+            event.set_times(start + std::time::Duration::from_millis(millis), None);
+            event
+        };
+        let source = VecEventSource::new(vec![
+            at(0x11, false, 0),   // Ctrl down
+            at(0x54, false, 100), // T down: pressed
+            at(0x54, false, 600), // auto-repeat: still held
+            at(0x11, true, 900),  // Ctrl up: held for 800 ms
+            at(0x11, false, 950), // Ctrl down again: pressed anew
+            at(0x54, true, 1000), // T up: held for 50 ms
+        ]);
+
+        let (tx, rx) = mpsc::channel();
+        ListenerBuilder::new()
+            .hotkey_with_hold(
+                Hotkey::new([VirtualKey::Control, VirtualKey::T]),
+                || {},
+                move |held_for| {
+                    let _ = tx.send(held_for.as_millis());
+                },
+            )
+            .run_with_source(source, |_| {});
+
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![800, 50]);
+    }
+}
@@ -0,0 +1,37 @@
+//! DPI queries for scaling pointer coordinates
+
+use crate::{RawInputError, ffi};
+use windows::Win32::Foundation::HWND;
+
+/// DPI corresponding to 100% display scaling
+pub const DEFAULT_DPI: u32 = 96;
+
+/// Returns the system DPI (`GetDpiForSystem`), e.g. 144 at 150% scaling.
+///
+/// The result depends on the DPI awareness of the process: a DPI-unaware process always
+/// gets [`DEFAULT_DPI`], as Windows virtualizes its coordinates. Declare the process DPI
+/// aware (in its manifest or with `SetProcessDpiAwarenessContext`) to get the real value.
+/// Divide by [`DEFAULT_DPI`] to get the scale factor between physical and logical pixels.
+pub fn system_dpi() -> u32 {
+    ffi::system_dpi()
+}
+
+/// Returns the DPI of a window (`GetDpiForWindow`), such as the one raw input is attached to.
+///
+/// For per-monitor DPI aware processes this is the DPI of the monitor the window is on; for
+/// system aware processes it is the system DPI, and for DPI-unaware ones [`DEFAULT_DPI`].
+///
+/// # Arguments
+/// * `hwnd` - The window handle (`HWND`) as an integer
+///
+/// # Returns
+/// * `Ok(u32)` - The window's DPI
+/// * `Err(RawInputError)` - If the handle is not a valid window
+pub fn window_dpi(hwnd: isize) -> Result<u32, RawInputError> {
+    match ffi::window_dpi(HWND(hwnd as *mut _)) {
+        0 => Err(RawInputError::WinApiError(format!(
+            "GetDpiForWindow failed for window {hwnd:#x}"
+        ))),
+        dpi => Ok(dpi),
+    }
+}
@@ -1,24 +1,178 @@
 //! Event types for RawInput library (keyboard and mouse)
 
+use crate::device::RawInputDeviceHandle;
+use crate::hid::RawHidEvent;
 use crate::keyboard::RawKeyboardEvent;
-use crate::mouse::RawMouseEvent;
+use crate::mouse::{MouseButtonAction, RawMouseEvent};
+#[cfg(windows)]
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 /// Represents a raw input event received from the Windows Raw Input API.
 ///
-/// This enum wraps either a keyboard or mouse event, parsed into ergonomic Rust types.
+/// This enum wraps a keyboard, mouse or generic HID event, parsed into ergonomic Rust types.
 #[derive(Debug, Clone)]
 pub enum RawInputEvent {
     /// A keyboard event (key press or release)
     Keyboard(RawKeyboardEvent),
     /// A mouse event (movement, button, or wheel)
     Mouse(RawMouseEvent),
+    /// An event from another HID device, only delivered for additional
+    /// [`UsageRegistration`](crate::UsageRegistration)s
+    Hid(RawHidEvent),
 }
 
-#[cfg(test)]
+/// Lightweight discriminant describing what kind of input an event represents.
+///
+/// Obtained via [`RawInputEvent::kind`]; cheap to copy and match on, e.g. for routing or
+/// per-kind counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// A key was pressed (or auto-repeated)
+    KeyDown,
+    /// A key was released
+    KeyUp,
+    /// The mouse moved without a button or wheel action
+    MouseMove,
+    /// A mouse button was pressed or released
+    MouseButton,
+    /// The mouse wheel was scrolled (vertically or horizontally)
+    MouseWheel,
+    /// An event from a generic HID device
+    Hid,
+}
+
+impl RawInputEvent {
+    /// Returns the kind of this event.
+    ///
+    /// Mouse events that carry both movement and a button or wheel action are classified
+    /// by the action.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::Keyboard(kbd) if kbd.key_up => EventKind::KeyUp,
+            Self::Keyboard(_) => EventKind::KeyDown,
+            Self::Mouse(mouse) if mouse.wheel_axis.is_some() => EventKind::MouseWheel,
+            Self::Mouse(mouse) => match mouse.button_action {
+                MouseButtonAction::None => EventKind::MouseMove,
+                MouseButtonAction::WheelUp(_)
+                | MouseButtonAction::WheelDown(_)
+                | MouseButtonAction::WheelRight(_)
+                | MouseButtonAction::WheelLeft(_) => EventKind::MouseWheel,
+                _ => EventKind::MouseButton,
+            },
+            Self::Hid(_) => EventKind::Hid,
+        }
+    }
+
+    /// Returns the device that produced the event; see [`RawKeyboardEvent::device`].
+    pub fn device(&self) -> RawInputDeviceHandle {
+        match self {
+            Self::Keyboard(kbd) => kbd.device,
+            Self::Mouse(mouse) => mouse.device,
+            Self::Hid(hid) => hid.device,
+        }
+    }
+
+    /// Monotonic time at which the listener dequeued the event.
+    ///
+    /// Use this for ordering and for computing intervals between events; unlike
+    /// [`system_time`](Self::system_time) it never jumps backward.
+    pub fn timestamp(&self) -> Instant {
+        match self {
+            Self::Keyboard(kbd) => kbd.timestamp,
+            Self::Mouse(mouse) => mouse.timestamp,
+            Self::Hid(hid) => hid.timestamp,
+        }
+    }
+
+    /// Wall-clock (UTC) time at which the listener dequeued the event.
+    ///
+    /// Only present when enabled via
+    /// [`ListenerBuilder::capture_system_time`](crate::ListenerBuilder::capture_system_time).
+    /// Suitable for audit logs, but the system clock can jump (including backward) on NTP
+    /// or manual adjustments, so do not use it to measure intervals.
+    pub fn system_time(&self) -> Option<SystemTime> {
+        match self {
+            Self::Keyboard(kbd) => kbd.system_time,
+            Self::Mouse(mouse) => mouse.system_time,
+            Self::Hid(hid) => hid.system_time,
+        }
+    }
+
+    /// Tick count (`GetMessageTime`) of the WM_INPUT message the event was read from, in
+    /// milliseconds since system start, or 0 if it did not come from a message (e.g. a
+    /// decoded or synthesized event).
+    ///
+    /// This is when Windows posted the message, before it waited in the listener's queue,
+    /// so it orders events as the system saw them; it has a resolution of the system timer
+    /// (10 to 16 ms by default) and wraps around after 49.7 days, so compute intervals
+    /// with `wrapping_sub`. [`timestamp`](Self::timestamp) is precise and never wraps.
+    pub fn message_time(&self) -> u32 {
+        match self {
+            Self::Keyboard(kbd) => kbd.message_time,
+            Self::Mouse(mouse) => mouse.message_time,
+            Self::Hid(hid) => hid.message_time,
+        }
+    }
+
+    /// Executable file name of the process owning the foreground window when the event
+    /// was read, e.g. "notepad.exe".
+    ///
+    /// Only present when enabled via
+    /// [`ListenerBuilder::capture_foreground_process`](crate::ListenerBuilder::capture_foreground_process),
+    /// and if the process could be queried.
+    pub fn foreground_process(&self) -> Option<&str> {
+        match self {
+            Self::Keyboard(kbd) => kbd.foreground_process.as_deref(),
+            Self::Mouse(mouse) => mouse.foreground_process.as_deref(),
+            Self::Hid(hid) => hid.foreground_process.as_deref(),
+        }
+    }
+
+    /// Stamps the event with the tick count of its message.
+    #[cfg(windows)]
+    pub(crate) fn set_message_time(&mut self, message_time: u32) {
+        match self {
+            Self::Keyboard(kbd) => kbd.message_time = message_time,
+            Self::Mouse(mouse) => mouse.message_time = message_time,
+            Self::Hid(hid) => hid.message_time = message_time,
+        }
+    }
+
+    /// Stamps the event with its foreground process name.
+    #[cfg(windows)]
+    pub(crate) fn set_foreground_process(&mut self, name: Option<Arc<str>>) {
+        match self {
+            Self::Keyboard(kbd) => kbd.foreground_process = name,
+            Self::Mouse(mouse) => mouse.foreground_process = name,
+            Self::Hid(hid) => hid.foreground_process = name,
+        }
+    }
+
+    /// Stamps the event with its dequeue time(s).
+    pub(crate) fn set_times(&mut self, timestamp: Instant, system_time: Option<SystemTime>) {
+        match self {
+            Self::Keyboard(kbd) => {
+                kbd.timestamp = timestamp;
+                kbd.system_time = system_time;
+            }
+            Self::Mouse(mouse) => {
+                mouse.timestamp = timestamp;
+                mouse.system_time = system_time;
+            }
+            Self::Hid(hid) => {
+                hid.timestamp = timestamp;
+                hid.system_time = system_time;
+            }
+        }
+    }
+}
+
+#[cfg(all(test, windows))]
 mod tests {
     use super::*;
     use crate::keyboard::{KeyEventMessage, VirtualKey};
-    use crate::mouse::{MouseButtonAction, MouseMoveMode};
+    use crate::mouse::MouseMoveMode;
     use windows::Win32::UI::Input::{RAWKEYBOARD, RAWMOUSE};
 
     #[test]
@@ -34,11 +188,31 @@ mod tests {
 
         let event = RawKeyboardEvent::from(&raw);
         assert_eq!(event.make_code, 42);
-        assert_eq!(event.key_up, false);
-        assert_eq!(event.extended, true);
+        assert!(!event.key_up);
+        assert!(event.extended);
         assert_eq!(event.message, KeyEventMessage::KeyDown);
         assert_eq!(event.vkey, VirtualKey::A);
+        assert_eq!(event.raw_vkey, 65);
         assert_eq!(event.extra_information, 123);
+        assert!(event.flags.e0_prefix);
+        assert!(!event.flags.e1_prefix);
+    }
+
+    #[test]
+    fn test_keyboard_event_resolves_zero_vkey() {
+        let raw = RAWKEYBOARD {
+            MakeCode: 0x1E, // Scan code of the 'A' position on a US layout
+            Flags: 0,
+            Reserved: 0,
+            VKey: 0,
+            Message: 256,
+            ExtraInformation: 0,
+        };
+
+        let event = RawKeyboardEvent::from(&raw);
+        assert_eq!(event.raw_vkey, 0);
+        // The resolved key depends on the active layout, but must not be lost
+        assert_ne!(event.vkey, VirtualKey::Unknown(0));
     }
 
     #[test]
@@ -65,4 +239,71 @@ mod tests {
         assert_eq!(event.last_y, -20);
         assert_eq!(event.extra_information, 0xDEADBEEF);
     }
+
+    #[test]
+    fn test_event_kind() {
+        let mut kbd: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        assert_eq!(
+            RawInputEvent::Keyboard(RawKeyboardEvent::from(&kbd)).kind(),
+            EventKind::KeyDown
+        );
+        kbd.Flags = 1; // RI_KEY_BREAK
+        assert_eq!(
+            RawInputEvent::Keyboard(RawKeyboardEvent::from(&kbd)).kind(),
+            EventKind::KeyUp
+        );
+
+        let mouse: RAWMOUSE = unsafe { std::mem::zeroed() };
+        let mut event = RawMouseEvent::from(&mouse);
+        assert_eq!(
+            RawInputEvent::Mouse(event.clone()).kind(),
+            EventKind::MouseMove
+        );
+        event.button_action = MouseButtonAction::RightUp;
+        assert_eq!(
+            RawInputEvent::Mouse(event.clone()).kind(),
+            EventKind::MouseButton
+        );
+        event.button_action = MouseButtonAction::WheelLeft(3);
+        assert_eq!(RawInputEvent::Mouse(event).kind(), EventKind::MouseWheel);
+    }
+
+    #[test]
+    fn test_event_times() {
+        let raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        let mut event = RawInputEvent::Mouse(RawMouseEvent::from(&raw));
+        assert!(event.system_time().is_none());
+        assert_eq!(event.message_time(), 0);
+
+        let timestamp = Instant::now();
+        let system_time = SystemTime::now();
+        event.set_times(timestamp, Some(system_time));
+        assert_eq!(event.timestamp(), timestamp);
+        assert_eq!(event.system_time(), Some(system_time));
+
+        event.set_message_time(123_456);
+        assert_eq!(event.message_time(), 123_456);
+    }
+
+    #[test]
+    fn test_mouse_event_pen_touch_signature() {
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+
+        raw.ulExtraInformation = 0xFF515780; // MOUSEEVENTF_FROMTOUCH
+        let event = RawMouseEvent::from(&raw);
+        assert!(event.is_from_pen_or_touch());
+        assert!(event.is_from_touch());
+        assert!(!event.is_from_pen());
+
+        raw.ulExtraInformation = 0xFF515700; // Pen
+        let event = RawMouseEvent::from(&raw);
+        assert!(event.is_from_pen());
+        assert!(!event.is_from_touch());
+
+        raw.ulExtraInformation = 0xDEADBEEF; // Regular mouse
+        let event = RawMouseEvent::from(&raw);
+        assert!(!event.is_from_pen_or_touch());
+        assert!(!event.is_from_touch());
+        assert!(!event.is_from_pen());
+    }
 }
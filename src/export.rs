@@ -0,0 +1,254 @@
+//! Export of captured events to CSV for analysis tools
+
+use crate::RawInputEvent;
+use std::fmt::Write as _;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Longest time the rows of an [`EventLog`] stay buffered while events arrive
+const LOG_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Header row written by [`write_events_csv`]; the columns are documented there.
+const CSV_HEADER: &str =
+    "time_ms,system_time_ms,device,kind,down,vkey,code,make_code,button,x,y,wheel_delta,hid_data";
+
+/// Writes events as CSV, one row per event after a header row, e.g. for a spreadsheet or
+/// `pandas.read_csv`.
+///
+/// The columns are stable; cells that do not apply to an event are left empty:
+///
+/// | Column | Content |
+/// |---|---|
+/// | `time_ms` | Milliseconds since the first event, from the monotonic timestamps (microsecond precision) |
+/// | `system_time_ms` | Milliseconds since the Unix epoch, if [`capture_system_time`](crate::ListenerBuilder::capture_system_time) was enabled |
+/// | `device` | Raw device handle (0 for injected input), see [`RawInputEvent::device`] |
+/// | `kind` | [`EventKind`](crate::EventKind) name: `KeyDown`, `KeyUp`, `MouseMove`, `MouseButton`, `MouseWheel` or `Hid` |
+/// | `down` | `1` for key and button presses, `0` for releases |
+/// | `vkey` | [`VirtualKey`](crate::VirtualKey) name, e.g. `A` or `Unknown(255)` |
+/// | `code` | Layout-independent [`KeyCode`](crate::KeyCode) name (W3C `code` value), e.g. `KeyA` |
+/// | `make_code` | Scan code |
+/// | `button` | [`MouseButton`](crate::MouseButton) name of a button event, e.g. `Left` |
+/// | `x`, `y` | Mouse movement (or absolute position, see [`RawMouseEvent::move_mode`](crate::RawMouseEvent::move_mode)) |
+/// | `wheel_delta` | Signed wheel delta of a wheel event (120 per notch) |
+/// | `hid_data` | Report bytes of a HID event, in hexadecimal |
+///
+/// # Returns
+/// * `Ok(())` - Every event was written
+/// * `Err(io::Error)` - If writing to `writer` fails
+pub fn write_events_csv<W: Write>(events: &[RawInputEvent], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{CSV_HEADER}")?;
+    let Some(start) = events.first().map(RawInputEvent::timestamp) else {
+        return writer.flush();
+    };
+    let mut row = String::new();
+    for event in events {
+        write_row(&mut row, event, start);
+        writeln!(writer, "{row}")?;
+    }
+    writer.flush()
+}
+
+/// Formats the CSV row of `event` into `row`, timed relative to `start`.
+fn write_row(row: &mut String, event: &RawInputEvent, start: Instant) {
+    row.clear();
+    let elapsed = event
+        .timestamp()
+        .saturating_duration_since(start)
+        .as_micros() as f64
+        / 1000.0;
+    let _ = write!(row, "{elapsed:.3},");
+    if let Some(ms) = event
+        .system_time()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_millis())
+    {
+        let _ = write!(row, "{ms}");
+    }
+    let _ = write!(row, ",{},{:?},", event.device().as_raw(), event.kind());
+    match event {
+        RawInputEvent::Keyboard(kbd) => {
+            let _ = write!(
+                row,
+                "{},{:?},{:?},{},,,,,",
+                u8::from(!kbd.key_up),
+                kbd.vkey,
+                kbd.key_code(),
+                kbd.make_code
+            );
+        }
+        RawInputEvent::Mouse(mouse) => {
+            if let Some((button, down)) = mouse.button_action.button_transition() {
+                let _ = write!(row, "{},,,,{button:?}", u8::from(down));
+            } else {
+                row.push_str(",,,,");
+            }
+            let _ = write!(row, ",{},{},", mouse.last_x, mouse.last_y);
+            if mouse.wheel_axis.is_some() {
+                let _ = write!(row, "{}", mouse.wheel_delta);
+            }
+            row.push(',');
+        }
+        RawInputEvent::Hid(hid) => {
+            row.push_str(",,,,,,,,");
+            hid.data.iter().for_each(|byte| {
+                let _ = write!(row, "{byte:02x}");
+            });
+        }
+    }
+}
+
+/// CSV log written by the listener as events are delivered, as set by
+/// [`ListenerBuilder::log_to`](crate::ListenerBuilder::log_to).
+///
+/// The output is that of [`write_events_csv`] over the delivered events. Rows are
+/// buffered and flushed at most [`LOG_FLUSH_INTERVAL`] after they were written (judged
+/// by the events' timestamps), and when the listener stops.
+pub(crate) struct EventLog {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    /// Timestamp of the first event, which the header is written with
+    start: Option<Instant>,
+    /// Timestamp of the event written before the last flush
+    flushed_at: Option<Instant>,
+    row: String,
+}
+
+impl EventLog {
+    pub(crate) fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer: BufWriter::new(writer),
+            start: None,
+            flushed_at: None,
+            row: String::new(),
+        }
+    }
+
+    /// Appends the row of `event`, flushing the buffer if the last flush is due.
+    pub(crate) fn write(&mut self, event: &RawInputEvent) -> io::Result<()> {
+        let timestamp = event.timestamp();
+        let start = match self.start {
+            Some(start) => start,
+            None => {
+                writeln!(self.writer, "{CSV_HEADER}")?;
+                *self.start.insert(timestamp)
+            }
+        };
+        write_row(&mut self.row, event, start);
+        writeln!(self.writer, "{}", self.row)?;
+        if self
+            .flushed_at
+            .is_none_or(|at| timestamp.saturating_duration_since(at) >= LOG_FLUSH_INTERVAL)
+        {
+            self.flushed_at = Some(timestamp);
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes out the buffered rows (and the header if no event was logged).
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        if self.start.is_none() {
+            writeln!(self.writer, "{CSV_HEADER}")?;
+            self.start = Some(Instant::now());
+        }
+        self.writer.flush()
+    }
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+    use crate::{RawHidEvent, RawKeyboardEvent, RawMouseEvent};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use windows::Win32::UI::Input::{RAWKEYBOARD, RAWMOUSE};
+
+    /// Test the rows written for each kind of event
+    #[test]
+    fn test_write_events_csv() {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.VKey = 0x41;
+        raw.MakeCode = 0x1E;
+        let key = RawKeyboardEvent::from(&raw);
+
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        raw.Anonymous.Anonymous.usButtonFlags = 0x0001; // RI_MOUSE_LEFT_BUTTON_DOWN
+        raw.lLastX = -3;
+        raw.lLastY = 4;
+        let mut click = RawMouseEvent::from(&raw);
+        click.timestamp = key.timestamp + Duration::from_micros(1500);
+
+        let hid = RawHidEvent {
+            report_size: 2,
+            report_count: 1,
+            data: vec![0x01, 0xAB],
+            device: Default::default(),
+            timestamp: key.timestamp + Duration::from_millis(2),
+            system_time: Some(UNIX_EPOCH + Duration::from_millis(1234)),
+            message_time: 0,
+            foreground_process: None,
+        };
+
+        let mut csv = Vec::new();
+        let events = [
+            RawInputEvent::Keyboard(key),
+            RawInputEvent::Mouse(click),
+            RawInputEvent::Hid(hid),
+        ];
+        write_events_csv(&events, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], "0.000,,0,KeyDown,1,A,KeyA,30,,,,,");
+        assert_eq!(lines[2], "1.500,,0,MouseButton,1,,,,Left,-3,4,,");
+        assert_eq!(lines[3], "2.000,1234,0,Hid,,,,,,,,,01ab");
+        let columns = CSV_HEADER.split(',').count();
+        assert!(lines.iter().all(|line| line.split(',').count() == columns));
+    }
+
+    /// Buffer shared between a test and the event log writing to it
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Test that the event log matches the exported CSV and flushes once a second
+    #[test]
+    fn test_event_log() {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.VKey = 0x41;
+        let down = RawKeyboardEvent::from(&raw);
+        let mut up = down.clone();
+        up.key_up = true;
+        up.timestamp += Duration::from_millis(500);
+        let mut again = down.clone();
+        again.timestamp += Duration::from_millis(1500);
+        let events = [
+            RawInputEvent::Keyboard(down),
+            RawInputEvent::Keyboard(up),
+            RawInputEvent::Keyboard(again),
+        ];
+
+        let buffer = SharedBuffer::default();
+        let mut log = EventLog::new(Box::new(buffer.clone()));
+        let logged = || String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        log.write(&events[0]).unwrap();
+        assert_eq!(logged().lines().count(), 2);
+        log.write(&events[1]).unwrap(); // Buffered
+        assert_eq!(logged().lines().count(), 2);
+        log.write(&events[2]).unwrap();
+        assert_eq!(logged().lines().count(), 4);
+        log.flush().unwrap();
+
+        let mut csv = Vec::new();
+        write_events_csv(&events, &mut csv).unwrap();
+        assert_eq!(logged(), String::from_utf8(csv).unwrap());
+    }
+}
@@ -3,9 +3,12 @@
 //! This module uses the `windows` crate for Win32 API access and provides the
 //! necessary types and functions for device registration and event handling.
 
+use windows::Win32::Foundation::ERROR_INSUFFICIENT_BUFFER;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::UI::Input::{
-    RAWINPUTDEVICE, RAWINPUTHEADER, RIDEV_INPUTSINK, RIDEV_NOLEGACY, RegisterRawInputDevices,
+    GetRegisteredRawInputDevices, RAWINPUTDEVICE, RAWINPUTDEVICE_FLAGS, RAWINPUTHEADER,
+    RIDEV_DEVNOTIFY, RIDEV_EXINPUTSINK, RIDEV_INPUTSINK, RIDEV_NOLEGACY, RIDEV_PAGEONLY,
+    RIDEV_REMOVE, RegisterRawInputDevices,
 };
 use windows::core::Result as WinResult;
 
@@ -14,37 +17,176 @@ pub const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
 pub const HID_USAGE_GENERIC_MOUSE: u16 = 0x02;
 pub const HID_USAGE_GENERIC_KEYBOARD: u16 = 0x06;
 
-/// Registers for raw input from keyboard and mouse devices.
-pub fn register_keyboard_mouse(hwnd: Option<HWND>) -> WinResult<()> {
-    let devices = [
-        RAWINPUTDEVICE {
-            usUsagePage: HID_USAGE_PAGE_GENERIC,
-            usUsage: HID_USAGE_GENERIC_MOUSE,
-            dwFlags: RIDEV_NOLEGACY | RIDEV_INPUTSINK,
-            hwndTarget: hwnd.unwrap_or_default(),
-        },
-        RAWINPUTDEVICE {
-            usUsagePage: HID_USAGE_PAGE_GENERIC,
-            usUsage: HID_USAGE_GENERIC_KEYBOARD,
-            dwFlags: RIDEV_NOLEGACY | RIDEV_INPUTSINK,
-            hwndTarget: hwnd.unwrap_or_default(),
-        },
-    ];
-    unsafe { RegisterRawInputDevices(&devices, std::mem::size_of::<RAWINPUTDEVICE>() as u32) }
+/// Keyboard/mouse flags for the hidden listener window: background capture as a pure
+/// observer. Registrations apply to the whole process, so legacy messages keep flowing to
+/// the application's own windows (see [`no_legacy`] to suppress them).
+pub const LISTENER_FLAGS: RAWINPUTDEVICE_FLAGS = RIDEV_INPUTSINK;
+
+/// Keyboard/mouse flags for an application window: background capture, but legacy messages
+/// (WM_KEYDOWN, WM_MOUSEMOVE, ...) keep flowing so the window still works normally.
+pub const ATTACHED_FLAGS: RAWINPUTDEVICE_FLAGS = RIDEV_INPUTSINK;
+
+/// Adds `RIDEV_NOLEGACY` to keyboard/mouse flags, suppressing the legacy messages
+/// (WM_KEYDOWN, WM_CHAR, WM_MOUSEMOVE, ...) of every window of the process.
+pub fn no_legacy(flags: RAWINPUTDEVICE_FLAGS) -> RAWINPUTDEVICE_FLAGS {
+    RAWINPUTDEVICE_FLAGS(flags.0 | RIDEV_NOLEGACY.0)
+}
+
+/// Swaps `RIDEV_INPUTSINK` for `RIDEV_EXINPUTSINK` in keyboard/mouse flags.
+///
+/// The two background capture flags are mutually exclusive, so the result never has both.
+pub fn extended_sink(flags: RAWINPUTDEVICE_FLAGS) -> RAWINPUTDEVICE_FLAGS {
+    RAWINPUTDEVICE_FLAGS((flags.0 & !RIDEV_INPUTSINK.0) | RIDEV_EXINPUTSINK.0)
+}
+
+/// Removes the background capture flags from keyboard/mouse flags, so that input is only
+/// received while the target window is in the foreground.
+pub fn without_sink(flags: RAWINPUTDEVICE_FLAGS) -> RAWINPUTDEVICE_FLAGS {
+    RAWINPUTDEVICE_FLAGS(flags.0 & !(RIDEV_INPUTSINK.0 | RIDEV_EXINPUTSINK.0))
+}
+
+/// Keeps only the background capture flag of keyboard/mouse flags, as `RIDEV_NOLEGACY`
+/// applies to keyboard and mouse only.
+fn sink_only(flags: RAWINPUTDEVICE_FLAGS) -> RAWINPUTDEVICE_FLAGS {
+    RAWINPUTDEVICE_FLAGS(flags.0 & (RIDEV_INPUTSINK.0 | RIDEV_EXINPUTSINK.0))
+}
+
+/// Builds the registration entry for a generic desktop usage.
+fn generic_device(usage: u16, flags: RAWINPUTDEVICE_FLAGS, hwnd: Option<HWND>) -> RAWINPUTDEVICE {
+    RAWINPUTDEVICE {
+        usUsagePage: HID_USAGE_PAGE_GENERIC,
+        usUsage: usage,
+        dwFlags: flags,
+        hwndTarget: hwnd.unwrap_or_default(),
+    }
+}
+
+/// Registers a single raw input device.
+fn register_device(device: RAWINPUTDEVICE) -> WinResult<()> {
+    unsafe { RegisterRawInputDevices(&[device], std::mem::size_of::<RAWINPUTDEVICE>() as u32) }
+}
+
+/// Registers for raw input from mouse devices.
+pub fn register_mouse(hwnd: Option<HWND>, flags: RAWINPUTDEVICE_FLAGS) -> WinResult<()> {
+    register_device(generic_device(HID_USAGE_GENERIC_MOUSE, flags, hwnd))
+}
+
+/// Registers for raw input from keyboard devices.
+///
+/// Keyboards are registered with `RIDEV_DEVNOTIFY`, so that the target window receives
+/// `WM_INPUT_DEVICE_CHANGE` when one is removed and the keys it held can be released.
+pub fn register_keyboard(hwnd: Option<HWND>, flags: RAWINPUTDEVICE_FLAGS) -> WinResult<()> {
+    register_device(generic_device(
+        HID_USAGE_GENERIC_KEYBOARD,
+        flags | RIDEV_DEVNOTIFY,
+        hwnd,
+    ))
+}
+
+/// Registers for raw input from an arbitrary usage page / usage with background capture.
+///
+/// `flags` are the keyboard/mouse flags, of which only the background capture flag is used.
+/// With `page_only`, every top level collection on the usage page is registered
+/// (`RIDEV_PAGEONLY`), in which case `usage` must be 0.
+pub fn register_usage(
+    usage_page: u16,
+    usage: u16,
+    page_only: bool,
+    flags: RAWINPUTDEVICE_FLAGS,
+    hwnd: Option<HWND>,
+) -> WinResult<()> {
+    let mut flags = sink_only(flags);
+    if page_only {
+        flags |= RIDEV_PAGEONLY;
+    }
+    register_device(RAWINPUTDEVICE {
+        usUsagePage: usage_page,
+        usUsage: usage,
+        dwFlags: flags,
+        hwndTarget: hwnd.unwrap_or_default(),
+    })
+}
+
+/// Stops raw input for a usage page / usage (`RIDEV_REMOVE`).
+///
+/// The registration is removed for the whole process, whichever window it targeted.
+pub fn unregister_usage(usage_page: u16, usage: u16, page_only: bool) -> WinResult<()> {
+    let mut flags: RAWINPUTDEVICE_FLAGS = RIDEV_REMOVE;
+    if page_only {
+        flags |= RIDEV_PAGEONLY;
+    }
+    register_device(RAWINPUTDEVICE {
+        usUsagePage: usage_page,
+        usUsage: usage,
+        dwFlags: flags,
+        hwndTarget: HWND::default(),
+    })
+}
+
+/// Returns the raw input registrations of the calling process.
+pub fn registered_devices() -> WinResult<Vec<RAWINPUTDEVICE>> {
+    let size = std::mem::size_of::<RAWINPUTDEVICE>() as u32;
+    let mut count = 0u32;
+    loop {
+        let mut devices = vec![RAWINPUTDEVICE::default(); count as usize];
+        let buffer = (!devices.is_empty()).then_some(devices.as_mut_ptr());
+        let rc = unsafe { GetRegisteredRawInputDevices(buffer, &mut count, size) };
+        if rc != u32::MAX {
+            // Without a buffer, only `count` is filled in
+            if buffer.is_none() && count > 0 {
+                continue;
+            }
+            devices.truncate(rc as usize);
+            return Ok(devices);
+        }
+        // Fails with ERROR_INSUFFICIENT_BUFFER when the buffer is missing or too small (a
+        // registration was added in between); `count` then holds the required number
+        let err = windows::core::Error::from_win32();
+        if err.code() != ERROR_INSUFFICIENT_BUFFER.to_hresult() {
+            return Err(err);
+        }
+    }
 }
 
 use std::ffi::c_void;
 use windows::Win32::Foundation::LPARAM;
-use windows::Win32::UI::Input::{GetRawInputData, HRAWINPUT, RAWINPUT, RID_INPUT};
+use windows::Win32::UI::Input::{GetRawInputData, HRAWINPUT, RID_HEADER, RID_INPUT};
 
-/// Reads and parses a raw input event from a WM_INPUT message.
+/// Reads only the header of the raw input of a WM_INPUT message (`RID_HEADER`), without
+/// copying its data.
+pub fn read_raw_input_header(lparam: LPARAM) -> WinResult<RAWINPUTHEADER> {
+    let mut header = RAWINPUTHEADER::default();
+    let mut size = std::mem::size_of::<RAWINPUTHEADER>() as u32;
+    let rc = unsafe {
+        GetRawInputData(
+            HRAWINPUT(lparam.0 as *mut c_void),
+            RID_HEADER,
+            Some(&mut header as *mut RAWINPUTHEADER as *mut c_void),
+            &mut size,
+            std::mem::size_of::<RAWINPUTHEADER>() as u32,
+        )
+    };
+    if rc == u32::MAX {
+        return Err(windows::core::Error::from_win32());
+    }
+    Ok(header)
+}
+
+/// Reads a raw input event from a WM_INPUT message into `buffer`.
+///
+/// The packet is read as raw bytes because its size varies: HID packets carry
+/// `dwSizeHid * dwCount` bytes of report data after the header. The buffer is sized from
+/// a preliminary size query, so packets holding several reports are read completely. The
+/// buffer's allocation is reused, so reading into the same buffer repeatedly does not
+/// allocate once it is large enough.
 ///
 /// # Arguments
 /// * `lparam` - The LPARAM from the WM_INPUT message.
-///
-/// # Returns
-/// A RAWINPUT structure on success, or an error.
-pub fn read_raw_input_event(lparam: LPARAM) -> Result<RAWINPUT, windows::core::Error> {
+/// * `buffer` - Receives the complete RAWINPUT packet (header and data).
+pub fn read_raw_input_event(
+    lparam: LPARAM,
+    buffer: &mut Vec<u8>,
+) -> Result<(), windows::core::Error> {
     unsafe {
         let hrawinput = HRAWINPUT(lparam.0 as *mut c_void);
         let mut size = 0u32;
@@ -60,11 +202,12 @@ pub fn read_raw_input_event(lparam: LPARAM) -> Result<RAWINPUT, windows::core::E
             return Err(windows::core::Error::from_win32());
         }
 
-        let mut raw_input_data_buffer = vec![0u8; size as usize];
+        buffer.clear();
+        buffer.resize(size as usize, 0);
         let rc = GetRawInputData(
             hrawinput,
             RID_INPUT,
-            Some(raw_input_data_buffer.as_mut_ptr() as *mut c_void),
+            Some(buffer.as_mut_ptr() as *mut c_void),
             &mut size,
             std::mem::size_of::<RAWINPUTHEADER>() as u32,
         );
@@ -73,10 +216,585 @@ pub fn read_raw_input_event(lparam: LPARAM) -> Result<RAWINPUT, windows::core::E
             return Err(windows::core::Error::from_win32());
         }
 
-        let raw_input_data_ptr = raw_input_data_buffer.as_ptr() as *const RAWINPUT;
-        let raw_input_data = raw_input_data_ptr.read();
-        Ok(raw_input_data)
+        buffer.truncate(rc as usize);
+        Ok(())
+    }
+}
+
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::UI::Input::{GetRawInputDeviceInfoW, RID_DEVICE_INFO, RIDI_DEVICEINFO};
+
+/// Queries the device information (type and type-specific details) of a raw input device.
+///
+/// # Arguments
+/// * `device` - The device handle, as found in `RAWINPUTHEADER.hDevice`.
+///
+/// # Returns
+/// A RID_DEVICE_INFO structure on success, or an error.
+pub fn device_info(device: HANDLE) -> Result<RID_DEVICE_INFO, windows::core::Error> {
+    let mut info = RID_DEVICE_INFO {
+        cbSize: std::mem::size_of::<RID_DEVICE_INFO>() as u32,
+        ..Default::default()
+    };
+    let mut size = info.cbSize;
+    let rc = unsafe {
+        GetRawInputDeviceInfoW(
+            Some(device),
+            RIDI_DEVICEINFO,
+            Some(&mut info as *mut RID_DEVICE_INFO as *mut c_void),
+            &mut size,
+        )
+    };
+
+    if rc == u32::MAX || rc == 0 {
+        return Err(windows::core::Error::from_win32());
+    }
+    Ok(info)
+}
+
+use windows::Win32::Foundation::{CloseHandle, E_ACCESSDENIED};
+use windows::Win32::Security::{
+    GetSidSubAuthority, GetSidSubAuthorityCount, GetTokenInformation, TOKEN_MANDATORY_LABEL,
+    TOKEN_QUERY, TokenIntegrityLevel,
+};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION, QueryFullProcessImageNameW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, GetMessageTime, GetWindowThreadProcessId,
+};
+
+/// Returns the mandatory integrity level RID of a process (e.g. 0x2000 for medium, 0x3000
+/// for high integrity).
+pub fn process_integrity_level(process: HANDLE) -> WinResult<u32> {
+    let mut token = HANDLE::default();
+    unsafe { OpenProcessToken(process, TOKEN_QUERY, &mut token) }?;
+
+    let result = (|| unsafe {
+        let mut size = 0u32;
+        // The first call only reports the required size and always fails
+        let _ = GetTokenInformation(token, TokenIntegrityLevel, None, 0, &mut size);
+        // u64 elements keep the pointer-containing label correctly aligned
+        let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+        GetTokenInformation(
+            token,
+            TokenIntegrityLevel,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            size,
+            &mut size,
+        )?;
+        let label = &*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL);
+        let count = *GetSidSubAuthorityCount(label.Label.Sid);
+        Ok(*GetSidSubAuthority(label.Label.Sid, u32::from(count) - 1))
+    })();
+
+    unsafe {
+        let _ = CloseHandle(token);
+    }
+    result
+}
+
+/// Returns the integrity level of the current process.
+pub fn current_integrity_level() -> WinResult<u32> {
+    process_integrity_level(unsafe { GetCurrentProcess() })
+}
+
+/// Returns true if the foreground window belongs to a process with a higher integrity
+/// level than `own_level`.
+///
+/// A process whose token cannot be opened for lack of access is assumed to be of higher
+/// integrity; other failures (including no foreground window) count as not higher.
+pub fn foreground_integrity_higher(own_level: u32) -> bool {
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut pid)) };
+    if pid == 0 {
+        return false;
+    }
+
+    let level =
+        unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.and_then(|process| {
+            let level = process_integrity_level(process);
+            unsafe {
+                let _ = CloseHandle(process);
+            }
+            level
+        });
+    match level {
+        Ok(level) => level > own_level,
+        Err(e) => e.code() == E_ACCESSDENIED,
+    }
+}
+
+use windows::core::PWSTR;
+
+/// Returns the tick count of the last message retrieved by the calling thread.
+pub fn message_time() -> u32 {
+    unsafe { GetMessageTime() as u32 }
+}
+
+/// Returns the foreground window as an integer, 0 if there is none.
+pub fn foreground_window() -> isize {
+    unsafe { GetForegroundWindow() }.0 as isize
+}
+
+/// Returns the file name of the executable of the process owning `hwnd` (e.g.
+/// "notepad.exe"), or None if the process cannot be queried.
+pub fn window_process_name(hwnd: isize) -> Option<String> {
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(HWND(hwnd as *mut _), Some(&mut pid)) };
+    if pid == 0 {
+        return None;
+    }
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+    let mut path = [0u16; 1024];
+    let mut len = path.len() as u32;
+    let result = unsafe {
+        QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(path.as_mut_ptr()),
+            &mut len,
+        )
+    };
+    unsafe {
+        let _ = CloseHandle(process);
+    }
+    result.ok()?;
+    let path = String::from_utf16_lossy(&path[..len as usize]);
+    let name = path.rsplit('\\').next().unwrap_or(&path);
+    Some(name.to_string())
+}
+
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, GetKeyNameTextW, GetKeyState, GetKeyboardLayout, HKL, INPUT,
+    MAPVK_VK_TO_VSC_EX, MAPVK_VSC_TO_VK, MapVirtualKeyExW, MapVirtualKeyW, SendInput, ToUnicodeEx,
+};
+
+/// Inserts inputs into the input stream with a single `SendInput` call.
+///
+/// # Returns
+/// The number of inputs inserted; fewer than `inputs.len()` means input was blocked.
+pub fn send_input(inputs: &[INPUT]) -> u32 {
+    unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) }
+}
+
+/// Returns the keyboard layout active for the foreground window's thread.
+pub fn foreground_keyboard_layout() -> HKL {
+    unsafe {
+        let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        GetKeyboardLayout(thread)
+    }
+}
+
+/// Maps a scan code, with its prefix in the high byte (e.g. `0xE01D`), to the virtual key
+/// code `layout` assigns it (0 if none).
+pub fn scan_code_to_vkey(scan_code: u32, layout: HKL) -> u16 {
+    unsafe { MapVirtualKeyExW(scan_code, MAPVK_VSC_TO_VK, Some(layout)) as u16 }
+}
+
+/// Maps a virtual key code to its scan code with the calling thread's keyboard layout, with
+/// the E0/E1 prefix in the high byte (0 if it has none).
+pub fn vkey_to_scan_code(vkey: u16) -> u32 {
+    unsafe { MapVirtualKeyW(u32::from(vkey), MAPVK_VK_TO_VSC_EX) }
+}
+
+/// Returns whether Windows reports the key as physically down right now
+/// (`GetAsyncKeyState`).
+pub fn async_key_down(vkey: u16) -> bool {
+    unsafe { GetAsyncKeyState(i32::from(vkey)) as u16 & 0x8000 != 0 }
+}
+
+/// Returns whether a toggle key (Caps Lock, Num Lock, Scroll Lock) is toggled on, as seen
+/// by the calling thread's keyboard state.
+pub fn key_toggled(vkey: u16) -> bool {
+    unsafe { GetKeyState(i32::from(vkey)) & 1 != 0 }
+}
+
+/// Returns the localized name of the key with scan code `scan_code` (without prefix),
+/// with the extended bit set for E0 keys, or None if `GetKeyNameTextW` has no name for it.
+pub fn key_name_text(scan_code: u16, extended: bool) -> Option<String> {
+    // Scan code in bits 16-23 and the extended bit in bit 24, as in a WM_KEYDOWN lParam
+    let lparam = (i32::from(scan_code & 0xFF) << 16) | (i32::from(extended) << 24);
+    let mut buffer = [0u16; 64];
+    let len = unsafe { GetKeyNameTextW(lparam, &mut buffer) };
+    (len > 0).then(|| String::from_utf16_lossy(&buffer[..len as usize]))
+}
+
+/// Translates a key press into UTF-16 text with `ToUnicodeEx`.
+///
+/// Dead keys are buffered in the calling thread's keyboard state and combined with the
+/// next translated key.
+///
+/// # Returns
+/// The number of UTF-16 units written to `buffer`, 0 if the key produces no text, or a
+/// negative value if the key is a dead key.
+pub fn to_unicode(
+    vkey: u16,
+    scan_code: u16,
+    key_state: &[u8; 256],
+    buffer: &mut [u16],
+    layout: HKL,
+) -> i32 {
+    unsafe {
+        ToUnicodeEx(
+            u32::from(vkey),
+            u32::from(scan_code),
+            key_state,
+            buffer,
+            0,
+            Some(layout),
+        )
+    }
+}
+
+use windows::Win32::Foundation::RECT;
+use windows::Win32::UI::WindowsAndMessaging::{
+    ClipCursor, GetClipCursor, GetSystemMetrics, SM_CXSCREEN, SM_CXVIRTUALSCREEN, SM_CYSCREEN,
+    SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+};
+
+/// Returns the rectangle the cursor is confined to, in screen coordinates.
+///
+/// When the cursor is not confined, this is the rectangle of the whole virtual screen.
+pub fn clip_cursor_rect() -> WinResult<RECT> {
+    let mut rect = RECT::default();
+    unsafe { GetClipCursor(&mut rect) }?;
+    Ok(rect)
+}
+
+/// Returns the bounding rectangle of all monitors (the virtual screen), in screen coordinates.
+pub fn virtual_screen_rect() -> RECT {
+    unsafe {
+        let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+        let top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+        RECT {
+            left,
+            top,
+            right: left + GetSystemMetrics(SM_CXVIRTUALSCREEN),
+            bottom: top + GetSystemMetrics(SM_CYVIRTUALSCREEN),
+        }
+    }
+}
+
+/// Returns the size of the primary monitor, whose top-left corner is the screen origin.
+pub fn primary_screen_size() -> (i32, i32) {
+    unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) }
+}
+
+/// Confines the cursor to `rect`, or releases it if None.
+pub fn clip_cursor(rect: Option<&RECT>) -> WinResult<()> {
+    unsafe { ClipCursor(rect.map(|rect| rect as *const RECT)) }
+}
+
+use windows::Win32::UI::HiDpi::{GetDpiForSystem, GetDpiForWindow};
+
+/// Returns the system DPI as seen by the calling process.
+pub fn system_dpi() -> u32 {
+    unsafe { GetDpiForSystem() }
+}
+
+/// Returns the DPI of a window, or 0 if the handle is invalid.
+pub fn window_dpi(hwnd: HWND) -> u32 {
+    unsafe { GetDpiForWindow(hwnd) }
+}
+
+use windows::Win32::System::RemoteDesktop::{
+    NOTIFY_FOR_THIS_SESSION, WTSRegisterSessionNotification, WTSUnRegisterSessionNotification,
+};
+
+/// Requests `WM_WTSSESSION_CHANGE` messages for the current session (lock, unlock, console
+/// and remote connections) to be sent to `hwnd`.
+pub fn register_session_notification(hwnd: HWND) -> WinResult<()> {
+    unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) }
+}
+
+/// Stops the session change messages requested by [`register_session_notification`]; must be
+/// called before the window is destroyed.
+pub fn unregister_session_notification(hwnd: HWND) -> WinResult<()> {
+    unsafe { WTSUnRegisterSessionNotification(hwnd) }
+}
+
+use windows::Win32::System::Power::{
+    ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED, SetThreadExecutionState,
+};
+
+/// Resets the display and system idle timers, as user input does (`SetThreadExecutionState`
+/// without `ES_CONTINUOUS`, so nothing is held after the call).
+pub fn reset_idle_timers() {
+    unsafe { SetThreadExecutionState(ES_DISPLAY_REQUIRED | ES_SYSTEM_REQUIRED) };
+}
+
+#[cfg(any(
+    feature = "pen",
+    feature = "gamepad",
+    feature = "leds",
+    feature = "media-keys"
+))]
+use windows::Win32::Devices::HumanInterfaceDevice::{HIDP_STATUS_SUCCESS, PHIDP_PREPARSED_DATA};
+#[cfg(any(feature = "pen", feature = "gamepad"))]
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    HIDP_VALUE_CAPS, HidP_GetSpecificValueCaps, HidP_GetUsageValue,
+};
+#[cfg(any(feature = "pen", feature = "gamepad", feature = "media-keys"))]
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    HidP_GetUsages, HidP_Input, HidP_MaxUsageListLength,
+};
+#[cfg(any(
+    feature = "pen",
+    feature = "gamepad",
+    feature = "leds",
+    feature = "media-keys"
+))]
+use windows::Win32::UI::Input::RIDI_PREPARSEDDATA;
+
+/// Reads the HID preparsed data of a raw input device, which describes the layout of its
+/// reports for the `HidP_*` functions.
+///
+/// The data is returned in `u64` units so that it is suitably aligned.
+#[cfg(any(
+    feature = "pen",
+    feature = "gamepad",
+    feature = "leds",
+    feature = "media-keys"
+))]
+pub fn preparsed_data(device: HANDLE) -> WinResult<Vec<u64>> {
+    let mut size = 0u32;
+    let rc = unsafe { GetRawInputDeviceInfoW(Some(device), RIDI_PREPARSEDDATA, None, &mut size) };
+    if rc == u32::MAX {
+        return Err(windows::core::Error::from_win32());
+    }
+
+    let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+    let rc = unsafe {
+        GetRawInputDeviceInfoW(
+            Some(device),
+            RIDI_PREPARSEDDATA,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            &mut size,
+        )
+    };
+    if rc == u32::MAX {
+        return Err(windows::core::Error::from_win32());
+    }
+    Ok(buffer)
+}
+
+#[cfg(any(
+    feature = "pen",
+    feature = "gamepad",
+    feature = "leds",
+    feature = "media-keys"
+))]
+fn preparsed(data: &[u64]) -> PHIDP_PREPARSED_DATA {
+    PHIDP_PREPARSED_DATA(data.as_ptr() as isize)
+}
+
+/// Returns the logical minimum, logical maximum and bit size of an input value usage, or
+/// None if the device has none.
+#[cfg(any(feature = "pen", feature = "gamepad"))]
+pub fn hid_value_range(data: &[u64], usage_page: u16, usage: u16) -> Option<(i32, i32, u16)> {
+    let mut caps = HIDP_VALUE_CAPS::default();
+    let mut len = 1u16;
+    let status = unsafe {
+        HidP_GetSpecificValueCaps(
+            HidP_Input,
+            Some(usage_page),
+            None,
+            Some(usage),
+            &mut caps,
+            &mut len,
+            preparsed(data),
+        )
+    };
+    (status == HIDP_STATUS_SUCCESS && len > 0).then_some((
+        caps.LogicalMin,
+        caps.LogicalMax,
+        caps.BitSize,
+    ))
+}
+
+/// Reads an input value usage from a report, or None if the report does not contain it.
+///
+/// The value is returned as its raw bits: values of signed fields narrower than 32 bits are
+/// not sign-extended.
+#[cfg(any(feature = "pen", feature = "gamepad"))]
+pub fn hid_usage_value(data: &[u64], usage_page: u16, usage: u16, report: &[u8]) -> Option<u32> {
+    let mut value = 0u32;
+    let status = unsafe {
+        HidP_GetUsageValue(
+            HidP_Input,
+            usage_page,
+            None,
+            usage,
+            &mut value,
+            preparsed(data),
+            report,
+        )
+    };
+    (status == HIDP_STATUS_SUCCESS).then_some(value)
+}
+
+/// Returns the button usages of a usage page that are set in a report (empty if the
+/// report has none).
+#[cfg(any(feature = "pen", feature = "gamepad"))]
+pub fn hid_active_usages(data: &[u64], usage_page: u16, report: &mut [u8]) -> Vec<u16> {
+    hid_usages(data, usage_page, report).unwrap_or_default()
+}
+
+/// Returns the button usages of a usage page that are set in a report, or None if the
+/// report does not contain the page's buttons (e.g. a report with another report ID).
+#[cfg(any(feature = "pen", feature = "gamepad", feature = "media-keys"))]
+pub fn hid_usages(data: &[u64], usage_page: u16, report: &mut [u8]) -> Option<Vec<u16>> {
+    let mut len = unsafe { HidP_MaxUsageListLength(HidP_Input, Some(usage_page), preparsed(data)) };
+    let mut usages = vec![0u16; len as usize];
+    let status = unsafe {
+        HidP_GetUsages(
+            HidP_Input,
+            usage_page,
+            None,
+            usages.as_mut_ptr(),
+            &mut len,
+            preparsed(data),
+            report,
+        )
+    };
+    if status != HIDP_STATUS_SUCCESS {
+        return None;
+    }
+    usages.truncate(len as usize);
+    Some(usages)
+}
+
+#[cfg(feature = "leds")]
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    HIDP_BUTTON_CAPS, HIDP_CAPS, HidD_SetOutputReport, HidP_GetCaps, HidP_GetSpecificButtonCaps,
+    HidP_InitializeReportForID, HidP_Output, HidP_SetUsages,
+};
+#[cfg(feature = "leds")]
+use windows::Win32::Foundation::{E_FAIL, GENERIC_WRITE};
+#[cfg(feature = "leds")]
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+};
+#[cfg(feature = "leds")]
+use windows::Win32::UI::Input::RIDI_DEVICENAME;
+#[cfg(feature = "leds")]
+use windows::core::PCWSTR;
+
+/// Reads the device interface path of a raw input device, null-terminated, which can be
+/// opened with `CreateFileW`.
+#[cfg(feature = "leds")]
+pub fn device_name(device: HANDLE) -> WinResult<Vec<u16>> {
+    let mut len = 0u32;
+    let rc = unsafe { GetRawInputDeviceInfoW(Some(device), RIDI_DEVICENAME, None, &mut len) };
+    if rc == u32::MAX {
+        return Err(windows::core::Error::from_win32());
+    }
+
+    // `len` counts characters, including the terminating null
+    let mut name = vec![0u16; len as usize + 1];
+    let rc = unsafe {
+        GetRawInputDeviceInfoW(
+            Some(device),
+            RIDI_DEVICENAME,
+            Some(name.as_mut_ptr() as *mut c_void),
+            &mut len,
+        )
+    };
+    if rc == u32::MAX {
+        return Err(windows::core::Error::from_win32());
+    }
+    Ok(name)
+}
+
+/// Builds an output report that sets exactly `usages` of a usage page (every other button
+/// usage of the report is cleared), or None if the device has no output buttons on that
+/// page.
+#[cfg(feature = "leds")]
+pub fn hid_output_report(data: &[u64], usage_page: u16, usages: &[u16]) -> Option<Vec<u8>> {
+    let mut caps = HIDP_CAPS::default();
+    if unsafe { HidP_GetCaps(preparsed(data), &mut caps) } != HIDP_STATUS_SUCCESS {
+        return None;
     }
+    let mut button = HIDP_BUTTON_CAPS::default();
+    let mut len = 1u16;
+    let status = unsafe {
+        HidP_GetSpecificButtonCaps(
+            HidP_Output,
+            Some(usage_page),
+            None,
+            None,
+            &mut button,
+            &mut len,
+            preparsed(data),
+        )
+    };
+    if status != HIDP_STATUS_SUCCESS || len == 0 || caps.OutputReportByteLength == 0 {
+        return None;
+    }
+
+    let mut report = vec![0u8; caps.OutputReportByteLength as usize];
+    let status = unsafe {
+        HidP_InitializeReportForID(HidP_Output, button.ReportID, preparsed(data), &mut report)
+    };
+    if status != HIDP_STATUS_SUCCESS {
+        return None;
+    }
+    if !usages.is_empty() {
+        let mut list = usages.to_vec();
+        let mut len = list.len() as u32;
+        // HidP_SetUsages writes to the report despite the binding taking it as a shared slice
+        let status = unsafe {
+            HidP_SetUsages(
+                HidP_Output,
+                usage_page,
+                None,
+                list.as_mut_ptr(),
+                &mut len,
+                preparsed(data),
+                &report,
+            )
+        };
+        if status != HIDP_STATUS_SUCCESS {
+            return None;
+        }
+    }
+    Some(report)
+}
+
+/// Sends an output report to the HID device at `path` (a null-terminated device interface
+/// path) with `HidD_SetOutputReport`.
+#[cfg(feature = "leds")]
+pub fn send_output_report(path: &[u16], report: &[u8]) -> WinResult<()> {
+    let file = unsafe {
+        CreateFileW(
+            PCWSTR(path.as_ptr()),
+            GENERIC_WRITE.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            None,
+        )
+    }?;
+    let sent = unsafe {
+        HidD_SetOutputReport(file, report.as_ptr() as *const c_void, report.len() as u32)
+    };
+    let result = if sent {
+        Ok(())
+    } else {
+        let err = windows::core::Error::from_win32();
+        // Some drivers fail without setting a last error
+        Err(if err.code().is_ok() {
+            windows::core::Error::from(E_FAIL)
+        } else {
+            err
+        })
+    };
+    let _ = unsafe { CloseHandle(file) };
+    result
 }
 
 // FFI tests are not included here because they would require interaction with the actual Windows API,
@@ -0,0 +1,517 @@
+//! Joystick and gamepad input parsed from generic desktop HID reports
+
+use crate::hid::sign_extend;
+use crate::{RawInputDeviceHandle, UsageRegistration};
+
+/// HID usage page of generic desktop controls
+const USAGE_PAGE_GENERIC: u16 = 0x01;
+/// Generic desktop usage of a joystick top level collection
+const USAGE_JOYSTICK: u16 = 0x04;
+/// Generic desktop usage of a gamepad top level collection
+const USAGE_GAMEPAD: u16 = 0x05;
+/// HID usage page of buttons, whose usages are the button numbers starting at 1
+const USAGE_PAGE_BUTTON: u16 = 0x09;
+const USAGE_HAT_SWITCH: u16 = 0x39;
+/// Axis usages reported in [`GamepadEvent::axes`], in order: X, Y, Z, Rx, Ry, Rz, Slider,
+/// Dial and Wheel
+const AXIS_USAGES: [u16; 9] = [0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38];
+
+impl UsageRegistration {
+    /// Registers joysticks (usage page 0x01, usage 0x04), such as flight sticks and many
+    /// generic controllers, whose reports can be parsed with a [`GamepadParser`].
+    pub fn joystick() -> Self {
+        Self::usage(USAGE_PAGE_GENERIC, USAGE_JOYSTICK)
+    }
+
+    /// Registers gamepads (usage page 0x01, usage 0x05), whose reports can be parsed with
+    /// a [`GamepadParser`].
+    ///
+    /// Xbox controllers are gamepads too, but their HID reports merge the triggers into a
+    /// single axis; XInput reads them completely.
+    pub fn gamepad() -> Self {
+        Self::usage(USAGE_PAGE_GENERIC, USAGE_GAMEPAD)
+    }
+}
+
+/// Direction of a hat switch (D-pad), with up pointing away from the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HatDirection {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl HatDirection {
+    /// Clockwise order of the eight positions, starting at up, as HID hat switches count
+    const CLOCKWISE: [Self; 8] = [
+        Self::Up,
+        Self::UpRight,
+        Self::Right,
+        Self::DownRight,
+        Self::Down,
+        Self::DownLeft,
+        Self::Left,
+        Self::UpLeft,
+    ];
+
+    /// Decodes a hat switch value by the logical range the device declares for it.
+    ///
+    /// Hats count their positions clockwise from up, across eight positions or, for
+    /// four-way hats, four; a value outside the logical range means centered.
+    fn from_value(value: i32, min: i32, max: i32) -> Option<Self> {
+        if value < min || value > max {
+            return None;
+        }
+        let index = (value - min) as usize;
+        match max - min + 1 {
+            4 => Some(Self::CLOCKWISE[index * 2]),
+            8 => Some(Self::CLOCKWISE[index]),
+            _ => None,
+        }
+    }
+}
+
+/// Usage and logical range of one of the [`GamepadEvent::axes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GamepadAxis {
+    /// Generic desktop usage of the axis, e.g. 0x30 for X or 0x35 for Rz
+    pub usage: u16,
+    /// Smallest value the device declares for the axis
+    pub min: i32,
+    /// Largest value the device declares for the axis
+    pub max: i32,
+}
+
+impl GamepadAxis {
+    /// Maps a value of the axis to -1.0 at [`min`](Self::min) through 1.0 at
+    /// [`max`](Self::max), with the middle of the range at 0.0.
+    ///
+    /// Values outside the range are clamped; an empty range gives 0.0.
+    pub fn normalize(&self, value: i32) -> f32 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        let span = f64::from(self.max) - f64::from(self.min);
+        let position = (f64::from(value) - f64::from(self.min)) / span;
+        (position * 2.0 - 1.0).clamp(-1.0, 1.0) as f32
+    }
+}
+
+/// State of a joystick or gamepad, parsed from a single HID input report.
+///
+/// Which physical control each axis and button is depends on the device: the HID usages
+/// only say that an axis is "X" or "Rz" and that a button is number 3, not where it sits
+/// on the controller. Map them per device (e.g. by vendor and product ID) or let users
+/// assign controls themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GamepadEvent {
+    /// Values of the axes the device has, in the order X, Y, Z, Rx, Ry, Rz, Slider, Dial,
+    /// Wheel (skipping those it lacks), in the logical range the device declares (often 0
+    /// to 255 or 0 to 65535, with the center in the middle)
+    pub axes: Vec<i32>,
+    /// Usage and logical range of each of the `axes`, in the same order
+    pub axis_layout: Vec<GamepadAxis>,
+    /// Pressed buttons, bit 0 for button 1 up to bit 31 for button 32
+    pub buttons: u32,
+    /// Direction of the hat switch (D-pad), or None if centered or absent
+    pub hat: Option<HatDirection>,
+    /// Device that produced the event
+    pub device: RawInputDeviceHandle,
+}
+
+/// Value layout of a gamepad's reports.
+#[derive(Debug, Clone, Default)]
+struct GamepadLayout {
+    /// Each axis the device has, with its signed bit size (0 if unsigned)
+    axes: Vec<(GamepadAxis, u16)>,
+    /// Logical range of the hat switch, if the device has one
+    hat: Option<(i32, i32)>,
+}
+
+impl GamepadEvent {
+    /// Builds an event from the raw axis values (in the order of the layout's axes), the
+    /// raw hat switch value and the pressed button usages read from a report.
+    fn from_report(
+        device: RawInputDeviceHandle,
+        layout: &GamepadLayout,
+        axes: &[u32],
+        hat: Option<u32>,
+        buttons: &[u16],
+    ) -> Self {
+        Self {
+            axes: layout
+                .axes
+                .iter()
+                .zip(axes)
+                .map(|(&(_, bits), &value)| sign_extend(value, bits))
+                .collect(),
+            axis_layout: layout.axes.iter().map(|&(axis, _)| axis).collect(),
+            buttons: buttons
+                .iter()
+                .filter(|&&usage| (1..=32).contains(&usage))
+                .fold(0, |bits, usage| bits | 1 << (usage - 1)),
+            hat: layout
+                .hat
+                .zip(hat)
+                .and_then(|((min, max), value)| HatDirection::from_value(value as i32, min, max)),
+            device,
+        }
+    }
+
+    /// Returns whether a button is pressed, by its number starting at 1.
+    pub fn is_button_pressed(&self, number: u32) -> bool {
+        (1..=32).contains(&number) && self.buttons & (1 << (number - 1)) != 0
+    }
+}
+
+#[cfg(windows)]
+pub use parser::GamepadParser;
+
+#[cfg(windows)]
+mod parser {
+    use super::*;
+    use crate::{RawHidEventRef, RawInputError, ffi};
+    use std::collections::HashMap;
+
+    /// Device information needed to parse a gamepad's reports
+    struct GamepadDevice {
+        preparsed: Vec<u64>,
+        layout: GamepadLayout,
+    }
+
+    /// Parses joystick and gamepad HID events into [`GamepadEvent`]s.
+    ///
+    /// Controllers lay out their reports differently, so the parser reads each device's
+    /// report descriptor (its HID preparsed data) the first time it sees the device and
+    /// keeps it for later events. Register for controller input with
+    /// [`UsageRegistration::gamepad`] and [`UsageRegistration::joystick`] and feed the HID
+    /// events to the parser, e.g. from a
+    /// [`hid_callback`](crate::ListenerBuilder::hid_callback).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mkb_raw_input::{GamepadParser, ListenerBuilder, UsageRegistration};
+    ///
+    /// let mut parser = GamepadParser::new();
+    /// let _listener = ListenerBuilder::new()
+    ///     .register_usage(UsageRegistration::gamepad())
+    ///     .register_usage(UsageRegistration::joystick())
+    ///     .hid_callback(move |hid| {
+    ///         for pad in parser.parse(&hid).unwrap_or_default() {
+    ///             println!("axes {:?} buttons {:#b} hat {:?}", pad.axes, pad.buttons, pad.hat);
+    ///         }
+    ///     })
+    ///     .start(|_event| {})
+    ///     .expect("Failed to start listener");
+    /// ```
+    #[derive(Default)]
+    pub struct GamepadParser {
+        devices: HashMap<RawInputDeviceHandle, GamepadDevice>,
+        /// Copy of the report being parsed, as `HidP_GetUsages` takes it mutably
+        report: Vec<u8>,
+    }
+
+    impl GamepadParser {
+        /// Creates a parser that knows no devices yet.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Parses every report of a HID event.
+        ///
+        /// Reports that do not carry the device's axes (other report IDs, devices without
+        /// axes) are skipped, so the result may be empty.
+        ///
+        /// # Returns
+        /// * `Ok(Vec<GamepadEvent>)` - One event per controller report, in order
+        /// * `Err(RawInputError)` - If the device's preparsed data cannot be read
+        pub fn parse(
+            &mut self,
+            hid: &RawHidEventRef<'_>,
+        ) -> Result<Vec<GamepadEvent>, RawInputError> {
+            let device = match self.devices.entry(hid.device) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(GamepadDevice::query(hid.device)?)
+                }
+            };
+
+            let mut events = Vec::new();
+            for report in hid.reports() {
+                self.report.clear();
+                self.report.extend_from_slice(report);
+                if let Some(event) = device.parse(hid.device, &mut self.report) {
+                    events.push(event);
+                }
+            }
+            Ok(events)
+        }
+    }
+
+    impl GamepadDevice {
+        fn query(device: RawInputDeviceHandle) -> Result<Self, RawInputError> {
+            let preparsed = ffi::preparsed_data(device.as_handle()).map_err(|e| {
+                RawInputError::WinApiError(format!("Failed to read HID preparsed data: {e}"))
+            })?;
+            let range = |usage| ffi::hid_value_range(&preparsed, USAGE_PAGE_GENERIC, usage);
+            let layout = GamepadLayout {
+                axes: AXIS_USAGES
+                    .iter()
+                    .filter_map(|&usage| {
+                        range(usage).map(|(min, max, bits)| {
+                            let signed_bits = if min < 0 { bits } else { 0 };
+                            (GamepadAxis { usage, min, max }, signed_bits)
+                        })
+                    })
+                    .collect(),
+                hat: range(USAGE_HAT_SWITCH).map(|(min, max, _)| (min, max)),
+            };
+            Ok(Self { preparsed, layout })
+        }
+
+        fn parse(&self, device: RawInputDeviceHandle, report: &mut [u8]) -> Option<GamepadEvent> {
+            let value = |usage, report: &[u8]| {
+                ffi::hid_usage_value(&self.preparsed, USAGE_PAGE_GENERIC, usage, report)
+            };
+            let axes = self
+                .layout
+                .axes
+                .iter()
+                .map(|&(axis, _)| value(axis.usage, report))
+                .collect::<Option<Vec<_>>>()
+                .filter(|axes| !axes.is_empty())?;
+            let hat = value(USAGE_HAT_SWITCH, report);
+            let buttons = ffi::hid_active_usages(&self.preparsed, USAGE_PAGE_BUTTON, report);
+            Some(GamepadEvent::from_report(
+                device,
+                &self.layout,
+                &axes,
+                hat,
+                &buttons,
+            ))
+        }
+    }
+}
+
+#[cfg(all(windows, feature = "gilrs"))]
+mod gilrs_mapping {
+    use super::*;
+    use gilrs::{Axis, Button};
+
+    /// gilrs buttons of HID buttons 1 to 10, in the order of the Xbox controller's HID
+    /// reports (A, B, X, Y, LB, RB, View, Menu, left and right stick)
+    const BUTTONS: [Button; 10] = [
+        Button::South,
+        Button::East,
+        Button::West,
+        Button::North,
+        Button::LeftTrigger,
+        Button::RightTrigger,
+        Button::Select,
+        Button::Start,
+        Button::LeftThumb,
+        Button::RightThumb,
+    ];
+
+    /// Returns the gilrs axis of an axis usage and whether its direction is reversed.
+    ///
+    /// Follows the default gilrs uses on Linux for the matching evdev axes of controllers
+    /// it has no mapping for.
+    fn gilrs_axis(usage: u16) -> Option<(Axis, bool)> {
+        match usage {
+            0x30 => Some((Axis::LeftStickX, false)),
+            0x31 => Some((Axis::LeftStickY, true)),
+            0x32 => Some((Axis::LeftZ, false)),
+            0x33 => Some((Axis::RightStickX, false)),
+            0x34 => Some((Axis::RightStickY, true)),
+            0x35 => Some((Axis::RightZ, false)),
+            _ => None,
+        }
+    }
+
+    impl HatDirection {
+        /// Returns the gilrs D-pad buttons held in this direction: two for diagonals.
+        pub fn gilrs_buttons(self) -> &'static [Button] {
+            match self {
+                Self::Up => &[Button::DPadUp],
+                Self::UpRight => &[Button::DPadUp, Button::DPadRight],
+                Self::Right => &[Button::DPadRight],
+                Self::DownRight => &[Button::DPadDown, Button::DPadRight],
+                Self::Down => &[Button::DPadDown],
+                Self::DownLeft => &[Button::DPadDown, Button::DPadLeft],
+                Self::Left => &[Button::DPadLeft],
+                Self::UpLeft => &[Button::DPadUp, Button::DPadLeft],
+            }
+        }
+    }
+
+    impl GamepadEvent {
+        /// Returns the gilrs button of a HID button number, starting at 1.
+        ///
+        /// Buttons 1 to 10 map in the order of the Xbox controller's HID reports: South
+        /// (A), East (B), West (X), North (Y), LeftTrigger (LB), RightTrigger (RB), Select,
+        /// Start, LeftThumb and RightThumb. Other numbers are [`Button::Unknown`]. HID does
+        /// not say where a button sits on the controller, so this is a best guess that
+        /// other controllers do not follow: a DualShock 4 numbers Square, Cross, Circle,
+        /// Triangle. Remap by vendor and product ID where it matters.
+        pub fn gilrs_button(number: u32) -> Button {
+            number
+                .checked_sub(1)
+                .and_then(|index| BUTTONS.get(index as usize))
+                .copied()
+                .unwrap_or(Button::Unknown)
+        }
+
+        /// Returns the gilrs buttons held in this report: the pressed buttons mapped by
+        /// [`gilrs_button`](Self::gilrs_button), skipping unknown ones, followed by the
+        /// D-pad buttons of the hat switch.
+        ///
+        /// gilrs events cannot be created outside of gilrs, so compare the buttons with
+        /// those of the previous report to find presses and releases.
+        pub fn gilrs_buttons(&self) -> Vec<Button> {
+            (1..=32)
+                .filter(|&number| self.is_button_pressed(number))
+                .map(Self::gilrs_button)
+                .filter(|&button| button != Button::Unknown)
+                .chain(
+                    self.hat
+                        .map(HatDirection::gilrs_buttons)
+                        .unwrap_or(&[])
+                        .iter()
+                        .copied(),
+                )
+                .collect()
+        }
+
+        /// Returns the axes of this report as gilrs axes with values from -1.0 to 1.0.
+        ///
+        /// The usages map like the evdev axes of unknown controllers in gilrs: X and Y to the
+        /// left stick, Rx and Ry to the right stick, Z to [`Axis::LeftZ`] and Rz to
+        /// [`Axis::RightZ`]. Y axes are reversed, as HID reports down as positive and gilrs
+        /// up. Slider, Dial and Wheel have no gilrs counterpart and are skipped. Values are
+        /// normalized by [`GamepadAxis::normalize`]; as with the buttons, controllers that
+        /// put their right stick on Z and Rz need a remapping of their own.
+        pub fn gilrs_axes(&self) -> Vec<(Axis, f32)> {
+            self.axis_layout
+                .iter()
+                .zip(&self.axes)
+                .filter_map(|(axis, &value)| {
+                    let (gilrs_axis, reversed) = gilrs_axis(axis.usage)?;
+                    let value = axis.normalize(value);
+                    Some((gilrs_axis, if reversed { -value } else { value }))
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that axes, buttons and the hat are decoded by the device's layout
+    #[test]
+    fn test_from_report() {
+        let axis = |usage, min, max| GamepadAxis { usage, min, max };
+        let layout = GamepadLayout {
+            axes: vec![
+                (axis(0x30, 0, 255), 0),
+                (axis(0x31, 0, 255), 0),
+                (axis(0x32, -128, 127), 8),
+            ],
+            hat: Some((0, 7)),
+        };
+        let pad = GamepadEvent::from_report(
+            RawInputDeviceHandle::from_raw(3),
+            &layout,
+            &[128, 255, 0x80], // -128 in an 8-bit signed field
+            Some(2),
+            &[1, 4, 33],
+        );
+        assert_eq!(pad.axes, vec![128, 255, -128]);
+        assert_eq!(pad.axis_layout[2], axis(0x32, -128, 127));
+        assert_eq!(pad.buttons, 0b1001);
+        assert!(pad.is_button_pressed(4) && !pad.is_button_pressed(2));
+        assert_eq!(pad.hat, Some(HatDirection::Right));
+
+        // Null state outside the logical range
+        let centered = GamepadEvent::from_report(
+            RawInputDeviceHandle::default(),
+            &layout,
+            &[0, 0, 0],
+            Some(8),
+            &[],
+        );
+        assert_eq!((centered.buttons, centered.hat), (0, None));
+    }
+
+    /// Test that axis values are normalized across the logical range
+    #[test]
+    fn test_normalize_axis() {
+        let axis = GamepadAxis {
+            usage: 0x30,
+            min: 0,
+            max: 255,
+        };
+        assert_eq!(axis.normalize(0), -1.0);
+        assert_eq!(axis.normalize(255), 1.0);
+        assert!(axis.normalize(128).abs() < 0.01);
+        assert_eq!(axis.normalize(300), 1.0);
+
+        let empty = GamepadAxis { max: 0, ..axis };
+        assert_eq!(empty.normalize(0), 0.0);
+    }
+
+    /// Test that buttons, the hat and axes map to their gilrs counterparts
+    #[cfg(all(windows, feature = "gilrs"))]
+    #[test]
+    fn test_gilrs_mapping() {
+        use gilrs::{Axis, Button};
+
+        let pad = GamepadEvent {
+            axes: vec![255, 0, 0],
+            axis_layout: vec![
+                GamepadAxis {
+                    usage: 0x30,
+                    min: 0,
+                    max: 255,
+                },
+                GamepadAxis {
+                    usage: 0x31,
+                    min: 0,
+                    max: 255,
+                },
+                GamepadAxis {
+                    usage: 0x36, // Slider
+                    min: 0,
+                    max: 255,
+                },
+            ],
+            buttons: 0b1000_0000_0001, // Buttons 1 and 12
+            hat: Some(HatDirection::UpLeft),
+            device: RawInputDeviceHandle::default(),
+        };
+        assert_eq!(
+            pad.gilrs_buttons(),
+            vec![Button::South, Button::DPadUp, Button::DPadLeft]
+        );
+        assert_eq!(
+            pad.gilrs_axes(),
+            vec![(Axis::LeftStickX, 1.0), (Axis::LeftStickY, 1.0)]
+        );
+        assert_eq!(GamepadEvent::gilrs_button(0), Button::Unknown);
+    }
+
+    /// Test that four-way hats map to the cardinal directions
+    #[test]
+    fn test_four_way_hat() {
+        assert_eq!(HatDirection::from_value(1, 1, 4), Some(HatDirection::Up));
+        assert_eq!(HatDirection::from_value(4, 1, 4), Some(HatDirection::Left));
+        assert_eq!(HatDirection::from_value(0, 1, 4), None);
+    }
+}
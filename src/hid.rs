@@ -0,0 +1,150 @@
+//! Generic HID event types for the Raw Input API
+
+use crate::device::RawInputDeviceHandle;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+/// Data for a raw input event from a HID device that is neither a mouse nor a keyboard.
+///
+/// HID devices deliver one or more fixed-size input reports per event; iterate them with
+/// [`reports`](Self::reports). The report bytes are device-specific and are passed through
+/// unparsed.
+#[derive(Debug, Clone)]
+pub struct RawHidEvent {
+    /// Size in bytes of each HID input report
+    pub report_size: u32,
+    /// Number of input reports contained in `data`
+    pub report_count: u32,
+    /// Report bytes (`report_size * report_count` bytes)
+    pub data: Vec<u8>,
+    /// Device that produced the event, as reported in `RAWINPUTHEADER.hDevice`
+    pub device: RawInputDeviceHandle,
+    /// Monotonic time at which the listener dequeued the event
+    pub timestamp: Instant,
+    /// Wall-clock time at which the listener dequeued the event, if enabled via
+    /// [`ListenerBuilder::capture_system_time`](crate::ListenerBuilder::capture_system_time)
+    pub system_time: Option<SystemTime>,
+    /// Tick count of the WM_INPUT message the event was read from (`GetMessageTime`), in
+    /// milliseconds since system start; 0 for events that did not come from a message
+    pub message_time: u32,
+    /// Executable file name of the foreground window's process when the event was read
+    /// (e.g. "notepad.exe"), if enabled via
+    /// [`ListenerBuilder::capture_foreground_process`](crate::ListenerBuilder::capture_foreground_process)
+    pub foreground_process: Option<Arc<str>>,
+}
+
+/// Borrowed view of a HID raw input event, as passed to a
+/// [`hid_callback`](crate::ListenerBuilder::hid_callback).
+///
+/// The report bytes point into the listener's read buffer and are only valid for the
+/// duration of the callback, which avoids allocating per event at high report rates. Use
+/// [`to_event`](Self::to_event) to keep a copy.
+#[derive(Debug, Clone, Copy)]
+pub struct RawHidEventRef<'a> {
+    /// Size in bytes of each HID input report
+    pub report_size: u32,
+    /// Number of input reports contained in `data`
+    pub report_count: u32,
+    /// Report bytes (`report_size * report_count` bytes)
+    pub data: &'a [u8],
+    /// Device that produced the event, as reported in `RAWINPUTHEADER.hDevice`
+    pub device: RawInputDeviceHandle,
+    /// Monotonic time at which the listener dequeued the event
+    pub timestamp: Instant,
+    /// Wall-clock time at which the listener dequeued the event, if enabled via
+    /// [`ListenerBuilder::capture_system_time`](crate::ListenerBuilder::capture_system_time)
+    pub system_time: Option<SystemTime>,
+    /// Tick count of the WM_INPUT message the event was read from (`GetMessageTime`), in
+    /// milliseconds since system start; 0 for events that did not come from a message
+    pub message_time: u32,
+    /// Executable file name of the foreground window's process when the event was read
+    /// (e.g. "notepad.exe"), if enabled via
+    /// [`ListenerBuilder::capture_foreground_process`](crate::ListenerBuilder::capture_foreground_process)
+    pub foreground_process: Option<&'a str>,
+}
+
+impl<'a> RawHidEventRef<'a> {
+    /// Parses the `RAWHID` part of a raw input packet (everything after the header).
+    ///
+    /// Returns None if the payload is too short to contain the `RAWHID` size fields.
+    /// Report data beyond the end of the payload is truncated.
+    pub(crate) fn from_payload(payload: &'a [u8]) -> Option<Self> {
+        let report_size = u32::from_ne_bytes(payload.get(0..4)?.try_into().ok()?);
+        let report_count = u32::from_ne_bytes(payload.get(4..8)?.try_into().ok()?);
+        let data = &payload[8..];
+        let len = (report_size as usize)
+            .saturating_mul(report_count as usize)
+            .min(data.len());
+
+        Some(Self {
+            report_size,
+            report_count,
+            data: &data[..len],
+            device: RawInputDeviceHandle::default(),
+            timestamp: Instant::now(),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        })
+    }
+
+    /// Returns an iterator over the individual input reports in the event.
+    ///
+    /// A single packet can carry several reports (`report_count > 1`) when the device
+    /// produced them faster than they were read; each is `report_size` bytes long.
+    pub fn reports(&self) -> impl ExactSizeIterator<Item = &'a [u8]> + use<'a> {
+        let size = self.report_size as usize;
+        let count = self.data.len().checked_div(size).unwrap_or(0);
+        self.data.chunks_exact(size.max(1)).take(count)
+    }
+
+    /// Copies the event into an owned [`RawHidEvent`].
+    pub fn to_event(&self) -> RawHidEvent {
+        RawHidEvent {
+            report_size: self.report_size,
+            report_count: self.report_count,
+            data: self.data.to_vec(),
+            device: self.device,
+            timestamp: self.timestamp,
+            system_time: self.system_time,
+            message_time: self.message_time,
+            foreground_process: self.foreground_process.map(Arc::from),
+        }
+    }
+}
+
+impl RawHidEvent {
+    /// Returns an iterator over the individual input reports in the event.
+    ///
+    /// See [`RawHidEventRef::reports`].
+    pub fn reports(&self) -> impl ExactSizeIterator<Item = &[u8]> {
+        self.as_event_ref().reports()
+    }
+
+    /// Returns a borrowed view of the event.
+    pub fn as_event_ref(&self) -> RawHidEventRef<'_> {
+        RawHidEventRef {
+            report_size: self.report_size,
+            report_count: self.report_count,
+            data: &self.data,
+            device: self.device,
+            timestamp: self.timestamp,
+            system_time: self.system_time,
+            message_time: self.message_time,
+            foreground_process: self.foreground_process.as_deref(),
+        }
+    }
+}
+
+/// Interprets the raw bits of a `bits` wide signed field (0 for unsigned fields), as read
+/// from a report with `HidP_GetUsageValue`.
+#[cfg(any(feature = "pen", feature = "gamepad"))]
+pub(crate) fn sign_extend(value: u32, bits: u16) -> i32 {
+    match bits {
+        1..32 => {
+            let shift = 32 - u32::from(bits);
+            ((value << shift) as i32) >> shift
+        }
+        _ => value as i32,
+    }
+}
@@ -0,0 +1,138 @@
+//! Key combinations matched against the tracked keyboard state
+
+use crate::VirtualKey;
+use crate::state::InputState;
+use std::time::{Duration, Instant};
+
+/// A combination of keys that must all be held down together, such as Ctrl+Shift+P.
+///
+/// A hotkey is triggered when the last of its keys goes down while all others are held,
+/// regardless of the order in which they were pressed. Auto-repeats do not trigger it
+/// again. Modifiers are matched as raw input reports them, i.e. as the side-independent
+/// [`VirtualKey::Shift`], [`VirtualKey::Control`] and [`VirtualKey::Alt`].
+///
+/// # Example
+/// ```
+/// use mkb_raw_input::{Hotkey, VirtualKey};
+///
+/// let save = Hotkey::new([VirtualKey::Control, VirtualKey::S]);
+/// assert_eq!(save.keys(), &[VirtualKey::Control, VirtualKey::S]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    keys: Vec<VirtualKey>,
+}
+
+impl Hotkey {
+    /// Creates a hotkey from its keys; duplicates are ignored.
+    pub fn new(keys: impl IntoIterator<Item = VirtualKey>) -> Self {
+        let mut unique = Vec::new();
+        for key in keys {
+            if !unique.contains(&key) {
+                unique.push(key);
+            }
+        }
+        Self { keys: unique }
+    }
+
+    /// Returns the keys of the hotkey.
+    pub fn keys(&self) -> &[VirtualKey] {
+        &self.keys
+    }
+
+    /// Returns true if pressing `key` (not an auto-repeat) completes the hotkey, given the
+    /// state after the press has been applied.
+    pub(crate) fn is_triggered_by(&self, key: VirtualKey, state: &InputState) -> bool {
+        self.keys.contains(&key) && self.is_held(state)
+    }
+
+    /// Returns true if all keys of the hotkey are held down.
+    pub(crate) fn is_held(&self, state: &InputState) -> bool {
+        self.keys.iter().all(|&k| state.is_key_down(k))
+    }
+}
+
+/// Boxed hotkey press callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type HotkeyCallback = Box<dyn FnMut() + Send + 'static>;
+
+/// Boxed hotkey release callback, taking how long the hotkey was held, as stored by
+/// [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type HotkeyReleaseCallback = Box<dyn FnMut(Duration) + Send + 'static>;
+
+/// Press or release of a binding, recorded until its callback runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    Press,
+    /// Release after the hotkey was held for the duration
+    Release(Duration),
+}
+
+/// A hotkey registered on the listener with its callbacks.
+///
+/// A binding goes through press/release cycles: it is pressed when the hotkey triggers,
+/// and released as soon as any of its keys goes up, in whatever order. Each cycle invokes
+/// each callback exactly once, and the release reports the time between the two.
+pub(crate) struct HotkeyBinding {
+    hotkey: Hotkey,
+    on_press: HotkeyCallback,
+    on_release: Option<HotkeyReleaseCallback>,
+    /// Timestamp of the event that pressed the hotkey, while it is held
+    pressed_at: Option<Instant>,
+    /// Transition seen by [`observe`](Self::observe) and not yet [`fire`](Self::fire)d
+    pending: Option<Transition>,
+}
+
+impl HotkeyBinding {
+    pub(crate) fn new(
+        hotkey: Hotkey,
+        on_press: HotkeyCallback,
+        on_release: Option<HotkeyReleaseCallback>,
+    ) -> Self {
+        Self {
+            hotkey,
+            on_press,
+            on_release,
+            pressed_at: None,
+            pending: None,
+        }
+    }
+
+    /// Records the press or release caused by a keyboard event, given the state after it
+    /// was applied, the key it pressed (None for releases and auto-repeats) and its
+    /// timestamp.
+    ///
+    /// A hotkey completed while paused is not pressed, so that no release follows a press
+    /// that was never reported; a pressed hotkey is always released.
+    pub(crate) fn observe(
+        &mut self,
+        pressed: Option<VirtualKey>,
+        state: &InputState,
+        paused: bool,
+        timestamp: Instant,
+    ) {
+        if let Some(pressed_at) = self.pressed_at {
+            if !self.hotkey.is_held(state) {
+                self.pressed_at = None;
+                let held_for = timestamp.saturating_duration_since(pressed_at);
+                self.pending = Some(Transition::Release(held_for));
+            }
+        } else if !paused && pressed.is_some_and(|key| self.hotkey.is_triggered_by(key, state)) {
+            self.pressed_at = Some(timestamp);
+            self.pending = Some(Transition::Press);
+        }
+    }
+
+    /// Invokes the callback of the transition recorded by [`observe`](Self::observe), if
+    /// any. Kept separate so callbacks run without the state locked.
+    pub(crate) fn fire(&mut self) {
+        match self.pending.take() {
+            Some(Transition::Press) => (self.on_press)(),
+            Some(Transition::Release(held_for)) => {
+                if let Some(on_release) = self.on_release.as_mut() {
+                    on_release(held_for);
+                }
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Conversion of captured events into `SendInput` input for replay
+
+use crate::keyboard::resolve_vkey;
+use crate::{
+    MouseButtonAction, MouseMoveMode, RawInputError, RawInputEvent, RawKeyboardEvent,
+    RawMouseEvent, WheelAxis, ffi,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, MOUSE_EVENT_FLAGS,
+    MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN,
+    MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN,
+    MOUSEEVENTF_XUP, MOUSEINPUT, VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{XBUTTON1, XBUTTON2};
+
+impl RawInputEvent {
+    /// Converts the event into the `INPUT` structures that replay it via `SendInput`.
+    ///
+    /// Keys are replayed by scan code (with `KEYEVENTF_EXTENDEDKEY` for E0-prefixed keys),
+    /// so they map to the same physical key under any layout; keys without a usable scan
+    /// code (such as the E1-prefixed Pause key) fall back to their virtual key. Relative
+    /// and absolute mouse movement keep their mode, with absolute coordinates passed
+    /// through in the normalized 0..=65535 range. HID events cannot be injected and
+    /// produce no input; neither do relative mouse events without movement or buttons.
+    pub fn to_input(&self) -> Vec<INPUT> {
+        match self {
+            Self::Keyboard(kbd) => vec![keyboard_input(kbd)],
+            Self::Mouse(mouse) => mouse_input(mouse).into_iter().collect(),
+            Self::Hid(_) => Vec::new(),
+        }
+    }
+}
+
+/// Replays a single event via `SendInput`.
+///
+/// See [`send_events`] for the details and failure modes.
+pub fn send_event(event: &RawInputEvent) -> Result<(), RawInputError> {
+    send_events(std::slice::from_ref(event))
+}
+
+/// Replays events via a single `SendInput` call.
+///
+/// `SendInput` inserts the whole batch into the input stream without interleaving it with
+/// other input, which makes a batch atomic with respect to the user's own typing and
+/// much cheaper than one call per event. Injected events carry the `LLKHF_INJECTED` flag
+/// and are seen by the raw input listener like any other input, but without a device:
+/// `SendInput` cannot target one, so events recorded from several keyboards or mice all
+/// replay as coming from the same virtual device. Use [`filter_device`](crate::filter_device)
+/// to replay the input of a single device.
+///
+/// # Returns
+/// * `Ok(())` - All inputs were inserted
+/// * `Err(RawInputError)` - Some inputs were blocked, e.g. by UIPI when the foreground
+///   window belongs to an elevated process
+pub fn send_events(events: &[RawInputEvent]) -> Result<(), RawInputError> {
+    let inputs: Vec<INPUT> = events.iter().flat_map(RawInputEvent::to_input).collect();
+    if inputs.is_empty() {
+        return Ok(());
+    }
+
+    let sent = ffi::send_input(&inputs);
+    if sent as usize != inputs.len() {
+        return Err(RawInputError::WinApiError(format!(
+            "SendInput inserted {sent} of {} inputs: {}",
+            inputs.len(),
+            windows::core::Error::from_win32()
+        )));
+    }
+    Ok(())
+}
+
+fn keyboard_input(kbd: &RawKeyboardEvent) -> INPUT {
+    let mut flags = KEYBD_EVENT_FLAGS(0);
+    if kbd.key_up {
+        flags |= KEYEVENTF_KEYUP;
+    }
+    if kbd.flags.e0_prefix {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+
+    // SendInput cannot express the E1 prefix, and synthesized keys may lack a scan code
+    let vkey = if kbd.make_code != 0 && !kbd.flags.e1_prefix {
+        flags |= KEYEVENTF_SCANCODE;
+        0
+    } else {
+        resolve_vkey(kbd.raw_vkey, kbd.make_code, kbd.flags)
+    };
+
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(vkey),
+                wScan: kbd.make_code,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: kbd.extra_information as usize,
+            },
+        },
+    }
+}
+
+fn mouse_input(mouse: &RawMouseEvent) -> Option<INPUT> {
+    let mut flags = match mouse.move_mode {
+        MouseMoveMode::Relative if mouse.last_x != 0 || mouse.last_y != 0 => MOUSEEVENTF_MOVE,
+        MouseMoveMode::Absolute => MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE,
+        MouseMoveMode::VirtualDesktop => {
+            MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK
+        }
+        _ => MOUSE_EVENT_FLAGS(0),
+    };
+
+    let mut data = 0i32;
+    match mouse.button_action {
+        MouseButtonAction::LeftDown => flags |= MOUSEEVENTF_LEFTDOWN,
+        MouseButtonAction::LeftUp => flags |= MOUSEEVENTF_LEFTUP,
+        MouseButtonAction::RightDown => flags |= MOUSEEVENTF_RIGHTDOWN,
+        MouseButtonAction::RightUp => flags |= MOUSEEVENTF_RIGHTUP,
+        MouseButtonAction::MiddleDown => flags |= MOUSEEVENTF_MIDDLEDOWN,
+        MouseButtonAction::MiddleUp => flags |= MOUSEEVENTF_MIDDLEUP,
+        MouseButtonAction::XButton1Down => {
+            flags |= MOUSEEVENTF_XDOWN;
+            data = i32::from(XBUTTON1);
+        }
+        MouseButtonAction::XButton1Up => {
+            flags |= MOUSEEVENTF_XUP;
+            data = i32::from(XBUTTON1);
+        }
+        MouseButtonAction::XButton2Down => {
+            flags |= MOUSEEVENTF_XDOWN;
+            data = i32::from(XBUTTON2);
+        }
+        MouseButtonAction::XButton2Up => {
+            flags |= MOUSEEVENTF_XUP;
+            data = i32::from(XBUTTON2);
+        }
+        _ => {}
+    }
+    // Wheel deltas below a notch have no wheel action, so go by the axis
+    match mouse.wheel_axis {
+        Some(WheelAxis::Vertical) => {
+            flags |= MOUSEEVENTF_WHEEL;
+            data = i32::from(mouse.wheel_delta);
+        }
+        Some(WheelAxis::Horizontal) => {
+            flags |= MOUSEEVENTF_HWHEEL;
+            data = i32::from(mouse.wheel_delta);
+        }
+        None => {}
+    }
+
+    if flags.0 == 0 {
+        return None;
+    }
+
+    Some(INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx: mouse.last_x,
+                dy: mouse.last_y,
+                mouseData: data as u32,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: mouse.extra_information as usize,
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::Win32::UI::Input::{RAWKEYBOARD, RAWMOUSE};
+
+    /// Test that an extended key release is replayed by scan code
+    #[test]
+    fn test_keyboard_to_input() {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.MakeCode = 0x4B; // Left arrow (E0 4B)
+        raw.Flags = 0x02 | 0x01; // E0, break
+        raw.VKey = 0x25;
+        let inputs = RawInputEvent::Keyboard(RawKeyboardEvent::from(&raw)).to_input();
+
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].r#type, INPUT_KEYBOARD);
+        let ki = unsafe { inputs[0].Anonymous.ki };
+        assert_eq!(ki.wScan, 0x4B);
+        assert_eq!(ki.wVk, VIRTUAL_KEY(0));
+        assert_eq!(
+            ki.dwFlags,
+            KEYEVENTF_SCANCODE | KEYEVENTF_EXTENDEDKEY | KEYEVENTF_KEYUP
+        );
+    }
+
+    /// Test that wheel and button events carry their data, and empty events produce nothing
+    #[test]
+    fn test_mouse_to_input() {
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        raw.Anonymous.Anonymous.usButtonFlags = 0x0400; // Vertical wheel
+        raw.Anonymous.Anonymous.usButtonData = (-240i16) as u16;
+        let inputs = RawInputEvent::Mouse(RawMouseEvent::from(&raw)).to_input();
+        let mi = unsafe { inputs[0].Anonymous.mi };
+        assert_eq!(mi.dwFlags, MOUSEEVENTF_WHEEL);
+        assert_eq!(mi.mouseData as i32, -240);
+
+        raw.Anonymous.Anonymous.usButtonFlags = 0x0100; // X button 2 down
+        raw.lLastX = 5;
+        let inputs = RawInputEvent::Mouse(RawMouseEvent::from(&raw)).to_input();
+        let mi = unsafe { inputs[0].Anonymous.mi };
+        assert_eq!(mi.dwFlags, MOUSEEVENTF_MOVE | MOUSEEVENTF_XDOWN);
+        assert_eq!(mi.mouseData, u32::from(XBUTTON2));
+        assert_eq!(mi.dx, 5);
+
+        let raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        assert!(
+            RawInputEvent::Mouse(RawMouseEvent::from(&raw))
+                .to_input()
+                .is_empty()
+        );
+    }
+}
@@ -1,8 +1,27 @@
 //! Keyboard event types and conversions for the Raw Input API
 
+use crate::device::RawInputDeviceHandle;
+use crate::keycode::KeyCode;
+use crate::modifiers::Modifiers;
+use crate::raw::RawInputRaw;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+#[cfg(windows)]
+use windows::Win32::UI::Input::KeyboardAndMouse::{MAPVK_VSC_TO_VK, MapVirtualKeyW};
+#[cfg(windows)]
 use windows::Win32::UI::Input::RAWKEYBOARD;
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP};
 
+#[cfg(not(windows))]
+const WM_KEYDOWN: u32 = 0x0100;
+#[cfg(not(windows))]
+const WM_KEYUP: u32 = 0x0101;
+#[cfg(not(windows))]
+const WM_SYSKEYDOWN: u32 = 0x0104;
+#[cfg(not(windows))]
+const WM_SYSKEYUP: u32 = 0x0105;
+
 /// Represents a keyboard event message based on legacy Windows keyboard input notification messages.
 ///
 /// This enum is derived from the `Message` field in the Windows `RAWKEYBOARD` structure,
@@ -48,14 +67,14 @@ impl From<u32> for KeyEventMessage {
 /// additional hardware-level information that can be useful for advanced keyboard handling.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct KeyFlags {
-    /// Whether the key is an extended key (e.g., right ALT/CTRL, arrow keys)
+    /// Whether the key has an E0 prefix (extended key)
+    /// The E0 prefix is used to distinguish between duplicate keys on modern keyboards
+    /// (e.g., right vs left Ctrl, numeric keypad vs. arrow keys)
     pub e0_prefix: bool,
     /// Whether the key was released (true) or pressed (false)
     /// This is the raw hardware state and corresponds to the RI_KEY_BREAK flag
     pub key_up: bool,
-    /// Whether the key has an E0 prefix
-    /// The E0 prefix is used to distinguish between duplicate keys on modern keyboards
-    /// (e.g., right vs left Ctrl, numeric keypad vs. arrow keys)
+    /// Whether the key has an E1 prefix (only used by the Pause key)
     pub e1_prefix: bool,
 }
 
@@ -78,12 +97,16 @@ impl From<u16> for KeyFlags {
 }
 
 /// Common virtual key codes
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u16)]
 pub enum VirtualKey {
     // Control keys
+    /// Break, i.e. Ctrl+Pause (`VK_CANCEL`)
+    Cancel = 0x03,
     Backspace = 0x08,
     Tab = 0x09,
+    /// Both Enter keys; Numpad Enter differs only by the E0 prefix of its scan code, see
+    /// [`KeyCode::NumpadEnter`]
     Enter = 0x0D,
     Shift = 0x10,
     Control = 0x11,
@@ -241,6 +264,9 @@ pub enum VirtualKey {
     OEM6 = 0xDD,
     /// '\'"' for US
     OEM8 = 0xDF,
+    /// A Unicode character injected with `SendInput` and `KEYEVENTF_UNICODE`
+    /// (`VK_PACKET`); see [`RawKeyboardEvent::packet_char`]
+    Packet = 0xE7,
 
     // Windows keys
     LWin = 0x5B,
@@ -254,6 +280,7 @@ pub enum VirtualKey {
 impl From<u16> for VirtualKey {
     fn from(vkey: u16) -> Self {
         match vkey {
+            0x03 => Self::Cancel,
             0x08 => Self::Backspace,
             0x09 => Self::Tab,
             0x0D => Self::Enter,
@@ -391,11 +418,182 @@ impl From<u16> for VirtualKey {
             0xDC => Self::OEM5,
             0xDD => Self::OEM6,
             0xDF => Self::OEM8,
+            0xE7 => Self::Packet,
             other => Self::Unknown(other),
         }
     }
 }
 
+impl From<VirtualKey> for u16 {
+    fn from(vkey: VirtualKey) -> Self {
+        match vkey {
+            VirtualKey::Unknown(code) => code,
+            // SAFETY: a `repr(u16)` enum starts with its `u16` discriminant, which is the
+            // virtual key code for every variant but `Unknown`
+            _ => unsafe { *(&vkey as *const VirtualKey).cast::<u16>() },
+        }
+    }
+}
+
+impl VirtualKey {
+    /// Returns the English name of the key, e.g. "Page Up" or "Num 5".
+    ///
+    /// Names of punctuation keys are their US layout labels, such as ";" for
+    /// [`OEM1`](Self::OEM1), and unknown keys are named by their code (e.g. "VK 0xFF").
+    /// Use [`localized_name`](Self::localized_name) for the name in the user's language.
+    pub fn name(self) -> String {
+        let code = u16::from(self);
+        let name = match code {
+            0x30..=0x39 | 0x41..=0x5A => return char::from(code as u8).to_string(),
+            0x60..=0x69 => return format!("Num {}", code - 0x60),
+            0x70..=0x87 => return format!("F{}", code - 0x6F),
+            0x03 => "Break",
+            0x08 => "Backspace",
+            0x09 => "Tab",
+            0x0D => "Enter",
+            0x10 => "Shift",
+            0x11 => "Ctrl",
+            0x12 => "Alt",
+            0x13 => "Pause",
+            0x14 => "Caps Lock",
+            0x1B => "Esc",
+            0x20 => "Space",
+            0x21 => "Page Up",
+            0x22 => "Page Down",
+            0x23 => "End",
+            0x24 => "Home",
+            0x25 => "Left",
+            0x26 => "Up",
+            0x27 => "Right",
+            0x28 => "Down",
+            0x2C => "Print Screen",
+            0x2D => "Insert",
+            0x2E => "Delete",
+            0x5B => "Left Windows",
+            0x5C => "Right Windows",
+            0x5D => "Menu",
+            0x6A => "Num *",
+            0x6B => "Num +",
+            0x6C => "Num Separator",
+            0x6D => "Num -",
+            0x6E => "Num .",
+            0x6F => "Num /",
+            0x90 => "Num Lock",
+            0x91 => "Scroll Lock",
+            0xA0 => "Left Shift",
+            0xA1 => "Right Shift",
+            0xA2 => "Left Ctrl",
+            0xA3 => "Right Ctrl",
+            0xA4 => "Left Alt",
+            0xA5 => "Right Alt",
+            0xA6 => "Browser Back",
+            0xA7 => "Browser Forward",
+            0xA8 => "Browser Refresh",
+            0xA9 => "Browser Stop",
+            0xAA => "Browser Search",
+            0xAB => "Browser Favorites",
+            0xAC => "Browser Home",
+            0xAD => "Volume Mute",
+            0xAE => "Volume Down",
+            0xAF => "Volume Up",
+            0xB0 => "Next Track",
+            0xB1 => "Previous Track",
+            0xB2 => "Stop Media",
+            0xB3 => "Play/Pause",
+            0xB4 => "Mail",
+            0xB5 => "Media Select",
+            0xB6 => "App 1",
+            0xB7 => "App 2",
+            0xBA => ";",
+            0xBB => "=",
+            0xBC => ",",
+            0xBD => "-",
+            0xBE => ".",
+            0xBF => "/",
+            0xC0 => "`",
+            0xDB => "[",
+            0xDC => "\\",
+            0xDD => "]",
+            0xDF => "OEM 8",
+            0xE7 => "Packet",
+            _ => return format!("VK 0x{code:02X}"),
+        };
+        name.to_string()
+    }
+}
+
+/// Returns the name Windows gives a key in the user's language (`GetKeyNameTextW`), by
+/// its scan code and prefix flags, or else the English name of the physical key
+/// ([`KeyCode::name`]), or None if neither knows the key.
+#[cfg(windows)]
+fn key_name_text(make_code: u16, flags: KeyFlags) -> Option<String> {
+    // GetKeyNameTextW knows the Pause key by the scan code of its trailing plain 45, and
+    // Num Lock (a plain 45 in raw input) by 45 with the extended bit
+    let localized = if flags.e1_prefix {
+        (make_code == SCAN_PAUSE_E1)
+            .then(|| crate::ffi::key_name_text(SCAN_NUM_LOCK, false))
+            .flatten()
+    } else {
+        let extended = flags.e0_prefix || make_code == SCAN_NUM_LOCK;
+        crate::ffi::key_name_text(make_code, extended)
+    };
+    localized.or_else(|| {
+        KeyCode::from_scan_code(make_code, flags)
+            .name()
+            .map(str::to_string)
+    })
+}
+
+#[cfg(windows)]
+impl VirtualKey {
+    /// Returns the name of the key in the user's language, as Windows shows it (e.g.
+    /// "Entrée" for Enter on French Windows), falling back to [`name`](Self::name).
+    ///
+    /// Windows names keys by scan code (`GetKeyNameTextW`), so the virtual key is first
+    /// mapped to a scan code with the current keyboard layout. Keys sharing a virtual key
+    /// get the name of one of them: Enter and Numpad Enter are both
+    /// [`Enter`](Self::Enter), and the navigation keys of the numpad are named after the
+    /// dedicated ones. Prefer [`RawKeyboardEvent::localized_key_name`], which names the
+    /// physical key by the event's scan code. Many keys, such as the media keys, have no
+    /// localized name and get the English one ([`KeyCode::name`] of the scan code).
+    /// `GetKeyNameTextW` uses the layout of the calling thread, so do not rely on the
+    /// localized names from threads whose layout differs from the user's.
+    pub fn localized_name(self) -> String {
+        let scan_code = crate::ffi::vkey_to_scan_code(u16::from(self));
+        let flags = KeyFlags {
+            e0_prefix: scan_code & 0xFF00 == 0xE000,
+            key_up: false,
+            e1_prefix: scan_code & 0xFF00 == 0xE100,
+        };
+        (scan_code != 0)
+            .then(|| key_name_text(scan_code as u16 & 0xFF, flags))
+            .flatten()
+            .unwrap_or_else(|| self.name())
+    }
+}
+
+/// Returns the reported virtual key code, or, if it is 0, the virtual key code the current
+/// keyboard layout maps the scan code to (`MapVirtualKeyW` with `MAPVK_VSC_TO_VK`).
+///
+/// The E0/E1 prefix is passed in the high byte of the scan code, as supported since
+/// Windows Vista. Returns 0 if the scan code does not map to a virtual key either.
+#[cfg(windows)]
+pub(crate) fn resolve_vkey(vkey: u16, make_code: u16, flags: KeyFlags) -> u16 {
+    if vkey != 0 {
+        return vkey;
+    }
+
+    let prefix = if flags.e0_prefix {
+        0xE000
+    } else if flags.e1_prefix {
+        0xE100
+    } else {
+        0
+    };
+    let mapped = unsafe { MapVirtualKeyW(u32::from(make_code) | prefix, MAPVK_VSC_TO_VK) };
+    mapped as u16
+}
+
 /// Data for a raw keyboard event, parsed from the Windows RAWKEYBOARD struct.
 ///
 /// This struct provides a more ergonomic interface compared to the raw Windows API,
@@ -437,9 +635,13 @@ impl From<u16> for VirtualKey {
 /// - `key_up` is `true` if the key was released, `false` if pressed (from the raw flags)
 /// - `extended` is `true` if the key has the E0 or E1 prefix (extended key)
 /// - `message` is the raw Windows message (e.g., WM_KEYDOWN, WM_KEYUP, etc.)
-/// - `vkey` is the Windows virtual key code mapped to a Rust enum
+/// - `vkey` is the Windows virtual key code mapped to a Rust enum (resolved from the scan
+///   code when the OS reports none)
+/// - `raw_vkey` is the virtual key code exactly as reported by the OS (possibly 0)
 /// - `make_code` is the hardware scan code
+/// - `flags` are the raw scan code flags, keeping the E0 and E1 prefixes apart
 /// - `extra_information` is additional driver/hardware info
+/// - `timestamp` / `system_time` are the monotonic and (optional) wall-clock arrival times
 #[derive(Debug, Clone)]
 pub struct RawKeyboardEvent {
     /// Scan code of the key (hardware-dependent)
@@ -448,28 +650,353 @@ pub struct RawKeyboardEvent {
     pub key_up: bool,
     /// Whether the key is extended (E0 or E1 prefix)
     pub extended: bool,
+    /// Raw scan code flags (break, E0 and E1 prefixes)
+    pub flags: KeyFlags,
     /// The raw Windows message (WM_KEYDOWN, WM_KEYUP, etc.)
     pub message: KeyEventMessage,
     /// Virtual key code (Windows virtual key code mapped to a Rust enum)
+    ///
+    /// Some keys (certain multimedia and OEM keys) are reported with a virtual key code of 0;
+    /// for those, this is resolved from the scan code with the current keyboard layout.
     pub vkey: VirtualKey,
+    /// Virtual key code exactly as reported in `RAWKEYBOARD.VKey` (may be 0)
+    pub raw_vkey: u16,
     /// Additional driver/hardware info
     pub extra_information: u32,
+    /// Device that produced the event, as reported in `RAWINPUTHEADER.hDevice`
+    ///
+    /// Input injected with `SendInput` (including replayed events) has no device and
+    /// reports the default (0) handle.
+    pub device: RawInputDeviceHandle,
+    /// Modifiers held once this event is taken into account, as tracked by the listener
+    ///
+    /// A Ctrl press therefore carries [`Modifiers::CTRL`] and its release does not. Always
+    /// empty for events that did not go through a listener.
+    pub modifiers: Modifiers,
+    /// The verbatim `RAWKEYBOARD` fields, if enabled via
+    /// [`ListenerBuilder::include_raw`](crate::ListenerBuilder::include_raw)
+    pub raw: Option<RawInputRaw>,
+    /// Monotonic time at which the listener dequeued the event
+    pub timestamp: Instant,
+    /// Wall-clock time at which the listener dequeued the event, if enabled via
+    /// [`ListenerBuilder::capture_system_time`](crate::ListenerBuilder::capture_system_time)
+    pub system_time: Option<SystemTime>,
+    /// Tick count of the WM_INPUT message the event was read from (`GetMessageTime`), in
+    /// milliseconds since system start; 0 for events that did not come from a message
+    pub message_time: u32,
+    /// Executable file name of the foreground window's process when the event was read
+    /// (e.g. "notepad.exe"), if enabled via
+    /// [`ListenerBuilder::capture_foreground_process`](crate::ListenerBuilder::capture_foreground_process)
+    pub foreground_process: Option<Arc<str>>,
 }
 
+#[cfg(windows)]
 impl From<&RAWKEYBOARD> for RawKeyboardEvent {
     fn from(kbd: &RAWKEYBOARD) -> Self {
-        let flags = kbd.Flags;
-        let key_up = (flags & 0x01) != 0;
-        let e0 = (flags & 0x02) != 0;
-        let e1 = (flags & 0x04) != 0;
-        let extended = e0 || e1;
+        let flags = KeyFlags::from(kbd.Flags);
         Self {
             make_code: kbd.MakeCode,
-            key_up,
-            extended,
+            key_up: flags.key_up,
+            extended: flags.e0_prefix || flags.e1_prefix,
+            flags,
             message: KeyEventMessage::from(kbd.Message),
-            vkey: VirtualKey::from(kbd.VKey),
+            vkey: VirtualKey::from(resolve_vkey(kbd.VKey, kbd.MakeCode, flags)),
+            raw_vkey: kbd.VKey,
             extra_information: kbd.ExtraInformation,
+            device: RawInputDeviceHandle::default(),
+            modifiers: Modifiers::NONE,
+            raw: None,
+            timestamp: Instant::now(),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        }
+    }
+}
+
+/// Virtual key code Windows reports for the Pause key and for Ctrl+NumLock
+const VK_PAUSE: u16 = 0x13;
+/// Virtual key code Windows reports for the key events of escaped sequences it discards
+const VK_NONE: u16 = 0xFF;
+/// Scan code of the Num Lock key
+const SCAN_NUM_LOCK: u16 = 0x45;
+/// Scan code of the first event of the Pause key's E1 sequence
+const SCAN_PAUSE_E1: u16 = 0x1D;
+
+/// Returns the virtual key code of the numpad key with scan code `make_code` (without E0
+/// prefix) when NumLock is on, as opposed to the navigation key it maps to otherwise.
+fn numpad_vkey(make_code: u16) -> Option<u16> {
+    Some(match make_code {
+        0x47 => 0x67, // Numpad 7 / Home
+        0x48 => 0x68, // Numpad 8 / Up
+        0x49 => 0x69, // Numpad 9 / Page Up
+        0x4B => 0x64, // Numpad 4 / Left
+        0x4C => 0x65, // Numpad 5 / Clear
+        0x4D => 0x66, // Numpad 6 / Right
+        0x4F => 0x61, // Numpad 1 / End
+        0x50 => 0x62, // Numpad 2 / Down
+        0x51 => 0x63, // Numpad 3 / Page Down
+        0x52 => 0x60, // Numpad 0 / Insert
+        0x53 => 0x6E, // Numpad decimal / Delete
+        _ => return None,
+    })
+}
+
+/// Scan code of the left Shift key
+const SCAN_LEFT_SHIFT: u16 = 0x2A;
+/// Scan code of the right Shift key
+const SCAN_RIGHT_SHIFT: u16 = 0x36;
+
+impl RawKeyboardEvent {
+    /// Returns true if this is a "fake shift" event synthesized by the keyboard.
+    ///
+    /// With NumLock on, pressing a numpad key while Shift is held makes the keyboard send
+    /// a Shift release before the key (so it acts as Home, Left, ... instead of a digit)
+    /// and a Shift press after it. Keys such as Print Screen and the dedicated navigation
+    /// keys can emit similar sequences. These Shift events carry the E0 prefix, which a
+    /// real Shift key never has, and do not correspond to any physical key movement.
+    ///
+    /// Held-key tracking ignores them; use
+    /// [`ListenerBuilder::filter_fake_shift`](crate::ListenerBuilder::filter_fake_shift)
+    /// to also keep them from the event callback.
+    pub fn is_fake_shift(&self) -> bool {
+        self.flags.e0_prefix && matches!(self.make_code, SCAN_LEFT_SHIFT | SCAN_RIGHT_SHIFT)
+    }
+
+    /// Returns the virtual key code the foreground keyboard layout assigns to this event's
+    /// scan code (`MapVirtualKeyExW`), or 0 if it assigns none.
+    ///
+    /// Compare with `raw_vkey`, the code Windows reported, or use
+    /// [`remapped`](Self::remapped).
+    #[cfg(windows)]
+    pub fn scan_code_vkey(&self) -> u16 {
+        let prefix = if self.flags.e0_prefix {
+            0xE000
+        } else if self.flags.e1_prefix {
+            0xE100
+        } else {
+            0
+        };
+        crate::ffi::scan_code_to_vkey(
+            u32::from(self.make_code) | prefix,
+            crate::ffi::foreground_keyboard_layout(),
+        )
+    }
+
+    /// Returns true if the reported virtual key differs from the one the keyboard layout
+    /// assigns to the scan code, i.e. software remapping (PowerToys Keyboard Manager, a
+    /// remapping driver) is likely in play.
+    ///
+    /// Firmware remapping (e.g. QMK) changes the scan code itself and cannot be detected.
+    /// Differences Windows itself introduces are not flagged: numpad keys producing their
+    /// digit codes with NumLock on, [Ctrl+NumLock](Self::is_ctrl_num_lock) reported as
+    /// Pause, [fake shifts](Self::is_fake_shift), injected Unicode characters (see
+    /// [`packet_char`](Self::packet_char)) and events without a scan code or virtual key.
+    /// The mapping uses the foreground window's layout, which normally is the one Windows
+    /// translated with.
+    #[cfg(windows)]
+    pub fn remapped(&self) -> bool {
+        if self.make_code == 0
+            || self.raw_vkey == 0
+            || self.vkey == VirtualKey::Packet
+            || self.is_fake_shift()
+            || self.is_ctrl_num_lock()
+        {
+            return false;
+        }
+        let numpad = !self.flags.e0_prefix && numpad_vkey(self.make_code) == Some(self.raw_vkey);
+        let implied = self.scan_code_vkey();
+        implied != 0 && implied != self.raw_vkey && !numpad
+    }
+
+    /// Returns true if this event comes from the dedicated Pause key.
+    ///
+    /// The Pause key sends the scan code sequence `E1 1D 45`, which Raw Input reports as
+    /// two events: `E1 1D` with [`VirtualKey::Pause`] (this one) and a plain `45` with a
+    /// virtual key of `0xFF`. The listener drops the second event, as it carries no
+    /// information and would otherwise read as Num Lock. The key also sends its release
+    /// sequence right after the press, so its down and up events arrive together when it
+    /// is pressed and nothing is reported when it is let go.
+    ///
+    /// Pressed together with Ctrl, the key sends `E0 46` instead, reported as
+    /// [`VirtualKey::Cancel`] (Break).
+    pub fn is_pause_key(&self) -> bool {
+        self.flags.e1_prefix && self.make_code == SCAN_PAUSE_E1
+    }
+
+    /// Returns true if this is Ctrl+Num Lock, which Windows reports as [`VirtualKey::Pause`].
+    ///
+    /// Keyboards without a Pause key emulate it with Ctrl+Num Lock, so `vkey` is
+    /// [`VirtualKey::Pause`] just like for the dedicated key, but the scan code is that of
+    /// Num Lock (`45`, without the E1 prefix) and [`key_code`](Self::key_code) is
+    /// [`KeyCode::NumLock`]. Check [`is_pause_key`](Self::is_pause_key) to bind to the
+    /// dedicated key only.
+    pub fn is_ctrl_num_lock(&self) -> bool {
+        self.raw_vkey == VK_PAUSE && !self.flags.e1_prefix && self.make_code == SCAN_NUM_LOCK
+    }
+
+    /// Returns true if this is the trailing plain `45` event of the Pause key's `E1 1D 45`
+    /// sequence, which the listener drops (see [`is_pause_key`](Self::is_pause_key)).
+    pub(crate) fn is_pause_continuation(&self) -> bool {
+        self.raw_vkey == VK_NONE && !self.flags.e1_prefix && self.make_code == SCAN_NUM_LOCK
+    }
+
+    /// Returns the name of the key in the user's language, as Windows shows it in
+    /// shortcut lists (`GetKeyNameTextW`), e.g. "Entrée" for Enter on French Windows.
+    ///
+    /// The name is looked up by the event's scan code, so it names the physical key:
+    /// Numpad Enter and Enter get different names, unlike with
+    /// [`VirtualKey::localized_name`]. Keys Windows has no name for, such as many media
+    /// keys or the numpad separator, get the English name of their physical key from a
+    /// built-in table of scan codes ([`KeyCode::name`]), and keys missing from it as well
+    /// (injected events without a scan code) the English [`VirtualKey::name`] of `vkey`.
+    #[cfg(windows)]
+    pub fn localized_key_name(&self) -> String {
+        if self.make_code == 0 {
+            return self.vkey.name();
         }
+        key_name_text(self.make_code, self.flags).unwrap_or_else(|| self.vkey.name())
+    }
+
+    /// Returns true if the key is on the numeric keypad, by its scan code.
+    ///
+    /// With Num Lock off (or Shift held), Windows reports the numpad digits with the
+    /// virtual keys of the navigation keys they double as, e.g. [`VirtualKey::Up`] for
+    /// Numpad 8, so `vkey` cannot tell them apart. The scan code can: the navigation
+    /// keys carry the E0 prefix and the numpad keys do not. See [`KeyCode::is_numpad`]
+    /// for which keys count.
+    pub fn is_numpad(&self) -> bool {
+        self.key_code().is_numpad()
+    }
+
+    /// Returns the physical, layout-independent [`KeyCode`] of this key.
+    ///
+    /// This is derived from the scan code and prefix flags, not from `vkey`, so it stays
+    /// the same regardless of the active keyboard layout.
+    pub fn key_code(&self) -> KeyCode {
+        KeyCode::from_scan_code(self.make_code, self.flags)
+    }
+
+    /// Returns the UTF-16 code unit of a Unicode character injected with `SendInput` and
+    /// `KEYEVENTF_UNICODE`, or None for other events.
+    ///
+    /// Such input has the virtual key [`VirtualKey::Packet`] (`VK_PACKET`) and carries the
+    /// code unit in place of the scan code, so [`key_code`](Self::key_code) means nothing
+    /// for it. A character outside the Basic Multilingual Plane (e.g. most emoji) is
+    /// injected as two characters, one per surrogate, each pressed and released.
+    pub fn packet_code_unit(&self) -> Option<u16> {
+        (self.vkey == VirtualKey::Packet && self.make_code != 0).then_some(self.make_code)
+    }
+
+    /// Returns the Unicode character injected with `SendInput` and `KEYEVENTF_UNICODE`,
+    /// or None for other events, e.g. to capture text that tools type without the
+    /// clipboard.
+    ///
+    /// The halves of a surrogate pair are no characters by themselves and return None too;
+    /// [`TextTranslator`](crate::TextTranslator) joins them, or decode
+    /// [`packet_code_unit`](Self::packet_code_unit)s with [`char::decode_utf16`].
+    pub fn packet_char(&self) -> Option<char> {
+        self.packet_code_unit()
+            .and_then(|unit| char::from_u32(u32::from(unit)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that virtual keys convert back to their codes
+    #[test]
+    fn test_virtual_key_code() {
+        for code in [0x03, 0x41, 0x5D, 0x5E, 0x87, 0xDF, 0xFF] {
+            assert_eq!(u16::from(VirtualKey::from(code)), code);
+        }
+        assert_eq!(u16::from(VirtualKey::Enter), 0x0D);
+    }
+
+    /// Test the English key names
+    #[test]
+    fn test_virtual_key_name() {
+        assert_eq!(VirtualKey::A.name(), "A");
+        assert_eq!(VirtualKey::Key7.name(), "7");
+        assert_eq!(VirtualKey::F24.name(), "F24");
+        assert_eq!(VirtualKey::Numpad5.name(), "Num 5");
+        assert_eq!(VirtualKey::PageUp.name(), "Page Up");
+        assert_eq!(VirtualKey::OEM5.name(), "\\");
+        assert_eq!(VirtualKey::Unknown(0xFF).name(), "VK 0xFF");
+    }
+
+    /// Test that an injected Unicode character is read from the scan code of VK_PACKET
+    #[test]
+    fn test_packet_char() {
+        let packet = |make_code: u16, vkey: VirtualKey| RawKeyboardEvent {
+            make_code,
+            key_up: false,
+            extended: false,
+            flags: KeyFlags::from(0),
+            message: KeyEventMessage::KeyDown,
+            vkey,
+            raw_vkey: u16::from(vkey),
+            extra_information: 0,
+            device: RawInputDeviceHandle::default(),
+            modifiers: Modifiers::NONE,
+            raw: None,
+            timestamp: Instant::now(),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        };
+
+        assert_eq!(VirtualKey::from(0xE7), VirtualKey::Packet);
+        assert_eq!(packet(0x20AC, VirtualKey::Packet).packet_char(), Some('€'));
+        // The high surrogate of U+1F600
+        let high = packet(0xD83D, VirtualKey::Packet);
+        assert_eq!(high.packet_code_unit(), Some(0xD83D));
+        assert_eq!(high.packet_char(), None);
+        assert_eq!(packet(0x1E, VirtualKey::A).packet_char(), None);
+    }
+
+    /// Builds a keyboard event from the RAWKEYBOARD fields that identify a key
+    #[cfg(windows)]
+    fn key(make_code: u16, flags: u16, vkey: u16) -> RawKeyboardEvent {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.MakeCode = make_code;
+        raw.Flags = flags;
+        raw.VKey = vkey;
+        RawKeyboardEvent::from(&raw)
+    }
+
+    /// Test that the differences Windows itself introduces are not flagged as remapping
+    #[cfg(windows)]
+    #[test]
+    fn test_remapped_ignores_windows_translations() {
+        assert!(!key(0x45, 0, 0x13).remapped()); // Ctrl+NumLock reported as Pause
+        assert!(!key(0x2A, 0x02, 0x10).remapped()); // Fake shift
+        assert!(!key(0, 0, 0xE7).remapped()); // Injected Unicode character (VK_PACKET)
+        assert!(!key(0x20AC, 0, 0xE7).remapped()); // Its UTF-16 code unit as the scan code
+    }
+
+    /// Test that the E0 prefix tells Numpad Enter from Enter, which share a virtual key,
+    /// while the two Plus keys already differ by virtual key
+    #[cfg(windows)]
+    #[test]
+    fn test_enter_and_plus_keys() {
+        let enter = key(0x1C, 0, 0x0D);
+        let numpad_enter = key(0x1C, 0x02, 0x0D); // RI_KEY_E0
+        assert_eq!(enter.vkey, numpad_enter.vkey);
+        assert_eq!(enter.key_code(), KeyCode::Enter);
+        assert_eq!(numpad_enter.key_code(), KeyCode::NumpadEnter);
+        assert!(!enter.is_numpad() && numpad_enter.is_numpad());
+
+        let plus = key(0x0D, 0, 0xBB);
+        let numpad_add = key(0x4E, 0, 0x6B);
+        assert_eq!(
+            (plus.vkey, plus.key_code()),
+            (VirtualKey::OEMPlus, KeyCode::Equal)
+        );
+        assert_eq!(
+            (numpad_add.vkey, numpad_add.key_code()),
+            (VirtualKey::NumpadAdd, KeyCode::NumpadAdd)
+        );
     }
 }
@@ -0,0 +1,666 @@
+//! Physical, layout-independent key codes derived from keyboard scan codes
+
+use crate::keyboard::KeyFlags;
+
+/// Physical key identity modeled on the W3C UI Events `KeyboardEvent.code` values.
+///
+/// Unlike [`VirtualKey`](crate::VirtualKey), which depends on the active keyboard layout,
+/// a `KeyCode` describes the *position* of the key: the key labeled `Q` on a US keyboard
+/// and `A` on a French keyboard both produce [`KeyCode::KeyQ`]. This makes it suitable for
+/// logic shared with other platforms, where the W3C code values are the common vocabulary.
+///
+/// The variant names match the W3C code strings exactly, so `format!("{code:?}")` yields
+/// the W3C value for every variant except [`KeyCode::Unknown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    // Writing system keys
+    Backquote,
+    Backslash,
+    BracketLeft,
+    BracketRight,
+    Comma,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Equal,
+    IntlBackslash,
+    IntlRo,
+    IntlYen,
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    Minus,
+    Period,
+    Quote,
+    Semicolon,
+    Slash,
+
+    // Functional keys
+    AltLeft,
+    AltRight,
+    Backspace,
+    CapsLock,
+    ContextMenu,
+    ControlLeft,
+    ControlRight,
+    Enter,
+    MetaLeft,
+    MetaRight,
+    ShiftLeft,
+    ShiftRight,
+    Space,
+    Tab,
+    Convert,
+    KanaMode,
+    Lang1,
+    Lang2,
+    NonConvert,
+
+    // Control pad and arrow keys
+    Delete,
+    End,
+    Home,
+    Insert,
+    PageDown,
+    PageUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+
+    // Numpad keys
+    NumLock,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadComma,
+    NumpadDecimal,
+    NumpadDivide,
+    /// Scan code `E0 1C`; the main Enter key is a plain `1C` ([`KeyCode::Enter`]), and both
+    /// report [`VirtualKey::Enter`](crate::VirtualKey::Enter)
+    NumpadEnter,
+    NumpadEqual,
+    NumpadMultiply,
+    NumpadSubtract,
+
+    // Function keys
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+
+    // Media and browser keys
+    BrowserBack,
+    BrowserFavorites,
+    BrowserForward,
+    BrowserHome,
+    BrowserRefresh,
+    BrowserSearch,
+    BrowserStop,
+    LaunchApp1,
+    LaunchApp2,
+    LaunchMail,
+    MediaPlayPause,
+    MediaSelect,
+    MediaStop,
+    MediaTrackNext,
+    MediaTrackPrevious,
+    AudioVolumeDown,
+    AudioVolumeMute,
+    AudioVolumeUp,
+    Power,
+    Sleep,
+    WakeUp,
+
+    /// Scan code without a known mapping, with the prefix folded into the high byte
+    /// (e.g. `0xE05E` for `E0 5E`)
+    Unknown(u16),
+}
+
+impl KeyCode {
+    /// Maps a set 1 scan code (as reported in `RAWKEYBOARD.MakeCode`) and its prefix flags
+    /// to a physical key code.
+    ///
+    /// Note that the Pause key is reported by Raw Input as an `E1 1D` event followed by a
+    /// plain `45` event; only the first maps to [`KeyCode::Pause`], while a plain `45` is
+    /// [`KeyCode::NumLock`]. The listener drops that second event, so a plain `45` it
+    /// delivers is Num Lock itself (possibly pressed with Ctrl, see
+    /// [`RawKeyboardEvent::is_ctrl_num_lock`](crate::RawKeyboardEvent::is_ctrl_num_lock)).
+    pub fn from_scan_code(make_code: u16, flags: KeyFlags) -> Self {
+        if flags.e1_prefix {
+            return match make_code {
+                0x1D => Self::Pause,
+                other => Self::Unknown(0xE100 | other),
+            };
+        }
+
+        if flags.e0_prefix {
+            return match make_code {
+                0x10 => Self::MediaTrackPrevious,
+                0x19 => Self::MediaTrackNext,
+                0x1C => Self::NumpadEnter,
+                0x1D => Self::ControlRight,
+                0x20 => Self::AudioVolumeMute,
+                0x21 => Self::LaunchApp2,
+                0x22 => Self::MediaPlayPause,
+                0x24 => Self::MediaStop,
+                0x2E => Self::AudioVolumeDown,
+                0x30 => Self::AudioVolumeUp,
+                0x32 => Self::BrowserHome,
+                0x35 => Self::NumpadDivide,
+                0x37 => Self::PrintScreen,
+                0x38 => Self::AltRight,
+                0x45 => Self::NumLock,
+                0x46 => Self::Pause, // Ctrl+Pause (Break)
+                0x47 => Self::Home,
+                0x48 => Self::ArrowUp,
+                0x49 => Self::PageUp,
+                0x4B => Self::ArrowLeft,
+                0x4D => Self::ArrowRight,
+                0x4F => Self::End,
+                0x50 => Self::ArrowDown,
+                0x51 => Self::PageDown,
+                0x52 => Self::Insert,
+                0x53 => Self::Delete,
+                0x5B => Self::MetaLeft,
+                0x5C => Self::MetaRight,
+                0x5D => Self::ContextMenu,
+                0x5E => Self::Power,
+                0x5F => Self::Sleep,
+                0x63 => Self::WakeUp,
+                0x65 => Self::BrowserSearch,
+                0x66 => Self::BrowserFavorites,
+                0x67 => Self::BrowserRefresh,
+                0x68 => Self::BrowserStop,
+                0x69 => Self::BrowserForward,
+                0x6A => Self::BrowserBack,
+                0x6B => Self::LaunchApp1,
+                0x6C => Self::LaunchMail,
+                0x6D => Self::MediaSelect,
+                other => Self::Unknown(0xE000 | other),
+            };
+        }
+
+        match make_code {
+            0x01 => Self::Escape,
+            0x02 => Self::Digit1,
+            0x03 => Self::Digit2,
+            0x04 => Self::Digit3,
+            0x05 => Self::Digit4,
+            0x06 => Self::Digit5,
+            0x07 => Self::Digit6,
+            0x08 => Self::Digit7,
+            0x09 => Self::Digit8,
+            0x0A => Self::Digit9,
+            0x0B => Self::Digit0,
+            0x0C => Self::Minus,
+            0x0D => Self::Equal,
+            0x0E => Self::Backspace,
+            0x0F => Self::Tab,
+            0x10 => Self::KeyQ,
+            0x11 => Self::KeyW,
+            0x12 => Self::KeyE,
+            0x13 => Self::KeyR,
+            0x14 => Self::KeyT,
+            0x15 => Self::KeyY,
+            0x16 => Self::KeyU,
+            0x17 => Self::KeyI,
+            0x18 => Self::KeyO,
+            0x19 => Self::KeyP,
+            0x1A => Self::BracketLeft,
+            0x1B => Self::BracketRight,
+            0x1C => Self::Enter,
+            0x1D => Self::ControlLeft,
+            0x1E => Self::KeyA,
+            0x1F => Self::KeyS,
+            0x20 => Self::KeyD,
+            0x21 => Self::KeyF,
+            0x22 => Self::KeyG,
+            0x23 => Self::KeyH,
+            0x24 => Self::KeyJ,
+            0x25 => Self::KeyK,
+            0x26 => Self::KeyL,
+            0x27 => Self::Semicolon,
+            0x28 => Self::Quote,
+            0x29 => Self::Backquote,
+            0x2A => Self::ShiftLeft,
+            0x2B => Self::Backslash,
+            0x2C => Self::KeyZ,
+            0x2D => Self::KeyX,
+            0x2E => Self::KeyC,
+            0x2F => Self::KeyV,
+            0x30 => Self::KeyB,
+            0x31 => Self::KeyN,
+            0x32 => Self::KeyM,
+            0x33 => Self::Comma,
+            0x34 => Self::Period,
+            0x35 => Self::Slash,
+            0x36 => Self::ShiftRight,
+            0x37 => Self::NumpadMultiply,
+            0x38 => Self::AltLeft,
+            0x39 => Self::Space,
+            0x3A => Self::CapsLock,
+            0x3B => Self::F1,
+            0x3C => Self::F2,
+            0x3D => Self::F3,
+            0x3E => Self::F4,
+            0x3F => Self::F5,
+            0x40 => Self::F6,
+            0x41 => Self::F7,
+            0x42 => Self::F8,
+            0x43 => Self::F9,
+            0x44 => Self::F10,
+            0x45 => Self::NumLock,
+            0x46 => Self::ScrollLock,
+            0x47 => Self::Numpad7,
+            0x48 => Self::Numpad8,
+            0x49 => Self::Numpad9,
+            0x4A => Self::NumpadSubtract,
+            0x4B => Self::Numpad4,
+            0x4C => Self::Numpad5,
+            0x4D => Self::Numpad6,
+            0x4E => Self::NumpadAdd,
+            0x4F => Self::Numpad1,
+            0x50 => Self::Numpad2,
+            0x51 => Self::Numpad3,
+            0x52 => Self::Numpad0,
+            0x53 => Self::NumpadDecimal,
+            0x54 => Self::PrintScreen, // Alt+PrintScreen (SysRq)
+            0x56 => Self::IntlBackslash,
+            0x57 => Self::F11,
+            0x58 => Self::F12,
+            0x59 => Self::NumpadEqual,
+            0x64 => Self::F13,
+            0x65 => Self::F14,
+            0x66 => Self::F15,
+            0x67 => Self::F16,
+            0x68 => Self::F17,
+            0x69 => Self::F18,
+            0x6A => Self::F19,
+            0x6B => Self::F20,
+            0x6C => Self::F21,
+            0x6D => Self::F22,
+            0x6E => Self::F23,
+            0x70 => Self::KanaMode,
+            0x71 => Self::Lang2,
+            0x72 => Self::Lang1,
+            0x73 => Self::IntlRo,
+            0x76 => Self::F24,
+            0x79 => Self::Convert,
+            0x7B => Self::NonConvert,
+            0x7D => Self::IntlYen,
+            0x7E => Self::NumpadComma,
+            other => Self::Unknown(other),
+        }
+    }
+
+    /// Returns the English name of the key, with the US layout labels for the writing
+    /// system keys, e.g. "Num Separator" or ";" for [`Semicolon`](Self::Semicolon), or
+    /// None for [`Unknown`](Self::Unknown) keys.
+    ///
+    /// This is a built-in table, independent of the keyboard layout and of
+    /// `GetKeyNameTextW`, for which it stands in when Windows has no name for a key (see
+    /// [`RawKeyboardEvent::localized_key_name`](crate::RawKeyboardEvent::localized_key_name)).
+    /// The names match those of [`VirtualKey::name`](crate::VirtualKey::name) where both
+    /// name the same key.
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            Self::Backquote => "`",
+            Self::Backslash => "\\",
+            Self::BracketLeft => "[",
+            Self::BracketRight => "]",
+            Self::Comma => ",",
+            Self::Digit0 => "0",
+            Self::Digit1 => "1",
+            Self::Digit2 => "2",
+            Self::Digit3 => "3",
+            Self::Digit4 => "4",
+            Self::Digit5 => "5",
+            Self::Digit6 => "6",
+            Self::Digit7 => "7",
+            Self::Digit8 => "8",
+            Self::Digit9 => "9",
+            Self::Equal => "=",
+            Self::IntlBackslash => "Intl Backslash",
+            Self::IntlRo => "Intl Ro",
+            Self::IntlYen => "Intl Yen",
+            Self::KeyA => "A",
+            Self::KeyB => "B",
+            Self::KeyC => "C",
+            Self::KeyD => "D",
+            Self::KeyE => "E",
+            Self::KeyF => "F",
+            Self::KeyG => "G",
+            Self::KeyH => "H",
+            Self::KeyI => "I",
+            Self::KeyJ => "J",
+            Self::KeyK => "K",
+            Self::KeyL => "L",
+            Self::KeyM => "M",
+            Self::KeyN => "N",
+            Self::KeyO => "O",
+            Self::KeyP => "P",
+            Self::KeyQ => "Q",
+            Self::KeyR => "R",
+            Self::KeyS => "S",
+            Self::KeyT => "T",
+            Self::KeyU => "U",
+            Self::KeyV => "V",
+            Self::KeyW => "W",
+            Self::KeyX => "X",
+            Self::KeyY => "Y",
+            Self::KeyZ => "Z",
+            Self::Minus => "-",
+            Self::Period => ".",
+            Self::Quote => "'",
+            Self::Semicolon => ";",
+            Self::Slash => "/",
+            Self::AltLeft => "Left Alt",
+            Self::AltRight => "Right Alt",
+            Self::Backspace => "Backspace",
+            Self::CapsLock => "Caps Lock",
+            Self::ContextMenu => "Menu",
+            Self::ControlLeft => "Left Ctrl",
+            Self::ControlRight => "Right Ctrl",
+            Self::Enter => "Enter",
+            Self::MetaLeft => "Left Windows",
+            Self::MetaRight => "Right Windows",
+            Self::ShiftLeft => "Left Shift",
+            Self::ShiftRight => "Right Shift",
+            Self::Space => "Space",
+            Self::Tab => "Tab",
+            Self::Convert => "Convert",
+            Self::KanaMode => "Kana",
+            Self::Lang1 => "Lang 1",
+            Self::Lang2 => "Lang 2",
+            Self::NonConvert => "Non-Convert",
+            Self::Delete => "Delete",
+            Self::End => "End",
+            Self::Home => "Home",
+            Self::Insert => "Insert",
+            Self::PageDown => "Page Down",
+            Self::PageUp => "Page Up",
+            Self::ArrowDown => "Down",
+            Self::ArrowLeft => "Left",
+            Self::ArrowRight => "Right",
+            Self::ArrowUp => "Up",
+            Self::NumLock => "Num Lock",
+            Self::Numpad0 => "Num 0",
+            Self::Numpad1 => "Num 1",
+            Self::Numpad2 => "Num 2",
+            Self::Numpad3 => "Num 3",
+            Self::Numpad4 => "Num 4",
+            Self::Numpad5 => "Num 5",
+            Self::Numpad6 => "Num 6",
+            Self::Numpad7 => "Num 7",
+            Self::Numpad8 => "Num 8",
+            Self::Numpad9 => "Num 9",
+            Self::NumpadAdd => "Num +",
+            Self::NumpadComma => "Num Separator",
+            Self::NumpadDecimal => "Num .",
+            Self::NumpadDivide => "Num /",
+            Self::NumpadEnter => "Num Enter",
+            Self::NumpadEqual => "Num =",
+            Self::NumpadMultiply => "Num *",
+            Self::NumpadSubtract => "Num -",
+            Self::Escape => "Esc",
+            Self::F1 => "F1",
+            Self::F2 => "F2",
+            Self::F3 => "F3",
+            Self::F4 => "F4",
+            Self::F5 => "F5",
+            Self::F6 => "F6",
+            Self::F7 => "F7",
+            Self::F8 => "F8",
+            Self::F9 => "F9",
+            Self::F10 => "F10",
+            Self::F11 => "F11",
+            Self::F12 => "F12",
+            Self::F13 => "F13",
+            Self::F14 => "F14",
+            Self::F15 => "F15",
+            Self::F16 => "F16",
+            Self::F17 => "F17",
+            Self::F18 => "F18",
+            Self::F19 => "F19",
+            Self::F20 => "F20",
+            Self::F21 => "F21",
+            Self::F22 => "F22",
+            Self::F23 => "F23",
+            Self::F24 => "F24",
+            Self::PrintScreen => "Print Screen",
+            Self::ScrollLock => "Scroll Lock",
+            Self::Pause => "Pause",
+            Self::BrowserBack => "Browser Back",
+            Self::BrowserFavorites => "Browser Favorites",
+            Self::BrowserForward => "Browser Forward",
+            Self::BrowserHome => "Browser Home",
+            Self::BrowserRefresh => "Browser Refresh",
+            Self::BrowserSearch => "Browser Search",
+            Self::BrowserStop => "Browser Stop",
+            Self::LaunchApp1 => "App 1",
+            Self::LaunchApp2 => "App 2",
+            Self::LaunchMail => "Mail",
+            Self::MediaPlayPause => "Play/Pause",
+            Self::MediaSelect => "Media Select",
+            Self::MediaStop => "Stop Media",
+            Self::MediaTrackNext => "Next Track",
+            Self::MediaTrackPrevious => "Previous Track",
+            Self::AudioVolumeDown => "Volume Down",
+            Self::AudioVolumeMute => "Volume Mute",
+            Self::AudioVolumeUp => "Volume Up",
+            Self::Power => "Power",
+            Self::Sleep => "Sleep",
+            Self::WakeUp => "Wake Up",
+            Self::Unknown(_) => return None,
+        })
+    }
+
+    /// Returns true for the keys of the numeric keypad: the digits, operators, decimal
+    /// separator and Numpad Enter (but not Num Lock).
+    ///
+    /// Numpad keys are told apart from their navigation twins by the missing E0 prefix
+    /// (plain `48` is Numpad 8, `E0 48` the Up arrow), and Numpad Enter and Divide by its
+    /// presence, regardless of Num Lock and of the virtual key Windows reports.
+    pub fn is_numpad(self) -> bool {
+        matches!(
+            self,
+            Self::Numpad0
+                | Self::Numpad1
+                | Self::Numpad2
+                | Self::Numpad3
+                | Self::Numpad4
+                | Self::Numpad5
+                | Self::Numpad6
+                | Self::Numpad7
+                | Self::Numpad8
+                | Self::Numpad9
+                | Self::NumpadAdd
+                | Self::NumpadComma
+                | Self::NumpadDecimal
+                | Self::NumpadDivide
+                | Self::NumpadEnter
+                | Self::NumpadEqual
+                | Self::NumpadMultiply
+                | Self::NumpadSubtract
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(e0_prefix: bool, e1_prefix: bool) -> KeyFlags {
+        KeyFlags {
+            e0_prefix,
+            key_up: false,
+            e1_prefix,
+        }
+    }
+
+    #[test]
+    fn test_writing_system_keys() {
+        assert_eq!(
+            KeyCode::from_scan_code(0x1E, flags(false, false)),
+            KeyCode::KeyA
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x10, flags(false, false)),
+            KeyCode::KeyQ
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x02, flags(false, false)),
+            KeyCode::Digit1
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x0B, flags(false, false)),
+            KeyCode::Digit0
+        );
+    }
+
+    #[test]
+    fn test_e0_prefix_distinguishes_keys() {
+        assert_eq!(
+            KeyCode::from_scan_code(0x1D, flags(false, false)),
+            KeyCode::ControlLeft
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x1D, flags(true, false)),
+            KeyCode::ControlRight
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x48, flags(false, false)),
+            KeyCode::Numpad8
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x48, flags(true, false)),
+            KeyCode::ArrowUp
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x1C, flags(false, false)),
+            KeyCode::Enter
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x1C, flags(true, false)),
+            KeyCode::NumpadEnter
+        );
+    }
+
+    #[test]
+    fn test_numpad_keys() {
+        let numpad = |make_code, e0_prefix| {
+            KeyCode::from_scan_code(make_code, flags(e0_prefix, false)).is_numpad()
+        };
+        assert!(numpad(0x48, false)); // Numpad 8, whatever Num Lock says
+        assert!(!numpad(0x48, true)); // Up arrow
+        assert!(numpad(0x1C, true)); // Numpad Enter
+        assert!(!numpad(0x1C, false)); // Enter
+        assert!(numpad(0x37, false)); // Numpad multiply
+        assert!(!numpad(0x09, false)); // Digit 8
+        assert!(!numpad(0x45, false)); // Num Lock
+    }
+
+    #[test]
+    fn test_pause_and_unknown() {
+        assert_eq!(
+            KeyCode::from_scan_code(0x1D, flags(false, true)),
+            KeyCode::Pause
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x45, flags(false, false)),
+            KeyCode::NumLock
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x2A, flags(true, false)),
+            KeyCode::Unknown(0xE02A)
+        );
+        assert_eq!(
+            KeyCode::from_scan_code(0x7F, flags(false, false)),
+            KeyCode::Unknown(0x7F)
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let name = |make_code, e0_prefix| {
+            KeyCode::from_scan_code(make_code, flags(e0_prefix, false)).name()
+        };
+        assert_eq!(name(0x1E, false), Some("A"));
+        assert_eq!(name(0x27, false), Some(";"));
+        assert_eq!(name(0x1C, false), Some("Enter"));
+        assert_eq!(name(0x1C, true), Some("Num Enter"));
+        assert_eq!(name(0x48, false), Some("Num 8"));
+        assert_eq!(name(0x48, true), Some("Up"));
+        assert_eq!(name(0x7E, false), Some("Num Separator"));
+        assert_eq!(name(0x56, false), Some("Intl Backslash"));
+        assert_eq!(name(0x30, true), Some("Volume Up"));
+        assert_eq!(name(0x7F, false), None);
+        assert_eq!(
+            KeyCode::from_scan_code(0x1D, flags(false, true)).name(),
+            Some("Pause")
+        );
+    }
+}
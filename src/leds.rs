@@ -0,0 +1,114 @@
+//! Lock key toggle state and keyboard LEDs set through HID output reports
+
+/// HID usage page of LEDs
+const USAGE_PAGE_LED: u16 = 0x08;
+const USAGE_NUM_LOCK: u16 = 0x01;
+const USAGE_CAPS_LOCK: u16 = 0x02;
+const USAGE_SCROLL_LOCK: u16 = 0x03;
+
+/// On/off state of the three lock keys, or of the keyboard LEDs showing them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LockKeys {
+    /// Caps Lock
+    pub caps_lock: bool,
+    /// Num Lock
+    pub num_lock: bool,
+    /// Scroll Lock
+    pub scroll_lock: bool,
+}
+
+impl LockKeys {
+    /// Returns the LED usages (usage page 0x08) of the locks that are on.
+    fn led_usages(self) -> Vec<u16> {
+        [
+            (self.num_lock, USAGE_NUM_LOCK),
+            (self.caps_lock, USAGE_CAPS_LOCK),
+            (self.scroll_lock, USAGE_SCROLL_LOCK),
+        ]
+        .into_iter()
+        .filter_map(|(on, usage)| on.then_some(usage))
+        .collect()
+    }
+}
+
+#[cfg(windows)]
+pub use device_leds::{lock_keys, set_keyboard_leds};
+
+#[cfg(windows)]
+mod device_leds {
+    use super::*;
+    use crate::{RawInputDeviceHandle, RawInputError, ffi};
+    use windows::Win32::UI::Input::KeyboardAndMouse::{VK_CAPITAL, VK_NUMLOCK, VK_SCROLL};
+
+    /// Returns the toggle state of the lock keys, which is what the keyboard LEDs show
+    /// unless an application changed them.
+    ///
+    /// Windows does not offer a way to read the LEDs themselves: keyboards take the LED
+    /// state as an output report but do not report it back. The toggle state is read with
+    /// `GetKeyState` and is shared by all keyboards.
+    pub fn lock_keys() -> LockKeys {
+        LockKeys {
+            caps_lock: ffi::key_toggled(VK_CAPITAL.0),
+            num_lock: ffi::key_toggled(VK_NUMLOCK.0),
+            scroll_lock: ffi::key_toggled(VK_SCROLL.0),
+        }
+    }
+
+    /// Turns the LEDs of a keyboard on or off, without changing the lock state.
+    ///
+    /// The LEDs are set with a HID output report (`HidD_SetOutputReport`), so this only
+    /// works with HID keyboards whose report descriptor declares LEDs, and device support
+    /// varies:
+    /// * Windows opens the keyboard collection of most keyboards for its own exclusive use,
+    ///   so opening it to write fails with `RawInputError::WinApiError` (access denied).
+    ///   Keyboards exposing their LEDs through another collection, and many custom or
+    ///   vendor devices, accept the report.
+    /// * The LEDs are not kept: Windows sends the real lock state to every keyboard the next
+    ///   time a lock key is pressed (or when a keyboard is connected).
+    ///
+    /// # Arguments
+    /// * `device` - Keyboard to change, e.g. [`RawKeyboardEvent::device`](crate::RawKeyboardEvent::device)
+    /// * `leds` - The LEDs to turn on; the others are turned off
+    ///
+    /// # Returns
+    /// * `Ok(())` - The report was accepted by the device
+    /// * `Err(RawInputError)` - If the device declares no LEDs or cannot be opened or written
+    pub fn set_keyboard_leds(
+        device: RawInputDeviceHandle,
+        leds: LockKeys,
+    ) -> Result<(), RawInputError> {
+        let preparsed = ffi::preparsed_data(device.as_handle()).map_err(|e| {
+            RawInputError::WinApiError(format!("Failed to read HID preparsed data: {e}"))
+        })?;
+        let report = ffi::hid_output_report(&preparsed, USAGE_PAGE_LED, &leds.led_usages())
+            .ok_or_else(|| {
+                RawInputError::Other(format!(
+                    "Device {:#x} declares no LED output report",
+                    device.as_raw()
+                ))
+            })?;
+        let path = ffi::device_name(device.as_handle()).map_err(|e| {
+            RawInputError::WinApiError(format!("Failed to read the device name: {e}"))
+        })?;
+        ffi::send_output_report(&path, &report).map_err(|e| {
+            RawInputError::WinApiError(format!("Failed to send the LED output report: {e}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that each lock maps to its LED usage
+    #[test]
+    fn test_led_usages() {
+        assert!(LockKeys::default().led_usages().is_empty());
+        let leds = LockKeys {
+            caps_lock: true,
+            num_lock: false,
+            scroll_lock: true,
+        };
+        assert_eq!(leds.led_usages(), vec![USAGE_CAPS_LOCK, USAGE_SCROLL_LOCK]);
+    }
+}
@@ -21,6 +21,7 @@
 //!         match event {
 //!             RawInputEvent::Keyboard(kbd) => println!("Keyboard: {kbd:?}"),
 //!             RawInputEvent::Mouse(mouse) => println!("Mouse: {mouse:?}"),
+//!             RawInputEvent::Hid(hid) => println!("HID: {hid:?}"),
 //!         }
 //!     },
 //!     Some(|err| eprintln!("Raw input runtime error: {err}")),
@@ -104,6 +105,9 @@
 //!                 eprintln!("Windows API error: {}", msg);
 //!                 // You might want to log this or take specific action
 //!             },
+//!             RawInputError::AccessDenied(msg) => {
+//!                 eprintln!("Input blocked: {}", msg);
+//!             },
 //!             RawInputError::Other(msg) => {
 //!                 eprintln!("Other error: {}", msg);
 //!             },
@@ -113,6 +117,10 @@
 //! std::thread::park();
 //! ```
 //!
+//! When no error callback is set, runtime errors are printed to stderr. Use
+//! [`ListenerBuilder::silent`] to suppress all direct output (e.g. in services) and query
+//! [`ListenerHandle::last_error`] instead.
+//!
 //! ## Stopping the Listener
 //! The listener will automatically stop and clean up resources when the `ListenerHandle` is dropped:
 //! ```rust,no_run
@@ -138,72 +146,301 @@
 //!
 //! ## API
 //! - [`start_listener`] - Start a background listener for raw input events; provide an event callback and optional error callback.
-//! - [`RawInputEvent`] - Enum for keyboard and mouse events.
+//! - [`start_listener_with_context`] - Like [`start_listener`], passing a user-owned context to a function pointer callback instead of capturing state in a closure.
+//! - [`start_listener_channel`] - Receive events and errors from an `mpsc` channel instead of a callback, for integration with existing event loops.
+//! - [`ListenerBuilder::start_polling`] - Queue events for [`ListenerHandle::poll_events`] instead of a callback, for game loops and immediate-mode GUIs.
+//! - [`capture_for`] - Record all input for a fixed duration and return it as a `Vec`, for quick scripts.
+//! - [`capture_next_key`] - Wait for the next key press and return its key, for "Press a key..." binding prompts.
+//! - [`ListenerBuilder`] - Configure the listener with additional options (e.g. [`ListenerBuilder::silent`]) before starting it.
+//! - [`RawInputEvent`] - Enum for keyboard, mouse and generic HID events; [`RawInputEvent::kind`] gives a cheap [`EventKind`] discriminant.
+//! - [`RawHidEventRef`] - Allocation-free, borrowed HID events delivered to a [`ListenerBuilder::hid_callback`].
+//! - [`ListenerBuilder::wheel_callback`] - Wheel-only subscription reporting fractional notches per [`WheelAxis`] for smooth scrolling.
+//! - [`ListenerBuilder::key_set_callback`] - [`KeySetChange`]s of the set of held keys, for combo systems and on-screen keyboards.
+//! - [`ListenerBuilder::tap_callback`] - [`KeyTap`]s for keys pressed and released quickly, told apart from holds.
+//! - [`UsageRegistration`] - Additional usage pages / usages to capture (e.g. a whole page via `RIDEV_PAGEONLY`).
+//! - [`RegistrationResult`] - Which registrations succeeded, as returned by [`ListenerBuilder::start_partial`].
 //! - [`RawKeyboardEvent`] / [`RawMouseEvent`] - Ergonomic Rust structs for event data.
-//! - [`ListenerHandle`] - Handle to the running listener; dropping this stops the listener.
+//! - [`RawInputRaw`] - The verbatim `RAWKEYBOARD` / `RAWMOUSE` fields next to the parsed data, for debugging (see [`ListenerBuilder::include_raw`]).
+//! - [`EventSource`] / [`VecEventSource`] - Drive the event pipeline from any source (e.g. a `Vec` in tests) via [`ListenerBuilder::run_with_source`].
+//! - [`is_foreground_elevated`] - Check whether an elevated window currently withholds input from this process (see [`ListenerBuilder::detect_uipi_blocking`]).
+//! - [`send_events`] / [`RawInputEvent::to_input`] - Replay captured events atomically with a single `SendInput` call.
+//! - [`write_events_csv`] - Export captured events as CSV with a documented column schema, for spreadsheets or `pandas`; [`ListenerBuilder::log_to`] writes them as they arrive.
+//! - [`RawInputEvent::to_bytes`] / [`RawInputEvent::from_bytes`] - Encode events in a compact, versioned binary format for streaming them to another process.
+//! - [`TimingStats`] - Statistics of the intervals between events, for diagnosing irregular input, via [`ListenerHandle::timing_stats`].
+//! - [`InputRecorder`] - Record input into an in-memory timeline that can be paused, resumed, cleared and exported, via [`ListenerBuilder::start_recorder`].
+//! - [`cursor_clip_rect`] / [`set_cursor_clip_rect`] - Query or set the cursor confinement (`ClipCursor`) used alongside relative mouse capture.
+//! - [`pointer_settings`] - Read the pointer speed and acceleration, which apply to the cursor but not to raw input.
+//! - [`system_dpi`] / [`window_dpi`] - DPI for scaling absolute pointer coordinates on high-DPI displays.
+//! - [`mouse_device_info`] - Query a mouse device's button count, sample rate and wheel support.
+//! - [`Button`] - A keyboard key or mouse button under one identity, with names for saving bindings, via [`RawInputEvent::button`].
+//! - [`KeyCode`] - Layout-independent physical key identity (W3C `code` values), via [`RawKeyboardEvent::key_code`].
+//! - [`AttachedListener`] - Raw input delivered to your own window's message loop (e.g. `winit`), via [`ListenerBuilder::attach`].
+//! - [`Modifiers`] - Sets of modifier keys, as carried by every keyboard event ([`RawKeyboardEvent::modifiers`]) and used by [`ListenerBuilder::require_modifiers`] to only deliver key events while they are held.
+//! - [`Hotkey`] - Key combinations, e.g. for an emergency-stop [`ListenerBuilder::panic_hotkey`] that toggles [`ListenerHandle::pause`] / [`ListenerHandle::resume`].
+//! - [`TextTranslator`] - Turn key presses into typed text with the active keyboard layout, including dead-key composition.
+//! - [`ListenerHandle`] - Handle to the running listener; dropping this stops the listener. Also tracks held keys and mouse buttons ([`ListenerHandle::pressed_keys`], [`ListenerHandle::reset_state`]), accumulates scrolling ([`ListenerHandle::take_scroll`]) and can retain recent events ([`ListenerHandle::recent_events`]).
 //!
 //! ## Platform
 //! - Windows only
 //!
+//! The crate still compiles on other platforms so that cross-platform workspaces can depend on
+//! it unconditionally: the public API keeps its shape, but [`start_listener`],
+//! [`ListenerBuilder::start`], [`ListenerBuilder::attach`], [`send_events`] and the other
+//! functions that need Win32 return [`RawInputError::Other`]`("unsupported platform")`
+//! ([`TextTranslator::translate`] returns None and [`is_foreground_elevated`] false).
+//! The event types and [`ListenerBuilder::run_with_source`] work everywhere.
+//!
 //! ## Implementation
 //!
 //! This library is built on top of the [`windows`](https://crates.io/crates/windows) crate for
 //! safe and idiomatic access to the Win32 API from Rust.
 
+// Without Win32 the stubs leave parts of the event pipeline unused
+#![cfg_attr(not(windows), allow(dead_code))]
+
+#[cfg(windows)]
+mod attach;
+mod button;
+mod device;
+#[cfg(windows)]
+mod dpi;
 mod event;
+mod export;
+#[cfg(windows)]
 mod ffi;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod hid;
+mod hotkey;
+#[cfg(windows)]
+mod inject;
 mod keyboard;
+mod keycode;
+#[cfg(feature = "leds")]
+mod leds;
+#[cfg(feature = "media-keys")]
+mod media;
+mod modifiers;
 mod mouse;
+#[cfg(feature = "pen")]
+mod pen;
+mod raw;
+#[cfg(windows)]
+mod reader;
+mod recorder;
+mod scroll;
+mod timing;
+#[cfg(not(windows))]
+mod unsupported;
+mod wire;
 
-pub use event::RawInputEvent;
+#[cfg(windows)]
+pub use attach::{AttachedListener, start_foreground_listener};
+pub use button::Button;
+pub use device::{
+    MouseDeviceInfo, RawInputDeviceHandle, RawInputDeviceType, RawInputHeader, filter_device,
+    recorded_devices,
+};
+#[cfg(windows)]
+pub use device::{mouse_device_info, read_raw_input_header};
+#[cfg(windows)]
+pub use dpi::{DEFAULT_DPI, system_dpi, window_dpi};
+pub use event::{EventKind, RawInputEvent};
+pub use export::write_events_csv;
+#[cfg(all(windows, feature = "gamepad"))]
+pub use gamepad::GamepadParser;
+#[cfg(feature = "gamepad")]
+pub use gamepad::{GamepadAxis, GamepadEvent, HatDirection};
+pub use hid::{RawHidEvent, RawHidEventRef};
+pub use hotkey::Hotkey;
+#[cfg(windows)]
+pub use inject::{send_event, send_events};
 pub use keyboard::RawKeyboardEvent;
 pub use mouse::RawMouseEvent;
+#[cfg(feature = "pen")]
+pub use pen::PenEvent;
+#[cfg(all(windows, feature = "pen"))]
+pub use pen::PenParser;
+pub use raw::RawInputRaw;
+pub use recorder::{InputRecorder, RecordingGap};
+pub use scroll::{ScrollSettings, ScrollStep, SmoothScrollAccumulator, WHEEL_PAGESCROLL};
+pub use timing::TimingStats;
+#[cfg(all(not(windows), feature = "gamepad"))]
+pub use unsupported::GamepadParser;
+#[cfg(all(not(windows), feature = "pen"))]
+pub use unsupported::PenParser;
+pub use wire::WIRE_FORMAT_VERSION;
 // Re-export key and mouse related enums for easier access
 pub use keyboard::{KeyEventMessage, KeyFlags, VirtualKey};
-pub use mouse::{MouseButtonAction, MouseMoveMode};
+pub use keycode::KeyCode;
+#[cfg(feature = "leds")]
+pub use leds::LockKeys;
+#[cfg(all(windows, feature = "leds"))]
+pub use leds::{lock_keys, set_keyboard_leds};
+#[cfg(feature = "media-keys")]
+pub use media::{MediaKeyEvent, MediaKeySource, MediaKeys};
+pub use modifiers::Modifiers;
+pub use mouse::{
+    MouseButton, MouseButtonAction, MouseMoveMode, NormalizeConfig, PointerSettings, PreviousMove,
+    WheelAxis,
+};
+#[cfg(windows)]
+pub use mouse::{cursor_clip_rect, pointer_settings, set_cursor_clip_rect};
+#[cfg(windows)]
+pub use registration::registered_devices;
+pub use registration::{
+    CaptureMode, CaptureScope, RegisteredDevice, RegistrationResult, UsageRegistration,
+};
+pub use session::SessionChange;
+#[cfg(not(windows))]
+pub use unsupported::{
+    AttachedListener, DEFAULT_DPI, ListenerHandle, TextTranslator, capture_for, capture_next_key,
+    cursor_clip_rect, is_foreground_elevated, mouse_device_info, pointer_settings,
+    read_raw_input_header, registered_devices, send_event, send_events, set_cursor_clip_rect,
+    start_foreground_listener, start_listener, start_listener_channel, start_listener_with_context,
+    system_dpi, window_dpi,
+};
+#[cfg(all(not(windows), feature = "leds"))]
+pub use unsupported::{lock_keys, set_keyboard_leds};
+#[cfg(windows)]
 use windows::Win32::UI::Input::RAWINPUT;
 
 /// Registers the library to receive raw input from keyboard and mouse devices.
 ///
+/// Each device type (and each additional usage) is registered independently, so a failure
+/// for one does not prevent capture from the others.
+///
 /// # Arguments
 /// * `hwnd` - Optional window handle. If provided, registers for input on that window. If None, attempts process-wide registration (generally requires at least one window).
+/// * `config` - The device types to capture and additional usage page / usage registrations
+/// * `flags` - Registration flags (see `ffi::LISTENER_FLAGS`)
 ///
 /// # Returns
-/// Ok(()) on success, or an error if registration fails.
+/// Ok with the outcome of every registration, or an error if nothing could be registered.
+#[cfg(windows)]
 pub(crate) fn register_raw_input(
     hwnd: Option<windows::Win32::Foundation::HWND>,
-) -> Result<(), RawInputError> {
-    ffi::register_keyboard_mouse(hwnd).map_err(|e| RawInputError::WinApiError(format!("{e}")))
+    config: &builder::ListenerConfig,
+    flags: windows::Win32::UI::Input::RAWINPUTDEVICE_FLAGS,
+) -> Result<RegistrationResult, RawInputError> {
+    let describe = |device: &str, result: windows::core::Result<()>| {
+        result.map_err(|e| {
+            RawInputError::WinApiError(format!("Failed to register {device} input: {e}"))
+        })
+    };
+
+    let result = RegistrationResult {
+        mouse: (!config.exclude_mouse).then(|| describe("mouse", ffi::register_mouse(hwnd, flags))),
+        keyboard: (!config.exclude_keyboard)
+            .then(|| describe("keyboard", ffi::register_keyboard(hwnd, flags))),
+        hid: config
+            .usages
+            .iter()
+            .map(|usage| {
+                let device = format!(
+                    "usage page {:#06x} usage {:#06x}",
+                    usage.usage_page, usage.usage
+                );
+                let registered = ffi::register_usage(
+                    usage.usage_page,
+                    usage.usage,
+                    usage.page_only,
+                    flags,
+                    hwnd,
+                );
+                (usage.usage_page, usage.usage, describe(&device, registered))
+            })
+            .collect(),
+    };
+
+    if !result.any_succeeded() {
+        let messages: Vec<String> = result.errors().iter().map(|e| e.to_string()).collect();
+        return Err(RawInputError::WinApiError(messages.join("; ")));
+    }
+    Ok(result)
 }
 
-/// Reads and parses a raw input event from a WM_INPUT message LPARAM.
-/// Returns a boxed RAWINPUT structure on success, or an error.
+/// Reads a raw input event from a WM_INPUT message LPARAM.
+/// Stores the complete RAWINPUT packet bytes in `buffer` (reusing its allocation).
+#[cfg(windows)]
 pub(crate) fn read_raw_input_event_from_lparam(
     lparam: windows::Win32::Foundation::LPARAM,
-) -> Result<RAWINPUT, RawInputError> {
-    ffi::read_raw_input_event(lparam).map_err(|e| RawInputError::WinApiError(format!("{e}")))
+    buffer: &mut Vec<u8>,
+) -> Result<(), RawInputError> {
+    ffi::read_raw_input_event(lparam, buffer)
+        .map_err(|e| RawInputError::WinApiError(format!("{e}")))
 }
 
-/// Parses a RAWINPUT struct into a high-level RawInputEvent (keyboard or mouse).
-/// Returns None if the event type is not supported.
-pub(crate) fn parse_rawinput_event(raw: &RAWINPUT) -> Option<RawInputEvent> {
+/// Parses a complete RAWINPUT packet as a borrowed HID event.
+/// Returns None if the packet is truncated or not a HID packet.
+#[cfg(windows)]
+pub(crate) fn parse_hid_buffer(bytes: &[u8]) -> Option<RawHidEventRef<'_>> {
+    use windows::Win32::UI::Input::{RAWINPUTHEADER, RIM_TYPEHID};
+
+    let header_size = std::mem::size_of::<RAWINPUTHEADER>();
+    if bytes.len() < header_size {
+        return None;
+    }
+    // The buffer has no alignment guarantees, so read the header unaligned
+    let header = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const RAWINPUTHEADER) };
+    if header.dwType != RIM_TYPEHID.0 {
+        return None;
+    }
+    let mut event = RawHidEventRef::from_payload(&bytes[header_size..])?;
+    event.device = RawInputDeviceHandle::from_raw(header.hDevice.0 as isize);
+    Some(event)
+}
+
+/// Parses a complete RAWINPUT packet (as read by `read_raw_input_event_from_lparam`) into a
+/// high-level RawInputEvent (keyboard, mouse or HID), attaching the verbatim fields if
+/// `include_raw` is set.
+/// Returns None if the packet is truncated or the event type is not supported.
+#[cfg(windows)]
+pub(crate) fn parse_rawinput_buffer(bytes: &[u8], include_raw: bool) -> Option<RawInputEvent> {
+    if let Some(hid) = parse_hid_buffer(bytes) {
+        return Some(RawInputEvent::Hid(hid.to_event()));
+    }
+
+    // Keyboard and mouse packets are fixed-size; copy into a zeroed RAWINPUT so a short
+    // buffer can never be read past its end
+    let mut raw: RAWINPUT = unsafe { std::mem::zeroed() };
+    let len = bytes.len().min(std::mem::size_of::<RAWINPUT>());
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), &mut raw as *mut RAWINPUT as *mut u8, len);
+    }
+    parse_rawinput_event(&raw, include_raw)
+}
+
+/// Parses a RAWINPUT struct into a high-level RawInputEvent (keyboard or mouse), attaching
+/// the verbatim fields if `include_raw` is set.
+/// Returns None if the event type is not supported; HID packets are variable-length and
+/// must go through `parse_rawinput_buffer` instead.
+#[cfg(windows)]
+pub(crate) fn parse_rawinput_event(raw: &RAWINPUT, include_raw: bool) -> Option<RawInputEvent> {
     use windows::Win32::UI::Input::{RIM_TYPEKEYBOARD, RIM_TYPEMOUSE};
+    let device = RawInputDeviceHandle::from_raw(raw.header.hDevice.0 as isize);
     unsafe {
         match raw.header.dwType {
             dwtype if dwtype == RIM_TYPEKEYBOARD.0 => {
                 let kbd = &raw.data.keyboard;
-                Some(RawInputEvent::Keyboard(RawKeyboardEvent::from(kbd)))
+                let mut event = RawKeyboardEvent::from(kbd);
+                event.raw = include_raw.then(|| RawInputRaw::from(kbd));
+                event.device = device;
+                Some(RawInputEvent::Keyboard(event))
             }
             dwtype if dwtype == RIM_TYPEMOUSE.0 => {
                 let mouse = &raw.data.mouse;
-                Some(RawInputEvent::Mouse(RawMouseEvent::from(mouse)))
+                let mut event = RawMouseEvent::from(mouse);
+                event.raw = include_raw.then(|| RawInputRaw::from(mouse));
+                event.device = device;
+                Some(RawInputEvent::Mouse(event))
             }
             _ => None,
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, windows))]
 mod tests {
     use super::*;
     use std::mem::zeroed;
@@ -243,6 +480,7 @@ mod tests {
         // Create a keyboard RAWINPUT structure
         let mut raw_input: RAWINPUT = unsafe { zeroed() };
         raw_input.header.dwType = RIM_TYPEKEYBOARD.0;
+        raw_input.header.hDevice = windows::Win32::Foundation::HANDLE(0x1234 as *mut _);
 
         // Set keyboard data
         unsafe {
@@ -257,18 +495,19 @@ mod tests {
         }
 
         // Parse the event
-        let event = parse_rawinput_event(&raw_input);
+        let event = parse_rawinput_event(&raw_input, false);
 
         // Verify the result
         assert!(event.is_some(), "Should parse keyboard event");
         match event {
             Some(RawInputEvent::Keyboard(kbd)) => {
                 assert_eq!(kbd.make_code, 30);
-                assert_eq!(kbd.key_up, false);
-                assert_eq!(kbd.extended, false);
+                assert!(!kbd.key_up);
+                assert!(!kbd.extended);
                 assert_eq!(kbd.vkey, VirtualKey::A);
                 assert_eq!(kbd.message, KeyEventMessage::KeyDown);
                 assert_eq!(kbd.extra_information, 123);
+                assert_eq!(kbd.device, RawInputDeviceHandle::from_raw(0x1234));
             }
             _ => panic!("Expected keyboard event"),
         }
@@ -281,10 +520,10 @@ mod tests {
         raw_input.header.dwType = RIM_TYPEMOUSE.0;
 
         // Set mouse data
-        use crate::mouse::{MOUSE_BUTTON_WHEEL_VERTICAL, MOUSE_MOVE_VIRTUAL_DESKTOP, WHEEL_DELTA};
+        use crate::mouse::{MOUSE_BUTTON_WHEEL_VERTICAL, MOUSE_MOVE_ABSOLUTE, WHEEL_DELTA};
         unsafe {
             let mouse = &mut raw_input.data.mouse;
-            mouse.usFlags = windows::Win32::UI::Input::MOUSE_STATE(MOUSE_MOVE_VIRTUAL_DESKTOP); // MOUSE_MOVE_ABSOLUTE | VIRTUAL_DESKTOP
+            mouse.usFlags = windows::Win32::UI::Input::MOUSE_STATE(MOUSE_MOVE_ABSOLUTE);
             mouse.ulRawButtons = 0;
             mouse.lLastX = 100;
             mouse.lLastY = 200;
@@ -299,7 +538,7 @@ mod tests {
         }
 
         // Parse the event
-        let event = parse_rawinput_event(&raw_input);
+        let event = parse_rawinput_event(&raw_input, false);
 
         // Verify the result
         assert!(event.is_some(), "Should parse mouse event");
@@ -317,11 +556,186 @@ mod tests {
                 assert_eq!(mouse.last_x, 100);
                 assert_eq!(mouse.last_y, 200);
                 assert_eq!(mouse.extra_information, 456);
+                assert_eq!(mouse.button_data, WHEEL_DELTA as u16);
             }
             _ => panic!("Expected mouse event"),
         }
     }
 
+    /// Test that the `RAWMOUSE` button union reads the same through either of its layouts:
+    /// `usButtonFlags` is the low and `usButtonData` the high word of `ulButtons`
+    #[test]
+    fn test_mouse_button_union_layouts() {
+        use crate::mouse::{MOUSE_BUTTON_LEFT_DOWN, MOUSE_BUTTON_WHEEL_HORIZONTAL};
+        use std::mem::{offset_of, size_of};
+        use windows::Win32::UI::Input::RAWMOUSE;
+
+        assert_eq!(size_of::<RAWMOUSE>(), 24);
+        assert_eq!(offset_of!(RAWMOUSE, Anonymous), 4);
+        assert_eq!(offset_of!(RAWMOUSE, ulRawButtons), 8);
+
+        let cases = [
+            (MOUSE_BUTTON_LEFT_DOWN, 0),
+            (MOUSE_BUTTON_WHEEL_HORIZONTAL, (-240i16) as u16),
+        ];
+        for (flags, data) in cases {
+            // Written as the combined DWORD, read split
+            let mut combined: RAWMOUSE = unsafe { zeroed() };
+            combined.Anonymous.ulButtons = u32::from(data) << 16 | u32::from(flags);
+            let (split_flags, split_data) = unsafe {
+                let inner = &combined.Anonymous.Anonymous;
+                (inner.usButtonFlags, inner.usButtonData)
+            };
+            assert_eq!((split_flags, split_data), (flags, data));
+
+            // Written split, read combined
+            let mut split: RAWMOUSE = unsafe { zeroed() };
+            split.Anonymous.Anonymous.usButtonFlags = flags;
+            split.Anonymous.Anonymous.usButtonData = data;
+            assert_eq!(unsafe { split.Anonymous.ulButtons }, unsafe {
+                combined.Anonymous.ulButtons
+            });
+
+            // Both parse to the same event
+            let (a, b) = (RawMouseEvent::from(&combined), RawMouseEvent::from(&split));
+            assert_eq!((a.button_data, a.wheel_delta), (data, b.wheel_delta));
+            assert_eq!(
+                (a.button_action, a.wheel_axis),
+                (b.button_action, b.wheel_axis)
+            );
+        }
+
+        let wheel = RawMouseEvent::from(&{
+            let mut raw: RAWMOUSE = unsafe { zeroed() };
+            raw.Anonymous.ulButtons = 0xFF10_0800; // Horizontal wheel, delta -240
+            raw
+        });
+        assert_eq!(wheel.wheel_axis, Some(WheelAxis::Horizontal));
+        assert_eq!(wheel.wheel_delta, -240);
+    }
+
+    /// Builds a HID packet as returned by GetRawInputData: header, RAWHID sizes, reports
+    fn hid_packet(report_size: u32, reports: &[&[u8]]) -> Vec<u8> {
+        use windows::Win32::UI::Input::{RAWINPUTHEADER, RIM_TYPEHID};
+
+        let mut header: RAWINPUTHEADER = unsafe { zeroed() };
+        header.dwType = RIM_TYPEHID.0;
+        header.hDevice = windows::Win32::Foundation::HANDLE(0x42 as *mut _);
+        let header_bytes = unsafe {
+            std::slice::from_raw_parts(
+                &header as *const RAWINPUTHEADER as *const u8,
+                std::mem::size_of::<RAWINPUTHEADER>(),
+            )
+        };
+
+        let mut bytes = header_bytes.to_vec();
+        bytes.extend_from_slice(&report_size.to_ne_bytes());
+        bytes.extend_from_slice(&(reports.len() as u32).to_ne_bytes());
+        for report in reports {
+            bytes.extend_from_slice(report);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_rawinput_buffer_hid() {
+        let bytes = hid_packet(3, &[&[1, 2, 3]]);
+
+        match parse_rawinput_buffer(&bytes, false) {
+            Some(RawInputEvent::Hid(hid)) => {
+                assert_eq!(hid.report_size, 3);
+                assert_eq!(hid.report_count, 1);
+                assert_eq!(hid.data, vec![1, 2, 3]);
+                assert_eq!(hid.device, RawInputDeviceHandle::from_raw(0x42));
+            }
+            other => panic!("Expected HID event, got: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_rawinput_buffer_hid_multiple_reports() {
+        let bytes = hid_packet(2, &[&[1, 2], &[3, 4], &[5, 6]]);
+
+        match parse_rawinput_buffer(&bytes, false) {
+            Some(RawInputEvent::Hid(hid)) => {
+                assert_eq!(hid.report_count, 3);
+                let reports: Vec<&[u8]> = hid.reports().collect();
+                assert_eq!(reports, vec![&[1, 2][..], &[3, 4], &[5, 6]]);
+            }
+            other => panic!("Expected HID event, got: {other:?}"),
+        }
+
+        // A truncated packet only yields its complete reports
+        let hid = parse_hid_buffer(&bytes[..bytes.len() - 1]).expect("Expected HID event");
+        assert_eq!(hid.reports().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_hid_buffer_borrows_reports() {
+        let bytes = hid_packet(2, &[&[7, 8]]);
+
+        let hid = parse_hid_buffer(&bytes).expect("Expected HID event");
+        assert_eq!(hid.data, &[7, 8]);
+        assert!(std::ptr::eq(
+            hid.data.as_ptr(),
+            bytes[bytes.len() - 2..].as_ptr()
+        ));
+        assert!(parse_hid_buffer(&bytes[..4]).is_none());
+    }
+
+    #[test]
+    fn test_parse_rawinput_buffer_keyboard() {
+        let mut raw_input: RAWINPUT = unsafe { zeroed() };
+        raw_input.header.dwType = RIM_TYPEKEYBOARD.0;
+        raw_input.data.keyboard.VKey = 65; // VK_A
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &raw_input as *const RAWINPUT as *const u8,
+                std::mem::size_of::<RAWINPUT>(),
+            )
+        };
+
+        match parse_rawinput_buffer(bytes, false) {
+            Some(RawInputEvent::Keyboard(kbd)) => assert_eq!(kbd.vkey, VirtualKey::A),
+            other => panic!("Expected keyboard event, got: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_rawinput_event_include_raw() {
+        let mut raw_input: RAWINPUT = unsafe { zeroed() };
+        raw_input.header.dwType = RIM_TYPEKEYBOARD.0;
+        raw_input.data.keyboard.MakeCode = 0x1E;
+        raw_input.data.keyboard.Flags = 0x01;
+        raw_input.data.keyboard.Message = 0x0101;
+        raw_input.data.keyboard.Reserved = 0xBEEF;
+
+        let Some(RawInputEvent::Keyboard(kbd)) = parse_rawinput_event(&raw_input, true) else {
+            panic!("Expected keyboard event");
+        };
+        assert_eq!(
+            kbd.raw,
+            Some(RawInputRaw::Keyboard {
+                make_code: 0x1E,
+                flags: 0x01,
+                reserved: 0xBEEF,
+                vkey: 0,
+                message: 0x0101,
+                extra_information: 0,
+            })
+        );
+
+        let Some(RawInputEvent::Keyboard(kbd)) = parse_rawinput_event(&raw_input, false) else {
+            panic!("Expected keyboard event");
+        };
+        assert!(kbd.raw.is_none());
+    }
+
+    #[test]
+    fn test_parse_rawinput_buffer_truncated() {
+        assert!(parse_rawinput_buffer(&[0u8; 4], false).is_none());
+    }
+
     #[test]
     fn test_parse_rawinput_event_unsupported() {
         // Create a RAWINPUT with unsupported type
@@ -329,7 +743,7 @@ mod tests {
         raw_input.header.dwType = 3; // Not keyboard (1) or mouse (2)
 
         // Parse the event
-        let event = parse_rawinput_event(&raw_input);
+        let event = parse_rawinput_event(&raw_input, false);
 
         // Verify it returns None for unsupported types
         assert!(
@@ -339,14 +753,42 @@ mod tests {
     }
 }
 
+mod builder;
+mod dispatch;
+#[cfg(windows)]
 mod listener;
-pub use listener::{ListenerHandle, start_listener};
+mod registration;
+mod session;
+mod source;
+mod state;
+mod tap;
+#[cfg(windows)]
+mod text;
+#[cfg(windows)]
+mod uipi;
+pub use builder::{EventReceiver, ListenerBuilder, ThreadPriority};
+#[cfg(windows)]
+pub use listener::{
+    ListenerHandle, capture_for, capture_next_key, start_listener, start_listener_channel,
+    start_listener_with_context,
+};
+pub use source::{EventSource, VecEventSource};
+pub use state::KeySetChange;
+pub use tap::KeyTap;
+#[cfg(windows)]
+pub use text::TextTranslator;
+#[cfg(windows)]
+pub use uipi::is_foreground_elevated;
 
 /// Error type for RawInput operations.
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum RawInputError {
     #[error("Windows API error: {0}")]
     WinApiError(String),
+    /// Input is withheld by Windows, e.g. while an elevated window is in the foreground
+    /// (see [`ListenerBuilder::detect_uipi_blocking`])
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -1,15 +1,33 @@
 //! Background-threaded listener implementation for mkb-raw-input
 
-use crate::{RawInputError, RawInputEvent, parse_rawinput_event};
+use crate::builder::{ListenerConfig, ThreadPriority};
+use crate::dispatch::{Dispatcher, ErrorReporter, ListenerCallbacks};
+use crate::reader::PacketReader;
+use crate::session::{SessionMonitor, WM_SESSION_CHANGE};
+use crate::source::EventSource;
+use crate::state::{PolledEvents, RecentEvents, ScrollAccumulator, SharedInputState};
+use crate::timing::TimingCollector;
+use crate::uipi::{UIPI_POLL_INTERVAL_MS, UIPI_TIMER_ID, UipiMonitor};
+use crate::{
+    CaptureMode, EventReceiver, ListenerBuilder, MouseButton, RawInputDeviceHandle, RawInputError,
+    RawInputEvent, RegistrationResult, TimingStats, VirtualKey, ffi,
+};
+use std::any::Any;
+use std::collections::VecDeque;
 use std::ptr::null_mut;
 use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+    atomic::{AtomicBool, AtomicIsize, Ordering},
+    mpsc,
 };
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
 
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Threading::{
+    GetCurrentThread, SetThreadAffinityMask, SetThreadPriority,
+};
 use windows::Win32::UI::WindowsAndMessaging::*;
 use windows::core::PCWSTR;
 
@@ -17,25 +35,320 @@ use std::sync::atomic::{AtomicBool as StdAtomicBool, Ordering as StdOrdering};
 
 static LISTENER_ACTIVE: StdAtomicBool = StdAtomicBool::new(false);
 
+/// Claims the process-wide listener slot; Raw Input allows one target per device type.
+pub(crate) fn acquire_singleton() -> Result<(), RawInputError> {
+    if LISTENER_ACTIVE.swap(true, StdOrdering::SeqCst) {
+        return Err(RawInputError::Other(
+            "Raw input listener already running (singleton enforcement)".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Releases the slot claimed by [`acquire_singleton`].
+pub(crate) fn release_singleton() {
+    LISTENER_ACTIVE.store(false, StdOrdering::SeqCst);
+}
+
 /// Handle to a running raw input listener thread.
 ///
 /// This struct manages the lifecycle of the background thread and window.
 /// When dropped, it automatically stops the listener, posts a quit message,
 /// and cleans up resources (window class, etc.).
 pub struct ListenerHandle {
-    join_handle: Option<JoinHandle<()>>,
+    join_handle: Option<JoinHandle<Result<(), RawInputError>>>,
     running: Arc<AtomicBool>,
-    hwnd: HWND,
+    hwnd: Arc<AtomicIsize>,
     class_name: Vec<u16>,
     hinstance: HINSTANCE,
+    last_error: Arc<Mutex<Option<RawInputError>>>,
+    state: SharedInputState,
+    paused: Arc<AtomicBool>,
+    scroll: Arc<ScrollAccumulator>,
+    recent: Arc<RecentEvents>,
+    polled: Arc<PolledEvents>,
+    timing: Arc<TimingCollector>,
+    reconfigure: mpsc::Sender<Reconfigure>,
+    capture_mode: Mutex<CaptureMode>,
+}
+
+impl ListenerHandle {
+    /// Returns the listener window, which is replaced when the listener
+    /// [restarts](ListenerBuilder::auto_restart).
+    fn hwnd(&self) -> HWND {
+        HWND(self.hwnd.load(Ordering::SeqCst) as *mut _)
+    }
+
+    /// Returns the most recent error reported by the listener, if any.
+    ///
+    /// Errors are recorded whether or not an error callback is set, which makes this the
+    /// way to observe failures of a [`silent`](ListenerBuilder::silent) listener.
+    pub fn last_error(&self) -> Option<RawInputError> {
+        self.last_error.lock().ok().and_then(|last| last.clone())
+    }
+
+    /// Returns true if the listener has seen the key go down and not yet come back up.
+    ///
+    /// The state is built from raw hardware events, so it is closest to the physical
+    /// state of the keyboard, as `GetAsyncKeyState` reports it, rather than to the logical
+    /// state of `GetKeyState`, which follows the thread's message queue and lags behind.
+    /// See [`verify_key_state`](Self::verify_key_state) to detect a missed event.
+    pub fn is_key_down(&self, key: VirtualKey) -> bool {
+        self.state.lock().is_ok_and(|state| state.is_key_down(key))
+    }
+
+    /// Returns true if the tracked state of `key` agrees with the physical state Windows
+    /// reports (`GetAsyncKeyState`), e.g. to debug stuck keys.
+    ///
+    /// A mismatch means the listener missed a press or release (typically while the
+    /// secure desktop or an elevated window had the input); [`reset_state`](Self::reset_state)
+    /// clears stuck keys. The two states are read at different times, so a key pressed or
+    /// released meanwhile can mismatch briefly: check again before acting on it. Windows
+    /// reports every key as up while input goes to another desktop, and
+    /// [`VirtualKey::Unknown`] keys are compared by their code.
+    pub fn verify_key_state(&self, key: VirtualKey) -> bool {
+        self.is_key_down(key) == ffi::async_key_down(u16::from(key))
+    }
+
+    /// Returns the keys currently held down, in no particular order.
+    pub fn pressed_keys(&self) -> Vec<VirtualKey> {
+        self.state
+            .lock()
+            .map(|state| state.pressed_keys())
+            .unwrap_or_default()
+    }
+
+    /// Returns the keys currently held down on one keyboard, in no particular order.
+    ///
+    /// With several keyboards attached, e.g. one per player in a local multiplayer game,
+    /// this tells their keys apart where [`pressed_keys`](Self::pressed_keys) merges
+    /// them. Keys are attributed to the [`device`](crate::RawKeyboardEvent::device) of
+    /// their events; injected input has the default handle, and keys already held when
+    /// the listener started belong to no keyboard. A key stays down in
+    /// [`pressed_keys`](Self::pressed_keys) while any keyboard holds it. A keyboard is
+    /// only kept while it holds keys, so memory does not grow with devices that come and
+    /// go, and the keys of one unplugged mid-press are released when Windows reports its
+    /// removal (`WM_INPUT_DEVICE_CHANGE`).
+    pub fn pressed_keys_for_device(&self, device: RawInputDeviceHandle) -> Vec<VirtualKey> {
+        self.state
+            .lock()
+            .map(|state| state.pressed_keys_for_device(device))
+            .unwrap_or_default()
+    }
+
+    /// Forgets the keys held on one keyboard; keys also held on another keyboard stay down.
+    ///
+    /// Removed keyboards are forgotten automatically; use this for one your application
+    /// stops trusting, e.g. a player's keyboard left in a state you want to discard.
+    pub fn forget_device(&self, device: RawInputDeviceHandle) {
+        if let Ok(mut state) = self.state.lock() {
+            state.forget_device(device);
+        }
+    }
+
+    /// Returns true if the listener has seen the mouse button go down and not yet come back up.
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.state
+            .lock()
+            .is_ok_and(|state| state.is_mouse_button_down(button))
+    }
+
+    /// Returns the mouse buttons currently held down, in no particular order.
+    pub fn pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        self.state
+            .lock()
+            .map(|state| state.pressed_mouse_buttons())
+            .unwrap_or_default()
+    }
+
+    /// Pauses event delivery without stopping the listener.
+    ///
+    /// While paused, no events reach the event callback, but held keys and buttons are
+    /// still tracked and a [`panic_hotkey`](ListenerBuilder::panic_hotkey) still works.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resumes event delivery after [`pause`](Self::pause).
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns true if event delivery is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Returns the raw wheel delta accumulated since the last call as `(vertical,
+    /// horizontal)`, and resets it to zero.
+    ///
+    /// Positive values scroll up and right; one notch of a standard wheel is 120
+    /// (`WHEEL_DELTA`). This suits polling once per frame without tracking wheel events
+    /// yourself. Both axes are taken in one atomic step and saturate rather than overflow.
+    /// Scrolling while paused is not accumulated.
+    pub fn take_scroll(&self) -> (i32, i32) {
+        self.scroll.take()
+    }
+
+    /// Returns the events retained by the [`ring_buffer`](ListenerBuilder::ring_buffer),
+    /// oldest first; empty if it is disabled.
+    ///
+    /// This copies the buffer and can be called from any thread, e.g. from a panic hook.
+    /// The listener thread waits while the copy is made.
+    pub fn recent_events(&self) -> Vec<RawInputEvent> {
+        self.recent.snapshot()
+    }
+
+    /// Takes the events received since the last poll, oldest first; empty unless the
+    /// listener was started with [`start_polling`](ListenerBuilder::start_polling).
+    ///
+    /// Call it once per frame. Events that exceeded the
+    /// [`poll_capacity`](ListenerBuilder::poll_capacity) are missing, the oldest first.
+    pub fn poll_events(&self) -> Vec<RawInputEvent> {
+        self.polled.drain()
+    }
+
+    /// Returns statistics of the intervals between the events so far; all zero unless
+    /// [`timing_stats`](ListenerBuilder::timing_stats) is enabled.
+    ///
+    /// The intervals are measured between dequeue times, not from the hardware; see
+    /// [`TimingStats`].
+    pub fn timing_stats(&self) -> TimingStats {
+        self.timing.stats()
+    }
+
+    /// Blocks until the listener stops on its own, then releases its resources.
+    ///
+    /// Unlike dropping the handle, this does not stop the listener: it waits for the
+    /// listener thread to exit, e.g. after a `WM_QUIT` message was posted to the listener
+    /// window or a fatal error occurred.
+    ///
+    /// # Returns
+    /// * `Ok(())` - The message loop ended normally
+    /// * `Err(RawInputError)` - The error that terminated the listener (e.g. a panic in the
+    ///   event callback or a failing message loop)
+    pub fn join(mut self) -> Result<(), RawInputError> {
+        let handle = self.join_handle.take();
+        // Dropping `self` afterwards only cleans up, as the thread has already exited
+        handle.map_or(Ok(()), |handle| {
+            handle.join().unwrap_or_else(|_| {
+                Err(RawInputError::Other("Listener thread panicked".to_string()))
+            })
+        })
+    }
+
+    /// Clears all tracked key and mouse button down-states.
+    ///
+    /// A release can go unseen, e.g. when input moves to the secure desktop (Ctrl+Alt+Del,
+    /// UAC prompts) or to a higher-integrity window mid-press, leaving the key "stuck" in
+    /// the tracked state. Call this when your application loses focus (e.g. on
+    /// `WM_ACTIVATEAPP`); the listener's own hidden window never receives focus changes.
+    pub fn reset_state(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.reset();
+        }
+    }
+
+    /// Returns how the listener captures input: in the background or only in the
+    /// foreground, and which usages it registered.
+    ///
+    /// The summary is kept up to date by [`reconfigure`](Self::reconfigure). See
+    /// [`registered_devices`](crate::registered_devices) for the registrations in effect
+    /// for the whole process.
+    pub fn capture_mode(&self) -> CaptureMode {
+        self.capture_mode
+            .lock()
+            .map(|mode| mode.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+
+    /// Changes the devices the listener captures without restarting it.
+    ///
+    /// Only the registration options of `builder` apply: its
+    /// [`capture_keyboard`](ListenerBuilder::capture_keyboard),
+    /// [`capture_mouse`](ListenerBuilder::capture_mouse) and
+    /// [`register_usage`](ListenerBuilder::register_usage) settings replace the current
+    /// ones, whose registrations are removed (`RIDEV_REMOVE`) unless requested again, and
+    /// [`extended_background`](ListenerBuilder::extended_background) applies to every
+    /// registration. Its callbacks and other options are ignored. The listener window and
+    /// thread, and the tracked key and button states, are kept.
+    ///
+    /// The registrations are made by the listener thread, which owns the listener window;
+    /// this call blocks until it has handled the request, so it waits for the event callback
+    /// to return if it is running.
+    ///
+    /// # Returns
+    /// * `Ok(RegistrationResult)` - The outcome of each registration; failed ones are not
+    ///   reported through the error callback
+    /// * `Err(RawInputError)` - If the configuration is invalid, a dropped usage cannot be
+    ///   removed, no registration succeeded or the listener has stopped
+    pub fn reconfigure(
+        &self,
+        builder: ListenerBuilder,
+    ) -> Result<RegistrationResult, RawInputError> {
+        let config = builder.into_config();
+        config.validate()?;
+        let requested = CaptureMode::requested(&config, false);
+        let stopped = || RawInputError::Other("Listener is not running".to_string());
+        let (reply, response) = mpsc::channel();
+        self.reconfigure
+            .send(Reconfigure { config, reply })
+            .map_err(|_| stopped())?;
+        unsafe { PostMessageW(Some(self.hwnd()), WM_RECONFIGURE, WPARAM(0), LPARAM(0)) }
+            .map_err(|e| RawInputError::WinApiError(format!("PostMessageW failed: {e}")))?;
+        let result = response.recv().unwrap_or_else(|_| Err(stopped()))?;
+        if let Ok(mut mode) = self.capture_mode.lock() {
+            *mode = requested.registered(&result);
+        }
+        Ok(result)
+    }
+
+    /// Returns the number of events the [`start_polling`](ListenerBuilder::start_polling)
+    /// queue discarded since the listener started, for tuning its
+    /// [`poll_capacity`](ListenerBuilder::poll_capacity); 0 when delivering to a callback,
+    /// which has no internal queue.
+    ///
+    /// This is an estimate of the input lost, a lower bound: input that Windows discards
+    /// from the listener thread's full message queue is never reported to the listener.
+    pub fn dropped_event_estimate(&self) -> u64 {
+        self.polled.dropped()
+    }
+}
+
+/// Timer ID of the listener thread's self-wake timer
+const WATCHDOG_TIMER_ID: usize = 0x4D4B_5744;
+/// Self-wake interval unless set with [`ListenerBuilder::watchdog_interval`]
+const DEFAULT_WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Timer ID of the one-time check of [`ListenerBuilder::no_input_warning`]
+const NO_INPUT_TIMER_ID: usize = 0x4D4B_4E49;
+
+/// Minimum time between two idle timer resets of [`KeepAwake`]
+const KEEP_AWAKE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Private message that wakes the listener thread to apply [`ListenerHandle::reconfigure`]
+/// requests.
+const WM_RECONFIGURE: u32 = WM_APP + 0x4D4C;
+
+/// Registrations requested by [`ListenerHandle::reconfigure`], with the channel to reply on.
+struct Reconfigure {
+    config: ListenerConfig,
+    reply: mpsc::Sender<Result<RegistrationResult, RawInputError>>,
 }
 
+/// Listener window handles and the sender of reconfigure requests, handed from the
+/// listener thread to its [`ListenerHandle`]. The handles are sent as integers (FFI-safe);
+/// the window handle is shared, as the listener thread replaces the window on restart.
+type ListenerWindow = (Arc<AtomicIsize>, isize, mpsc::Sender<Reconfigure>);
+
 impl Drop for ListenerHandle {
     fn drop(&mut self) {
+        // A listener restarting concurrently either sees the flag or has published its new
+        // window by the time it is read here
         self.running.store(false, Ordering::SeqCst);
         // Post WM_QUIT to wake up the message loop immediately
         unsafe {
-            let _ = PostMessageW(Some(self.hwnd), WM_QUIT, WPARAM(0), LPARAM(0));
+            let _ = PostMessageW(Some(self.hwnd()), WM_QUIT, WPARAM(0), LPARAM(0));
         }
 
         // Wait for the thread to finish
@@ -49,7 +362,7 @@ impl Drop for ListenerHandle {
         }
 
         // Allow another listener to be created
-        LISTENER_ACTIVE.store(false, StdOrdering::SeqCst);
+        release_singleton();
     }
 }
 
@@ -75,6 +388,7 @@ impl Drop for ListenerHandle {
 ///     |event| match event {
 ///         RawInputEvent::Keyboard(kbd) => println!("Key: {:?}", kbd.vkey),
 ///         RawInputEvent::Mouse(mouse) => println!("Mouse: {},{}", mouse.last_x, mouse.last_y),
+///         RawInputEvent::Hid(hid) => println!("HID: {} bytes", hid.data.len()),
 ///     },
 ///     Some(|err| eprintln!("Error: {}", err)),
 /// ).expect("Failed to start listener");
@@ -90,34 +404,171 @@ where
     F: FnMut(RawInputEvent) + Send + 'static,
     E: FnMut(RawInputError) + Send + 'static,
 {
-    // Singleton enforcement
-    if LISTENER_ACTIVE.swap(true, StdOrdering::SeqCst) {
-        return Err(RawInputError::Other(
-            "Raw input listener already running (singleton enforcement)".to_string(),
-        ));
+    let mut builder = ListenerBuilder::new();
+    if let Some(on_error) = on_error {
+        builder = builder.error_callback(on_error);
     }
+    builder.start(callback)
+}
+
+/// Start the background listener, passing a user-owned context to the callback along with
+/// every event.
+///
+/// A shorthand for [`ListenerBuilder::start_with_context`]; use the builder to also set an
+/// error callback.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{RawInputEvent, start_listener_with_context};
+///
+/// #[derive(Default)]
+/// struct Counter {
+///     keys: usize,
+/// }
+///
+/// fn on_event(counter: &mut Counter, event: RawInputEvent) {
+///     if let RawInputEvent::Keyboard(kbd) = event
+///         && !kbd.key_up
+///     {
+///         counter.keys += 1;
+///         println!("{} keys pressed", counter.keys);
+///     }
+/// }
+///
+/// let _listener = start_listener_with_context(Counter::default(), on_event)
+///     .expect("Failed to start listener");
+/// ```
+pub fn start_listener_with_context<T>(
+    context: T,
+    callback: fn(&mut T, RawInputEvent),
+) -> Result<ListenerHandle, RawInputError>
+where
+    T: Send + 'static,
+{
+    ListenerBuilder::new().start_with_context(context, callback)
+}
+
+/// Start the background listener, sending events and runtime errors into a channel
+/// instead of calling back.
+///
+/// A shorthand for [`ListenerBuilder::start_channel`]; see there for how errors and a
+/// dropped receiver are handled.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{RawInputEvent, start_listener_channel};
+///
+/// let (_listener, events) = start_listener_channel().expect("Failed to start listener");
+/// for event in events {
+///     match event {
+///         Ok(RawInputEvent::Keyboard(kbd)) => println!("Key: {:?}", kbd.vkey),
+///         Ok(_) => {}
+///         Err(err) => eprintln!("Error: {err}"),
+///     }
+/// }
+/// ```
+pub fn start_listener_channel() -> Result<(ListenerHandle, EventReceiver), RawInputError> {
+    ListenerBuilder::new().start_channel()
+}
+
+/// Records all input for `duration` with a default listener and returns the events.
+///
+/// A shorthand for [`ListenerBuilder::capture_for`]; see there for the memory implications
+/// of long captures.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let events = mkb_raw_input::capture_for(Duration::from_secs(3)).expect("Capture failed");
+/// println!("Captured {} events", events.len());
+/// ```
+pub fn capture_for(duration: Duration) -> Result<Vec<RawInputEvent>, RawInputError> {
+    ListenerBuilder::new().capture_for(duration)
+}
+
+/// Waits up to `timeout` for the next key press with a default listener and returns its
+/// key, or None if no key was pressed in time.
+///
+/// A shorthand for [`ListenerBuilder::capture_next_key`], which returns the whole
+/// [`RawKeyboardEvent`](crate::RawKeyboardEvent) with its scan code and modifiers.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// println!("Press a key...");
+/// match mkb_raw_input::capture_next_key(Duration::from_secs(10)).expect("Capture failed") {
+///     Some(key) => println!("Bound to {}", key.name()),
+///     None => println!("No key pressed"),
+/// }
+/// ```
+pub fn capture_next_key(timeout: Duration) -> Result<Option<VirtualKey>, RawInputError> {
+    ListenerBuilder::new()
+        .capture_next_key(timeout)
+        .map(|kbd| kbd.map(|kbd| kbd.vkey))
+}
+
+/// Spawns the listener thread with the given configuration.
+///
+/// This is the implementation behind [`ListenerBuilder::start`] and
+/// [`ListenerBuilder::start_partial`].
+pub(crate) fn spawn_listener<F>(
+    callback: F,
+    config: ListenerConfig,
+    callbacks: ListenerCallbacks,
+) -> Result<(ListenerHandle, RegistrationResult), RawInputError>
+where
+    F: FnMut(RawInputEvent) + Send + 'static,
+{
+    // Singleton enforcement
+    acquire_singleton()?;
 
-    use std::sync::mpsc;
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
+    let last_error = Arc::new(Mutex::new(None));
+    let state = SharedInputState::default();
+    let reporter = ErrorReporter::new(callbacks.on_error, config.silent, last_error.clone())
+        .with_repeat_interval(config.error_repeat_interval());
+    let dispatcher = Dispatcher::new(callback, &config, reporter, state.clone())
+        .with_panic_callback(callbacks.on_panic_hotkey)
+        .with_wheel_callback(callbacks.on_wheel)
+        .with_key_set_callback(callbacks.on_key_set)
+        .with_hotkeys(callbacks.hotkeys)
+        .with_taps(callbacks.taps)
+        .with_first_event_callback(callbacks.on_first_event)
+        .with_log(callbacks.log);
+    let paused = dispatcher.paused();
+    let scroll = dispatcher.scroll();
+    let recent = dispatcher.recent();
+    let polled = dispatcher.polled();
+    let timing = dispatcher.timing();
+    // The callbacks not taken by the dispatcher are used by the message loop
+    let callbacks = ListenerCallbacks {
+        on_hid: callbacks.on_hid,
+        on_session: callbacks.on_session,
+        ..Default::default()
+    };
+    let requested = CaptureMode::requested(&config, false);
     let (init_tx, init_rx) = mpsc::channel();
-    let (hwnd_tx, hwnd_rx) = mpsc::channel::<(isize, isize)>();
+    let (hwnd_tx, hwnd_rx) = mpsc::channel::<ListenerWindow>();
     let class_name = widestring("MkbRawInputHiddenWindow");
     let class_name_for_handle = class_name.clone();
     let join_handle = thread::spawn(move || {
         listener_thread_main(
-            callback,
-            on_error,
+            dispatcher,
+            config,
+            callbacks,
             running_clone,
             init_tx,
             hwnd_tx,
             class_name,
-        );
+        )
     });
 
     // Wait for initialization result
-    let (hwnd_raw, hinstance_raw) = match hwnd_rx.recv().unwrap_or((0isize, 0isize)) {
-        (hwnd, hinstance) if hwnd != 0 => (hwnd, hinstance),
+    let (window_handle, hinstance_raw, reconfigure) = match hwnd_rx.recv() {
+        Ok(window) if window.0.load(Ordering::SeqCst) != 0 => window,
         _ => {
             return Err(RawInputError::Other(
                 "Failed to receive HWND from listener thread".to_string(),
@@ -125,63 +576,222 @@ where
         }
     };
 
-    let hwnd = HWND(hwnd_raw as *mut _);
     let hinstance = HINSTANCE(hinstance_raw as *mut _);
 
     match init_rx.recv().unwrap_or(Err(RawInputError::Other(
         "Listener thread failed to initialize".to_string(),
     ))) {
-        Ok(()) => Ok(ListenerHandle {
-            join_handle: Some(join_handle),
-            running,
-            hwnd,
-            class_name: class_name_for_handle,
-            hinstance,
-        }),
+        Ok(registration) => Ok((
+            ListenerHandle {
+                join_handle: Some(join_handle),
+                running,
+                hwnd: window_handle,
+                class_name: class_name_for_handle,
+                hinstance,
+                last_error,
+                state,
+                paused,
+                scroll,
+                recent,
+                polled,
+                timing,
+                reconfigure,
+                capture_mode: Mutex::new(requested.registered(&registration)),
+            },
+            registration,
+        )),
         Err(e) => {
-            LISTENER_ACTIVE.store(false, StdOrdering::SeqCst);
+            release_singleton();
             Err(e)
         }
     }
 }
 
 // Extracted thread logic for readability
-fn listener_thread_main<F, E>(
-    mut callback: F,
-    mut on_error: Option<E>,
+fn listener_thread_main<F>(
+    mut dispatcher: Dispatcher<F>,
+    config: ListenerConfig,
+    callbacks: ListenerCallbacks,
     running_clone: Arc<AtomicBool>,
-    init_tx: std::sync::mpsc::Sender<Result<(), RawInputError>>,
-    hwnd_tx: std::sync::mpsc::Sender<(isize, isize)>,
+    init_tx: mpsc::Sender<Result<RegistrationResult, RawInputError>>,
+    hwnd_tx: mpsc::Sender<ListenerWindow>,
     class_name: Vec<u16>,
-) where
+) -> Result<(), RawInputError>
+where
     F: FnMut(RawInputEvent) + Send + 'static,
-    E: FnMut(RawInputError) + Send + 'static,
 {
-    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
-        // Initialize window and register for raw input
-        let hwnd = match initialize_listener_window(&class_name, &init_tx, &hwnd_tx) {
-            Ok(handles) => handles,
-            Err(_) => return, // Error already sent through init_tx
+    if config.thread_priority != ThreadPriority::Normal
+        && let Err(e) =
+            unsafe { SetThreadPriority(GetCurrentThread(), config.thread_priority.to_win32()) }
+    {
+        dispatcher.report(RawInputError::WinApiError(format!(
+            "SetThreadPriority failed: {e}"
+        )));
+    }
+    if let Some(mask) = config.cpu_affinity
+        && unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) } == 0
+    {
+        dispatcher.report(RawInputError::WinApiError(format!(
+            "SetThreadAffinityMask failed: {}",
+            windows::core::Error::from_win32()
+        )));
+    }
+
+    let (reconfigure_tx, reconfigure_rx) = mpsc::channel();
+    let window_handle = Arc::new(AtomicIsize::new(0));
+    let setup = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+        // Initialize window and register for raw input; errors are sent through init_tx
+        let hwnd = initialize_listener_window(
+            &class_name,
+            &config,
+            &init_tx,
+            &hwnd_tx,
+            reconfigure_tx,
+            window_handle.clone(),
+            &mut dispatcher.reporter,
+        )
+        .ok()?;
+
+        let uipi = if config.detect_uipi_blocking {
+            UipiMonitor::new().map_err(|e| dispatcher.report(e)).ok()
+        } else {
+            None
         };
+        set_timers(hwnd, &config, uipi.is_some());
 
-        // Run the message loop
-        run_message_loop(hwnd, running_clone, &mut callback, &mut on_error);
+        let session = callbacks.on_session.and_then(|on_session| {
+            SessionMonitor::new(hwnd, on_session, dispatcher.state())
+                .map_err(|e| dispatcher.report(e))
+                .ok()
+        });
+
+        let mut window = WindowState::new(
+            PacketReader::new(
+                config.capture_system_time,
+                config.include_raw,
+                callbacks.on_hid,
+                dispatcher.paused(),
+            )
+            .with_foreground_process(config.capture_foreground_process),
+            dispatcher.state(),
+        );
+        window.keep_awake = config.keep_awake.then(KeepAwake::default);
+        Some(MessageLoopSource {
+            hwnd,
+            window_handle,
+            running: running_clone,
+            window: window.attach(hwnd),
+            uipi,
+            session,
+            registration: config.clone(),
+            reconfigure: reconfigure_rx,
+            failure: None,
+        })
     }));
+    let mut source = match setup {
+        Ok(Some(source)) => source,
+        Ok(None) => return Ok(()),
+        Err(panic) => return Err(report_panic(&mut dispatcher, panic)),
+    };
+    // Taken once registered, so that later transitions queue up behind the snapshot
+    seed_state(&dispatcher.state());
 
-    if let Err(panic) = result {
-        if let Some(ref mut err_cb) = on_error {
-            let err_msg = if let Some(s) = panic.downcast_ref::<&str>() {
-                s.to_string()
-            } else if let Some(s) = panic.downcast_ref::<String>() {
-                s.clone()
-            } else {
-                "Listener thread panicked".to_string()
-            };
-            err_cb(RawInputError::Other(format!(
-                "Listener thread panicked: {err_msg}"
+    // Run the message loop, restarting it after fatal errors as configured
+    let mut restarts = 0;
+    loop {
+        let run =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| source.run(&mut dispatcher)));
+        let mut err = match run {
+            Ok(()) => match source.failure.take() {
+                Some(err) => err,
+                None => return Ok(()),
+            },
+            Err(panic) => report_panic(&mut dispatcher, panic),
+        };
+        let Some(policy) = config.auto_restart else {
+            return Err(err);
+        };
+        loop {
+            if restarts >= policy.max_retries {
+                return Err(err);
+            }
+            restarts += 1;
+            dispatcher.report(RawInputError::Other(format!(
+                "Restarting the listener after: {err} (restart {restarts} of {})",
+                policy.max_retries
             )));
-        } else {
-            eprintln!("Listener thread panicked");
+            if !wait_while_running(&source.running, policy.delay(restarts)) {
+                return Err(err);
+            }
+            match unsafe { source.restart(&class_name) } {
+                Ok(errors) => {
+                    errors.into_iter().for_each(|e| dispatcher.report(e));
+                    // Transitions during the restart delay were missed
+                    seed_state(&dispatcher.state());
+                    break;
+                }
+                Err(e) => {
+                    dispatcher.report(e.clone());
+                    err = e;
+                }
+            }
+        }
+    }
+}
+
+/// Seeds the tracked state with the keys and buttons currently held.
+fn seed_state(state: &SharedInputState) {
+    if let Ok(mut state) = state.lock() {
+        state.seed_from_system();
+    }
+}
+
+/// Converts a panic caught on the listener thread into an error and reports it.
+fn report_panic<F>(dispatcher: &mut Dispatcher<F>, panic: Box<dyn Any + Send>) -> RawInputError
+where
+    F: FnMut(RawInputEvent),
+{
+    let err_msg = if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Listener thread panicked".to_string()
+    };
+    let err = RawInputError::Other(format!("Listener thread panicked: {err_msg}"));
+    dispatcher.report(err.clone());
+    err
+}
+
+/// Sleeps for `delay` in short steps, returning false as soon as the listener is stopped.
+fn wait_while_running(running: &AtomicBool, delay: Duration) -> bool {
+    const STEP: Duration = Duration::from_millis(50);
+    let mut remaining = delay;
+    while running.load(Ordering::SeqCst) && !remaining.is_zero() {
+        let step = remaining.min(STEP);
+        thread::sleep(step);
+        remaining -= step;
+    }
+    running.load(Ordering::SeqCst)
+}
+
+/// Starts the watchdog timer, the no-input check if configured and, with a UIPI monitor,
+/// the UIPI polling timer of `hwnd`.
+fn set_timers(hwnd: HWND, config: &ListenerConfig, uipi: bool) {
+    let watchdog = config
+        .watchdog_interval
+        .unwrap_or(DEFAULT_WATCHDOG_INTERVAL);
+    unsafe {
+        if !watchdog.is_zero() {
+            let millis = u32::try_from(watchdog.as_millis()).unwrap_or(u32::MAX);
+            SetTimer(Some(hwnd), WATCHDOG_TIMER_ID, millis, None);
+        }
+        if let Some(grace) = config.no_input_warning {
+            let millis = u32::try_from(grace.as_millis()).unwrap_or(u32::MAX);
+            SetTimer(Some(hwnd), NO_INPUT_TIMER_ID, millis, None);
+        }
+        if uipi {
+            SetTimer(Some(hwnd), UIPI_TIMER_ID, UIPI_POLL_INTERVAL_MS, None);
         }
     }
 }
@@ -192,8 +802,8 @@ fn listener_thread_main<F, E>(
 /// 1. Gets the module handle
 /// 2. Registers the window class
 /// 3. Creates the hidden window
-/// 4. Registers for raw input events
-/// 5. Sends the window handle back to the main thread
+/// 4. Registers for raw input events (partial failures are reported, not fatal)
+/// 5. Sends the window handle, published through `window_handle`, back to the main thread
 ///
 /// # Safety
 /// This function is unsafe because it calls into Win32 API functions.
@@ -203,8 +813,12 @@ fn listener_thread_main<F, E>(
 /// - `Err(())` - If any step fails (error details are sent through `init_tx`)
 unsafe fn initialize_listener_window(
     class_name: &[u16],
-    init_tx: &std::sync::mpsc::Sender<Result<(), RawInputError>>,
-    hwnd_tx: &std::sync::mpsc::Sender<(isize, isize)>,
+    config: &ListenerConfig,
+    init_tx: &mpsc::Sender<Result<RegistrationResult, RawInputError>>,
+    hwnd_tx: &mpsc::Sender<ListenerWindow>,
+    reconfigure_tx: mpsc::Sender<Reconfigure>,
+    window_handle: Arc<AtomicIsize>,
+    reporter: &mut ErrorReporter,
 ) -> Result<HWND, ()> {
     // 1. Register window class
     let hmodule = match unsafe { GetModuleHandleW(None) } {
@@ -234,87 +848,406 @@ unsafe fn initialize_listener_window(
     }
 
     // 2. Create hidden window
-    let hwnd = match unsafe {
+    let hwnd = match create_listener_window(class_name, config, hinstance) {
+        Ok(h) => h,
+        Err(e) => {
+            let _ = init_tx.send(Err(e));
+            return Err(());
+        }
+    };
+
+    // Send HWND and HINSTANCE as raw integer values to main thread (FFI-safe)
+    window_handle.store(hwnd.0 as isize, Ordering::SeqCst);
+    let _ = hwnd_tx.send((window_handle, hinstance.0 as isize, reconfigure_tx));
+
+    // 3. Register for raw input; the listener starts as long as one device type registered
+    match crate::register_raw_input(Some(hwnd), config, config.flags(ffi::LISTENER_FLAGS)) {
+        Ok(registration) => {
+            registration
+                .errors()
+                .into_iter()
+                .for_each(|e| reporter.report(e));
+            // Signal success to main thread
+            let _ = init_tx.send(Ok(registration));
+        }
+        Err(e) => {
+            let _ = init_tx.send(Err(e));
+            return Err(());
+        }
+    }
+
+    Ok(hwnd)
+}
+
+/// Creates the hidden listener window of the registered window class.
+///
+/// Unless a top-level window is requested, the window is message-only (parented to
+/// `HWND_MESSAGE`): it receives WM_INPUT like any other, but can never be shown and is not
+/// enumerated.
+fn create_listener_window(
+    class_name: &[u16],
+    config: &ListenerConfig,
+    hinstance: HINSTANCE,
+) -> Result<HWND, RawInputError> {
+    let (style, parent) = if config.top_level_window {
+        (WS_OVERLAPPEDWINDOW, None)
+    } else {
+        (WINDOW_STYLE(0), Some(HWND_MESSAGE))
+    };
+    unsafe {
         CreateWindowExW(
             Default::default(),
             PCWSTR(class_name.as_ptr()),
             PCWSTR(class_name.as_ptr()),
-            WS_OVERLAPPEDWINDOW,
+            style,
             0,
             0,
             0,
             0,
-            None,
+            parent,
             None,
             Some(hinstance),
             Some(null_mut()),
         )
-    } {
-        Ok(h) => h,
-        Err(e) => {
-            let _ = init_tx.send(Err(RawInputError::WinApiError(format!(
-                "CreateWindowExW failed: {e}",
-            ))));
-            return Err(());
+    }
+    .map_err(|e| RawInputError::WinApiError(format!("CreateWindowExW failed: {e}")))
+}
+
+/// Outcome of a `GetMessageW` call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GetMessageResult {
+    /// A message other than WM_QUIT was retrieved
+    Message,
+    /// WM_QUIT was retrieved
+    Quit,
+    /// Retrieving a message failed; the MSG is not valid
+    Failed,
+}
+
+impl GetMessageResult {
+    /// Interprets the BOOL returned by `GetMessageW`.
+    ///
+    /// Despite its type, the return value has three states: nonzero for a message, 0 for
+    /// WM_QUIT and -1 on error. Treating it as a plain BOOL would take -1 for a message
+    /// and process a garbage MSG.
+    fn from_return(value: i32) -> Self {
+        match value {
+            0 => Self::Quit,
+            -1 => Self::Failed,
+            _ => Self::Message,
         }
-    };
+    }
+}
 
-    // Send HWND and HINSTANCE as raw integer values to main thread (FFI-safe)
-    let _ = hwnd_tx.send((hwnd.0 as isize, hinstance.0 as isize));
+/// State of the listener window, reachable from its window procedure through
+/// `GWLP_USERDATA`.
+///
+/// WM_INPUT is read by the window procedure, whichever loop dispatched it, and the results
+/// are queued for the message loop to yield once `DispatchMessageW` returns.
+struct WindowState {
+    reader: PacketReader,
+    /// Tracked state, whose keys of removed keyboards are released
+    input_state: SharedInputState,
+    pending: VecDeque<Result<RawInputEvent, RawInputError>>,
+    /// Panic of the HID callback, which cannot unwind through the window procedure; the
+    /// message loop resumes it
+    panic: Option<Box<dyn Any + Send>>,
+    keep_awake: Option<KeepAwake>,
+    /// Whether any WM_INPUT was read, for [`ListenerBuilder::no_input_warning`]
+    received_input: bool,
+}
 
-    // 3. Register for raw input
-    if let Err(e) = crate::register_raw_input(Some(hwnd)) {
-        let _ = init_tx.send(Err(e));
-        return Err(());
+impl WindowState {
+    fn new(reader: PacketReader, input_state: SharedInputState) -> Self {
+        Self {
+            reader,
+            input_state,
+            pending: VecDeque::new(),
+            panic: None,
+            keep_awake: None,
+            received_input: false,
+        }
+    }
+
+    /// Moves the state to the heap and attaches it to `hwnd`, returning the pointer to
+    /// pass to [`detach`](Self::detach) once the window no longer needs it.
+    ///
+    /// # Safety
+    /// `hwnd` must be a window of the calling thread whose window procedure is
+    /// [`wnd_proc`], and the state must be detached before the window's user data is
+    /// replaced.
+    unsafe fn attach(self, hwnd: HWND) -> *mut Self {
+        let state = Box::into_raw(Box::new(self));
+        unsafe { SetWindowLongPtrW(hwnd, GWLP_USERDATA, state as isize) };
+        state
     }
 
-    // Signal success to main thread
-    let _ = init_tx.send(Ok(()));
+    /// Detaches the state from `hwnd` and takes it back.
+    ///
+    /// # Safety
+    /// `state` must have been returned by [`attach`](Self::attach) for `hwnd`, and not
+    /// been detached already.
+    unsafe fn detach(hwnd: HWND, state: *mut Self) -> Self {
+        unsafe {
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            *Box::from_raw(state)
+        }
+    }
 
-    Ok(hwnd)
+    /// Returns the state attached to `hwnd`, if any.
+    ///
+    /// # Safety
+    /// Must be called on the window's thread, while no other reference to the state is in
+    /// use (as in the window procedure).
+    unsafe fn of<'a>(hwnd: HWND) -> Option<&'a mut Self> {
+        let state = unsafe { GetWindowLongPtrW(hwnd, GWLP_USERDATA) } as *mut Self;
+        unsafe { state.as_mut() }
+    }
+
+    /// Reads the packet of a WM_INPUT message into the queue.
+    fn read(&mut self, lparam: LPARAM) {
+        self.received_input = true;
+        if let Some(keep_awake) = self.keep_awake.as_mut() {
+            keep_awake.input();
+        }
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.reader.read(lparam))) {
+            Ok(item) => self.pending.extend(item),
+            Err(payload) => self.panic = Some(payload),
+        }
+    }
 }
 
-/// Run the Windows message loop, processing raw input events.
-///
-/// This function handles the Windows message pump, watching for WM_INPUT messages
-/// and dispatching them to the user's callback. It continues running until the
-/// `running` flag is set to false or a WM_QUIT message is received.
+/// Keeps the display on while input arrives, as set by
+/// [`ListenerBuilder::keep_awake`].
+#[derive(Default)]
+struct KeepAwake {
+    /// When the idle timers were last reset
+    last_reset: Option<Instant>,
+}
+
+impl KeepAwake {
+    /// Resets the idle timers for an input, unless they were reset recently.
+    fn input(&mut self) {
+        let now = Instant::now();
+        if self
+            .last_reset
+            .is_none_or(|last| now.duration_since(last) >= KEEP_AWAKE_INTERVAL)
+        {
+            ffi::reset_idle_timers();
+            self.last_reset = Some(now);
+        }
+    }
+}
+
+/// Event source backed by the hidden window's Windows message loop.
 ///
-/// # Safety
-/// This function is unsafe because it calls into Win32 API functions.
-unsafe fn run_message_loop<F, E>(
+/// Pumps messages until a WM_INPUT message yields an event (or a read error), and ends
+/// once the `running` flag is set to false, a WM_QUIT message is received or retrieving a
+/// message fails. With a UIPI monitor, its timer messages can also yield an error, and so
+/// can restoring capture after a session change with a session monitor and the check for
+/// missing input.
+struct MessageLoopSource {
     hwnd: HWND,
+    /// The listener window as read by the [`ListenerHandle`]
+    window_handle: Arc<AtomicIsize>,
     running: Arc<AtomicBool>,
-    callback: &mut F,
-    on_error: &mut Option<E>,
-) where
-    F: FnMut(RawInputEvent),
-    E: FnMut(RawInputError),
-{
-    let mut msg = MSG::default();
-    while running.load(Ordering::SeqCst)
-        && unsafe { GetMessageW(&mut msg, Some(hwnd), 0, 0) }.into()
-    {
-        if msg.message == WM_INPUT {
-            let lparam = msg.lParam;
-            match crate::read_raw_input_event_from_lparam(lparam) {
-                Ok(raw) => {
-                    if let Some(event) = parse_rawinput_event(&raw) {
-                        callback(event);
-                    }
+    /// State attached to the window; only accessed outside of `DispatchMessageW`
+    window: *mut WindowState,
+    uipi: Option<UipiMonitor>,
+    session: Option<SessionMonitor>,
+    /// Configuration whose registrations are in effect
+    registration: ListenerConfig,
+    reconfigure: mpsc::Receiver<Reconfigure>,
+    /// Error that ended the message loop, if any
+    failure: Option<RawInputError>,
+}
+
+/// What the message loop yields: an event or error, or a tick of the watchdog timer
+enum LoopItem {
+    Input(Result<RawInputEvent, RawInputError>),
+    WatchdogTick,
+}
+
+impl EventSource for MessageLoopSource {
+    fn next_event(&mut self) -> Option<Result<RawInputEvent, RawInputError>> {
+        loop {
+            if let LoopItem::Input(item) = self.next_item()? {
+                return Some(item);
+            }
+        }
+    }
+}
+
+impl MessageLoopSource {
+    /// Dispatches events until the message loop ends. Coalesced error repeats whose
+    /// interval is over are passed on at every watchdog tick, so that they are not held
+    /// back until the error occurs again.
+    fn run<F: FnMut(RawInputEvent)>(&mut self, dispatcher: &mut Dispatcher<F>) {
+        while let Some(item) = self.next_item() {
+            match item {
+                LoopItem::Input(Ok(event)) => dispatcher.dispatch(event),
+                LoopItem::Input(Err(e)) => dispatcher.report(e),
+                LoopItem::WatchdogTick => dispatcher.flush_expired_errors(),
+            }
+        }
+    }
+
+    fn next_item(&mut self) -> Option<LoopItem> {
+        let mut msg = MSG::default();
+        while self.running.load(Ordering::SeqCst) && self.failure.is_none() {
+            if let Some(item) = self.window().pending.pop_front() {
+                return Some(LoopItem::Input(item));
+            }
+
+            let result = unsafe { GetMessageW(&mut msg, Some(self.hwnd), 0, 0) };
+            match GetMessageResult::from_return(result.0) {
+                GetMessageResult::Quit => break,
+                GetMessageResult::Failed => {
+                    let err = RawInputError::WinApiError(format!(
+                        "GetMessageW failed: {}",
+                        windows::core::Error::from_win32()
+                    ));
+                    self.failure = Some(err.clone());
+                    return Some(LoopItem::Input(Err(err)));
                 }
-                Err(e) => {
-                    if let Some(err_cb) = on_error {
-                        err_cb(e);
-                    } else {
-                        eprintln!("Raw input event error: {e}");
-                    }
+                GetMessageResult::Message => {}
+            }
+
+            let mut item = None;
+            let mut watchdog_tick = false;
+            if msg.message == WM_TIMER
+                && msg.wParam.0 == UIPI_TIMER_ID
+                && let Some(monitor) = self.uipi.as_mut()
+            {
+                item = monitor.poll().map(Err);
+            } else if msg.message == WM_TIMER && msg.wParam.0 == NO_INPUT_TIMER_ID {
+                // A one-time check
+                unsafe {
+                    let _ = KillTimer(Some(self.hwnd), NO_INPUT_TIMER_ID);
+                }
+                if !self.window().received_input {
+                    item = Some(Err(RawInputError::Other(format!(
+                        "No input received within {:?} of registering; the registration may \
+                         be ineffective, e.g. if another component of the process registered \
+                         the same devices (see registered_devices) or a driver takes \
+                         exclusive access",
+                        self.registration.no_input_warning.unwrap_or_default()
+                    ))));
+                }
+            } else if msg.message == WM_SESSION_CHANGE
+                && let Some(monitor) = self.session.as_mut()
+                && let Some(change) = monitor.handle(msg.wParam.0 as u32)
+                && !change.suspends_capture()
+            {
+                // Register again in case the registration was lost while suspended
+                item = match self.register(self.registration.clone()) {
+                    Ok(registration) => registration.errors().into_iter().next().map(Err),
+                    Err(e) => Some(Err(e)),
+                };
+            } else if msg.message == WM_RECONFIGURE
+                || (msg.message == WM_TIMER && msg.wParam.0 == WATCHDOG_TIMER_ID)
+            {
+                // The watchdog timer also picks up requests whose message was lost; the
+                // loop condition re-checks the running flag
+                while let Ok(request) = self.reconfigure.try_recv() {
+                    let _ = request.reply.send(self.register(request.config));
                 }
+                watchdog_tick = msg.message == WM_TIMER;
+            }
+
+            // DispatchMessageW doesn't return a meaningful value for us to check; WM_INPUT
+            // is read by the window procedure
+            unsafe { DispatchMessageW(&msg) };
+
+            if let Some(payload) = self.window().panic.take() {
+                std::panic::resume_unwind(payload);
+            }
+            if let Some(item) = item {
+                return Some(LoopItem::Input(item));
+            }
+            if watchdog_tick {
+                return Some(LoopItem::WatchdogTick);
             }
         }
+        None
+    }
+}
 
-        // DispatchMessageW doesn't return a meaningful value for us to check
-        unsafe { DispatchMessageW(&msg) };
+impl Drop for MessageLoopSource {
+    fn drop(&mut self) {
+        drop(unsafe { WindowState::detach(self.hwnd, self.window) });
+    }
+}
+
+impl MessageLoopSource {
+    fn window(&mut self) -> &mut WindowState {
+        // The window procedure only uses the state during DispatchMessageW
+        unsafe { &mut *self.window }
+    }
+
+    /// Replaces the listener window with a new one registered for raw input as currently
+    /// configured, after a fatal error ended the message loop.
+    ///
+    /// The window state, and with it the HID callback, moves to the new window, as do the
+    /// session notifications. The old window is only destroyed once the new one exists.
+    ///
+    /// # Safety
+    /// Must be called on the listener thread, outside of the message loop.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<RawInputError>)` - The new window is in place; the errors of registrations
+    ///   and session notifications that failed without preventing capture
+    /// * `Err(RawInputError)` - If the window cannot be created or no registration succeeded
+    unsafe fn restart(&mut self, class_name: &[u16]) -> Result<Vec<RawInputError>, RawInputError> {
+        let hmodule = unsafe { GetModuleHandleW(None) }
+            .map_err(|e| RawInputError::WinApiError(format!("GetModuleHandleW failed: {e}")))?;
+        let hwnd = create_listener_window(class_name, &self.registration, HINSTANCE(hmodule.0))?;
+        let old = std::mem::replace(&mut self.hwnd, hwnd);
+        // Detached from the old window first, so that destroying it does not post WM_QUIT
+        unsafe {
+            SetWindowLongPtrW(old, GWLP_USERDATA, 0);
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, self.window as isize);
+        }
+        self.window().pending.clear();
+        self.window_handle.store(hwnd.0 as isize, Ordering::SeqCst);
+
+        let mut errors = Vec::new();
+        if let Some(session) = self.session.as_mut()
+            && let Err(e) = session.rebind(hwnd)
+        {
+            errors.push(e);
+        }
+        unsafe {
+            let _ = DestroyWindow(old);
+        }
+        set_timers(hwnd, &self.registration, self.uipi.is_some());
+
+        let flags = self.registration.flags(ffi::LISTENER_FLAGS);
+        let registration = crate::register_raw_input(Some(hwnd), &self.registration, flags)?;
+        errors.extend(registration.errors());
+        Ok(errors)
+    }
+
+    /// Registers raw input for the listener window as configured, after removing the
+    /// current registrations that are no longer requested.
+    fn register(&mut self, config: ListenerConfig) -> Result<RegistrationResult, RawInputError> {
+        let requested = config.registered_usages();
+        for usage in self.registration.registered_usages() {
+            if requested.contains(&usage) {
+                continue;
+            }
+            ffi::unregister_usage(usage.usage_page, usage.usage, usage.page_only).map_err(|e| {
+                RawInputError::WinApiError(format!(
+                    "Failed to remove registration for usage page {:#06x}, usage {:#06x}: {e}",
+                    usage.usage_page, usage.usage
+                ))
+            })?;
+        }
+
+        let flags = config.flags(ffi::LISTENER_FLAGS);
+        let registration = crate::register_raw_input(Some(self.hwnd), &config, flags)?;
+        self.registration = config;
+        Ok(registration)
     }
 }
 
@@ -332,17 +1265,34 @@ fn widestring(s: &str) -> Vec<u16> {
 
 /// Window procedure for the hidden window that receives raw input events.
 ///
-/// This is a minimal implementation that only handles WM_DESTROY by posting
-/// a quit message to terminate the message loop.
+/// This is a minimal implementation that reads WM_INPUT into the attached [`WindowState`],
+/// releases the keys of keyboards removed according to WM_INPUT_DEVICE_CHANGE, handles
+/// WM_DESTROY of the window with the state by posting a quit message to terminate
+/// the message loop (a window replaced on restart has none), and re-posts
+/// WM_WTSSESSION_CHANGE (which may be sent rather than posted) for the message loop to
+/// handle.
 unsafe extern "system" fn wnd_proc(
     hwnd: HWND,
     msg: u32,
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
-    if msg == WM_DESTROY {
+    if msg == WM_INPUT
+        && let Some(state) = unsafe { WindowState::of(hwnd) }
+    {
+        // Falls through: DefWindowProcW must clean up after RIM_INPUT
+        state.read(lparam);
+    } else if msg == WM_INPUT_DEVICE_CHANGE
+        && let Some(state) = unsafe { WindowState::of(hwnd) }
+        && let Ok(mut input_state) = state.input_state.lock()
+    {
+        input_state.device_changed(wparam.0, lparam.0);
+    } else if msg == WM_DESTROY && unsafe { WindowState::of(hwnd) }.is_some() {
         // PostQuitMessage doesn't return a value, no need to handle result
         unsafe { PostQuitMessage(0) };
+    } else if msg == WM_WTSSESSION_CHANGE {
+        let _ = unsafe { PostMessageW(Some(hwnd), WM_SESSION_CHANGE, wparam, lparam) };
+        return LRESULT(0);
     }
     // Always call the default window procedure for unhandled messages
     unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
@@ -351,11 +1301,20 @@ unsafe extern "system" fn wnd_proc(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::RawInputEvent;
+    use crate::builder::RestartPolicy;
+    use crate::{RawInputEvent, RawMouseEvent, UsageRegistration};
     use std::sync::mpsc;
     use std::thread;
     use std::time::Duration;
 
+    /// Test that a failing GetMessageW is not mistaken for a retrieved message
+    #[test]
+    fn test_get_message_result() {
+        assert_eq!(GetMessageResult::from_return(1), GetMessageResult::Message);
+        assert_eq!(GetMessageResult::from_return(0), GetMessageResult::Quit);
+        assert_eq!(GetMessageResult::from_return(-1), GetMessageResult::Failed);
+    }
+
     /// Test that only one listener can be active at a time (singleton enforcement)
     #[test]
     fn test_singleton_enforcement() {
@@ -444,4 +1403,223 @@ mod tests {
             drop(l);
         }
     }
+
+    /// Test that the restart delay doubles with every restart, up to a minute
+    #[test]
+    fn test_restart_delay() {
+        let policy = RestartPolicy {
+            max_retries: 100,
+            backoff: Duration::from_millis(500),
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+        assert_eq!(policy.delay(40), Duration::from_secs(60));
+    }
+
+    /// Test that a listener whose callback panicked restarts with the same callback
+    #[test]
+    fn test_auto_restart_after_panic() {
+        let nudge = || {
+            let mut raw: windows::Win32::UI::Input::RAWMOUSE = unsafe { std::mem::zeroed() };
+            raw.lLastX = 1;
+            crate::send_event(&RawInputEvent::Mouse(RawMouseEvent::from(&raw))).unwrap();
+        };
+        let (errors_tx, errors_rx) = mpsc::channel();
+        let (events_tx, events_rx) = mpsc::channel();
+        let mut panicked = false;
+        let listener = ListenerBuilder::new()
+            .capture_keyboard(false)
+            .auto_restart(1, Duration::ZERO)
+            .error_callback(move |err| {
+                let _ = errors_tx.send(err.to_string());
+            })
+            .start(move |event| {
+                if !std::mem::replace(&mut panicked, true) {
+                    panic!("Intentional panic for testing");
+                }
+                let _ = events_tx.send(event);
+            })
+            .unwrap();
+
+        nudge();
+        let restarted = std::iter::from_fn(|| errors_rx.recv_timeout(Duration::from_secs(2)).ok())
+            .find(|err| err.starts_with("Restarting the listener"));
+        assert!(restarted.is_some_and(|err| err.contains("restart 1 of 1")));
+        thread::sleep(Duration::from_millis(200));
+
+        nudge();
+        assert!(events_rx.recv_timeout(Duration::from_secs(2)).is_ok());
+        drop(listener);
+    }
+
+    /// Test that the context lives on the listener thread until the listener stops
+    #[test]
+    fn test_context_dropped_with_listener() {
+        let (tx, rx) = mpsc::channel::<()>();
+        let listener = start_listener_with_context(tx, |_tx, _event| {}).unwrap();
+        assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Empty));
+
+        drop(listener);
+        assert_eq!(rx.recv(), Err(mpsc::RecvError));
+    }
+
+    /// Test that WM_INPUT reaches the state attached to the window through its user data,
+    /// and is ignored once the state is detached
+    #[test]
+    fn test_window_state_receives_wm_input() {
+        let class_name = widestring("MkbRawInputTestWindow");
+        let hinstance = HINSTANCE(unsafe { GetModuleHandleW(None) }.unwrap().0);
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(wnd_proc),
+            hInstance: hinstance,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        };
+        assert_ne!(unsafe { RegisterClassW(&wc) }, 0);
+        let hwnd = unsafe {
+            CreateWindowExW(
+                Default::default(),
+                PCWSTR(class_name.as_ptr()),
+                PCWSTR(class_name.as_ptr()),
+                WS_OVERLAPPEDWINDOW,
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                Some(hinstance),
+                None,
+            )
+        }
+        .unwrap();
+        let send_input = || unsafe { SendMessageW(hwnd, WM_INPUT, None, Some(LPARAM(0))) };
+
+        let reader = PacketReader::new(false, false, None, Arc::default());
+        let state = unsafe { WindowState::new(reader, SharedInputState::default()).attach(hwnd) };
+        // A null HRAWINPUT cannot be read, so the window procedure queues a read error
+        send_input();
+        let attached = unsafe { WindowState::of(hwnd) }.unwrap();
+        assert!(matches!(attached.pending.pop_front(), Some(Err(_))));
+
+        let detached = unsafe { WindowState::detach(hwnd, state) };
+        assert!(unsafe { WindowState::of(hwnd) }.is_none());
+        send_input();
+        assert!(detached.pending.is_empty());
+
+        unsafe {
+            let _ = DestroyWindow(hwnd);
+            let _ = UnregisterClassW(PCWSTR(class_name.as_ptr()), Some(hinstance));
+        }
+    }
+
+    /// Test that the keyboard usage is not registered at all when keyboard capture is
+    /// disabled
+    #[test]
+    fn test_mouse_only_registers_no_keyboard() {
+        let (listener, registration) = ListenerBuilder::mouse_only()
+            .start_partial(|_event| {})
+            .unwrap();
+        assert!(registration.keyboard.is_none());
+        let devices = crate::registered_devices().unwrap();
+        assert!(
+            !devices
+                .iter()
+                .any(|device| (device.usage_page, device.usage) == (0x01, 0x06))
+        );
+        assert!(
+            devices
+                .iter()
+                .any(|device| (device.usage_page, device.usage) == (0x01, 0x02))
+        );
+        drop(listener);
+    }
+
+    /// Test that the channel of a stopped listener disconnects after its last event
+    #[test]
+    fn test_channel_disconnects_when_stopped() {
+        let (listener, events) = start_listener_channel().unwrap();
+        drop(listener);
+        let end = loop {
+            if let Err(err) = events.recv_timeout(Duration::from_secs(5)) {
+                break err;
+            }
+        };
+        assert_eq!(end, mpsc::RecvTimeoutError::Disconnected);
+    }
+
+    /// Test that the default listener observes keyboard and mouse without suppressing
+    /// legacy messages, so that typing keeps working in the process's own windows
+    #[test]
+    fn test_default_registration_keeps_legacy_messages() {
+        let listener = ListenerBuilder::new().start(|_event| {}).unwrap();
+        let devices = crate::registered_devices().unwrap();
+        for usage in [0x02, 0x06] {
+            let device = devices
+                .iter()
+                .find(|device| (device.usage_page, device.usage) == (0x01, usage))
+                .expect("Keyboard and mouse must be registered");
+            assert!(device.is_background() && !device.is_no_legacy());
+        }
+        drop(listener);
+    }
+
+    /// Test that the hidden window is message-only unless a top-level window is requested
+    #[test]
+    fn test_message_only_window() {
+        let message_only = |listener: &ListenerHandle| unsafe {
+            FindWindowExW(
+                Some(HWND_MESSAGE),
+                None,
+                PCWSTR(listener.class_name.as_ptr()),
+                PCWSTR::null(),
+            )
+            .is_ok_and(|hwnd| hwnd == listener.hwnd())
+        };
+
+        let listener = ListenerBuilder::new().start(|_event| {}).unwrap();
+        assert!(message_only(&listener));
+        drop(listener);
+
+        let listener = ListenerBuilder::new()
+            .message_only_window(false)
+            .start(|_event| {})
+            .unwrap();
+        assert!(!message_only(&listener));
+        drop(listener);
+    }
+
+    /// Test that reconfiguring adds new usages and removes dropped ones
+    #[test]
+    fn test_reconfigure() {
+        let registered = |usage: UsageRegistration| {
+            crate::registered_devices()
+                .unwrap()
+                .iter()
+                .any(|device| (device.usage_page, device.usage) == (usage.usage_page, usage.usage))
+        };
+        let gamepad = UsageRegistration::usage(0x01, 0x05);
+        let listener = ListenerBuilder::new().start(|_event| {}).unwrap();
+        assert!(!registered(gamepad));
+
+        let registration = listener
+            .reconfigure(ListenerBuilder::new().register_usage(gamepad))
+            .unwrap();
+        assert_eq!(registration.hid.len(), 1);
+        assert!(registered(gamepad));
+
+        listener.reconfigure(ListenerBuilder::new()).unwrap();
+        assert!(!registered(gamepad));
+
+        let invalid = UsageRegistration {
+            usage_page: 0x01,
+            usage: 0x05,
+            page_only: true,
+        };
+        assert!(
+            listener
+                .reconfigure(ListenerBuilder::new().register_usage(invalid))
+                .is_err()
+        );
+    }
 }
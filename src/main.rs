@@ -42,6 +42,12 @@ fn main() {
                     action_str, mouse.last_x, mouse.last_y, mouse.move_mode
                 );
             }
+            RawInputEvent::Hid(hid) => {
+                println!(
+                    "HID: {} report(s) of {} bytes",
+                    hid.report_count, hid.report_size
+                );
+            }
         },
         Some(|err| eprintln!("Error: {}", err)),
     )
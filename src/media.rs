@@ -0,0 +1,395 @@
+//! Media keys from keyboards and consumer control devices, with duplicates merged
+
+use crate::{
+    RawInputDeviceHandle, RawInputError, RawInputEvent, RawKeyboardEvent, UsageRegistration,
+    VirtualKey,
+};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// HID usage page of consumer controls (media, volume and application launch keys)
+const USAGE_PAGE_CONSUMER: u16 = 0x0C;
+/// Consumer page usage of a consumer control top level collection
+const USAGE_CONSUMER_CONTROL: u16 = 0x01;
+
+/// Consumer page usages and the virtual keys Windows reports for them through keyboards
+const CONSUMER_KEYS: [(u16, VirtualKey); 18] = [
+    (0xB5, VirtualKey::MediaNextTrack),
+    (0xB6, VirtualKey::MediaPrevTrack),
+    (0xB7, VirtualKey::MediaStop),
+    (0xCD, VirtualKey::MediaPlayPause),
+    (0xE2, VirtualKey::VolumeMute),
+    (0xE9, VirtualKey::VolumeUp),
+    (0xEA, VirtualKey::VolumeDown),
+    (0x183, VirtualKey::LaunchMediaSelect),
+    (0x18A, VirtualKey::LaunchMail),
+    (0x192, VirtualKey::LaunchApp2),
+    (0x194, VirtualKey::LaunchApp1),
+    (0x221, VirtualKey::BrowserSearch),
+    (0x223, VirtualKey::BrowserHome),
+    (0x224, VirtualKey::BrowserBack),
+    (0x225, VirtualKey::BrowserForward),
+    (0x226, VirtualKey::BrowserStop),
+    (0x227, VirtualKey::BrowserRefresh),
+    (0x22A, VirtualKey::BrowserFavorites),
+];
+
+/// Time within which the same transition of a media key from the other source is taken
+/// for a duplicate, unless set with [`MediaKeys::with_window`]
+const DEFAULT_DUPLICATE_WINDOW: Duration = Duration::from_millis(50);
+
+impl UsageRegistration {
+    /// Registers consumer controls (usage page 0x0C, usage 0x01), the HID collection
+    /// through which many keyboards and remotes report their media and volume keys, for
+    /// [`MediaKeys`].
+    pub fn consumer_control() -> Self {
+        Self::usage(USAGE_PAGE_CONSUMER, USAGE_CONSUMER_CONTROL)
+    }
+}
+
+/// Returns the virtual key of a consumer page usage, if it is a media key.
+fn consumer_usage_key(usage: u16) -> Option<VirtualKey> {
+    CONSUMER_KEYS
+        .iter()
+        .find(|&&(u, _)| u == usage)
+        .map(|&(_, key)| key)
+}
+
+/// Path a media key press or release arrived through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKeySource {
+    /// A keyboard event carrying the media key's virtual key
+    Keyboard,
+    /// A report of a consumer control HID collection
+    ConsumerControl,
+}
+
+/// A press or release of a media key, as reported by [`MediaKeys`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaKeyEvent {
+    /// The media key, as the virtual key keyboards report for it
+    pub key: VirtualKey,
+    /// Whether the key was released (true) or pressed (false)
+    pub key_up: bool,
+    /// Path the transition arrived through first
+    pub source: MediaKeySource,
+    /// Device that reported the transition
+    pub device: RawInputDeviceHandle,
+    /// Monotonic time at which the listener dequeued the event
+    pub timestamp: Instant,
+}
+
+/// Consumer control collection whose reports are parsed into media keys
+#[cfg(windows)]
+struct ConsumerDevice {
+    preparsed: Vec<u64>,
+    /// Media keys held according to the last report
+    held: Vec<VirtualKey>,
+}
+
+/// Turns keyboard and consumer control events into media key presses and releases,
+/// reporting each transition once.
+///
+/// Depending on the device, a media key arrives as a keyboard event with its virtual key
+/// (e.g. [`VirtualKey::VolumeUp`]), as a report of a consumer control HID collection, or
+/// as both: Windows turns consumer control reports into keyboard input too, so with
+/// [`UsageRegistration::consumer_control`] registered, one press can be seen twice. The
+/// merger passes on the first of the two and drops the same transition (press or
+/// release) of the same key from the other source if it follows within the duplicate
+/// window, 50 ms unless set with [`with_window`](Self::with_window). Each
+/// [`MediaKeyEvent`] tells which source it came from. Repeated presses from the same
+/// source, such as the auto-repeat of a held volume key, are never dropped.
+///
+/// Feed it every event of the listener, e.g. from the event callback, which receives the
+/// HID events as long as no [`hid_callback`](crate::ListenerBuilder::hid_callback) is set.
+/// HID events of other collections are ignored.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{ListenerBuilder, MediaKeys, UsageRegistration};
+///
+/// let mut media = MediaKeys::new();
+/// let _listener = ListenerBuilder::new()
+///     .register_usage(UsageRegistration::consumer_control())
+///     .start(move |event| {
+///         for key in media.process(&event).unwrap_or_default() {
+///             println!("{:?} up: {} via {:?}", key.key, key.key_up, key.source);
+///         }
+///     })
+///     .expect("Failed to start listener");
+/// ```
+pub struct MediaKeys {
+    window: Duration,
+    /// Last transition passed on per key: whether it was a release, its source and time
+    last: HashMap<VirtualKey, (bool, MediaKeySource, Instant)>,
+    #[cfg(windows)]
+    devices: HashMap<RawInputDeviceHandle, Option<ConsumerDevice>>,
+    /// Copy of the report being parsed, as `HidP_GetUsages` takes it mutably
+    #[cfg(windows)]
+    report: Vec<u8>,
+}
+
+impl Default for MediaKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaKeys {
+    /// Creates a merger with the default duplicate window of 50 ms.
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_DUPLICATE_WINDOW)
+    }
+
+    /// Creates a merger that takes the same transition of a key from the other source
+    /// within `window` for a duplicate ([`Duration::ZERO`] only merges simultaneous ones).
+    pub fn with_window(window: Duration) -> Self {
+        Self {
+            window,
+            last: HashMap::new(),
+            #[cfg(windows)]
+            devices: HashMap::new(),
+            #[cfg(windows)]
+            report: Vec::new(),
+        }
+    }
+
+    /// Returns the media key transitions of a listener event: at most one for a keyboard
+    /// event, any number for a HID event, none for mouse events and duplicates.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MediaKeyEvent>)` - The transitions, in order
+    /// * `Err(RawInputError)` - If the device of a HID event cannot be queried
+    pub fn process(&mut self, event: &RawInputEvent) -> Result<Vec<MediaKeyEvent>, RawInputError> {
+        match event {
+            RawInputEvent::Keyboard(kbd) => Ok(self.keyboard(kbd).into_iter().collect()),
+            RawInputEvent::Hid(hid) => self.hid(&hid.as_event_ref()),
+            RawInputEvent::Mouse(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the media key transition of a keyboard event, or None for other keys and
+    /// duplicates.
+    pub fn keyboard(&mut self, kbd: &RawKeyboardEvent) -> Option<MediaKeyEvent> {
+        if !CONSUMER_KEYS.iter().any(|&(_, key)| key == kbd.vkey) {
+            return None;
+        }
+        self.accept(MediaKeyEvent {
+            key: kbd.vkey,
+            key_up: kbd.key_up,
+            source: MediaKeySource::Keyboard,
+            device: kbd.device,
+            timestamp: kbd.timestamp,
+        })
+    }
+
+    /// Passes on a transition unless it duplicates the last one of the key from the other
+    /// source.
+    fn accept(&mut self, event: MediaKeyEvent) -> Option<MediaKeyEvent> {
+        let current = (event.key_up, event.source, event.timestamp);
+        if let Some(&(key_up, source, at)) = self.last.get(&event.key)
+            && key_up == event.key_up
+            && source != event.source
+            && event.timestamp.saturating_duration_since(at) <= self.window
+        {
+            return None;
+        }
+        self.last.insert(event.key, current);
+        Some(event)
+    }
+}
+
+#[cfg(windows)]
+impl MediaKeys {
+    /// Returns the media key transitions of every report of a HID event, or none if the
+    /// event is not from a consumer control collection.
+    ///
+    /// The first event of a device queries its collection and report descriptor, which
+    /// are kept for later events.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MediaKeyEvent>)` - The transitions, in order
+    /// * `Err(RawInputError)` - If the device cannot be queried
+    pub fn hid(
+        &mut self,
+        hid: &crate::RawHidEventRef<'_>,
+    ) -> Result<Vec<MediaKeyEvent>, RawInputError> {
+        let device = match self.devices.entry(hid.device) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(ConsumerDevice::query(hid.device)?)
+            }
+        };
+        let Some(device) = device else {
+            return Ok(Vec::new());
+        };
+
+        let mut transitions = Vec::new();
+        for report in hid.reports() {
+            self.report.clear();
+            self.report.extend_from_slice(report);
+            // Reports without the consumer page's usages, e.g. of another report ID, leave
+            // the held keys as they were
+            let Some(usages) =
+                crate::ffi::hid_usages(&device.preparsed, USAGE_PAGE_CONSUMER, &mut self.report)
+            else {
+                continue;
+            };
+            let held: Vec<_> = usages.into_iter().filter_map(consumer_usage_key).collect();
+            let released = device.held.iter().filter(|key| !held.contains(key));
+            transitions.extend(released.map(|&key| (key, true)));
+            let pressed = held.iter().filter(|key| !device.held.contains(key));
+            transitions.extend(pressed.map(|&key| (key, false)));
+            device.held = held;
+        }
+
+        Ok(transitions
+            .into_iter()
+            .filter_map(|(key, key_up)| {
+                self.accept(MediaKeyEvent {
+                    key,
+                    key_up,
+                    source: MediaKeySource::ConsumerControl,
+                    device: hid.device,
+                    timestamp: hid.timestamp,
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(windows)]
+impl ConsumerDevice {
+    /// Reads the report descriptor of a consumer control collection, or returns None for
+    /// other collections.
+    fn query(device: RawInputDeviceHandle) -> Result<Option<Self>, RawInputError> {
+        let info = crate::ffi::device_info(device.as_handle()).map_err(|e| {
+            RawInputError::WinApiError(format!("GetRawInputDeviceInfoW failed: {e}"))
+        })?;
+        if info.dwType != windows::Win32::UI::Input::RIM_TYPEHID {
+            return Ok(None);
+        }
+        let collection = unsafe { info.Anonymous.hid };
+        if (collection.usUsagePage, collection.usUsage)
+            != (USAGE_PAGE_CONSUMER, USAGE_CONSUMER_CONTROL)
+        {
+            return Ok(None);
+        }
+        let preparsed = crate::ffi::preparsed_data(device.as_handle()).map_err(|e| {
+            RawInputError::WinApiError(format!("Failed to read HID preparsed data: {e}"))
+        })?;
+        Ok(Some(Self {
+            preparsed,
+            held: Vec::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyEventMessage, KeyFlags, Modifiers};
+
+    fn key(vkey: VirtualKey, key_up: bool, timestamp: Instant) -> RawKeyboardEvent {
+        RawKeyboardEvent {
+            make_code: 0,
+            key_up,
+            extended: true,
+            flags: KeyFlags {
+                e0_prefix: true,
+                key_up,
+                e1_prefix: false,
+            },
+            message: if key_up {
+                KeyEventMessage::KeyUp
+            } else {
+                KeyEventMessage::KeyDown
+            },
+            vkey,
+            raw_vkey: u16::from(vkey),
+            extra_information: 0,
+            device: RawInputDeviceHandle::default(),
+            modifiers: Modifiers::NONE,
+            raw: None,
+            timestamp,
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        }
+    }
+
+    fn consumer(key: VirtualKey, key_up: bool, timestamp: Instant) -> MediaKeyEvent {
+        MediaKeyEvent {
+            key,
+            key_up,
+            source: MediaKeySource::ConsumerControl,
+            device: RawInputDeviceHandle::default(),
+            timestamp,
+        }
+    }
+
+    /// Test that a transition seen through both paths is passed on once, from the first
+    #[test]
+    fn test_merges_duplicates_across_sources() {
+        let mut media = MediaKeys::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let press = media.accept(consumer(VirtualKey::VolumeUp, false, at(0)));
+        assert_eq!(
+            press.map(|e| e.source),
+            Some(MediaKeySource::ConsumerControl)
+        );
+        assert!(
+            media
+                .keyboard(&key(VirtualKey::VolumeUp, false, at(2)))
+                .is_none()
+        );
+        // The auto-repeat of the held key is not a duplicate
+        let repeat = media.keyboard(&key(VirtualKey::VolumeUp, false, at(500)));
+        assert_eq!(repeat.map(|e| e.source), Some(MediaKeySource::Keyboard));
+
+        // The keyboard path may also come first
+        assert!(
+            media
+                .keyboard(&key(VirtualKey::VolumeUp, true, at(600)))
+                .is_some()
+        );
+        assert!(
+            media
+                .accept(consumer(VirtualKey::VolumeUp, true, at(601)))
+                .is_none()
+        );
+
+        // Outside the window, or for another key, both are passed on
+        assert!(
+            media
+                .accept(consumer(VirtualKey::MediaPlayPause, false, at(700)))
+                .is_some()
+        );
+        assert!(
+            media
+                .keyboard(&key(VirtualKey::MediaPlayPause, false, at(800)))
+                .is_some()
+        );
+        assert!(
+            media
+                .keyboard(&key(VirtualKey::A, false, at(800)))
+                .is_none()
+        );
+    }
+
+    /// Test that every consumer usage maps to a distinct virtual key
+    #[test]
+    fn test_consumer_usage_keys() {
+        assert_eq!(consumer_usage_key(0xCD), Some(VirtualKey::MediaPlayPause));
+        assert_eq!(consumer_usage_key(0x30), None);
+        for (i, &(_, key)) in CONSUMER_KEYS.iter().enumerate() {
+            assert!(
+                CONSUMER_KEYS[i + 1..]
+                    .iter()
+                    .all(|&(_, other)| other != key)
+            );
+        }
+    }
+}
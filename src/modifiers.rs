@@ -0,0 +1,119 @@
+//! Sets of modifier keys
+
+use std::ops::{BitOr, BitOrAssign};
+
+/// A set of modifier keys, combined with `|`.
+///
+/// [`SHIFT`](Self::SHIFT), [`CTRL`](Self::CTRL), [`ALT`](Self::ALT) and [`WIN`](Self::WIN)
+/// are side-independent: [`SHIFT`](Self::SHIFT) is held if either Shift key is. The
+/// `LEFT_*` / `RIGHT_*` flags tell the sides apart; a tracked set always contains the
+/// side-independent flag along with the sided one, so checking for
+/// [`SHIFT`](Self::SHIFT) works whichever Shift key is held.
+///
+/// # Example
+/// ```
+/// use mkb_raw_input::Modifiers;
+///
+/// let ctrl_alt = Modifiers::CTRL | Modifiers::ALT;
+/// assert!(ctrl_alt.contains(Modifiers::CTRL));
+/// assert!(!ctrl_alt.contains(Modifiers::SHIFT));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers(u16);
+
+impl Modifiers {
+    /// No modifiers
+    pub const NONE: Self = Self(0);
+    /// Either Shift key
+    pub const SHIFT: Self = Self(0x01);
+    /// Either Ctrl key
+    pub const CTRL: Self = Self(0x02);
+    /// Either Alt key
+    pub const ALT: Self = Self(0x04);
+    /// Either Windows key
+    pub const WIN: Self = Self(0x08);
+    /// Left Shift key
+    pub const LEFT_SHIFT: Self = Self(0x10);
+    /// Right Shift key
+    pub const RIGHT_SHIFT: Self = Self(0x20);
+    /// Left Ctrl key
+    pub const LEFT_CTRL: Self = Self(0x40);
+    /// Right Ctrl key
+    pub const RIGHT_CTRL: Self = Self(0x80);
+    /// Left Alt key
+    pub const LEFT_ALT: Self = Self(0x100);
+    /// Right Alt key (AltGr on many layouts)
+    pub const RIGHT_ALT: Self = Self(0x200);
+    /// Left Windows key
+    pub const LEFT_WIN: Self = Self(0x400);
+    /// Right Windows key
+    pub const RIGHT_WIN: Self = Self(0x800);
+
+    /// Returns true if every modifier in `other` is also in `self`.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if any modifier in `other` is also in `self`.
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Removes the modifiers in `other` from the set.
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// Returns true if the set holds no modifiers.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the raw bit representation.
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// Creates a set from its raw bit representation, as returned by [`bits`](Self::bits).
+    pub(crate) fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test combining and containment of modifier sets
+    #[test]
+    fn test_combine_and_contains() {
+        let mut modifiers = Modifiers::CTRL | Modifiers::SHIFT;
+        assert!(modifiers.contains(Modifiers::CTRL));
+        assert!(modifiers.contains(Modifiers::CTRL | Modifiers::SHIFT));
+        assert!(!modifiers.contains(Modifiers::CTRL | Modifiers::ALT));
+        assert!(modifiers.contains(Modifiers::NONE));
+
+        modifiers |= Modifiers::ALT;
+        assert_eq!(modifiers.bits(), 0x07);
+        assert!(Modifiers::default().is_empty());
+
+        modifiers.remove(Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(modifiers, Modifiers::ALT);
+        assert!(modifiers.intersects(Modifiers::ALT | Modifiers::WIN));
+        assert!(!modifiers.intersects(Modifiers::LEFT_ALT | Modifiers::RIGHT_ALT));
+    }
+}
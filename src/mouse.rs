@@ -1,14 +1,30 @@
 //! Mouse event types and conversions for the Raw Input API
 
-use windows::Win32::UI::Input::{MOUSE_MOVE_ABSOLUTE, MOUSE_MOVE_RELATIVE, RAWMOUSE};
+use crate::device::RawInputDeviceHandle;
+use crate::raw::RawInputRaw;
+#[cfg(windows)]
+use crate::{RawInputError, ffi};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+#[cfg(windows)]
+use windows::Win32::UI::Input::RAWMOUSE;
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{
-    SPI_GETWHEELSCROLLLINES, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, SystemParametersInfoW,
+    SPI_GETMOUSE, SPI_GETMOUSESPEED, SPI_GETWHEELSCROLLCHARS, SPI_GETWHEELSCROLLLINES,
+    SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, SystemParametersInfoW,
 };
 
 /// The unit delta for one wheel notch (Microsoft standard)
 pub const WHEEL_DELTA: i16 = 120;
 /// The Windows default for lines to scroll per wheel notch
 pub const WHEEL_SCROLL_LINES_DEFAULT: u32 = 3;
+/// Signature Windows places in the extra information of mouse events synthesized from pen or touch input
+pub const MI_WP_SIGNATURE: u32 = 0xFF51_5700;
+/// Mask applied to the extra information before comparing against [`MI_WP_SIGNATURE`]
+pub const SIGNATURE_MASK: u32 = 0xFFFF_FF00;
+/// Bit set in the extra information when a pen/touch-synthesized event came from touch (clear for pen)
+pub const TOUCH_SIGNATURE_BIT: u32 = 0x80;
 
 /// Represents mouse movement mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,21 +41,62 @@ pub enum MouseMoveMode {
     Unknown(u16),
 }
 
-pub(crate) const MOUSE_MOVE_VIRTUAL_DESKTOP: u16 = 0x01;
+pub(crate) const MOUSE_MOVE_RELATIVE: u16 = 0x00;
+pub(crate) const MOUSE_MOVE_ABSOLUTE: u16 = 0x01;
+pub(crate) const MOUSE_MOVE_VIRTUAL_DESKTOP: u16 = 0x02;
 pub(crate) const MOUSE_MOVE_ATTRIBUTE_CHANGE: u16 = 0x04;
 
 impl From<u16> for MouseMoveMode {
     fn from(flags: u16) -> Self {
         match flags {
-            f if f == MOUSE_MOVE_RELATIVE.0 => Self::Relative,
-            f if f == MOUSE_MOVE_ABSOLUTE.0 => Self::Absolute,
-            f if f == (MOUSE_MOVE_ABSOLUTE.0 | MOUSE_MOVE_VIRTUAL_DESKTOP) => Self::VirtualDesktop,
+            f if f == MOUSE_MOVE_RELATIVE => Self::Relative,
+            f if f == MOUSE_MOVE_ABSOLUTE => Self::Absolute,
+            f if f == (MOUSE_MOVE_ABSOLUTE | MOUSE_MOVE_VIRTUAL_DESKTOP) => Self::VirtualDesktop,
             f if f == MOUSE_MOVE_ATTRIBUTE_CHANGE => Self::AttributeChange,
             other => Self::Unknown(other),
         }
     }
 }
 
+/// Largest coordinate of an absolute position; 0 to this value spans the reference area
+const ABSOLUTE_MAX: f32 = 65535.0;
+
+/// Options for [`RawMouseEvent::normalized`].
+///
+/// The screen fields describe the monitor layout in pixels, as needed to place positions
+/// reported relative to the primary monitor on the whole virtual desktop.
+/// [`new`](Self::new) fills them in from the current layout; set them yourself to
+/// normalize against a layout captured elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizeConfig {
+    /// Factor applied to relative deltas (in mouse counts) before clamping them to
+    /// [-1.0, 1.0], e.g. 0.01 maps a movement of 100 counts to 1.0
+    pub sensitivity: f32,
+    /// Bounding rectangle `(left, top, right, bottom)` of all monitors (the virtual
+    /// desktop), which absolute positions are normalized across
+    pub virtual_screen: (i32, i32, i32, i32),
+    /// Size `(width, height)` of the primary monitor, whose top-left corner is the origin
+    /// of the virtual desktop
+    pub primary_screen: (i32, i32),
+}
+
+#[cfg(windows)]
+impl NormalizeConfig {
+    /// Creates a configuration with the given relative sensitivity and the current
+    /// monitor layout.
+    ///
+    /// The layout is captured once; create a new configuration when monitors change (e.g.
+    /// on `WM_DISPLAYCHANGE`).
+    pub fn new(sensitivity: f32) -> Self {
+        let screen = ffi::virtual_screen_rect();
+        Self {
+            sensitivity,
+            virtual_screen: (screen.left, screen.top, screen.right, screen.bottom),
+            primary_screen: ffi::primary_screen_size(),
+        }
+    }
+}
+
 /// Mouse button action
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MouseButtonAction {
@@ -75,20 +132,169 @@ pub enum MouseButtonAction {
     None,
 }
 
-/// Converts button flags and data to a MouseButtonAction
+impl MouseButtonAction {
+    /// Returns the button affected by this action and whether it was pressed (`true`) or
+    /// released (`false`), or None for wheel actions and [`MouseButtonAction::None`].
+    pub fn button_transition(&self) -> Option<(MouseButton, bool)> {
+        match self {
+            Self::LeftDown => Some((MouseButton::Left, true)),
+            Self::LeftUp => Some((MouseButton::Left, false)),
+            Self::RightDown => Some((MouseButton::Right, true)),
+            Self::RightUp => Some((MouseButton::Right, false)),
+            Self::MiddleDown => Some((MouseButton::Middle, true)),
+            Self::MiddleUp => Some((MouseButton::Middle, false)),
+            Self::XButton1Down => Some((MouseButton::X1, true)),
+            Self::XButton1Up => Some((MouseButton::X1, false)),
+            Self::XButton2Down => Some((MouseButton::X2, true)),
+            Self::XButton2Up => Some((MouseButton::X2, false)),
+            _ => None,
+        }
+    }
+}
+
+/// Axis of a mouse wheel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WheelAxis {
+    /// The regular wheel; positive deltas scroll up (away from the user)
+    Vertical,
+    /// The tilt wheel or horizontal trackpad scrolling; positive deltas scroll right
+    Horizontal,
+}
+
+/// Physical mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    /// Left button
+    Left,
+    /// Right button
+    Right,
+    /// Middle button (wheel click)
+    Middle,
+    /// X button 1 (typically "back")
+    X1,
+    /// X button 2 (typically "forward")
+    X2,
+}
+
+/// Returns the rectangle `(left, top, right, bottom)` the cursor is confined to, in screen
+/// coordinates, or None if it is not confined (or the query fails).
+///
+/// Raw input itself never confines the cursor: relative motion keeps arriving when the
+/// cursor hits a screen edge, so it is a convenience for "mouselook" style capture, where
+/// the cursor is clipped (typically to the game window) with [`set_cursor_clip_rect`]
+/// while relative deltas drive the camera.
+#[cfg(windows)]
+pub fn cursor_clip_rect() -> Option<(i32, i32, i32, i32)> {
+    let rect = ffi::clip_cursor_rect().ok()?;
+    // An unconfined cursor reports the whole virtual screen
+    if rect == ffi::virtual_screen_rect() {
+        return None;
+    }
+    Some((rect.left, rect.top, rect.right, rect.bottom))
+}
+
+/// Confines the cursor to `(left, top, right, bottom)` in screen coordinates, or releases it
+/// if None.
+///
+/// Windows releases the clip when another window is activated, so re-apply it when your
+/// window regains focus.
+///
+/// # Returns
+/// * `Ok(())` - The cursor was clipped or released
+/// * `Err(RawInputError)` - If `ClipCursor` fails
+#[cfg(windows)]
+pub fn set_cursor_clip_rect(rect: Option<(i32, i32, i32, i32)>) -> Result<(), RawInputError> {
+    let rect = rect.map(
+        |(left, top, right, bottom)| windows::Win32::Foundation::RECT {
+            left,
+            top,
+            right,
+            bottom,
+        },
+    );
+    ffi::clip_cursor(rect.as_ref())
+        .map_err(|e| RawInputError::WinApiError(format!("ClipCursor failed: {e}")))
+}
+
+/// The system pointer speed and acceleration settings, as returned by
+/// [`pointer_settings`].
+///
+/// They shape the cursor movement derived from mouse input, not raw input: relative
+/// motion is reported before the speed and acceleration are applied, so games reading it
+/// see the device's own counts whatever these settings are. Displaying them lets players
+/// confirm this, and tells apps that mix raw motion with cursor positions why the two
+/// disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerSettings {
+    /// Pointer speed from 1 (slowest) to 20 (fastest), 10 by default
+    /// (`SPI_GETMOUSESPEED`)
+    pub speed: u32,
+    /// Whether "Enhance pointer precision" accelerates the cursor (`SPI_GETMOUSE`)
+    pub acceleration_enabled: bool,
+    /// First legacy acceleration threshold, in counts per move (6 by default)
+    pub threshold1: i32,
+    /// Second legacy acceleration threshold, in counts per move (10 by default)
+    pub threshold2: i32,
+}
+
+/// Returns the user's pointer speed and acceleration settings.
+///
+/// # Returns
+/// * `Ok(PointerSettings)` - The current settings
+/// * `Err(RawInputError)` - If `SystemParametersInfoW` fails
+#[cfg(windows)]
+pub fn pointer_settings() -> Result<PointerSettings, RawInputError> {
+    use std::ffi::c_void;
+    let map_err = |e: windows::core::Error| {
+        RawInputError::WinApiError(format!("SystemParametersInfoW failed: {e}"))
+    };
+    let speed = system_parameter(SPI_GETMOUSESPEED).map_err(map_err)?;
+    // Thresholds 1 and 2, then the acceleration (0 when off)
+    let mut mouse = [0i32; 3];
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETMOUSE,
+            0,
+            Some(mouse.as_mut_ptr() as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .map_err(map_err)?;
+    Ok(PointerSettings {
+        speed,
+        acceleration_enabled: mouse[2] != 0,
+        threshold1: mouse[0],
+        threshold2: mouse[1],
+    })
+}
+
+/// Returns the lines to scroll per vertical wheel notch.
+#[cfg(windows)]
 pub(crate) fn get_wheel_scroll_lines() -> Result<u32, windows::core::Error> {
-    let mut lines: u32 = 0;
+    system_parameter(SPI_GETWHEELSCROLLLINES)
+}
+
+/// Returns the characters to scroll per horizontal wheel notch.
+#[cfg(windows)]
+pub(crate) fn get_wheel_scroll_chars() -> Result<u32, windows::core::Error> {
+    system_parameter(SPI_GETWHEELSCROLLCHARS)
+}
+
+/// Reads a `u32` system parameter with `SystemParametersInfoW`.
+#[cfg(windows)]
+fn system_parameter(action: SYSTEM_PARAMETERS_INFO_ACTION) -> Result<u32, windows::core::Error> {
+    let mut value: u32 = 0;
     use std::ffi::c_void;
     let ok = unsafe {
         SystemParametersInfoW(
-            SPI_GETWHEELSCROLLLINES,
+            action,
             0,
-            Some(&mut lines as *mut u32 as *mut c_void),
+            Some(&mut value as *mut u32 as *mut c_void),
             SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
         )
     };
     if ok.is_ok() {
-        Ok(lines)
+        Ok(value)
     } else {
         Err(windows::core::Error::from_win32())
     }
@@ -107,6 +313,7 @@ pub(crate) const MOUSE_BUTTON_XBUTTON2_UP: u16 = 0x0200;
 pub(crate) const MOUSE_BUTTON_WHEEL_VERTICAL: u16 = 0x0400;
 pub(crate) const MOUSE_BUTTON_WHEEL_HORIZONTAL: u16 = 0x0800;
 
+#[cfg(windows)]
 fn button_flags_to_action(button_flags: u16, button_data: u16) -> MouseButtonAction {
     match button_flags {
         MOUSE_BUTTON_LEFT_DOWN => MouseButtonAction::LeftDown,
@@ -155,6 +362,19 @@ pub struct RawMouseEvent {
     pub move_mode: MouseMoveMode,
     /// Button action (if any)
     pub button_action: MouseButtonAction,
+    /// Signed wheel delta of a wheel event, in multiples or fractions of `WHEEL_DELTA`
+    /// (120; 0 for other events)
+    pub wheel_delta: i16,
+    /// `usButtonData` as reported, whatever the button flags
+    ///
+    /// This holds the wheel delta of wheel events, but some devices use it for other data;
+    /// see [`button_data_signed`](Self::button_data_signed) for the signed interpretation.
+    pub button_data: u16,
+    /// Axis of a wheel event (None for other events)
+    ///
+    /// Set even when the delta is less than a notch, unlike the wheel variants of
+    /// `button_action`.
+    pub wheel_axis: Option<WheelAxis>,
     /// Raw button state
     pub raw_buttons: u32,
     /// Movement in X direction
@@ -163,24 +383,442 @@ pub struct RawMouseEvent {
     pub last_y: i32,
     /// Additional driver/hardware info
     pub extra_information: u32,
+    /// Device that produced the event, as reported in `RAWINPUTHEADER.hDevice`
+    ///
+    /// Input injected with `SendInput` (including replayed events) has no device and
+    /// reports the default (0) handle.
+    pub device: RawInputDeviceHandle,
+    /// The previous movement of the same device, if enabled via
+    /// [`ListenerBuilder::track_velocity`](crate::ListenerBuilder::track_velocity)
+    ///
+    /// Only set on movement events that follow another movement in the same mode; see
+    /// [`velocity`](Self::velocity).
+    pub previous_move: Option<PreviousMove>,
+    /// The verbatim `RAWMOUSE` fields, if enabled via
+    /// [`ListenerBuilder::include_raw`](crate::ListenerBuilder::include_raw)
+    pub raw: Option<RawInputRaw>,
+    /// Monotonic time at which the listener dequeued the event
+    pub timestamp: Instant,
+    /// Wall-clock time at which the listener dequeued the event, if enabled via
+    /// [`ListenerBuilder::capture_system_time`](crate::ListenerBuilder::capture_system_time)
+    pub system_time: Option<SystemTime>,
+    /// Tick count of the WM_INPUT message the event was read from (`GetMessageTime`), in
+    /// milliseconds since system start; 0 for events that did not come from a message
+    pub message_time: u32,
+    /// Executable file name of the foreground window's process when the event was read
+    /// (e.g. "notepad.exe"), if enabled via
+    /// [`ListenerBuilder::capture_foreground_process`](crate::ListenerBuilder::capture_foreground_process)
+    pub foreground_process: Option<Arc<str>>,
 }
 
+#[cfg(windows)]
 impl From<&RAWMOUSE> for RawMouseEvent {
     fn from(mouse: &RAWMOUSE) -> Self {
-        // Extract button flags and data from the union
+        // Extract button flags and data from the union, whose `ulButtons` view holds them
+        // as the low and high word
         let (button_flags, button_data) = unsafe {
             let anonymous = &mouse.Anonymous;
             let inner = &anonymous.Anonymous;
             (inner.usButtonFlags, inner.usButtonData)
         };
 
+        let wheel_axis = if button_flags & MOUSE_BUTTON_WHEEL_VERTICAL != 0 {
+            Some(WheelAxis::Vertical)
+        } else if button_flags & MOUSE_BUTTON_WHEEL_HORIZONTAL != 0 {
+            Some(WheelAxis::Horizontal)
+        } else {
+            None
+        };
+
         Self {
             move_mode: MouseMoveMode::from(mouse.usFlags.0),
             button_action: button_flags_to_action(button_flags, button_data),
+            wheel_delta: if wheel_axis.is_some() {
+                button_data as i16
+            } else {
+                0
+            },
+            button_data,
+            wheel_axis,
             raw_buttons: mouse.ulRawButtons,
             last_x: mouse.lLastX,
             last_y: mouse.lLastY,
             extra_information: mouse.ulExtraInformation,
+            device: RawInputDeviceHandle::default(),
+            previous_move: None,
+            raw: None,
+            timestamp: Instant::now(),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        }
+    }
+}
+
+/// Coordinates and time of a mouse movement, as attached to the next movement of the same
+/// device by [`ListenerBuilder::track_velocity`](crate::ListenerBuilder::track_velocity).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreviousMove {
+    /// `last_x` of the previous movement: a delta for relative movement, a position for
+    /// absolute movement
+    pub last_x: i32,
+    /// `last_y` of the previous movement
+    pub last_y: i32,
+    /// Monotonic time of the previous movement
+    pub timestamp: Instant,
+}
+
+/// Remembers the last movement of each device to attach it to the next one.
+#[derive(Default)]
+pub(crate) struct MoveTracker {
+    last: HashMap<RawInputDeviceHandle, (MouseMoveMode, PreviousMove)>,
+}
+
+impl MoveTracker {
+    /// Attaches the device's previous movement to a movement event and remembers the event
+    /// in its place. Events without movement are left alone.
+    pub(crate) fn track(&mut self, mouse: &mut RawMouseEvent) {
+        let moved = match mouse.move_mode {
+            MouseMoveMode::Relative => mouse.last_x != 0 || mouse.last_y != 0,
+            MouseMoveMode::Absolute | MouseMoveMode::VirtualDesktop => true,
+            _ => false,
+        };
+        if !moved {
+            return;
+        }
+        let current = PreviousMove {
+            last_x: mouse.last_x,
+            last_y: mouse.last_y,
+            timestamp: mouse.timestamp,
+        };
+        mouse.previous_move = self
+            .last
+            .insert(mouse.device, (mouse.move_mode, current))
+            .filter(|(mode, _)| *mode == mouse.move_mode)
+            .map(|(_, previous)| previous);
+    }
+}
+
+impl RawMouseEvent {
+    /// Returns the velocity of a movement as `(x, y)` units per second, from the
+    /// [`previous_move`](Self::previous_move) attached with
+    /// [`ListenerBuilder::track_velocity`](crate::ListenerBuilder::track_velocity).
+    ///
+    /// The units are those of `last_x` and `last_y`: mouse counts (mickeys) for relative
+    /// movement, which depend on the mouse's resolution (DPI) and not on the pointer speed
+    /// setting, and absolute coordinates (0 to 65535 across the reference area) for
+    /// absolute movement. For relative movement, the velocity is this event's delta over
+    /// the time since the previous movement; for absolute movement, the distance between
+    /// both positions over that time.
+    ///
+    /// Returns None without a previous movement, or if both events have the same timestamp.
+    pub fn velocity(&self) -> Option<(f32, f32)> {
+        let previous = self.previous_move?;
+        let seconds = self
+            .timestamp
+            .saturating_duration_since(previous.timestamp)
+            .as_secs_f32();
+        if seconds <= 0.0 {
+            return None;
+        }
+        let (dx, dy) = match self.move_mode {
+            MouseMoveMode::Relative => (self.last_x, self.last_y),
+            _ => (
+                self.last_x.saturating_sub(previous.last_x),
+                self.last_y.saturating_sub(previous.last_y),
+            ),
+        };
+        Some((dx as f32 / seconds, dy as f32 / seconds))
+    }
+
+    /// Returns the wheel axis and the scrolled distance in notches, if this is a wheel event.
+    ///
+    /// The distance is `wheel_delta / WHEEL_DELTA`, unaffected by the user's lines-per-notch
+    /// setting. High-precision devices such as trackpads report fractions of a notch, which
+    /// this preserves for smooth scrolling.
+    pub fn wheel_notches(&self) -> Option<(WheelAxis, f32)> {
+        self.wheel_axis
+            .map(|axis| (axis, f32::from(self.wheel_delta) / f32::from(WHEEL_DELTA)))
+    }
+
+    /// Returns `button_data` reinterpreted as signed, as Windows does for wheel deltas.
+    pub fn button_data_signed(&self) -> i16 {
+        self.button_data as i16
+    }
+
+    /// Returns the movement of this event as normalized floating point coordinates.
+    ///
+    /// * Relative movement yields the deltas multiplied by
+    ///   [`sensitivity`](NormalizeConfig::sensitivity) and clamped to [-1.0, 1.0].
+    /// * Absolute positions (tablets, touch screens, remote desktop sessions) yield the
+    ///   position on the virtual desktop, in [0.0, 1.0] from its top-left to its
+    ///   bottom-right corner. [`MouseMoveMode::Absolute`] positions span the primary monitor
+    ///   and are mapped onto the desktop with the configuration's screen layout, whereas
+    ///   [`MouseMoveMode::VirtualDesktop`] positions already span the whole desktop.
+    ///
+    /// Returns None for events without movement (e.g. a button press from a relative
+    /// device), for attribute changes and unknown modes, and for absolute positions when
+    /// the configured virtual desktop is empty.
+    pub fn normalized(&self, config: &NormalizeConfig) -> Option<(f32, f32)> {
+        let absolute = |coord: i32| (coord as f32 / ABSOLUTE_MAX).clamp(0.0, 1.0);
+        match self.move_mode {
+            MouseMoveMode::Relative if self.last_x != 0 || self.last_y != 0 => {
+                let scale = |delta: i32| (delta as f32 * config.sensitivity).clamp(-1.0, 1.0);
+                Some((scale(self.last_x), scale(self.last_y)))
+            }
+            MouseMoveMode::Absolute => {
+                let (left, top, right, bottom) = config.virtual_screen;
+                let (width, height) = ((right - left) as f32, (bottom - top) as f32);
+                if width <= 0.0 || height <= 0.0 {
+                    return None;
+                }
+                let (primary_width, primary_height) = config.primary_screen;
+                let x = (absolute(self.last_x) * primary_width as f32 - left as f32) / width;
+                let y = (absolute(self.last_y) * primary_height as f32 - top as f32) / height;
+                Some((x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+            }
+            MouseMoveMode::VirtualDesktop => Some((absolute(self.last_x), absolute(self.last_y))),
+            _ => None,
+        }
+    }
+
+    /// Returns the position of an absolute event in screen pixels, at full precision.
+    ///
+    /// The result uses the coordinates of the virtual desktop (as `GetCursorPos`), with
+    /// the configuration's screen layout mapping the position as in
+    /// [`normalized`](Self::normalized), but is neither rounded to whole pixels nor reduced
+    /// to `f32`. Absolute devices report 16-bit coordinates across the reference area, so a
+    /// step of one unit is a fraction of a pixel (`width / 65535`, about 0.03 pixels on a
+    /// 1920 pixels wide monitor); drawing applications keep that sub-pixel precision by
+    /// not rounding. How much of it is real depends on the device: Windows scales
+    /// tablets and touch screens to the 16-bit range from their own, often coarser,
+    /// resolution.
+    ///
+    /// Returns None for relative movement, attribute changes and unknown modes.
+    pub fn absolute_position(&self, config: &NormalizeConfig) -> Option<(f64, f64)> {
+        let absolute = |coord: i32| (f64::from(coord) / f64::from(ABSOLUTE_MAX)).clamp(0.0, 1.0);
+        let (x, y) = (absolute(self.last_x), absolute(self.last_y));
+        match self.move_mode {
+            MouseMoveMode::Absolute => {
+                let (width, height) = config.primary_screen;
+                Some((x * f64::from(width), y * f64::from(height)))
+            }
+            MouseMoveMode::VirtualDesktop => {
+                let (left, top, right, bottom) = config.virtual_screen;
+                Some((
+                    f64::from(left) + x * f64::from(right - left),
+                    f64::from(top) + y * f64::from(bottom - top),
+                ))
+            }
+            _ => None,
         }
     }
+
+    /// Returns true if Windows synthesized this event from pen or touch input.
+    ///
+    /// Checks `extra_information & SIGNATURE_MASK == MI_WP_SIGNATURE`
+    /// (`0xFFFFFF00` and `0xFF515700` respectively).
+    pub fn is_from_pen_or_touch(&self) -> bool {
+        (self.extra_information & SIGNATURE_MASK) == MI_WP_SIGNATURE
+    }
+
+    /// Returns true if Windows synthesized this event from touch input.
+    ///
+    /// The event carries the pen/touch signature and has bit `0x80` set, i.e. the
+    /// extra information matches `MOUSEEVENTF_FROMTOUCH` (`0xFF515780`).
+    pub fn is_from_touch(&self) -> bool {
+        self.is_from_pen_or_touch() && (self.extra_information & TOUCH_SIGNATURE_BIT) != 0
+    }
+
+    /// Returns true if Windows synthesized this event from pen input.
+    ///
+    /// The event carries the pen/touch signature and has bit `0x80` clear.
+    pub fn is_from_pen(&self) -> bool {
+        self.is_from_pen_or_touch() && (self.extra_information & TOUCH_SIGNATURE_BIT) == 0
+    }
+
+    /// Returns true if this event reports relative movement straight from a mouse, as
+    /// mouselook in games needs.
+    ///
+    /// Such deltas are counts from the sensor before the pointer speed and acceleration
+    /// settings apply, and they are not bounded by the screen: when the cursor is stuck at
+    /// a screen edge or confined by
+    /// [`set_cursor_clip_rect`](crate::set_cursor_clip_rect), the mouse keeps reporting how
+    /// far it moved. Neither the listener nor [`velocity`](Self::velocity) clamps them;
+    /// only [`normalized`](Self::normalized) does, by its definition.
+    ///
+    /// Relative movement with additional flags (such as `MOUSE_MOVE_NOCOALESCE`, decoded as
+    /// [`MouseMoveMode::Unknown`]) counts as well. Absolute movement, attribute changes
+    /// and movement synthesized from pen or touch input do not: they report positions that
+    /// stop at the edge of the reference area.
+    pub fn is_relative_uncapped(&self) -> bool {
+        let relative = match self.move_mode {
+            MouseMoveMode::Relative => true,
+            MouseMoveMode::Unknown(flags) => {
+                flags & (MOUSE_MOVE_ABSOLUTE | MOUSE_MOVE_ATTRIBUTE_CHANGE) == 0
+            }
+            _ => false,
+        };
+        relative && !self.is_from_pen_or_touch()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn moved(move_mode: MouseMoveMode, last_x: i32, last_y: i32) -> RawMouseEvent {
+        RawMouseEvent {
+            move_mode,
+            button_action: MouseButtonAction::None,
+            wheel_delta: 0,
+            button_data: 0,
+            wheel_axis: None,
+            raw_buttons: 0,
+            last_x,
+            last_y,
+            extra_information: 0,
+            device: RawInputDeviceHandle::default(),
+            previous_move: None,
+            raw: None,
+            timestamp: Instant::now(),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        }
+    }
+
+    /// Test the signed interpretation of the button data
+    #[test]
+    fn test_button_data_signed() {
+        let mut event = moved(MouseMoveMode::Relative, 0, 0);
+        event.button_data = 0xFF88;
+        assert_eq!(event.button_data_signed(), -120);
+        event.button_data = 120;
+        assert_eq!(event.button_data_signed(), 120);
+    }
+
+    /// Test normalization of relative deltas and of both kinds of absolute positions
+    #[test]
+    fn test_normalized() {
+        // A 1920x1080 primary monitor with a second one of the same size to its left
+        let config = NormalizeConfig {
+            sensitivity: 0.01,
+            virtual_screen: (-1920, 0, 1920, 1080),
+            primary_screen: (1920, 1080),
+        };
+
+        let relative = |x, y| moved(MouseMoveMode::Relative, x, y).normalized(&config);
+        assert_eq!(relative(50, -250), Some((0.5, -1.0)));
+        assert_eq!(relative(0, 0), None);
+
+        let absolute = moved(MouseMoveMode::Absolute, 0, 65535).normalized(&config);
+        assert_eq!(absolute, Some((0.5, 1.0)));
+        let desktop = moved(MouseMoveMode::VirtualDesktop, 65535, 0).normalized(&config);
+        assert_eq!(desktop, Some((1.0, 0.0)));
+
+        let empty = NormalizeConfig {
+            virtual_screen: (0, 0, 0, 0),
+            ..config
+        };
+        assert_eq!(
+            moved(MouseMoveMode::Absolute, 100, 100).normalized(&empty),
+            None
+        );
+        assert_eq!(
+            moved(MouseMoveMode::AttributeChange, 1, 1).normalized(&config),
+            None
+        );
+    }
+
+    /// Test that absolute positions map to fractional screen pixels
+    #[test]
+    fn test_absolute_position() {
+        let config = NormalizeConfig {
+            sensitivity: 1.0,
+            virtual_screen: (-1920, 0, 1920, 1080),
+            primary_screen: (1920, 1080),
+        };
+
+        let (x, y) = moved(MouseMoveMode::Absolute, 32768, 65535)
+            .absolute_position(&config)
+            .unwrap();
+        assert!((x - 960.0146).abs() < 1e-4, "x = {x}");
+        assert_eq!(y, 1080.0);
+
+        let desktop = moved(MouseMoveMode::VirtualDesktop, 0, 0).absolute_position(&config);
+        assert_eq!(desktop, Some((-1920.0, 0.0)));
+        assert_eq!(
+            moved(MouseMoveMode::Relative, 5, 5).absolute_position(&config),
+            None
+        );
+    }
+
+    /// Test which movement counts as uncapped relative input, and that it is not clamped
+    #[test]
+    fn test_is_relative_uncapped() {
+        // A flick far beyond any screen, e.g. while the cursor rests at an edge
+        let mut flick = moved(MouseMoveMode::Relative, 40_000, -40_000);
+        assert!(flick.is_relative_uncapped());
+        flick.previous_move = Some(PreviousMove {
+            last_x: 0,
+            last_y: 0,
+            timestamp: flick.timestamp - Duration::from_secs(1),
+        });
+        assert_eq!(flick.velocity(), Some((40_000.0, -40_000.0)));
+
+        // MOUSE_MOVE_NOCOALESCE
+        assert!(moved(MouseMoveMode::Unknown(0x08), 1, 1).is_relative_uncapped());
+        assert!(!moved(MouseMoveMode::Unknown(0x09), 1, 1).is_relative_uncapped());
+        assert!(!moved(MouseMoveMode::Absolute, 1, 1).is_relative_uncapped());
+        assert!(!moved(MouseMoveMode::AttributeChange, 0, 0).is_relative_uncapped());
+
+        let mut touch = moved(MouseMoveMode::Relative, 1, 1);
+        touch.extra_information = MI_WP_SIGNATURE | TOUCH_SIGNATURE_BIT;
+        assert!(!touch.is_relative_uncapped());
+    }
+
+    /// Test that the movement mode flags are decoded per `RAWMOUSE.usFlags`
+    #[test]
+    fn test_move_mode_from_flags() {
+        assert_eq!(MouseMoveMode::from(0x00), MouseMoveMode::Relative);
+        assert_eq!(MouseMoveMode::from(0x01), MouseMoveMode::Absolute);
+        assert_eq!(MouseMoveMode::from(0x03), MouseMoveMode::VirtualDesktop);
+        assert_eq!(MouseMoveMode::from(0x04), MouseMoveMode::AttributeChange);
+    }
+
+    /// Test that movements are chained per mode and turned into velocities
+    #[test]
+    fn test_move_tracker_velocity() {
+        let mut tracker = MoveTracker::default();
+        let mut first = moved(MouseMoveMode::Relative, 4, -2);
+        tracker.track(&mut first);
+        assert_eq!(first.previous_move, None);
+        assert_eq!(first.velocity(), None);
+
+        let mut second = moved(MouseMoveMode::Relative, 10, -5);
+        second.timestamp = first.timestamp + std::time::Duration::from_millis(250);
+        tracker.track(&mut second);
+        assert_eq!(
+            second.previous_move.map(|previous| previous.last_x),
+            Some(4)
+        );
+        assert_eq!(second.velocity(), Some((40.0, -20.0)));
+
+        // A button press without movement is not a movement to chain
+        let mut click = moved(MouseMoveMode::Relative, 0, 0);
+        tracker.track(&mut click);
+        assert_eq!(click.previous_move, None);
+
+        // Absolute positions are differenced; a change of mode restarts the chain
+        let mut absolute = moved(MouseMoveMode::Absolute, 1000, 1000);
+        absolute.timestamp = second.timestamp + std::time::Duration::from_millis(10);
+        tracker.track(&mut absolute);
+        assert_eq!(absolute.previous_move, None);
+        let mut next = moved(MouseMoveMode::Absolute, 1500, 500);
+        next.timestamp = absolute.timestamp + std::time::Duration::from_millis(500);
+        tracker.track(&mut next);
+        assert_eq!(next.velocity(), Some((1000.0, -1000.0)));
+    }
 }
@@ -0,0 +1,264 @@
+//! Pen (stylus) input parsed from digitizer HID reports
+
+use crate::hid::sign_extend;
+use crate::{RawInputDeviceHandle, UsageRegistration};
+
+/// HID usage page of digitizers
+const USAGE_PAGE_DIGITIZER: u16 = 0x0D;
+/// Digitizer usage of a pen top level collection
+const USAGE_PEN: u16 = 0x02;
+/// HID usage page of the X and Y axes
+const USAGE_PAGE_GENERIC: u16 = 0x01;
+const USAGE_X: u16 = 0x30;
+const USAGE_Y: u16 = 0x31;
+const USAGE_TIP_PRESSURE: u16 = 0x30;
+const USAGE_IN_RANGE: u16 = 0x32;
+const USAGE_INVERT: u16 = 0x3C;
+const USAGE_X_TILT: u16 = 0x3D;
+const USAGE_Y_TILT: u16 = 0x3E;
+const USAGE_TIP_SWITCH: u16 = 0x42;
+const USAGE_BARREL_SWITCH: u16 = 0x44;
+const USAGE_ERASER: u16 = 0x45;
+
+impl UsageRegistration {
+    /// Registers pen digitizers (usage page 0x0D, usage 0x02), such as Wacom tablets and
+    /// the Surface Pen, whose reports can be parsed with a [`PenParser`].
+    pub fn pen() -> Self {
+        Self::usage(USAGE_PAGE_DIGITIZER, USAGE_PEN)
+    }
+}
+
+/// State of a pen, parsed from a single digitizer input report.
+///
+/// Positions and pressure are normalized by the ranges the device declares, so they do
+/// not depend on the digitizer's resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PenEvent {
+    /// Horizontal position across the digitizer surface, from 0.0 (left) to 1.0 (right)
+    pub x: f32,
+    /// Vertical position across the digitizer surface, from 0.0 (top) to 1.0 (bottom)
+    pub y: f32,
+    /// Tip pressure from 0.0 to 1.0 (0.0 if the pen reports none)
+    pub pressure: f32,
+    /// Tilt towards the right (positive) as reported, usually in degrees from -90 to 90
+    /// (0 if the pen reports none)
+    pub tilt_x: i32,
+    /// Tilt towards the user (positive) as reported, usually in degrees from -90 to 90
+    /// (0 if the pen reports none)
+    pub tilt_y: i32,
+    /// Whether the tip (or the eraser) touches the surface
+    pub tip: bool,
+    /// Whether the barrel (side) button is pressed
+    pub barrel_button: bool,
+    /// Whether the pen is turned around to use its eraser end
+    pub eraser: bool,
+    /// Whether the pen is close enough to the surface to be tracked (hovering or touching)
+    pub in_range: bool,
+    /// Device that produced the event
+    pub device: RawInputDeviceHandle,
+}
+
+/// Value ranges a pen digitizer declares for its reports.
+#[derive(Debug, Clone, Copy, Default)]
+struct PenRanges {
+    x_max: i32,
+    y_max: i32,
+    pressure_max: i32,
+    /// Bit sizes of the X and Y tilt fields if they are signed, 0 otherwise
+    tilt_bits: (u16, u16),
+}
+
+/// Scales a logical value to 0.0..=1.0 by its logical maximum.
+fn normalize(value: u32, max: i32) -> f32 {
+    if max <= 0 {
+        return 0.0;
+    }
+    (value as f32 / max as f32).clamp(0.0, 1.0)
+}
+
+impl PenEvent {
+    /// Builds an event from the raw values read from a report and its set button usages.
+    fn from_report(
+        device: RawInputDeviceHandle,
+        ranges: PenRanges,
+        (x, y): (u32, u32),
+        pressure: Option<u32>,
+        tilt: (Option<u32>, Option<u32>),
+        switches: &[u16],
+    ) -> Self {
+        let signed = |value: Option<u32>, bits| value.map_or(0, |value| sign_extend(value, bits));
+        Self {
+            x: normalize(x, ranges.x_max),
+            y: normalize(y, ranges.y_max),
+            pressure: pressure.map_or(0.0, |pressure| normalize(pressure, ranges.pressure_max)),
+            tilt_x: signed(tilt.0, ranges.tilt_bits.0),
+            tilt_y: signed(tilt.1, ranges.tilt_bits.1),
+            tip: switches.contains(&USAGE_TIP_SWITCH),
+            barrel_button: switches.contains(&USAGE_BARREL_SWITCH),
+            eraser: switches.contains(&USAGE_ERASER) || switches.contains(&USAGE_INVERT),
+            in_range: switches.contains(&USAGE_IN_RANGE),
+            device,
+        }
+    }
+}
+
+#[cfg(windows)]
+pub use parser::PenParser;
+
+#[cfg(windows)]
+mod parser {
+    use super::*;
+    use crate::{RawHidEventRef, RawInputError, ffi};
+    use std::collections::HashMap;
+
+    /// Device information needed to parse a pen's reports
+    struct PenDevice {
+        preparsed: Vec<u64>,
+        ranges: PenRanges,
+    }
+
+    /// Parses pen digitizer HID events into [`PenEvent`]s.
+    ///
+    /// Digitizers lay out their reports differently, so the parser reads each device's
+    /// report descriptor (its HID preparsed data) the first time it sees the device and
+    /// keeps it for later events. Register for pen input with [`UsageRegistration::pen`]
+    /// and feed the HID events to the parser, e.g. from a
+    /// [`hid_callback`](crate::ListenerBuilder::hid_callback).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mkb_raw_input::{ListenerBuilder, PenParser, UsageRegistration};
+    ///
+    /// let mut parser = PenParser::new();
+    /// let _listener = ListenerBuilder::new()
+    ///     .register_usage(UsageRegistration::pen())
+    ///     .hid_callback(move |hid| {
+    ///         for pen in parser.parse(&hid).unwrap_or_default() {
+    ///             println!("{:.3},{:.3} pressure {:.2}", pen.x, pen.y, pen.pressure);
+    ///         }
+    ///     })
+    ///     .start(|_event| {})
+    ///     .expect("Failed to start listener");
+    /// ```
+    #[derive(Default)]
+    pub struct PenParser {
+        devices: HashMap<RawInputDeviceHandle, PenDevice>,
+        /// Copy of the report being parsed, as `HidP_GetUsages` takes it mutably
+        report: Vec<u8>,
+    }
+
+    impl PenParser {
+        /// Creates a parser that knows no devices yet.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Parses every report of a HID event.
+        ///
+        /// Reports that carry no pen position (other report IDs, devices that are not
+        /// pens) are skipped, so the result may be empty.
+        ///
+        /// # Returns
+        /// * `Ok(Vec<PenEvent>)` - One event per pen report, in order
+        /// * `Err(RawInputError)` - If the device's preparsed data cannot be read
+        pub fn parse(&mut self, hid: &RawHidEventRef<'_>) -> Result<Vec<PenEvent>, RawInputError> {
+            let device = match self.devices.entry(hid.device) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(PenDevice::query(hid.device)?)
+                }
+            };
+
+            let mut events = Vec::new();
+            for report in hid.reports() {
+                self.report.clear();
+                self.report.extend_from_slice(report);
+                if let Some(event) = device.parse(hid.device, &mut self.report) {
+                    events.push(event);
+                }
+            }
+            Ok(events)
+        }
+    }
+
+    impl PenDevice {
+        fn query(device: RawInputDeviceHandle) -> Result<Self, RawInputError> {
+            let preparsed = ffi::preparsed_data(device.as_handle()).map_err(|e| {
+                RawInputError::WinApiError(format!("Failed to read HID preparsed data: {e}"))
+            })?;
+            let range = |page, usage| ffi::hid_value_range(&preparsed, page, usage);
+            let max = |page, usage| range(page, usage).map_or(0, |(_, max, _)| max);
+            let signed_bits = |usage| match range(USAGE_PAGE_DIGITIZER, usage) {
+                Some((min, _, bits)) if min < 0 => bits,
+                _ => 0,
+            };
+            let ranges = PenRanges {
+                x_max: max(USAGE_PAGE_GENERIC, USAGE_X),
+                y_max: max(USAGE_PAGE_GENERIC, USAGE_Y),
+                pressure_max: max(USAGE_PAGE_DIGITIZER, USAGE_TIP_PRESSURE),
+                tilt_bits: (signed_bits(USAGE_X_TILT), signed_bits(USAGE_Y_TILT)),
+            };
+            Ok(Self { preparsed, ranges })
+        }
+
+        fn parse(&self, device: RawInputDeviceHandle, report: &mut [u8]) -> Option<PenEvent> {
+            let value = |page, usage, report: &[u8]| {
+                ffi::hid_usage_value(&self.preparsed, page, usage, report)
+            };
+            let x = value(USAGE_PAGE_GENERIC, USAGE_X, report)?;
+            let y = value(USAGE_PAGE_GENERIC, USAGE_Y, report)?;
+            let pressure = value(USAGE_PAGE_DIGITIZER, USAGE_TIP_PRESSURE, report);
+            let tilt = (
+                value(USAGE_PAGE_DIGITIZER, USAGE_X_TILT, report),
+                value(USAGE_PAGE_DIGITIZER, USAGE_Y_TILT, report),
+            );
+            let switches = ffi::hid_active_usages(&self.preparsed, USAGE_PAGE_DIGITIZER, report);
+            Some(PenEvent::from_report(
+                device,
+                self.ranges,
+                (x, y),
+                pressure,
+                tilt,
+                &switches,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that report values are normalized and switches decoded
+    #[test]
+    fn test_from_report() {
+        let ranges = PenRanges {
+            x_max: 20000,
+            y_max: 10000,
+            pressure_max: 4095,
+            tilt_bits: (8, 8),
+        };
+        let pen = PenEvent::from_report(
+            RawInputDeviceHandle::from_raw(7),
+            ranges,
+            (5000, 10000),
+            Some(4095),
+            (Some(0xE2), None), // -30 in an 8-bit field
+            &[USAGE_IN_RANGE, USAGE_TIP_SWITCH, USAGE_BARREL_SWITCH],
+        );
+        assert_eq!((pen.x, pen.y, pen.pressure), (0.25, 1.0, 1.0));
+        assert_eq!((pen.tilt_x, pen.tilt_y), (-30, 0));
+        assert!(pen.in_range && pen.tip && pen.barrel_button && !pen.eraser);
+
+        let hovering_eraser = PenEvent::from_report(
+            RawInputDeviceHandle::default(),
+            PenRanges::default(),
+            (10, 10),
+            None,
+            (None, None),
+            &[USAGE_IN_RANGE, USAGE_INVERT],
+        );
+        assert_eq!((hovering_eraser.x, hovering_eraser.pressure), (0.0, 0.0));
+        assert!(hovering_eraser.eraser && !hovering_eraser.tip);
+    }
+}
@@ -0,0 +1,81 @@
+//! Verbatim Win32 field values attached to events for debugging
+
+#[cfg(windows)]
+use windows::Win32::UI::Input::{RAWKEYBOARD, RAWMOUSE};
+
+/// The fields of the Win32 structure an event was parsed from, exactly as Windows reported
+/// them.
+///
+/// Attached to keyboard and mouse events when enabled with
+/// [`ListenerBuilder::include_raw`](crate::ListenerBuilder::include_raw). Comparing these
+/// values against the parsed fields shows whether an unexpected event comes from Windows
+/// or from the crate's interpretation, which makes for precise bug reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawInputRaw {
+    /// Fields of `RAWKEYBOARD`
+    Keyboard {
+        /// `MakeCode`: the scan code
+        make_code: u16,
+        /// `Flags`: `RI_KEY_BREAK`, `RI_KEY_E0` and `RI_KEY_E1`
+        flags: u16,
+        /// `Reserved`: unused by standard keyboards, but some specialized hardware passes
+        /// extra data here
+        reserved: u16,
+        /// `VKey`: the virtual key code
+        vkey: u16,
+        /// `Message`: the corresponding window message
+        message: u32,
+        /// `ExtraInformation`
+        extra_information: u32,
+    },
+    /// Fields of `RAWMOUSE`
+    Mouse {
+        /// `usFlags`: the `MOUSE_MOVE_*` mode flags
+        flags: u16,
+        /// `usButtonFlags`: the `RI_MOUSE_*` button and wheel flags
+        button_flags: u16,
+        /// `usButtonData`: the wheel delta, if a wheel flag is set
+        button_data: u16,
+        /// `ulRawButtons`
+        raw_buttons: u32,
+        /// `lLastX`
+        last_x: i32,
+        /// `lLastY`
+        last_y: i32,
+        /// `ulExtraInformation`
+        extra_information: u32,
+    },
+}
+
+#[cfg(windows)]
+impl From<&RAWKEYBOARD> for RawInputRaw {
+    fn from(kbd: &RAWKEYBOARD) -> Self {
+        Self::Keyboard {
+            make_code: kbd.MakeCode,
+            flags: kbd.Flags,
+            reserved: kbd.Reserved,
+            vkey: kbd.VKey,
+            message: kbd.Message,
+            extra_information: kbd.ExtraInformation,
+        }
+    }
+}
+
+#[cfg(windows)]
+impl From<&RAWMOUSE> for RawInputRaw {
+    fn from(mouse: &RAWMOUSE) -> Self {
+        let (button_flags, button_data) = unsafe {
+            let inner = &mouse.Anonymous.Anonymous;
+            (inner.usButtonFlags, inner.usButtonData)
+        };
+        Self::Mouse {
+            flags: mouse.usFlags.0,
+            button_flags,
+            button_data,
+            raw_buttons: mouse.ulRawButtons,
+            last_x: mouse.lLastX,
+            last_y: mouse.lLastY,
+            extra_information: mouse.ulExtraInformation,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Reading of WM_INPUT packets into events
+
+use crate::dispatch::HidCallback;
+use crate::{
+    RawInputError, RawInputEvent, ffi, parse_hid_buffer, parse_rawinput_buffer,
+    read_raw_input_event_from_lparam,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime};
+use windows::Win32::Foundation::LPARAM;
+
+/// Process name of the foreground window, queried again only when the foreground window
+/// changes.
+#[derive(Default)]
+struct ForegroundProcess {
+    hwnd: isize,
+    name: Option<Arc<str>>,
+}
+
+impl ForegroundProcess {
+    /// Returns the executable name of the foreground window's process.
+    ///
+    /// `GetForegroundWindow` is a cheap call, unlike opening the process to query its
+    /// image name, which is only done when the window handle differs from the last one.
+    fn current(&mut self) -> Option<Arc<str>> {
+        let hwnd = ffi::foreground_window();
+        if hwnd != self.hwnd {
+            self.hwnd = hwnd;
+            self.name = (hwnd != 0)
+                .then(|| ffi::window_process_name(hwnd))
+                .flatten()
+                .map(Arc::from);
+        }
+        self.name.clone()
+    }
+}
+
+/// Reads and parses the raw input of WM_INPUT messages.
+///
+/// Packets are read into a buffer that is reused across messages. When a HID callback is
+/// set, HID packets are passed to it borrowed from that buffer instead of being copied
+/// into a [`RawInputEvent::Hid`] (or dropped while the listener is paused).
+pub(crate) struct PacketReader {
+    buffer: Vec<u8>,
+    capture_system_time: bool,
+    include_raw: bool,
+    on_hid: Option<HidCallback>,
+    paused: Arc<AtomicBool>,
+    foreground: Option<ForegroundProcess>,
+}
+
+impl PacketReader {
+    pub(crate) fn new(
+        capture_system_time: bool,
+        include_raw: bool,
+        on_hid: Option<HidCallback>,
+        paused: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            buffer: Vec::new(),
+            capture_system_time,
+            include_raw,
+            on_hid,
+            paused,
+            foreground: None,
+        }
+    }
+
+    /// Attaches the foreground window's process name to every event if `enabled`.
+    pub(crate) fn with_foreground_process(mut self, enabled: bool) -> Self {
+        self.foreground = enabled.then(ForegroundProcess::default);
+        self
+    }
+
+    /// Reads the packet of a WM_INPUT message.
+    ///
+    /// # Returns
+    /// The event or read error, or None if the packet went to the HID callback or is of an
+    /// unsupported type.
+    pub(crate) fn read(&mut self, lparam: LPARAM) -> Option<Result<RawInputEvent, RawInputError>> {
+        // Capture the dequeue time before doing any work on the event
+        let timestamp = Instant::now();
+        let system_time = self.capture_system_time.then(SystemTime::now);
+        // The message being dispatched is the last one the thread retrieved
+        let message_time = ffi::message_time();
+        let foreground_process = self
+            .foreground
+            .as_mut()
+            .and_then(ForegroundProcess::current);
+
+        if let Err(e) = read_raw_input_event_from_lparam(lparam, &mut self.buffer) {
+            return Some(Err(e));
+        }
+
+        if let Some(on_hid) = self.on_hid.as_mut()
+            && let Some(mut hid) = parse_hid_buffer(&self.buffer)
+        {
+            if !self.paused.load(Ordering::SeqCst) {
+                hid.timestamp = timestamp;
+                hid.system_time = system_time;
+                hid.message_time = message_time;
+                hid.foreground_process = foreground_process.as_deref();
+                on_hid(hid);
+            }
+            return None;
+        }
+
+        parse_rawinput_buffer(&self.buffer, self.include_raw).map(|mut event| {
+            event.set_times(timestamp, system_time);
+            event.set_message_time(message_time);
+            event.set_foreground_process(foreground_process);
+            Ok(event)
+        })
+    }
+}
@@ -0,0 +1,245 @@
+//! Turnkey recording of input into an in-memory timeline
+
+use crate::{ListenerBuilder, ListenerHandle, RawInputError, RawInputEvent, write_events_csv};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A span of time in which an [`InputRecorder`] was paused and recorded nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingGap {
+    /// When recording was paused
+    pub paused_at: Instant,
+    /// When recording was resumed
+    pub resumed_at: Instant,
+}
+
+impl RecordingGap {
+    /// Returns how long recording was paused.
+    pub fn duration(&self) -> Duration {
+        self.resumed_at.saturating_duration_since(self.paused_at)
+    }
+}
+
+/// Events and pauses recorded so far
+#[derive(Debug, Default)]
+struct Timeline {
+    events: Vec<RawInputEvent>,
+    gaps: Vec<RecordingGap>,
+    /// Start of the current pause, if paused
+    paused_at: Option<Instant>,
+}
+
+impl Timeline {
+    fn record(&mut self, event: RawInputEvent) {
+        if self.paused_at.is_none() {
+            self.events.push(event);
+        }
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.paused_at.get_or_insert(now);
+    }
+
+    fn resume(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.gaps.push(RecordingGap {
+                paused_at,
+                resumed_at: now,
+            });
+        }
+    }
+
+    fn clear(&mut self, now: Instant) {
+        self.events.clear();
+        self.gaps.clear();
+        // A pause in progress now starts with the empty timeline
+        if let Some(paused_at) = &mut self.paused_at {
+            *paused_at = now;
+        }
+    }
+}
+
+/// Records every event of a listener into a growable in-memory timeline.
+///
+/// The recorder runs its own listener, so it is subject to the one-listener-per-process
+/// limit. Recording can be paused and resumed without stopping the listener: while paused,
+/// events are dropped instead of recorded, and the pause is kept as a [`RecordingGap`].
+/// This is distinct from [`ListenerHandle::pause`], which stops delivery altogether and
+/// is available through [`listener`](Self::listener).
+///
+/// Events keep the timestamps the listener gave them when they were dequeued (and their
+/// system time, if [`capture_system_time`](ListenerBuilder::capture_system_time) is
+/// enabled); pausing does not shift them. A pause therefore shows as a jump in the
+/// timestamps between the last event before it and the first event after it, e.g. in the
+/// `time_ms` column of [`export_csv`](Self::export_csv). Subtract the
+/// [`gaps`](Self::gaps) to get the timeline without pauses.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::InputRecorder;
+///
+/// let recorder = InputRecorder::start().expect("Failed to start recorder");
+/// std::thread::sleep(std::time::Duration::from_secs(5));
+/// recorder.pause();
+/// std::thread::sleep(std::time::Duration::from_secs(5)); // Not recorded
+/// recorder.resume();
+/// std::thread::sleep(std::time::Duration::from_secs(5));
+///
+/// let events = recorder.stop();
+/// println!("Recorded {} events", events.len());
+/// ```
+pub struct InputRecorder {
+    listener: ListenerHandle,
+    timeline: Arc<Mutex<Timeline>>,
+}
+
+impl InputRecorder {
+    /// Starts a listener with the default configuration and records its events.
+    ///
+    /// Use [`ListenerBuilder::start_recorder`] to configure the listener.
+    ///
+    /// # Returns
+    /// * `Ok(InputRecorder)` - The recording recorder (stop by dropping or with
+    ///   [`stop`](Self::stop))
+    /// * `Err(RawInputError)` - If the listener cannot be started
+    pub fn start() -> Result<Self, RawInputError> {
+        ListenerBuilder::new().start_recorder()
+    }
+
+    pub(crate) fn from_builder(builder: ListenerBuilder) -> Result<Self, RawInputError> {
+        let timeline = Arc::new(Mutex::new(Timeline::default()));
+        let recorded = Arc::clone(&timeline);
+        let listener = builder.start(move |event| {
+            if let Ok(mut timeline) = recorded.lock() {
+                timeline.record(event);
+            }
+        })?;
+        Ok(Self { listener, timeline })
+    }
+
+    fn timeline(&self) -> MutexGuard<'_, Timeline> {
+        // The timeline stays consistent even if a panic poisoned the lock
+        self.timeline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pauses recording; events arriving until [`resume`](Self::resume) are dropped.
+    ///
+    /// Pausing an already paused recorder has no effect.
+    pub fn pause(&self) {
+        self.timeline().pause(Instant::now());
+    }
+
+    /// Resumes recording after [`pause`](Self::pause), adding the pause to the
+    /// [`gaps`](Self::gaps).
+    pub fn resume(&self) {
+        self.timeline().resume(Instant::now());
+    }
+
+    /// Returns true unless recording is paused.
+    pub fn is_recording(&self) -> bool {
+        self.timeline().paused_at.is_none()
+    }
+
+    /// Discards the recorded events and gaps, keeping the recorder running (or paused).
+    pub fn clear(&self) {
+        self.timeline().clear(Instant::now());
+    }
+
+    /// Returns the number of recorded events.
+    pub fn len(&self) -> usize {
+        self.timeline().events.len()
+    }
+
+    /// Returns true if no event has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.timeline().events.is_empty()
+    }
+
+    /// Returns a copy of the recorded events, in the order they arrived.
+    pub fn events(&self) -> Vec<RawInputEvent> {
+        self.timeline().events.clone()
+    }
+
+    /// Returns the finished pauses, oldest first; a pause in progress is not included.
+    pub fn gaps(&self) -> Vec<RecordingGap> {
+        self.timeline().gaps.clone()
+    }
+
+    /// Writes the recorded events as CSV, in the format of [`write_events_csv`].
+    ///
+    /// # Returns
+    /// * `Ok(())` - Every event was written
+    /// * `Err(io::Error)` - If writing to `writer` fails
+    pub fn export_csv<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_events_csv(&self.timeline().events, writer)
+    }
+
+    /// Returns the listener the recorder records from, e.g. to query the held keys.
+    pub fn listener(&self) -> &ListenerHandle {
+        &self.listener
+    }
+
+    /// Stops the listener and returns the recorded events.
+    pub fn stop(self) -> Vec<RawInputEvent> {
+        // Dropping the handle joins the listener thread, so no event is recorded later
+        drop(self.listener);
+        let mut timeline = self.timeline.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut timeline.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RawHidEvent;
+
+    fn hid(timestamp: Instant) -> RawInputEvent {
+        RawInputEvent::Hid(RawHidEvent {
+            report_size: 1,
+            report_count: 1,
+            data: vec![0],
+            device: Default::default(),
+            timestamp,
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        })
+    }
+
+    /// Test that events are dropped while paused and the pause is kept as a gap
+    #[test]
+    fn test_pause_leaves_a_gap() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut timeline = Timeline::default();
+        timeline.record(hid(at(0)));
+        timeline.pause(at(10));
+        timeline.pause(at(15)); // Already paused
+        timeline.record(hid(at(20)));
+        timeline.resume(at(30));
+        timeline.resume(at(35)); // Not paused
+        timeline.record(hid(at(40)));
+
+        let times: Vec<_> = timeline.events.iter().map(|e| e.timestamp()).collect();
+        assert_eq!(times, vec![at(0), at(40)]);
+        assert_eq!(timeline.gaps.len(), 1);
+        assert_eq!(timeline.gaps[0].paused_at, at(10));
+        assert_eq!(timeline.gaps[0].duration(), Duration::from_millis(20));
+    }
+
+    /// Test that clearing while paused restarts the pause with the empty timeline
+    #[test]
+    fn test_clear_while_paused() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut timeline = Timeline::default();
+        timeline.record(hid(at(0)));
+        timeline.pause(at(10));
+        timeline.clear(at(20));
+        assert!(timeline.events.is_empty());
+
+        timeline.resume(at(30));
+        assert_eq!(timeline.gaps[0].paused_at, at(20));
+    }
+}
@@ -0,0 +1,408 @@
+//! Additional usage page / usage registrations for raw input
+
+use crate::RawInputError;
+use crate::builder::ListenerConfig;
+
+/// A HID usage page and usage to register for raw input, in addition to keyboard and mouse.
+///
+/// Events from devices matched by these registrations that are not keyboards or mice are
+/// delivered as [`RawInputEvent::Hid`](crate::RawInputEvent::Hid).
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{ListenerBuilder, UsageRegistration};
+///
+/// // Every device on the Generic Desktop page (0x01), e.g. composite devices whose
+/// // top level collections don't match the mouse/keyboard usages exactly
+/// let _listener = ListenerBuilder::new()
+///     .register_usage(UsageRegistration::page(0x01))
+///     .start(|event| println!("{event:?}"))
+///     .expect("Failed to start listener");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UsageRegistration {
+    /// HID usage page (e.g. 0x01 for Generic Desktop)
+    pub usage_page: u16,
+    /// HID usage within the page; must be 0 when `page_only` is set
+    pub usage: u16,
+    /// Register every top level collection on the usage page (`RIDEV_PAGEONLY`)
+    pub page_only: bool,
+}
+
+impl UsageRegistration {
+    /// Registers a single usage on a usage page.
+    pub fn usage(usage_page: u16, usage: u16) -> Self {
+        Self {
+            usage_page,
+            usage,
+            page_only: false,
+        }
+    }
+
+    /// Registers every usage on a usage page (`RIDEV_PAGEONLY`).
+    pub fn page(usage_page: u16) -> Self {
+        Self {
+            usage_page,
+            usage: 0,
+            page_only: true,
+        }
+    }
+
+    /// The keyboard usage the listener registers unless keyboard capture is disabled.
+    pub(crate) fn keyboard() -> Self {
+        Self::usage(0x01, 0x06)
+    }
+
+    /// The mouse usage the listener registers unless mouse capture is disabled.
+    pub(crate) fn mouse() -> Self {
+        Self::usage(0x01, 0x02)
+    }
+
+    /// Returns true if the registration delivers keystrokes: the keyboard and keypad usages,
+    /// or the whole Generic Desktop page.
+    pub(crate) fn covers_keyboard(&self) -> bool {
+        self.usage_page == 0x01 && (self.page_only || matches!(self.usage, 0x06 | 0x07))
+    }
+
+    /// Checks the Win32 constraints on the registration.
+    pub(crate) fn validate(&self) -> Result<(), RawInputError> {
+        if self.page_only && self.usage != 0 {
+            return Err(RawInputError::Other(format!(
+                "Usage must be 0 for a page-only registration (page {:#06x}, usage {:#06x})",
+                self.usage_page, self.usage
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// `RIDEV_INPUTSINK`: input is received in the background
+const RIDEV_INPUTSINK: u32 = 0x100;
+/// `RIDEV_EXINPUTSINK`: input is received in the background unless the foreground
+/// application registered for it
+const RIDEV_EXINPUTSINK: u32 = 0x1000;
+/// Bits shared by `RIDEV_PAGEONLY` (0x20) and `RIDEV_NOLEGACY` (0x30)
+const RIDEV_PAGE_MASK: u32 = 0x30;
+const RIDEV_PAGEONLY: u32 = 0x20;
+const RIDEV_NOLEGACY: u32 = 0x30;
+
+/// A raw input registration of the current process, as returned by [`registered_devices`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegisteredDevice {
+    /// HID usage page (e.g. 0x01 for Generic Desktop)
+    pub usage_page: u16,
+    /// HID usage within the page (0 for page-only registrations)
+    pub usage: u16,
+    /// The `RIDEV_*` registration flags
+    pub flags: u32,
+    /// Window receiving the input (`HWND` value), or 0 if input follows the keyboard focus
+    pub target: isize,
+}
+
+impl RegisteredDevice {
+    /// Returns true if input is received while the target window is in the background
+    /// (`RIDEV_INPUTSINK` or `RIDEV_EXINPUTSINK`).
+    pub fn is_background(&self) -> bool {
+        self.flags & (RIDEV_INPUTSINK | RIDEV_EXINPUTSINK) != 0
+    }
+
+    /// Returns true if every usage on the page is registered (`RIDEV_PAGEONLY`).
+    pub fn is_page_only(&self) -> bool {
+        self.flags & RIDEV_PAGE_MASK == RIDEV_PAGEONLY
+    }
+
+    /// Returns true if legacy messages (WM_KEYDOWN, WM_MOUSEMOVE, ...) are suppressed
+    /// for the process (`RIDEV_NOLEGACY`).
+    pub fn is_no_legacy(&self) -> bool {
+        self.flags & RIDEV_PAGE_MASK == RIDEV_NOLEGACY
+    }
+}
+
+/// Returns the raw input registrations currently in effect for this process.
+///
+/// Raw input registrations are per process and per usage: registering a usage replaces
+/// any earlier registration of it, whichever code in the process made it. Comparing this
+/// snapshot with what the listener registered shows whether another component (a game
+/// engine, a UI toolkit) took over a usage, which is a common reason for events to stop.
+///
+/// # Returns
+/// * `Ok(Vec<RegisteredDevice>)` - One entry per registered usage
+/// * `Err(RawInputError)` - If `GetRegisteredRawInputDevices` fails
+#[cfg(windows)]
+pub fn registered_devices() -> Result<Vec<RegisteredDevice>, RawInputError> {
+    let devices = crate::ffi::registered_devices().map_err(|e| {
+        RawInputError::WinApiError(format!("GetRegisteredRawInputDevices failed: {e}"))
+    })?;
+    Ok(devices
+        .into_iter()
+        .map(|device| RegisteredDevice {
+            usage_page: device.usUsagePage,
+            usage: device.usUsage,
+            flags: device.dwFlags.0,
+            target: device.hwndTarget.0 as isize,
+        })
+        .collect())
+}
+
+/// When a listener receives keyboard and mouse input, as part of its [`CaptureMode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureScope {
+    /// Whichever application has focus (`RIDEV_INPUTSINK`), the default
+    Background,
+    /// In the background only while the foreground application does not read raw input
+    /// itself (`RIDEV_EXINPUTSINK`), see
+    /// [`ListenerBuilder::extended_background`](crate::ListenerBuilder::extended_background)
+    ExtendedBackground,
+    /// Only while the listener's window has focus, see
+    /// [`ListenerBuilder::attach_foreground`](crate::ListenerBuilder::attach_foreground)
+    ForegroundOnly,
+}
+
+/// Summary of how a listener captures input, as returned by
+/// [`ListenerHandle::capture_mode`](crate::ListenerHandle::capture_mode).
+///
+/// This is what the listener registered, as of its start or last
+/// [`reconfigure`](crate::ListenerHandle::reconfigure). Compare it with
+/// [`registered_devices`], which reads what is in effect for the whole process, to find
+/// registrations another component took over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureMode {
+    /// When keyboard and mouse input is received
+    pub scope: CaptureScope,
+    /// True if raw input goes to an application window (attach mode) rather than the
+    /// listener's hidden window
+    pub attached: bool,
+    /// True if legacy messages are suppressed for the process (`RIDEV_NOLEGACY`)
+    pub no_legacy: bool,
+    /// Registrations that succeeded, mice and keyboards included (usage page 0x01,
+    /// usages 0x02 and 0x06)
+    pub usages: Vec<UsageRegistration>,
+}
+
+impl CaptureMode {
+    /// Returns the mode `config` asks for, as if every registration succeeded.
+    pub(crate) fn requested(config: &ListenerConfig, attached: bool) -> Self {
+        let scope = if config.foreground_only {
+            CaptureScope::ForegroundOnly
+        } else if config.extended_background {
+            CaptureScope::ExtendedBackground
+        } else {
+            CaptureScope::Background
+        };
+        Self {
+            scope,
+            attached,
+            no_legacy: config.no_legacy,
+            usages: config.registered_usages(),
+        }
+    }
+
+    /// Drops the usages whose registration failed.
+    pub(crate) fn registered(mut self, result: &RegistrationResult) -> Self {
+        // Both list mice, keyboards and the other usages in the same order
+        self.usages = self
+            .usages
+            .into_iter()
+            .zip(result.results())
+            .filter(|(_, result)| result.is_ok())
+            .map(|(usage, _)| usage)
+            .collect();
+        self
+    }
+
+    /// Returns true if keyboard and mouse input is received while another application
+    /// has focus.
+    pub fn is_background(&self) -> bool {
+        self.scope != CaptureScope::ForegroundOnly
+    }
+}
+
+/// Outcome of each raw input registration made when a listener starts.
+///
+/// Returned by [`ListenerBuilder::start_partial`](crate::ListenerBuilder::start_partial).
+/// The listener runs as long as at least one registration succeeded; the failed ones are
+/// also reported through the error callback.
+#[derive(Debug, Clone)]
+pub struct RegistrationResult {
+    /// Registration of mice, None if disabled with
+    /// [`ListenerBuilder::capture_mouse`](crate::ListenerBuilder::capture_mouse)
+    pub mouse: Option<Result<(), RawInputError>>,
+    /// Registration of keyboards, None if disabled with
+    /// [`ListenerBuilder::capture_keyboard`](crate::ListenerBuilder::capture_keyboard)
+    pub keyboard: Option<Result<(), RawInputError>>,
+    /// Registrations added with
+    /// [`ListenerBuilder::register_usage`](crate::ListenerBuilder::register_usage), as
+    /// `(usage_page, usage, result)` in the order they were added
+    pub hid: Vec<(u16, u16, Result<(), RawInputError>)>,
+}
+
+impl RegistrationResult {
+    fn results(&self) -> impl Iterator<Item = &Result<(), RawInputError>> {
+        [&self.mouse, &self.keyboard]
+            .into_iter()
+            .flatten()
+            .chain(self.hid.iter().map(|(_, _, result)| result))
+    }
+
+    /// Returns true if every registration succeeded.
+    pub fn is_complete(&self) -> bool {
+        self.results().all(Result::is_ok)
+    }
+
+    /// Returns true if at least one registration succeeded.
+    pub fn any_succeeded(&self) -> bool {
+        self.results().any(Result::is_ok)
+    }
+
+    /// Returns the errors of the failed registrations.
+    pub fn errors(&self) -> Vec<RawInputError> {
+        self.results()
+            .filter_map(|result| result.clone().err())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_result_summaries() {
+        let failed = || Err(RawInputError::WinApiError("failed".to_string()));
+        let mut result = RegistrationResult {
+            mouse: Some(Ok(())),
+            keyboard: None,
+            hid: vec![(0x01, 0x05, Ok(()))],
+        };
+        assert!(result.is_complete());
+        assert!(result.errors().is_empty());
+
+        result.hid.push((0x0C, 0x01, failed()));
+        result.keyboard = Some(failed());
+        assert!(!result.is_complete());
+        assert!(result.any_succeeded());
+        assert_eq!(result.errors().len(), 2);
+
+        result.mouse = Some(failed());
+        result.hid.retain(|(_, _, result)| result.is_err());
+        assert!(!result.any_succeeded());
+    }
+
+    /// Test that the capture mode lists the usages that registered, in the requested scope
+    #[test]
+    fn test_capture_mode() {
+        let config = crate::ListenerBuilder::new()
+            .extended_background(true)
+            .register_usage(UsageRegistration::usage(0x0C, 0x01))
+            .into_config();
+        let result = RegistrationResult {
+            mouse: Some(Ok(())),
+            keyboard: Some(Err(RawInputError::WinApiError("failed".to_string()))),
+            hid: vec![(0x0C, 0x01, Ok(()))],
+        };
+
+        let mode = CaptureMode::requested(&config, false).registered(&result);
+        assert_eq!(mode.scope, CaptureScope::ExtendedBackground);
+        assert!(mode.is_background() && !mode.attached);
+        assert_eq!(
+            mode.usages,
+            vec![
+                UsageRegistration::mouse(),
+                UsageRegistration::usage(0x0C, 0x01)
+            ]
+        );
+
+        let foreground = crate::ListenerBuilder::new().into_config();
+        let foreground = ListenerConfig {
+            foreground_only: true,
+            ..foreground
+        };
+        assert!(!CaptureMode::requested(&foreground, true).is_background());
+    }
+
+    #[test]
+    fn test_page_only_requires_zero_usage() {
+        assert!(UsageRegistration::page(0x01).validate().is_ok());
+        assert!(UsageRegistration::usage(0x01, 0x05).validate().is_ok());
+
+        let invalid = UsageRegistration {
+            usage_page: 0x01,
+            usage: 0x05,
+            page_only: true,
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    /// Test that a keyboard-less configuration rejects registrations delivering keystrokes
+    /// and never includes the keyboard usage
+    #[test]
+    fn test_keyboard_exclusion() {
+        let config = || crate::ListenerBuilder::mouse_only().into_config();
+        assert!(config().validate().is_ok());
+        assert!(
+            !config()
+                .registered_usages()
+                .contains(&UsageRegistration::keyboard())
+        );
+        assert!(
+            crate::ListenerBuilder::new()
+                .into_config()
+                .registered_usages()
+                .contains(&UsageRegistration::keyboard())
+        );
+
+        let with = |usage| {
+            crate::ListenerBuilder::mouse_only()
+                .register_usage(usage)
+                .into_config()
+                .validate()
+        };
+        assert!(with(UsageRegistration::usage(0x01, 0x06)).is_err());
+        assert!(with(UsageRegistration::usage(0x01, 0x07)).is_err()); // Keypad
+        assert!(with(UsageRegistration::page(0x01)).is_err());
+        assert!(with(UsageRegistration::usage(0x01, 0x05)).is_ok()); // Gamepad
+
+        let nothing = crate::ListenerBuilder::mouse_only().capture_mouse(false);
+        assert!(nothing.into_config().validate().is_err());
+    }
+
+    /// Test the interpretation of the registration flags
+    #[test]
+    fn test_registered_device_flags() {
+        let device = |flags| RegisteredDevice {
+            usage_page: 0x01,
+            usage: 0x02,
+            flags,
+            target: 0,
+        };
+        // RIDEV_NOLEGACY | RIDEV_INPUTSINK
+        let listener = device(0x130);
+        assert!(listener.is_background() && listener.is_no_legacy() && !listener.is_page_only());
+
+        let page = device(0x1020); // RIDEV_PAGEONLY | RIDEV_EXINPUTSINK
+        assert!(page.is_background() && page.is_page_only() && !page.is_no_legacy());
+        assert!(!device(0).is_background());
+    }
+
+    /// Test that a start rejected for an invalid registration can be reported to the error
+    /// callback as well
+    #[test]
+    fn test_invalid_registration_reported_as_init_error() {
+        let invalid = UsageRegistration {
+            usage_page: 0x01,
+            usage: 0x05,
+            page_only: true,
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let result = crate::ListenerBuilder::new()
+            .register_usage(invalid)
+            .error_callback(move |err| {
+                let _ = tx.send(err);
+            })
+            .report_init_errors(true)
+            .start(|_| {});
+
+        assert!(result.is_err());
+        assert_eq!(rx.try_iter().count(), 1);
+    }
+}
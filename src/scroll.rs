@@ -0,0 +1,251 @@
+//! Conversion of raw wheel deltas into smooth line and pixel scrolling
+
+use crate::mouse::{WHEEL_DELTA, WHEEL_SCROLL_LINES_DEFAULT};
+use crate::{RawMouseEvent, WheelAxis};
+
+/// Lines-per-notch setting meaning "one page per notch" (`WHEEL_PAGESCROLL`).
+pub const WHEEL_PAGESCROLL: u32 = u32::MAX;
+
+/// How far a wheel notch scrolls, from the user's wheel settings and the application's
+/// line metrics.
+///
+/// Windows only defines how many lines (vertically) or characters (horizontally) a notch
+/// scrolls; how tall a line is belongs to the application. The pixel distance of a notch
+/// is therefore `lines_per_notch * line_height` vertically and `chars_per_notch *
+/// char_width` horizontally, and a raw delta scrolls `delta / WHEEL_DELTA` of that.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollSettings {
+    /// Lines scrolled per vertical notch (the "Choose how many lines to scroll each time"
+    /// setting), or [`WHEEL_PAGESCROLL`] to scroll a page per notch
+    pub lines_per_notch: u32,
+    /// Characters scrolled per horizontal notch (or [`WHEEL_PAGESCROLL`])
+    pub chars_per_notch: u32,
+    /// Height of a line in pixels
+    pub line_height: f32,
+    /// Width of a character in pixels
+    pub char_width: f32,
+}
+
+impl ScrollSettings {
+    /// Returns the distance in lines (or characters) and pixels of one notch on `axis`,
+    /// or None when it scrolls by pages.
+    fn per_notch(&self, axis: WheelAxis) -> Option<(u32, f32)> {
+        let (units, size) = match axis {
+            WheelAxis::Vertical => (self.lines_per_notch, self.line_height),
+            WheelAxis::Horizontal => (self.chars_per_notch, self.char_width),
+        };
+        (units != WHEEL_PAGESCROLL).then_some((units, units as f32 * size))
+    }
+}
+
+impl Default for ScrollSettings {
+    /// Returns the Windows default of 3 lines and 3 characters per notch, with a 16 pixel
+    /// line and an 8 pixel character (typical text at 96 DPI).
+    fn default() -> Self {
+        Self {
+            lines_per_notch: WHEEL_SCROLL_LINES_DEFAULT,
+            chars_per_notch: WHEEL_SCROLL_LINES_DEFAULT,
+            line_height: 16.0,
+            char_width: 8.0,
+        }
+    }
+}
+
+/// Distance to scroll for one wheel event, as returned by [`SmoothScrollAccumulator`].
+///
+/// Positive values scroll up (vertical) or right (horizontal), like the wheel delta. Each
+/// field counts whole units only; the fractions are carried over to the next event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrollStep {
+    /// Wheel axis the event scrolled
+    pub axis: WheelAxis,
+    /// Whole lines (vertical) or characters (horizontal), for applications that scroll
+    /// by line
+    pub lines: i32,
+    /// Whole pixels, for applications that scroll smoothly
+    pub pixels: i32,
+    /// Whole pages, if the settings scroll by page (lines and pixels are then 0)
+    pub pages: i32,
+}
+
+/// Remainders carried between the events of one axis
+#[derive(Debug, Clone, Copy, Default)]
+struct AxisRemainder {
+    /// Raw delta times the lines (or pages) per notch not yet scrolled, below
+    /// `WHEEL_DELTA`
+    units: i32,
+    /// Fraction of a pixel not yet scrolled
+    pixels: f32,
+}
+
+/// Turns raw wheel deltas into whole line and pixel scroll steps the way Windows
+/// applications scroll, keeping the fractional remainder between events.
+///
+/// A classic wheel sends `WHEEL_DELTA` (120) per notch, but high-precision wheels and
+/// trackpads send many smaller deltas. Scrolling by `delta * lines_per_notch /
+/// WHEEL_DELTA` lines per event rounds each of them down to nothing; the accumulator
+/// instead sums the deltas and only reports the whole lines and pixels reached so far,
+/// as the Windows documentation recommends. Like Windows applications, it discards the
+/// remainder when the scrolling direction reverses.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{ListenerBuilder, RawInputEvent, ScrollSettings, SmoothScrollAccumulator};
+///
+/// let mut scroll = SmoothScrollAccumulator::new(ScrollSettings::new(20.0, 9.0));
+/// let _listener = ListenerBuilder::new()
+///     .start(move |event| {
+///         if let RawInputEvent::Mouse(mouse) = event
+///             && let Some(step) = scroll.add(&mouse)
+///         {
+///             println!("Scroll {:?} by {} px", step.axis, step.pixels);
+///         }
+///     })
+///     .expect("Failed to start listener");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SmoothScrollAccumulator {
+    settings: ScrollSettings,
+    vertical: AxisRemainder,
+    horizontal: AxisRemainder,
+}
+
+impl SmoothScrollAccumulator {
+    /// Creates an accumulator with no remainder.
+    pub fn new(settings: ScrollSettings) -> Self {
+        Self {
+            settings,
+            vertical: AxisRemainder::default(),
+            horizontal: AxisRemainder::default(),
+        }
+    }
+
+    /// Returns the settings the deltas are converted with.
+    pub fn settings(&self) -> ScrollSettings {
+        self.settings
+    }
+
+    /// Adds the delta of a wheel event, or returns None if `mouse` is not one.
+    pub fn add(&mut self, mouse: &RawMouseEvent) -> Option<ScrollStep> {
+        let axis = mouse.wheel_axis?;
+        Some(self.add_delta(axis, mouse.wheel_delta))
+    }
+
+    /// Adds a raw wheel delta (in units of `WHEEL_DELTA` per notch) on `axis`.
+    pub fn add_delta(&mut self, axis: WheelAxis, delta: i16) -> ScrollStep {
+        let per_notch = self.settings.per_notch(axis);
+        let remainder = match axis {
+            WheelAxis::Vertical => &mut self.vertical,
+            WheelAxis::Horizontal => &mut self.horizontal,
+        };
+        let delta = i32::from(delta);
+        if delta.signum() * remainder.units.signum() < 0 || delta as f32 * remainder.pixels < 0.0 {
+            *remainder = AxisRemainder::default();
+        }
+
+        let mut step = ScrollStep {
+            axis,
+            lines: 0,
+            pixels: 0,
+            pages: 0,
+        };
+        let Some((units, pixels)) = per_notch else {
+            remainder.units += delta;
+            step.pages = remainder.units / i32::from(WHEEL_DELTA);
+            remainder.units %= i32::from(WHEEL_DELTA);
+            return step;
+        };
+
+        // Bounded so that the product of a delta and the lines per notch fits an i32
+        remainder.units += delta * units.min(0xFFFF) as i32;
+        step.lines = remainder.units / i32::from(WHEEL_DELTA);
+        remainder.units %= i32::from(WHEEL_DELTA);
+
+        let exact = remainder.pixels + delta as f32 * pixels / f32::from(WHEEL_DELTA);
+        step.pixels = exact.trunc() as i32;
+        remainder.pixels = exact.fract();
+        step
+    }
+
+    /// Discards the remainders, e.g. when the scrolled view changes.
+    pub fn reset(&mut self) {
+        self.vertical = AxisRemainder::default();
+        self.horizontal = AxisRemainder::default();
+    }
+}
+
+#[cfg(windows)]
+impl ScrollSettings {
+    /// Creates settings from the user's current lines and characters per notch
+    /// (`SPI_GETWHEELSCROLLLINES` and `SPI_GETWHEELSCROLLCHARS`, defaulting to 3 if they
+    /// cannot be read) and the application's line height and character width in pixels.
+    ///
+    /// The settings are read once; create new settings to pick up changes (Windows
+    /// broadcasts `WM_SETTINGCHANGE` when they change).
+    pub fn new(line_height: f32, char_width: f32) -> Self {
+        use crate::mouse::{get_wheel_scroll_chars, get_wheel_scroll_lines};
+        Self {
+            lines_per_notch: get_wheel_scroll_lines().unwrap_or(WHEEL_SCROLL_LINES_DEFAULT),
+            chars_per_notch: get_wheel_scroll_chars().unwrap_or(WHEEL_SCROLL_LINES_DEFAULT),
+            line_height,
+            char_width,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(lines_per_notch: u32) -> ScrollSettings {
+        ScrollSettings {
+            lines_per_notch,
+            chars_per_notch: 3,
+            line_height: 10.0,
+            char_width: 4.0,
+        }
+    }
+
+    /// Test that small deltas add up to whole lines and pixels
+    #[test]
+    fn test_small_deltas_accumulate() {
+        let mut scroll = SmoothScrollAccumulator::new(settings(3));
+
+        // A full notch scrolls 3 lines of 10 pixels
+        let step = scroll.add_delta(WheelAxis::Vertical, 120);
+        assert_eq!((step.lines, step.pixels, step.pages), (3, 30, 0));
+
+        // 30 units are 0.75 lines and 7.5 pixels
+        let step = scroll.add_delta(WheelAxis::Vertical, 30);
+        assert_eq!((step.lines, step.pixels), (0, 7));
+        let step = scroll.add_delta(WheelAxis::Vertical, 30);
+        assert_eq!((step.lines, step.pixels), (1, 8));
+
+        // Horizontal deltas are tracked separately, in characters
+        let step = scroll.add_delta(WheelAxis::Horizontal, -40);
+        assert_eq!(
+            (step.axis, step.lines, step.pixels),
+            (WheelAxis::Horizontal, -1, -4)
+        );
+    }
+
+    /// Test that reversing the direction discards the remainder
+    #[test]
+    fn test_reversal_resets_remainder() {
+        let mut scroll = SmoothScrollAccumulator::new(settings(3));
+        scroll.add_delta(WheelAxis::Vertical, 30); // 0.75 lines
+        let step = scroll.add_delta(WheelAxis::Vertical, -30);
+        assert_eq!((step.lines, step.pixels), (0, -7));
+        let step = scroll.add_delta(WheelAxis::Vertical, -10);
+        assert_eq!((step.lines, step.pixels), (-1, -3));
+    }
+
+    /// Test that page scrolling counts pages only
+    #[test]
+    fn test_page_scroll() {
+        let mut scroll = SmoothScrollAccumulator::new(settings(WHEEL_PAGESCROLL));
+        assert_eq!(scroll.add_delta(WheelAxis::Vertical, 60).pages, 0);
+        let step = scroll.add_delta(WheelAxis::Vertical, 60);
+        assert_eq!((step.lines, step.pixels, step.pages), (0, 0, 1));
+    }
+}
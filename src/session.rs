@@ -0,0 +1,153 @@
+//! Session lock/unlock and connection changes, during which input is not captured
+
+/// Change of the Windows session the listener runs in, as reported by
+/// [`ListenerBuilder::session_callback`](crate::ListenerBuilder::session_callback).
+///
+/// While the session is locked or disconnected, input goes to the secure desktop (the lock
+/// screen) or to another session, and no application receives it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionChange {
+    /// The session was locked (Win+L, lock screen); capture is suspended
+    Locked,
+    /// The session was unlocked; capture resumes
+    Unlocked,
+    /// The session was connected to the physical console (e.g. after fast user switching)
+    ConsoleConnected,
+    /// The session was disconnected from the physical console; capture is suspended
+    ConsoleDisconnected,
+    /// The session was connected to a Remote Desktop client
+    RemoteConnected,
+    /// The session was disconnected from its Remote Desktop client; capture is suspended
+    RemoteDisconnected,
+}
+
+impl SessionChange {
+    /// Interprets the status code (`WPARAM`) of a `WM_WTSSESSION_CHANGE` message.
+    ///
+    /// Returns None for changes that do not affect input (logon, logoff, remote control,
+    /// ...).
+    pub(crate) fn from_status(status: u32) -> Option<Self> {
+        Some(match status {
+            0x1 => Self::ConsoleConnected,
+            0x2 => Self::ConsoleDisconnected,
+            0x3 => Self::RemoteConnected,
+            0x4 => Self::RemoteDisconnected,
+            0x7 => Self::Locked,
+            0x8 => Self::Unlocked,
+            _ => return None,
+        })
+    }
+
+    /// Returns true if input is no longer delivered to the session after this change.
+    pub fn suspends_capture(self) -> bool {
+        matches!(
+            self,
+            Self::Locked | Self::ConsoleDisconnected | Self::RemoteDisconnected
+        )
+    }
+}
+
+#[cfg(windows)]
+pub(crate) use monitor::{SessionMonitor, WM_SESSION_CHANGE};
+
+#[cfg(windows)]
+mod monitor {
+    use super::SessionChange;
+    use crate::dispatch::SessionCallback;
+    use crate::state::SharedInputState;
+    use crate::{RawInputError, ffi};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::WM_APP;
+
+    /// Private message the listener window re-posts `WM_WTSSESSION_CHANGE` as, so that the
+    /// message loop sees it whether it was sent or posted.
+    pub(crate) const WM_SESSION_CHANGE: u32 = WM_APP + 0x4D4B;
+
+    /// Reports session changes to the user and restores capture afterwards.
+    pub(crate) struct SessionMonitor {
+        hwnd: HWND,
+        on_session: SessionCallback,
+        state: SharedInputState,
+    }
+
+    impl SessionMonitor {
+        /// Subscribes `hwnd` to session change notifications.
+        pub(crate) fn new(
+            hwnd: HWND,
+            on_session: SessionCallback,
+            state: SharedInputState,
+        ) -> Result<Self, RawInputError> {
+            ffi::register_session_notification(hwnd).map_err(|e| {
+                RawInputError::WinApiError(format!(
+                    "Failed to register for session notifications: {e}"
+                ))
+            })?;
+            Ok(Self {
+                hwnd,
+                on_session,
+                state,
+            })
+        }
+
+        /// Moves the subscription to `hwnd`, which replaces the listener window.
+        pub(crate) fn rebind(&mut self, hwnd: HWND) -> Result<(), RawInputError> {
+            let _ = ffi::unregister_session_notification(self.hwnd);
+            self.hwnd = hwnd;
+            ffi::register_session_notification(hwnd).map_err(|e| {
+                RawInputError::WinApiError(format!(
+                    "Failed to register for session notifications: {e}"
+                ))
+            })
+        }
+
+        /// Handles a `WM_WTSSESSION_CHANGE` status code.
+        ///
+        /// Held keys are forgotten when capture is suspended, as their releases happen on
+        /// the secure desktop (e.g. the Win key of Win+L). Returns the change, so that the
+        /// caller can register raw input again when capture resumes, in case the
+        /// registration was lost.
+        pub(crate) fn handle(&mut self, status: u32) -> Option<SessionChange> {
+            let change = SessionChange::from_status(status)?;
+            if change.suspends_capture()
+                && let Ok(mut state) = self.state.lock()
+            {
+                state.reset();
+            }
+            (self.on_session)(change);
+            Some(change)
+        }
+    }
+
+    impl Drop for SessionMonitor {
+        fn drop(&mut self) {
+            let _ = ffi::unregister_session_notification(self.hwnd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test the mapping of WTS status codes and which changes suspend capture
+    #[test]
+    fn test_from_status() {
+        assert_eq!(SessionChange::from_status(0x7), Some(SessionChange::Locked));
+        assert_eq!(
+            SessionChange::from_status(0x8),
+            Some(SessionChange::Unlocked)
+        );
+        assert_eq!(
+            SessionChange::from_status(0x2),
+            Some(SessionChange::ConsoleDisconnected)
+        );
+        // Logon and logoff do not affect a running session's input
+        assert_eq!(SessionChange::from_status(0x5), None);
+        assert_eq!(SessionChange::from_status(0x6), None);
+
+        assert!(SessionChange::Locked.suspends_capture());
+        assert!(SessionChange::RemoteDisconnected.suspends_capture());
+        assert!(!SessionChange::Unlocked.suspends_capture());
+        assert!(!SessionChange::ConsoleConnected.suspends_capture());
+    }
+}
@@ -0,0 +1,51 @@
+//! Event sources that feed the listener's dispatch pipeline
+
+use crate::{RawInputError, RawInputEvent};
+
+/// A source of raw input events.
+///
+/// The background listener reads events from the Win32 message loop through this trait,
+/// and [`ListenerBuilder::run_with_source`](crate::ListenerBuilder::run_with_source) lets any
+/// other implementation drive the exact same dispatch pipeline (filters, state tracking,
+/// callbacks). This makes event-processing logic testable without Windows input.
+pub trait EventSource {
+    /// Returns the next event or error, or `None` once the source is exhausted.
+    fn next_event(&mut self) -> Option<Result<RawInputEvent, RawInputError>>;
+}
+
+/// Deterministic event source that replays a fixed sequence of events.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{ListenerBuilder, RawInputEvent, VecEventSource};
+///
+/// # let recorded: Vec<RawInputEvent> = Vec::new();
+/// let mut seen = Vec::new();
+/// ListenerBuilder::new().run_with_source(VecEventSource::new(recorded), |event| {
+///     seen.push(event);
+/// });
+/// ```
+#[derive(Debug)]
+pub struct VecEventSource {
+    items: std::vec::IntoIter<Result<RawInputEvent, RawInputError>>,
+}
+
+impl VecEventSource {
+    /// Creates a source that yields the given events in order.
+    pub fn new(events: Vec<RawInputEvent>) -> Self {
+        Self::from_results(events.into_iter().map(Ok).collect())
+    }
+
+    /// Creates a source that yields the given events and errors in order.
+    pub fn from_results(items: Vec<Result<RawInputEvent, RawInputError>>) -> Self {
+        Self {
+            items: items.into_iter(),
+        }
+    }
+}
+
+impl EventSource for VecEventSource {
+    fn next_event(&mut self) -> Option<Result<RawInputEvent, RawInputError>> {
+        self.items.next()
+    }
+}
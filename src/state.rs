@@ -0,0 +1,661 @@
+//! Tracking of currently held keys and mouse buttons, of accumulated scrolling, of
+//! recently delivered events and of events waiting to be polled
+
+use crate::{
+    KeyCode, Modifiers, MouseButton, RawInputDeviceHandle, RawInputEvent, VirtualKey, WheelAxis,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// State shared between the dispatcher (which updates it) and the listener handle.
+pub(crate) type SharedInputState = Arc<Mutex<InputState>>;
+
+/// Set of keys and mouse buttons currently held down, maintained from raw input events.
+///
+/// Because it is built from raw hardware events, the tracked state is closest to the
+/// physical state of the devices. Keys are tracked per keyboard, and a key is held as long
+/// as any keyboard holds it.
+#[derive(Debug, Default)]
+pub(crate) struct InputState {
+    buttons: HashSet<MouseButton>,
+    /// Keys held down when the state was seeded, which belong to no keyboard
+    seeded: HeldKeys,
+    /// Keys held down per keyboard; a keyboard is removed once it holds no key
+    devices: HashMap<RawInputDeviceHandle, HeldKeys>,
+}
+
+/// Keys held down on one keyboard.
+#[derive(Debug, Default)]
+struct HeldKeys {
+    keys: HashSet<VirtualKey>,
+    /// Sided modifier keys held down, told apart by scan code as `keys` cannot
+    sided: Modifiers,
+}
+
+impl HeldKeys {
+    /// Records a key going down, with its sided modifier flag if it is a modifier.
+    fn press(&mut self, key: VirtualKey, modifier: Option<Modifiers>) {
+        self.keys.insert(key);
+        if let Some(modifier) = modifier {
+            self.sided |= modifier;
+        }
+    }
+
+    /// Records a key going up, with its sided modifier flag if it is a modifier.
+    fn release(&mut self, key: VirtualKey, modifier: Option<Modifiers>) {
+        self.keys.remove(&key);
+        if let Some(modifier) = modifier {
+            self.sided.remove(modifier);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.sided.is_empty()
+    }
+}
+
+/// Returns the sided modifier flag of a physical key, if it is a modifier.
+fn sided_modifier(code: KeyCode) -> Option<Modifiers> {
+    Some(match code {
+        KeyCode::ShiftLeft => Modifiers::LEFT_SHIFT,
+        KeyCode::ShiftRight => Modifiers::RIGHT_SHIFT,
+        KeyCode::ControlLeft => Modifiers::LEFT_CTRL,
+        KeyCode::ControlRight => Modifiers::RIGHT_CTRL,
+        KeyCode::AltLeft => Modifiers::LEFT_ALT,
+        KeyCode::AltRight => Modifiers::RIGHT_ALT,
+        KeyCode::MetaLeft => Modifiers::LEFT_WIN,
+        KeyCode::MetaRight => Modifiers::RIGHT_WIN,
+        _ => return None,
+    })
+}
+
+/// Change in the set of held keys caused by a keyboard event, as reported by
+/// [`ListenerBuilder::key_set_callback`](crate::ListenerBuilder::key_set_callback).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeySetChange {
+    /// Keys that went down with the event
+    pub newly_pressed: Vec<VirtualKey>,
+    /// Keys that went up with the event
+    pub newly_released: Vec<VirtualKey>,
+    /// Keys held down after the event, in no particular order
+    pub currently_held: Vec<VirtualKey>,
+}
+
+impl InputState {
+    /// Updates the held keys/buttons from an event.
+    pub(crate) fn apply(&mut self, event: &RawInputEvent) {
+        match event {
+            // Fake shifts do not reflect the physical state of the Shift keys
+            RawInputEvent::Keyboard(kbd) if kbd.is_fake_shift() => {}
+            RawInputEvent::Keyboard(kbd) => {
+                let modifier = sided_modifier(kbd.key_code());
+                if kbd.key_up {
+                    // Whichever keyboard releases a seeded key held it
+                    self.seeded.release(kbd.vkey, modifier);
+                    if let Some(held) = self.devices.get_mut(&kbd.device) {
+                        held.release(kbd.vkey, modifier);
+                        if held.is_empty() {
+                            self.devices.remove(&kbd.device);
+                        }
+                    }
+                } else {
+                    self.devices
+                        .entry(kbd.device)
+                        .or_default()
+                        .press(kbd.vkey, modifier);
+                }
+            }
+            RawInputEvent::Mouse(mouse) => match mouse.button_action.button_transition() {
+                Some((button, true)) => {
+                    self.buttons.insert(button);
+                }
+                Some((button, false)) => {
+                    self.buttons.remove(&button);
+                }
+                None => {}
+            },
+            RawInputEvent::Hid(_) => {}
+        }
+    }
+
+    /// Returns the held keys of every keyboard, seeded ones first.
+    fn held(&self) -> impl Iterator<Item = &HeldKeys> {
+        std::iter::once(&self.seeded).chain(self.devices.values())
+    }
+
+    /// Returns true if the key is currently held down on any keyboard.
+    pub(crate) fn is_key_down(&self, key: VirtualKey) -> bool {
+        self.held().any(|held| held.keys.contains(&key))
+    }
+
+    /// Returns how a key event changed the held keys, given whether its key was held
+    /// before the event was applied, or None if it changed nothing (auto-repeat).
+    pub(crate) fn key_set_change(&self, key: VirtualKey, was_down: bool) -> Option<KeySetChange> {
+        let is_down = self.is_key_down(key);
+        if is_down == was_down {
+            return None;
+        }
+        let mut change = KeySetChange {
+            currently_held: self.pressed_keys(),
+            ..KeySetChange::default()
+        };
+        if is_down {
+            change.newly_pressed.push(key);
+        } else {
+            change.newly_released.push(key);
+        }
+        Some(change)
+    }
+
+    /// Returns the keys currently held down on any keyboard, in no particular order.
+    pub(crate) fn pressed_keys(&self) -> Vec<VirtualKey> {
+        let keys: HashSet<VirtualKey> = self
+            .held()
+            .flat_map(|held| held.keys.iter().copied())
+            .collect();
+        keys.into_iter().collect()
+    }
+
+    /// Returns the modifiers currently held down, both side-independent and sided.
+    pub(crate) fn modifiers(&self) -> Modifiers {
+        let sided = self
+            .held()
+            .fold(Modifiers::NONE, |sided, held| sided | held.sided);
+        let mut modifiers = sided;
+        for (keys, sides, modifier) in [
+            (
+                &[VirtualKey::Shift][..],
+                Modifiers::LEFT_SHIFT | Modifiers::RIGHT_SHIFT,
+                Modifiers::SHIFT,
+            ),
+            (
+                &[VirtualKey::Control][..],
+                Modifiers::LEFT_CTRL | Modifiers::RIGHT_CTRL,
+                Modifiers::CTRL,
+            ),
+            (
+                &[VirtualKey::Alt][..],
+                Modifiers::LEFT_ALT | Modifiers::RIGHT_ALT,
+                Modifiers::ALT,
+            ),
+            (
+                &[VirtualKey::LWin, VirtualKey::RWin][..],
+                Modifiers::LEFT_WIN | Modifiers::RIGHT_WIN,
+                Modifiers::WIN,
+            ),
+        ] {
+            if sided.intersects(sides) || keys.iter().any(|&key| self.is_key_down(key)) {
+                modifiers |= modifier;
+            }
+        }
+        modifiers
+    }
+
+    /// Returns true if the mouse button is currently held down.
+    pub(crate) fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// Returns the mouse buttons currently held down, in no particular order.
+    pub(crate) fn pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        self.buttons.iter().copied().collect()
+    }
+
+    /// Returns the keys currently held down on one keyboard, in no particular order.
+    pub(crate) fn pressed_keys_for_device(&self, device: RawInputDeviceHandle) -> Vec<VirtualKey> {
+        self.devices
+            .get(&device)
+            .map(|held| held.keys.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forgets the keys held on one keyboard, e.g. after it was unplugged mid-press.
+    ///
+    /// Keys also held on another keyboard stay down.
+    pub(crate) fn forget_device(&mut self, device: RawInputDeviceHandle) {
+        self.devices.remove(&device);
+    }
+
+    /// Handles a `WM_INPUT_DEVICE_CHANGE` message, forgetting the keys held on a keyboard
+    /// that was removed.
+    #[cfg(windows)]
+    pub(crate) fn device_changed(&mut self, wparam: usize, lparam: isize) {
+        use windows::Win32::UI::WindowsAndMessaging::GIDC_REMOVAL;
+
+        if wparam as u32 == GIDC_REMOVAL {
+            self.forget_device(RawInputDeviceHandle::from_raw(lparam));
+        }
+    }
+
+    /// Replaces the held keys and buttons with those `is_down` reports down, by virtual key
+    /// code (mouse buttons by `VK_LBUTTON` and so on, sided modifiers by `VK_LSHIFT` and
+    /// so on).
+    ///
+    /// Keys are recorded as raw input reports them, e.g. Shift as [`VirtualKey::Shift`]
+    /// only, so that their release clears them.
+    pub(crate) fn seed(&mut self, is_down: impl Fn(u16) -> bool) {
+        const BUTTONS: [(u16, MouseButton); 5] = [
+            (0x01, MouseButton::Left),
+            (0x02, MouseButton::Right),
+            (0x04, MouseButton::Middle),
+            (0x05, MouseButton::X1),
+            (0x06, MouseButton::X2),
+        ];
+        const SIDED: [(u16, Modifiers); 8] = [
+            (0xA0, Modifiers::LEFT_SHIFT),
+            (0xA1, Modifiers::RIGHT_SHIFT),
+            (0xA2, Modifiers::LEFT_CTRL),
+            (0xA3, Modifiers::RIGHT_CTRL),
+            (0xA4, Modifiers::LEFT_ALT),
+            (0xA5, Modifiers::RIGHT_ALT),
+            (0x5B, Modifiers::LEFT_WIN),
+            (0x5C, Modifiers::RIGHT_WIN),
+        ];
+
+        self.reset();
+        for (vkey, button) in BUTTONS {
+            if is_down(vkey) {
+                self.buttons.insert(button);
+            }
+        }
+        for (vkey, modifier) in SIDED {
+            if is_down(vkey) {
+                self.seeded.sided |= modifier;
+            }
+        }
+        // Mouse buttons and the sided modifiers, which raw input reports as the
+        // side-independent keys, are not keys
+        let keys = (0x07..=0xFE).filter(|vkey| !(0xA0..=0xA5).contains(vkey));
+        self.seeded
+            .keys
+            .extend(keys.filter(|&vkey| is_down(vkey)).map(VirtualKey::from));
+    }
+
+    /// Replaces the held keys and buttons with those Windows reports physically down
+    /// (`GetAsyncKeyState`), so that keys held when the listener starts are tracked.
+    ///
+    /// This is a best-effort snapshot: Windows reports nothing as down while input goes
+    /// to another desktop, and a key pressed between the registration and the snapshot
+    /// is seen twice, which tracking tolerates.
+    #[cfg(windows)]
+    pub(crate) fn seed_from_system(&mut self) {
+        self.seed(crate::ffi::async_key_down);
+    }
+
+    /// Forgets all held keys and buttons.
+    pub(crate) fn reset(&mut self) {
+        self.buttons.clear();
+        self.seeded = HeldKeys::default();
+        self.devices.clear();
+    }
+}
+
+/// Raw wheel deltas summed per axis until taken, shared between the dispatcher (which adds
+/// to it) and the listener handle (which takes it).
+///
+/// Both axes are packed into a single atomic, so taking them is one atomic swap and never
+/// splits a concurrent update.
+#[derive(Debug, Default)]
+pub(crate) struct ScrollAccumulator {
+    /// Vertical sum in the high 32 bits, horizontal sum in the low 32 bits
+    packed: AtomicU64,
+}
+
+impl ScrollAccumulator {
+    fn pack(vertical: i32, horizontal: i32) -> u64 {
+        (u64::from(vertical as u32) << 32) | u64::from(horizontal as u32)
+    }
+
+    fn unpack(packed: u64) -> (i32, i32) {
+        ((packed >> 32) as u32 as i32, packed as u32 as i32)
+    }
+
+    /// Adds a raw wheel delta to the sum for `axis`, saturating at the `i32` bounds.
+    pub(crate) fn add(&self, axis: WheelAxis, delta: i32) {
+        let _ = self
+            .packed
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |packed| {
+                let (vertical, horizontal) = Self::unpack(packed);
+                Some(match axis {
+                    WheelAxis::Vertical => Self::pack(vertical.saturating_add(delta), horizontal),
+                    WheelAxis::Horizontal => Self::pack(vertical, horizontal.saturating_add(delta)),
+                })
+            });
+    }
+
+    /// Returns the `(vertical, horizontal)` sums and resets both to zero.
+    pub(crate) fn take(&self) -> (i32, i32) {
+        Self::unpack(self.packed.swap(0, Ordering::SeqCst))
+    }
+}
+
+/// Ring buffer of the most recently delivered events, shared between the dispatcher
+/// (which records them) and the listener handle (which snapshots them).
+#[derive(Debug, Default)]
+pub(crate) struct RecentEvents {
+    capacity: usize,
+    events: Mutex<VecDeque<RawInputEvent>>,
+}
+
+impl RecentEvents {
+    /// Creates a buffer keeping the last `capacity` events; 0 disables recording.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Records an event, evicting the oldest one when full.
+    pub(crate) fn push(&self, event: &RawInputEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() == self.capacity {
+                events.pop_front();
+            }
+            events.push_back(event.clone());
+        }
+    }
+
+    /// Returns a copy of the recorded events, oldest first.
+    pub(crate) fn snapshot(&self) -> Vec<RawInputEvent> {
+        self.events
+            .lock()
+            .map(|events| events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Queue of delivered events waiting for
+/// [`ListenerHandle::poll_events`](crate::ListenerHandle::poll_events), shared between the
+/// dispatcher (which fills it instead of calling the event callback) and the listener
+/// handle (which drains it).
+#[derive(Debug, Default)]
+pub(crate) struct PolledEvents {
+    enabled: bool,
+    /// Maximum number of queued events (None for unbounded)
+    capacity: Option<usize>,
+    queue: Mutex<PollQueue>,
+    /// Number of events discarded since the listener started
+    dropped: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct PollQueue {
+    events: VecDeque<RawInputEvent>,
+    /// Whether events were discarded since the last poll
+    overflowed: bool,
+}
+
+impl PolledEvents {
+    /// Creates a queue holding at most `capacity` events (None for unbounded), or a
+    /// disabled one that is never filled.
+    pub(crate) fn new(enabled: bool, capacity: Option<usize>) -> Self {
+        Self {
+            enabled,
+            capacity,
+            queue: Mutex::default(),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns true if events are queued for polling instead of delivered to the callback.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Queues an event, discarding the oldest one when full.
+    ///
+    /// Returns true the first time an event is discarded since the last poll.
+    pub(crate) fn push(&self, event: RawInputEvent) -> bool {
+        let Ok(mut queue) = self.queue.lock() else {
+            return false;
+        };
+        queue.events.push_back(event);
+        if self
+            .capacity
+            .is_some_and(|capacity| queue.events.len() > capacity)
+        {
+            queue.events.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return !std::mem::replace(&mut queue.overflowed, true);
+        }
+        false
+    }
+
+    /// Returns the number of events discarded since the listener started.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Takes the queued events, oldest first.
+    pub(crate) fn drain(&self) -> Vec<RawInputEvent> {
+        self.queue
+            .lock()
+            .map(|mut queue| {
+                queue.overflowed = false;
+                queue.events.drain(..).collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+    use crate::{MouseButtonAction, RawKeyboardEvent, RawMouseEvent};
+    use windows::Win32::UI::Input::{RAWKEYBOARD, RAWMOUSE};
+
+    fn key(vkey: u16, key_up: bool) -> RawInputEvent {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.VKey = vkey;
+        raw.Flags = if key_up { 1 } else { 0 };
+        RawInputEvent::Keyboard(RawKeyboardEvent::from(&raw))
+    }
+
+    fn left_shift(flags: u16) -> RawInputEvent {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.VKey = 0x10;
+        raw.MakeCode = 0x2A;
+        raw.Flags = flags;
+        RawInputEvent::Keyboard(RawKeyboardEvent::from(&raw))
+    }
+
+    fn on(device: isize, event: RawInputEvent) -> RawInputEvent {
+        let RawInputEvent::Keyboard(mut kbd) = event else {
+            unreachable!()
+        };
+        kbd.device = RawInputDeviceHandle::from_raw(device);
+        RawInputEvent::Keyboard(kbd)
+    }
+
+    fn button(action: MouseButtonAction) -> RawInputEvent {
+        let raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        let mut event = RawMouseEvent::from(&raw);
+        event.button_action = action;
+        RawInputEvent::Mouse(event)
+    }
+
+    #[test]
+    fn test_tracks_keys_and_buttons() {
+        let mut state = InputState::default();
+        state.apply(&key(0x41, false)); // A down
+        state.apply(&key(0x42, false)); // B down
+        state.apply(&key(0x41, true)); // A up
+        state.apply(&button(MouseButtonAction::LeftDown));
+
+        assert!(!state.is_key_down(VirtualKey::A));
+        assert!(state.is_key_down(VirtualKey::B));
+        assert_eq!(state.pressed_keys(), vec![VirtualKey::B]);
+        assert!(state.is_mouse_button_down(MouseButton::Left));
+
+        state.apply(&button(MouseButtonAction::LeftUp));
+        assert!(state.pressed_mouse_buttons().is_empty());
+    }
+
+    #[test]
+    fn test_reset_clears_stuck_keys() {
+        let mut state = InputState::default();
+        state.apply(&key(0x12, false)); // Alt down, release never seen (Alt-Tab)
+        state.apply(&button(MouseButtonAction::RightDown));
+
+        state.reset();
+
+        assert!(state.pressed_keys().is_empty());
+        assert!(state.pressed_mouse_buttons().is_empty());
+    }
+
+    #[test]
+    fn test_tracks_keys_per_device() {
+        let (first, second) = (
+            RawInputDeviceHandle::from_raw(1),
+            RawInputDeviceHandle::from_raw(2),
+        );
+        let mut state = InputState::default();
+        state.apply(&on(1, key(0x57, false))); // W down on the first keyboard
+        state.apply(&on(2, key(0x26, false))); // Up down on the second keyboard
+        state.apply(&on(2, key(0x57, false))); // W down on the second keyboard
+
+        assert_eq!(state.pressed_keys_for_device(first), vec![VirtualKey::W]);
+        state.apply(&on(2, key(0x57, true)));
+        assert_eq!(state.pressed_keys_for_device(first), vec![VirtualKey::W]);
+        assert_eq!(state.pressed_keys_for_device(second), vec![VirtualKey::Up]);
+
+        // Keyboards holding no key are dropped
+        state.apply(&on(2, key(0x26, true)));
+        assert!(!state.devices.contains_key(&second));
+
+        state.forget_device(first);
+        assert!(state.pressed_keys_for_device(first).is_empty());
+        assert!(state.pressed_keys().is_empty());
+    }
+
+    /// Test that a key stays down while any keyboard holds it
+    #[test]
+    fn test_merges_keys_of_all_devices() {
+        let mut state = InputState::default();
+        state.apply(&on(1, key(0x57, false))); // W down on the first keyboard
+        state.apply(&on(2, key(0x57, false))); // W down on the second keyboard
+        state.apply(&on(2, key(0x57, true))); // W up on the second keyboard
+        assert!(state.is_key_down(VirtualKey::W));
+        assert_eq!(state.pressed_keys(), vec![VirtualKey::W]);
+
+        // A keyboard that never held the key does not release it
+        state.apply(&on(3, key(0x57, true)));
+        assert!(state.is_key_down(VirtualKey::W));
+
+        // Left Shift held on the first keyboard outlives the second one
+        state.apply(&on(1, left_shift(0)));
+        state.apply(&on(2, left_shift(0)));
+        state.forget_device(RawInputDeviceHandle::from_raw(2));
+        assert!(state.modifiers().contains(Modifiers::LEFT_SHIFT));
+        state.forget_device(RawInputDeviceHandle::from_raw(1));
+        assert_eq!(state.modifiers(), Modifiers::NONE);
+        assert!(state.pressed_keys().is_empty());
+
+        // A seeded key is released by whichever keyboard sends its release
+        state.seed(|vkey| vkey == 0x41);
+        state.apply(&on(1, key(0x41, true)));
+        assert!(!state.is_key_down(VirtualKey::A));
+    }
+
+    #[test]
+    fn test_ignores_fake_shift() {
+        let mut state = InputState::default();
+        state.apply(&left_shift(0)); // Physical Shift down
+        state.apply(&left_shift(0x02 | 0x01)); // Fake Shift up (E0) before a numpad key
+        assert!(state.is_key_down(VirtualKey::Shift));
+
+        state.apply(&left_shift(0x02)); // Fake Shift down (E0) after it
+        state.apply(&left_shift(0x01)); // Physical Shift up
+        assert!(!state.is_key_down(VirtualKey::Shift));
+    }
+
+    #[test]
+    fn test_scroll_accumulates_saturates_and_resets() {
+        let scroll = ScrollAccumulator::default();
+        scroll.add(WheelAxis::Vertical, 120);
+        scroll.add(WheelAxis::Vertical, -360);
+        scroll.add(WheelAxis::Horizontal, 60);
+        assert_eq!(scroll.take(), (-240, 60));
+        assert_eq!(scroll.take(), (0, 0));
+
+        scroll.add(WheelAxis::Horizontal, i32::MAX);
+        scroll.add(WheelAxis::Horizontal, 120);
+        scroll.add(WheelAxis::Vertical, i32::MIN);
+        scroll.add(WheelAxis::Vertical, -120);
+        assert_eq!(scroll.take(), (i32::MIN, i32::MAX));
+    }
+
+    #[test]
+    fn test_recent_events_keeps_the_last_n() {
+        let recent = RecentEvents::new(2);
+        for vkey in [0x41, 0x42, 0x43] {
+            recent.push(&key(vkey, false));
+        }
+        let keys: Vec<_> = recent
+            .snapshot()
+            .into_iter()
+            .filter_map(|event| match event {
+                RawInputEvent::Keyboard(kbd) => Some(kbd.vkey),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(keys, vec![VirtualKey::B, VirtualKey::C]);
+
+        let disabled = RecentEvents::new(0);
+        disabled.push(&key(0x41, false));
+        assert!(disabled.snapshot().is_empty());
+    }
+
+    /// Test that seeding records keys as raw input reports them
+    #[test]
+    fn test_seed() {
+        let mut state = InputState::default();
+        state.apply(&key(0x42, false)); // B: forgotten
+        // Left Shift, A and the left mouse button down
+        let down = [0x10, 0xA0, 0x41, 0x01];
+        state.seed(|vkey| down.contains(&vkey));
+
+        let mut keys = state.pressed_keys();
+        keys.sort_by_key(|&key| u16::from(key));
+        assert_eq!(keys, vec![VirtualKey::Shift, VirtualKey::A]);
+        assert_eq!(state.pressed_mouse_buttons(), vec![MouseButton::Left]);
+        assert_eq!(state.modifiers(), Modifiers::SHIFT | Modifiers::LEFT_SHIFT);
+
+        // Releasing Shift clears it
+        state.apply(&left_shift(0x01));
+        assert_eq!(state.modifiers(), Modifiers::NONE);
+    }
+
+    #[test]
+    fn test_polled_events_discard_the_oldest() {
+        let polled = PolledEvents::new(true, Some(2));
+        let overflows: Vec<_> = [0x41, 0x42, 0x43, 0x44]
+            .into_iter()
+            .map(|vkey| polled.push(key(vkey, false)))
+            .collect();
+        // Only the first discarded event since the last poll is reported
+        assert_eq!(overflows, vec![false, false, true, false]);
+        assert_eq!(polled.dropped(), 2);
+
+        let keys: Vec<_> = polled
+            .drain()
+            .into_iter()
+            .filter_map(|event| match event {
+                RawInputEvent::Keyboard(kbd) => Some(kbd.vkey),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(keys, vec![VirtualKey::C, VirtualKey::D]);
+        assert!(polled.drain().is_empty());
+
+        polled.push(key(0x41, false));
+        polled.push(key(0x42, false));
+        assert!(polled.push(key(0x43, false)));
+        assert_eq!(polled.dropped(), 3);
+    }
+}
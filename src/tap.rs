@@ -0,0 +1,107 @@
+//! Quick press-and-release gestures ("taps") synthesized from keyboard events
+
+use crate::{RawKeyboardEvent, VirtualKey};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A key pressed and released again within the tap threshold, without auto-repeating in
+/// between, as reported by [`ListenerBuilder::tap_callback`](crate::ListenerBuilder::tap_callback).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyTap {
+    /// The tapped key
+    pub vkey: VirtualKey,
+    /// Time the key was held, from the press to the release
+    pub duration: Duration,
+    /// Monotonic time of the release, e.g. to measure the time between two taps
+    pub timestamp: Instant,
+}
+
+/// Boxed tap callback as stored by [`ListenerBuilder`](crate::ListenerBuilder).
+pub(crate) type TapCallback = Box<dyn FnMut(KeyTap) + Send + 'static>;
+
+/// Recognizes taps from the press and release timestamps of each key.
+pub(crate) struct TapDetector {
+    threshold: Duration,
+    on_tap: TapCallback,
+    /// Press time of each held key; None once it auto-repeated, as it can no longer be a tap
+    held: HashMap<VirtualKey, Option<Instant>>,
+}
+
+impl TapDetector {
+    pub(crate) fn new(threshold: Duration, on_tap: TapCallback) -> Self {
+        Self {
+            threshold,
+            on_tap,
+            held: HashMap::new(),
+        }
+    }
+
+    /// Returns the tap completed by a keyboard event, if any.
+    fn observe(&mut self, kbd: &RawKeyboardEvent) -> Option<KeyTap> {
+        if !kbd.key_up {
+            self.held
+                .entry(kbd.vkey)
+                .and_modify(|pressed| *pressed = None)
+                .or_insert(Some(kbd.timestamp));
+            return None;
+        }
+        let pressed = self.held.remove(&kbd.vkey)??;
+        let duration = kbd.timestamp.saturating_duration_since(pressed);
+        (duration <= self.threshold).then_some(KeyTap {
+            vkey: kbd.vkey,
+            duration,
+            timestamp: kbd.timestamp,
+        })
+    }
+
+    /// Tracks a keyboard event and invokes the callback if it completes a tap.
+    ///
+    /// Keys are tracked while paused so that no tap is made of a press that was missed, but
+    /// taps completed while paused are not reported.
+    pub(crate) fn process(&mut self, kbd: &RawKeyboardEvent, paused: bool) {
+        if let Some(tap) = self.observe(kbd)
+            && !paused
+        {
+            (self.on_tap)(tap);
+        }
+    }
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+    use windows::Win32::UI::Input::RAWKEYBOARD;
+
+    fn key(vkey: u16, key_up: bool, millis: u64, start: Instant) -> RawKeyboardEvent {
+        let mut raw: RAWKEYBOARD = unsafe { std::mem::zeroed() };
+        raw.VKey = vkey;
+        raw.Flags = if key_up { 1 } else { 0 };
+        let mut kbd = RawKeyboardEvent::from(&raw);
+        kbd.timestamp = start + Duration::from_millis(millis);
+        kbd
+    }
+
+    /// Test that only quick presses without auto-repeat are taps
+    #[test]
+    fn test_observe() {
+        let start = Instant::now();
+        let mut detector = TapDetector::new(Duration::from_millis(200), Box::new(|_| {}));
+
+        assert_eq!(detector.observe(&key(0x41, false, 0, start)), None);
+        let tap = detector.observe(&key(0x41, true, 120, start)).unwrap();
+        assert_eq!(tap.vkey, VirtualKey::A);
+        assert_eq!(tap.duration, Duration::from_millis(120));
+
+        // Held too long
+        detector.observe(&key(0x41, false, 1000, start));
+        assert_eq!(detector.observe(&key(0x41, true, 1300, start)), None);
+
+        // Auto-repeated within the threshold
+        detector.observe(&key(0x42, false, 2000, start));
+        detector.observe(&key(0x42, false, 2030, start));
+        assert_eq!(detector.observe(&key(0x42, true, 2060, start)), None);
+
+        // Released without a press seen
+        assert_eq!(detector.observe(&key(0x43, true, 3000, start)), None);
+    }
+}
@@ -0,0 +1,235 @@
+//! Translation of keyboard events into text
+
+use crate::keyboard::resolve_vkey;
+use crate::{RawKeyboardEvent, ffi};
+
+/// High bit of a key state entry: the key is down
+const KEY_DOWN: u8 = 0x80;
+/// Low bit of a key state entry: the key is toggled on (Caps Lock, Num Lock, ...)
+const KEY_TOGGLED: u8 = 0x01;
+/// Virtual keys whose toggle state affects translation
+const TOGGLE_KEYS: [u16; 3] = [0x14, 0x90, 0x91]; // Caps Lock, Num Lock, Scroll Lock
+
+/// Translates keyboard events into the text they type, using the active keyboard layout.
+///
+/// Raw input reports keys, not characters; this applies the keyboard layout of the
+/// foreground window (including Shift, AltGr and Caps Lock) to turn key presses into text.
+/// Dead keys (like `^` followed by `e` producing `ê` on a French layout) are buffered: the
+/// dead key itself produces no text, and the next key produces the composed character, or
+/// both characters if they do not compose.
+///
+/// Unicode characters injected with `SendInput` (see
+/// [`RawKeyboardEvent::packet_char`]) are returned as they are, whatever the layout, with
+/// the two halves of a surrogate pair joined into one character.
+///
+/// The translator tracks modifier and lock key states from the events it is fed, so feed
+/// it every keyboard event, in order. It starts from the keys held and the lock keys
+/// toggled on when it is created. Dead-key state lives in the calling thread's keyboard
+/// state, so use a translator from a single thread, e.g. inside the listener callback.
+///
+/// # Example
+/// ```no_run
+/// use mkb_raw_input::{start_listener, RawInputEvent, TextTranslator};
+///
+/// let mut translator = TextTranslator::new();
+/// let _listener = start_listener(
+///     move |event| {
+///         if let RawInputEvent::Keyboard(kbd) = event
+///             && let Some(text) = translator.translate(&kbd)
+///         {
+///             print!("{text}");
+///         }
+///     },
+///     None::<fn(_)>,
+/// )
+/// .expect("Failed to start listener");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TextTranslator {
+    key_state: [u8; 256],
+    dead_key_pending: bool,
+    /// High surrogate of an injected character waiting for its low half
+    pending_surrogate: Option<u16>,
+}
+
+impl Default for TextTranslator {
+    fn default() -> Self {
+        Self {
+            key_state: [0; 256],
+            dead_key_pending: false,
+            pending_surrogate: None,
+        }
+    }
+}
+
+impl TextTranslator {
+    /// Creates a translator starting from the keys currently held (`GetAsyncKeyState`) and
+    /// the lock keys currently toggled on (`GetKeyState`).
+    ///
+    /// This is a best-effort snapshot, so that e.g. Caps Lock turned on before the
+    /// translator existed still applies; [`default`](Self::default) starts with no key
+    /// held and all lock keys off instead.
+    pub fn new() -> Self {
+        let mut translator = Self::default();
+        for (vkey, state) in (0u16..).zip(translator.key_state.iter_mut()) {
+            if ffi::async_key_down(vkey) {
+                *state |= KEY_DOWN;
+            }
+            if TOGGLE_KEYS.contains(&vkey) && ffi::key_toggled(vkey) {
+                *state |= KEY_TOGGLED;
+            }
+        }
+        translator
+    }
+
+    /// Feeds a keyboard event, returning the text it produces, if any.
+    ///
+    /// Only key presses (including auto-repeats) produce text. Dead keys and keys without
+    /// a character (arrows, modifiers, ...) return None.
+    pub fn translate(&mut self, event: &RawKeyboardEvent) -> Option<String> {
+        let vkey = resolve_vkey(event.raw_vkey, event.make_code, event.flags);
+        self.track(vkey, event.key_up);
+        if event.key_up {
+            return None;
+        }
+        if let Some(unit) = event.packet_code_unit() {
+            return self.apply_packet(unit);
+        }
+
+        let mut buffer = [0u16; 8];
+        let rc = ffi::to_unicode(
+            vkey,
+            event.make_code,
+            &self.key_state,
+            &mut buffer,
+            ffi::foreground_keyboard_layout(),
+        );
+        self.apply_result(rc, &buffer)
+    }
+
+    /// Returns true if a dead key was pressed and is waiting for the next key to compose.
+    pub fn is_dead_key_pending(&self) -> bool {
+        self.dead_key_pending
+    }
+
+    /// Clears all tracked key states and any pending dead key flag.
+    ///
+    /// Call this together with [`ListenerHandle::reset_state`](crate::ListenerHandle::reset_state)
+    /// when key releases may have been missed.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Updates the key state array from a key transition.
+    fn track(&mut self, vkey: u16, key_up: bool) {
+        let Some(state) = self.key_state.get_mut(usize::from(vkey)) else {
+            return;
+        };
+        if key_up {
+            *state &= !KEY_DOWN;
+        } else {
+            // Lock keys toggle on the press, not on auto-repeats
+            if *state & KEY_DOWN == 0 && TOGGLE_KEYS.contains(&vkey) {
+                *state ^= KEY_TOGGLED;
+            }
+            *state |= KEY_DOWN;
+        }
+    }
+
+    /// Decodes the UTF-16 code unit of an injected character, holding a high surrogate
+    /// back until its low half arrives.
+    fn apply_packet(&mut self, unit: u16) -> Option<String> {
+        if (0xD800..0xDC00).contains(&unit) {
+            self.pending_surrogate = Some(unit);
+            return None;
+        }
+        // Unpaired surrogates are dropped
+        let text: String =
+            char::decode_utf16(self.pending_surrogate.take().into_iter().chain([unit]))
+                .filter_map(Result::ok)
+                .collect();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Interprets a `ToUnicodeEx` result, updating the dead key state.
+    fn apply_result(&mut self, rc: i32, buffer: &[u16]) -> Option<String> {
+        match rc {
+            rc if rc < 0 => {
+                self.dead_key_pending = true;
+                None
+            }
+            0 => None,
+            len => {
+                self.dead_key_pending = false;
+                let len = (len as usize).min(buffer.len());
+                String::from_utf16(&buffer[..len]).ok()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a dead key suppresses output until the composed character arrives
+    #[test]
+    fn test_dead_key_composition() {
+        let mut translator = TextTranslator::new();
+
+        // '^' dead key
+        assert_eq!(translator.apply_result(-1, &['^' as u16]), None);
+        assert!(translator.is_dead_key_pending());
+
+        // 'e' composes to 'ê'
+        assert_eq!(
+            translator.apply_result(1, &['ê' as u16]),
+            Some("ê".to_string())
+        );
+        assert!(!translator.is_dead_key_pending());
+
+        // A key that does not compose yields both characters
+        translator.apply_result(-1, &['^' as u16]);
+        assert_eq!(
+            translator.apply_result(2, &['^' as u16, 'x' as u16]),
+            Some("^x".to_string())
+        );
+    }
+
+    /// Test that injected characters are decoded, joining surrogate pairs
+    #[test]
+    fn test_packet_surrogate_pairs() {
+        let mut translator = TextTranslator::default();
+
+        assert_eq!(translator.apply_packet(0x20AC), Some("€".to_string()));
+
+        // U+1F600 arrives as two packets
+        assert_eq!(translator.apply_packet(0xD83D), None);
+        assert_eq!(translator.apply_packet(0xDE00), Some("😀".to_string()));
+
+        // A high surrogate followed by anything else is dropped, as is a lone low one
+        translator.apply_packet(0xD83D);
+        assert_eq!(translator.apply_packet('a' as u16), Some("a".to_string()));
+        assert_eq!(translator.apply_packet(0xDE00), None);
+    }
+
+    /// Test that lock keys toggle once per press and modifiers track up/down
+    #[test]
+    fn test_tracks_modifiers_and_lock_keys() {
+        // Not seeded from the keyboard, so that Caps Lock starts off
+        let mut translator = TextTranslator::default();
+
+        translator.track(0x14, false); // Caps Lock down
+        translator.track(0x14, false); // auto-repeat
+        translator.track(0x14, true);
+        assert_eq!(translator.key_state[0x14], KEY_TOGGLED);
+
+        translator.track(0x10, false); // Shift down
+        assert_eq!(translator.key_state[0x10], KEY_DOWN);
+        translator.track(0x10, true);
+        assert_eq!(translator.key_state[0x10], 0);
+
+        translator.reset();
+        assert_eq!(translator.key_state[0x14], 0);
+    }
+}
@@ -0,0 +1,190 @@
+//! Statistics of the intervals between dequeued events
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Sub-buckets per power of two of the interval histogram, bounding the error of
+/// [`TimingStats::p99`] to an eighth of the interval
+const SUB_BUCKETS: u64 = 8;
+/// Histogram buckets needed to cover every `u64` number of microseconds
+const BUCKETS: usize = 62 * SUB_BUCKETS as usize;
+
+/// Statistics of the intervals between consecutive events, as returned by
+/// [`ListenerHandle::timing_stats`](crate::ListenerHandle::timing_stats).
+///
+/// Intervals are measured between the dequeue timestamps of the events (see
+/// [`RawInputEvent::timestamp`](crate::RawInputEvent::timestamp)), i.e. when the listener
+/// thread took them from its message queue. They show how evenly input reaches the
+/// listener: a mouse polled at 1000 Hz should give intervals close to 1 ms, and a
+/// maximum far above it means the listener thread was held up, typically by a slow
+/// callback. They are not hardware-to-application latency, which raw input does not
+/// report: a device's own timing, the USB polling and the driver are all before the
+/// dequeue, and the time the callback takes is after it.
+///
+/// All durations are zero until two events were seen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    /// Number of intervals measured (one less than the number of events)
+    pub count: u64,
+    /// Shortest interval
+    pub min: Duration,
+    /// Longest interval
+    pub max: Duration,
+    /// Average interval
+    pub mean: Duration,
+    /// Interval that 99% of the intervals do not exceed, to within an eighth (taken from a
+    /// histogram of microseconds)
+    pub p99: Duration,
+}
+
+/// Running interval statistics, with a log-linear histogram of microseconds
+#[derive(Debug)]
+struct Histogram {
+    last: Option<Instant>,
+    stats: TimingStats,
+    total: Duration,
+    buckets: Vec<u64>,
+}
+
+/// Returns the histogram bucket of an interval in microseconds.
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let exponent = u64::from(63 - micros.leading_zeros());
+    let sub = (micros >> (exponent - 3)) & (SUB_BUCKETS - 1);
+    ((exponent - 2) * SUB_BUCKETS + sub) as usize
+}
+
+/// Returns the largest interval in microseconds that falls into `bucket`.
+fn bucket_upper_bound(bucket: usize) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < SUB_BUCKETS {
+        return bucket;
+    }
+    let exponent = bucket / SUB_BUCKETS + 2;
+    let lower = (SUB_BUCKETS + bucket % SUB_BUCKETS) << (exponent - 3);
+    lower + ((1 << (exponent - 3)) - 1)
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            last: None,
+            stats: TimingStats::default(),
+            total: Duration::ZERO,
+            buckets: vec![0; BUCKETS],
+        }
+    }
+
+    fn record(&mut self, timestamp: Instant) {
+        let Some(last) = self.last.replace(timestamp) else {
+            return;
+        };
+        let interval = timestamp.saturating_duration_since(last);
+        let stats = &mut self.stats;
+        if stats.count == 0 || interval < stats.min {
+            stats.min = interval;
+        }
+        stats.max = stats.max.max(interval);
+        stats.count += 1;
+        self.total = self.total.saturating_add(interval);
+        let micros = u64::try_from(interval.as_micros()).unwrap_or(u64::MAX);
+        self.buckets[bucket(micros)] += 1;
+    }
+
+    fn stats(&self) -> TimingStats {
+        let mut stats = self.stats;
+        if stats.count == 0 {
+            return stats;
+        }
+        stats.mean = self.total / u32::try_from(stats.count).unwrap_or(u32::MAX);
+        // Smallest bucket holding the interval ranked at 99% (rounded up)
+        let rank = stats.count - stats.count / 100;
+        let mut seen = 0;
+        for (index, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let p99 = Duration::from_micros(bucket_upper_bound(index));
+                stats.p99 = p99.clamp(stats.min, stats.max);
+                break;
+            }
+        }
+        stats
+    }
+}
+
+/// Interval statistics shared between the dispatcher (which records every event) and the
+/// listener handle (which reads them); does nothing unless enabled.
+#[derive(Debug, Default)]
+pub(crate) struct TimingCollector {
+    histogram: Option<Mutex<Histogram>>,
+}
+
+impl TimingCollector {
+    /// Creates a collector, allocating the histogram only if `enabled`.
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self {
+            histogram: enabled.then(|| Mutex::new(Histogram::new())),
+        }
+    }
+
+    /// Records the dequeue time of the next event.
+    pub(crate) fn record(&self, timestamp: Instant) {
+        if let Some(Ok(mut histogram)) = self.histogram.as_ref().map(Mutex::lock) {
+            histogram.record(timestamp);
+        }
+    }
+
+    /// Returns the statistics so far (all zero if disabled).
+    pub(crate) fn stats(&self) -> TimingStats {
+        self.histogram
+            .as_ref()
+            .and_then(|histogram| histogram.lock().ok().map(|h| h.stats()))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the intervals of a burst give its minimum, maximum, mean and p99
+    #[test]
+    fn test_interval_stats() {
+        let collector = TimingCollector::new(true);
+        let start = Instant::now();
+        // 99 intervals of 1 ms and one of 50 ms
+        let mut at = start;
+        collector.record(at);
+        for _ in 0..99 {
+            at += Duration::from_millis(1);
+            collector.record(at);
+        }
+        collector.record(at + Duration::from_millis(50));
+
+        let stats = collector.stats();
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(50));
+        assert_eq!(stats.mean, Duration::from_micros(1490));
+        // 1000 us falls into the bucket of 960 to 1023 us
+        assert_eq!(stats.p99, Duration::from_micros(1023));
+
+        let disabled = TimingCollector::new(false);
+        disabled.record(start);
+        disabled.record(at);
+        assert_eq!(disabled.stats(), TimingStats::default());
+    }
+
+    /// Test that the histogram buckets are contiguous and bound their intervals
+    #[test]
+    fn test_buckets() {
+        for micros in [0, 7, 8, 15, 16, 1000, 123_456, u64::MAX] {
+            let index = bucket(micros);
+            assert!(bucket_upper_bound(index) >= micros);
+            assert!(index == 0 || bucket_upper_bound(index - 1) < micros);
+        }
+        assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+    }
+}
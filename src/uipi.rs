@@ -0,0 +1,86 @@
+//! Detection of input blocked by User Interface Privilege Isolation (UIPI)
+
+use crate::{RawInputError, ffi};
+
+/// Timer ID used by the listener window to poll the foreground window
+pub(crate) const UIPI_TIMER_ID: usize = 0x4D4B_4231;
+/// Interval between foreground checks, in milliseconds
+pub(crate) const UIPI_POLL_INTERVAL_MS: u32 = 1000;
+
+/// Returns true if the foreground window belongs to a process running at a higher
+/// integrity level than the current process.
+///
+/// While this is the case, Windows (UIPI) does not deliver that window's keyboard and mouse
+/// input to this process. To capture input over elevated windows, run the host process
+/// elevated, or give it a manifest with `uiAccess="true"` (which requires the executable
+/// to be signed and installed in a secure location such as `Program Files`).
+pub fn is_foreground_elevated() -> bool {
+    ffi::current_integrity_level().is_ok_and(ffi::foreground_integrity_higher)
+}
+
+/// Tracks whether the foreground window belongs to a higher-integrity process.
+///
+/// Windows does not deliver input to a process while a window of a higher integrity
+/// level (an elevated application, Task Manager, ...) is in the foreground, and the
+/// listener gets no notification about it: input simply stops. This monitor turns the
+/// transition into that state into a [`RawInputError::AccessDenied`].
+pub(crate) struct UipiMonitor {
+    own_level: u32,
+    blocked: bool,
+}
+
+impl UipiMonitor {
+    /// Creates a monitor for the current process.
+    pub(crate) fn new() -> Result<Self, RawInputError> {
+        let own_level = ffi::current_integrity_level().map_err(|e| {
+            RawInputError::WinApiError(format!("Failed to query process integrity level: {e}"))
+        })?;
+        Ok(Self::with_level(own_level))
+    }
+
+    fn with_level(own_level: u32) -> Self {
+        Self {
+            own_level,
+            blocked: false,
+        }
+    }
+
+    /// Checks the current foreground window, returning an error when input becomes blocked.
+    pub(crate) fn poll(&mut self) -> Option<RawInputError> {
+        self.update(ffi::foreground_integrity_higher(self.own_level))
+    }
+
+    /// Records whether input is blocked, returning an error only on the transition into
+    /// the blocked state.
+    fn update(&mut self, blocked: bool) -> Option<RawInputError> {
+        let newly_blocked = blocked && !self.blocked;
+        self.blocked = blocked;
+        newly_blocked.then(|| {
+            RawInputError::AccessDenied(
+                "The foreground window belongs to a higher-integrity (elevated) process; \
+                 input is not delivered until it loses focus"
+                    .to_string(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that an error is raised once per transition into the blocked state
+    #[test]
+    fn test_reports_each_transition_once() {
+        let mut monitor = UipiMonitor::with_level(0x2000);
+
+        assert!(monitor.update(false).is_none());
+        assert!(matches!(
+            monitor.update(true),
+            Some(RawInputError::AccessDenied(_))
+        ));
+        assert!(monitor.update(true).is_none());
+        assert!(monitor.update(false).is_none());
+        assert!(monitor.update(true).is_some());
+    }
+}
@@ -0,0 +1,591 @@
+//! Stand-ins for the Win32-backed API on platforms other than Windows
+//!
+//! Everything here has the same signature as its Windows counterpart so that code which
+//! only calls into the crate behind `cfg(windows)` (or handles the error) still compiles.
+//! Functions that would touch Win32 fail with [`unsupported`]; the listener types are
+//! uninhabited, as they can never be created.
+
+use crate::builder::ListenerConfig;
+use crate::dispatch::ListenerCallbacks;
+use crate::{
+    CaptureMode, EventReceiver, ListenerBuilder, MouseButton, MouseDeviceInfo, NormalizeConfig,
+    RawInputDeviceHandle, RawInputError, RawInputEvent, RawKeyboardEvent, RegisteredDevice,
+    RegistrationResult, TimingStats, VirtualKey,
+};
+use std::convert::Infallible;
+use std::time::Duration;
+
+/// The error returned by every Win32-backed function on this platform.
+fn unsupported() -> RawInputError {
+    RawInputError::Other("unsupported platform".to_string())
+}
+
+/// Handle to a running raw input listener thread (never created on this platform).
+pub struct ListenerHandle {
+    never: Infallible,
+}
+
+impl ListenerHandle {
+    /// Returns the most recent error reported by the listener, if any.
+    pub fn last_error(&self) -> Option<RawInputError> {
+        match self.never {}
+    }
+
+    /// Returns true if the listener has seen the key go down and not yet come back up.
+    pub fn is_key_down(&self, _key: VirtualKey) -> bool {
+        match self.never {}
+    }
+
+    /// Returns the keys currently held down, in no particular order.
+    pub fn pressed_keys(&self) -> Vec<VirtualKey> {
+        match self.never {}
+    }
+
+    /// Returns the keys currently held down on one keyboard, in no particular order.
+    pub fn pressed_keys_for_device(&self, _device: RawInputDeviceHandle) -> Vec<VirtualKey> {
+        match self.never {}
+    }
+
+    /// Forgets the keys held on one keyboard.
+    pub fn forget_device(&self, _device: RawInputDeviceHandle) {
+        match self.never {}
+    }
+
+    /// Returns true if the listener has seen the mouse button go down and not yet come back up.
+    pub fn is_mouse_button_down(&self, _button: MouseButton) -> bool {
+        match self.never {}
+    }
+
+    /// Returns the mouse buttons currently held down, in no particular order.
+    pub fn pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        match self.never {}
+    }
+
+    /// Pauses event delivery without stopping the listener.
+    pub fn pause(&self) {
+        match self.never {}
+    }
+
+    /// Resumes event delivery after [`pause`](Self::pause).
+    pub fn resume(&self) {
+        match self.never {}
+    }
+
+    /// Returns true if event delivery is paused.
+    pub fn is_paused(&self) -> bool {
+        match self.never {}
+    }
+
+    /// Returns the raw wheel delta accumulated since the last call, and resets it to zero.
+    pub fn take_scroll(&self) -> (i32, i32) {
+        match self.never {}
+    }
+
+    /// Returns the events retained by the ring buffer, oldest first.
+    pub fn recent_events(&self) -> Vec<RawInputEvent> {
+        match self.never {}
+    }
+
+    /// Takes the events received since the last poll, oldest first.
+    pub fn poll_events(&self) -> Vec<RawInputEvent> {
+        match self.never {}
+    }
+
+    /// Returns statistics of the intervals between the events so far.
+    pub fn timing_stats(&self) -> TimingStats {
+        match self.never {}
+    }
+
+    /// Blocks until the listener stops on its own, then releases its resources.
+    pub fn join(self) -> Result<(), RawInputError> {
+        match self.never {}
+    }
+
+    /// Returns true if the tracked state of `key` agrees with the physical state Windows
+    /// reports.
+    pub fn verify_key_state(&self, _key: VirtualKey) -> bool {
+        match self.never {}
+    }
+
+    /// Returns how the listener captures input.
+    pub fn capture_mode(&self) -> CaptureMode {
+        match self.never {}
+    }
+
+    /// Clears all tracked key and mouse button down-states.
+    pub fn reset_state(&self) {
+        match self.never {}
+    }
+
+    /// Changes the devices the listener captures without restarting it.
+    pub fn reconfigure(
+        &self,
+        _builder: ListenerBuilder,
+    ) -> Result<RegistrationResult, RawInputError> {
+        match self.never {}
+    }
+
+    /// Returns the number of events the polling queue discarded since the listener started.
+    pub fn dropped_event_estimate(&self) -> u64 {
+        match self.never {}
+    }
+}
+
+impl Drop for ListenerHandle {
+    fn drop(&mut self) {
+        match self.never {}
+    }
+}
+
+/// Raw input registered against an existing application window (never created on this
+/// platform).
+pub struct AttachedListener {
+    never: Infallible,
+}
+
+impl AttachedListener {
+    /// Parses the raw input of a `WM_INPUT` message received by the attached window.
+    pub fn handle_raw_event(&mut self, _lparam: isize) -> Option<RawInputEvent> {
+        match self.never {}
+    }
+
+    /// Handles a `WM_INPUT_DEVICE_CHANGE` message received by the attached window.
+    pub fn handle_device_change(&self, _wparam: usize, _lparam: isize) {
+        match self.never {}
+    }
+
+    /// Pauses event delivery.
+    pub fn pause(&self) {
+        match self.never {}
+    }
+
+    /// Resumes event delivery after [`pause`](Self::pause).
+    pub fn resume(&self) {
+        match self.never {}
+    }
+
+    /// Returns true if event delivery is paused.
+    pub fn is_paused(&self) -> bool {
+        match self.never {}
+    }
+
+    /// Returns the raw wheel delta accumulated since the last call, and resets it to zero.
+    pub fn take_scroll(&self) -> (i32, i32) {
+        match self.never {}
+    }
+
+    /// Returns the events retained by the ring buffer, oldest first.
+    pub fn recent_events(&self) -> Vec<RawInputEvent> {
+        match self.never {}
+    }
+
+    /// Returns statistics of the intervals between the events so far.
+    pub fn timing_stats(&self) -> TimingStats {
+        match self.never {}
+    }
+
+    /// Returns the most recent error reported by the listener, if any.
+    pub fn last_error(&self) -> Option<RawInputError> {
+        match self.never {}
+    }
+
+    /// Returns true if the listener has seen the key go down and not yet come back up.
+    pub fn is_key_down(&self, _key: VirtualKey) -> bool {
+        match self.never {}
+    }
+
+    /// Returns the keys currently held down, in no particular order.
+    pub fn pressed_keys(&self) -> Vec<VirtualKey> {
+        match self.never {}
+    }
+
+    /// Returns the keys currently held down on one keyboard, in no particular order.
+    pub fn pressed_keys_for_device(&self, _device: RawInputDeviceHandle) -> Vec<VirtualKey> {
+        match self.never {}
+    }
+
+    /// Forgets the keys held on one keyboard.
+    pub fn forget_device(&self, _device: RawInputDeviceHandle) {
+        match self.never {}
+    }
+
+    /// Returns true if the listener has seen the mouse button go down and not yet come back up.
+    pub fn is_mouse_button_down(&self, _button: MouseButton) -> bool {
+        match self.never {}
+    }
+
+    /// Returns the mouse buttons currently held down, in no particular order.
+    pub fn pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        match self.never {}
+    }
+
+    /// Returns true if the tracked state of `key` agrees with the physical state Windows
+    /// reports.
+    pub fn verify_key_state(&self, _key: VirtualKey) -> bool {
+        match self.never {}
+    }
+
+    /// Returns how the listener captures input.
+    pub fn capture_mode(&self) -> CaptureMode {
+        match self.never {}
+    }
+
+    /// Clears all tracked key and mouse button down-states.
+    pub fn reset_state(&self) {
+        match self.never {}
+    }
+}
+
+impl Drop for AttachedListener {
+    fn drop(&mut self) {
+        match self.never {}
+    }
+}
+
+/// Start the background listener thread and window for raw input events.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn start_listener<F, E>(
+    callback: F,
+    on_error: Option<E>,
+) -> Result<ListenerHandle, RawInputError>
+where
+    F: FnMut(RawInputEvent) + Send + 'static,
+    E: FnMut(RawInputError) + Send + 'static,
+{
+    let mut builder = ListenerBuilder::new();
+    if let Some(on_error) = on_error {
+        builder = builder.error_callback(on_error);
+    }
+    builder.start(callback)
+}
+
+/// Start the background listener, passing a user-owned context to the callback along with
+/// every event.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn start_listener_with_context<T>(
+    context: T,
+    callback: fn(&mut T, RawInputEvent),
+) -> Result<ListenerHandle, RawInputError>
+where
+    T: Send + 'static,
+{
+    ListenerBuilder::new().start_with_context(context, callback)
+}
+
+/// Start a foreground-only listener on an application window with default settings.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn start_foreground_listener(hwnd: isize) -> Result<AttachedListener, RawInputError> {
+    ListenerBuilder::new().attach_foreground(hwnd)
+}
+
+/// Start the background listener, sending events and runtime errors into a channel.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn start_listener_channel() -> Result<(ListenerHandle, EventReceiver), RawInputError> {
+    ListenerBuilder::new().start_channel()
+}
+
+/// Records all input for `duration` with a default listener and returns the events.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn capture_for(duration: Duration) -> Result<Vec<RawInputEvent>, RawInputError> {
+    ListenerBuilder::new().capture_for(duration)
+}
+
+/// Waits up to `timeout` for the next key press with a default listener and returns its
+/// key.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn capture_next_key(timeout: Duration) -> Result<Option<VirtualKey>, RawInputError> {
+    ListenerBuilder::new()
+        .capture_next_key(timeout)
+        .map(|kbd| kbd.map(|kbd| kbd.vkey))
+}
+
+pub(crate) fn spawn_listener<F>(
+    _callback: F,
+    _config: ListenerConfig,
+    _callbacks: ListenerCallbacks,
+) -> Result<(ListenerHandle, RegistrationResult), RawInputError>
+where
+    F: FnMut(RawInputEvent) + Send + 'static,
+{
+    Err(unsupported())
+}
+
+pub(crate) fn attach_listener(
+    _hwnd: isize,
+    _config: ListenerConfig,
+    _callbacks: ListenerCallbacks,
+) -> Result<AttachedListener, RawInputError> {
+    Err(unsupported())
+}
+
+/// Replays a single event via `SendInput`.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn send_event(_event: &RawInputEvent) -> Result<(), RawInputError> {
+    Err(unsupported())
+}
+
+/// Replays events via a single `SendInput` call.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn send_events(_events: &[RawInputEvent]) -> Result<(), RawInputError> {
+    Err(unsupported())
+}
+
+/// Returns the raw input registrations currently in effect for this process.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn registered_devices() -> Result<Vec<RegisteredDevice>, RawInputError> {
+    Err(unsupported())
+}
+
+/// Reads only the header of the raw input of a `WM_INPUT` message.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn read_raw_input_header(_lparam: isize) -> Result<crate::RawInputHeader, RawInputError> {
+    Err(unsupported())
+}
+
+/// Queries the capabilities of a mouse device.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn mouse_device_info(_handle: RawInputDeviceHandle) -> Result<MouseDeviceInfo, RawInputError> {
+    Err(unsupported())
+}
+
+/// Returns the rectangle the cursor is confined to.
+///
+/// Always None on this platform.
+pub fn cursor_clip_rect() -> Option<(i32, i32, i32, i32)> {
+    None
+}
+
+/// Confines the cursor to a rectangle, or releases it if None.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn set_cursor_clip_rect(_rect: Option<(i32, i32, i32, i32)>) -> Result<(), RawInputError> {
+    Err(unsupported())
+}
+
+/// Returns the user's pointer speed and acceleration settings.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn pointer_settings() -> Result<crate::PointerSettings, RawInputError> {
+    Err(unsupported())
+}
+
+/// DPI corresponding to 100% display scaling
+pub const DEFAULT_DPI: u32 = 96;
+
+/// Returns the system DPI.
+///
+/// Always [`DEFAULT_DPI`] on this platform.
+pub fn system_dpi() -> u32 {
+    DEFAULT_DPI
+}
+
+/// Returns the DPI of a window.
+///
+/// Always fails with "unsupported platform" on this platform.
+pub fn window_dpi(_hwnd: isize) -> Result<u32, RawInputError> {
+    Err(unsupported())
+}
+
+impl RawKeyboardEvent {
+    /// Returns the virtual key code the keyboard layout assigns to this event's scan code.
+    ///
+    /// No keyboard layout is available on this platform, so this is always 0.
+    pub fn scan_code_vkey(&self) -> u16 {
+        0
+    }
+
+    /// Returns true if the reported virtual key differs from the one the keyboard layout
+    /// assigns to the scan code; always false on this platform.
+    pub fn remapped(&self) -> bool {
+        false
+    }
+
+    /// Returns the name of the key in the user's language.
+    ///
+    /// No localized names are available on this platform, so this is the English
+    /// [`VirtualKey::name`] of `vkey`.
+    pub fn localized_key_name(&self) -> String {
+        self.vkey.name()
+    }
+}
+
+impl VirtualKey {
+    /// Returns the name of the key in the user's language.
+    ///
+    /// No localized names are available on this platform, so this is the English
+    /// [`name`](Self::name).
+    pub fn localized_name(self) -> String {
+        self.name()
+    }
+}
+
+impl NormalizeConfig {
+    /// Creates a configuration with the given relative sensitivity and the current
+    /// monitor layout.
+    ///
+    /// No monitor layout is available on this platform, so the screens are empty and
+    /// absolute positions are not normalized.
+    pub fn new(sensitivity: f32) -> Self {
+        Self {
+            sensitivity,
+            virtual_screen: (0, 0, 0, 0),
+            primary_screen: (0, 0),
+        }
+    }
+}
+
+/// Returns true if the foreground window belongs to a process of higher integrity.
+///
+/// Always false on this platform.
+pub fn is_foreground_elevated() -> bool {
+    false
+}
+
+/// Translates keyboard events into the text they type, using the active keyboard layout.
+///
+/// No keyboard layout is available on this platform, so nothing is ever translated.
+#[derive(Debug, Clone, Default)]
+pub struct TextTranslator {
+    _private: (),
+}
+
+impl TextTranslator {
+    /// Creates a translator starting from the keys currently held and the lock keys
+    /// currently toggled on.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a keyboard event, returning the text it produces; always None on this platform.
+    pub fn translate(&mut self, _event: &RawKeyboardEvent) -> Option<String> {
+        None
+    }
+
+    /// Returns true if a dead key was pressed and is waiting for the next key to compose.
+    pub fn is_dead_key_pending(&self) -> bool {
+        false
+    }
+
+    /// Clears all tracked key states and any pending dead key flag.
+    pub fn reset(&mut self) {}
+}
+
+/// Parser of pen digitizer HID events (never parses anything on this platform).
+#[cfg(feature = "pen")]
+#[derive(Default)]
+pub struct PenParser {
+    _private: (),
+}
+
+#[cfg(feature = "pen")]
+impl PenParser {
+    /// Creates a parser that knows no devices yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses every report of a HID event; always fails on this platform.
+    pub fn parse(
+        &mut self,
+        _hid: &crate::RawHidEventRef<'_>,
+    ) -> Result<Vec<crate::PenEvent>, RawInputError> {
+        Err(unsupported())
+    }
+}
+
+/// Returns the toggle state of the lock keys (all off on this platform).
+/// Parser of joystick and gamepad HID events (never parses anything on this platform).
+#[cfg(feature = "gamepad")]
+#[derive(Default)]
+pub struct GamepadParser {
+    _private: (),
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadParser {
+    /// Creates a parser that knows no devices yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses every report of a HID event; always fails on this platform.
+    pub fn parse(
+        &mut self,
+        _hid: &crate::RawHidEventRef<'_>,
+    ) -> Result<Vec<crate::GamepadEvent>, RawInputError> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(feature = "media-keys")]
+impl crate::MediaKeys {
+    /// Returns the media key transitions of a HID event; always fails on this platform.
+    pub fn hid(
+        &mut self,
+        _hid: &crate::RawHidEventRef<'_>,
+    ) -> Result<Vec<crate::MediaKeyEvent>, RawInputError> {
+        Err(unsupported())
+    }
+}
+
+impl crate::ScrollSettings {
+    /// Creates settings from the user's current lines and characters per notch and the
+    /// application's line height and character width in pixels.
+    ///
+    /// No wheel settings are available on this platform, so the Windows defaults of 3
+    /// lines and 3 characters per notch are used.
+    pub fn new(line_height: f32, char_width: f32) -> Self {
+        Self {
+            line_height,
+            char_width,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "leds")]
+pub fn lock_keys() -> crate::LockKeys {
+    crate::LockKeys::default()
+}
+
+/// Turns the LEDs of a keyboard on or off; always fails on this platform.
+#[cfg(feature = "leds")]
+pub fn set_keyboard_leds(
+    _device: RawInputDeviceHandle,
+    _leds: crate::LockKeys,
+) -> Result<(), RawInputError> {
+    Err(unsupported())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the Win32-backed entry points fail with a clear error
+    #[test]
+    fn test_entry_points_report_unsupported_platform() {
+        let err = start_listener(|_| {}, None::<fn(RawInputError)>)
+            .err()
+            .expect("Listener must not start");
+        assert!(err.to_string().contains("unsupported platform"));
+        assert!(ListenerBuilder::new().attach(1).is_err());
+        assert!(send_events(&[]).is_err());
+        assert!(registered_devices().is_err());
+        assert!(pointer_settings().is_err());
+        assert!(capture_for(Duration::ZERO).is_err());
+        assert!(capture_next_key(Duration::ZERO).is_err());
+        assert!(start_listener_with_context((), |_, _| {}).is_err());
+        assert!(start_listener_channel().is_err());
+    }
+}
@@ -0,0 +1,585 @@
+//! Compact binary encoding of events, for streaming them to another process
+
+use crate::{
+    KeyEventMessage, KeyFlags, Modifiers, MouseButtonAction, MouseMoveMode, PreviousMove,
+    RawHidEvent, RawInputDeviceHandle, RawInputError, RawInputEvent, RawKeyboardEvent,
+    RawMouseEvent, VirtualKey, WheelAxis,
+};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Version of the binary format written by [`RawInputEvent::to_bytes`]
+///
+/// Incremented whenever the layout changes; [`RawInputEvent::from_bytes`] rejects records
+/// of other versions.
+pub const WIRE_FORMAT_VERSION: u8 = 1;
+
+/// Size of the header common to all records
+const HEADER_SIZE: usize = 30;
+/// Event type tags
+const TYPE_KEYBOARD: u8 = 0;
+const TYPE_MOUSE: u8 = 1;
+const TYPE_HID: u8 = 2;
+/// System time value of events without one
+const NO_SYSTEM_TIME: i64 = i64::MIN;
+
+/// Mouse button actions without a payload, in the order of their wire values
+const BUTTON_ACTIONS: [MouseButtonAction; 10] = [
+    MouseButtonAction::LeftDown,
+    MouseButtonAction::LeftUp,
+    MouseButtonAction::RightDown,
+    MouseButtonAction::RightUp,
+    MouseButtonAction::MiddleDown,
+    MouseButtonAction::MiddleUp,
+    MouseButtonAction::XButton1Down,
+    MouseButtonAction::XButton1Up,
+    MouseButtonAction::XButton2Down,
+    MouseButtonAction::XButton2Up,
+];
+
+/// Returns `timestamp` in microseconds relative to `epoch`, negative if before it.
+fn micros_since(timestamp: Instant, epoch: Instant) -> i64 {
+    let micros = |d: Duration| i64::try_from(d.as_micros()).unwrap_or(i64::MAX);
+    match timestamp.checked_duration_since(epoch) {
+        Some(after) => micros(after),
+        None => -micros(epoch.duration_since(timestamp)),
+    }
+}
+
+/// Returns the instant `micros` microseconds after `epoch` (before it if negative),
+/// saturating at `epoch` if out of range.
+fn instant_at(epoch: Instant, micros: i64) -> Instant {
+    let offset = Duration::from_micros(micros.unsigned_abs());
+    if micros >= 0 {
+        epoch.checked_add(offset)
+    } else {
+        epoch.checked_sub(offset)
+    }
+    .unwrap_or(epoch)
+}
+
+fn encode_system_time(time: Option<SystemTime>) -> i64 {
+    let Some(time) = time else {
+        return NO_SYSTEM_TIME;
+    };
+    let micros = |d: Duration| i64::try_from(d.as_micros()).unwrap_or(i64::MAX);
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => micros(after),
+        Err(before) => -micros(before.duration()),
+    }
+}
+
+fn decode_system_time(micros: i64) -> Option<SystemTime> {
+    if micros == NO_SYSTEM_TIME {
+        return None;
+    }
+    let offset = Duration::from_micros(micros.unsigned_abs());
+    if micros >= 0 {
+        UNIX_EPOCH.checked_add(offset)
+    } else {
+        UNIX_EPOCH.checked_sub(offset)
+    }
+}
+
+fn encode_message(message: KeyEventMessage) -> u32 {
+    match message {
+        KeyEventMessage::KeyDown => 0x0100,
+        KeyEventMessage::KeyUp => 0x0101,
+        KeyEventMessage::SysKeyDown => 0x0104,
+        KeyEventMessage::SysKeyUp => 0x0105,
+        KeyEventMessage::Unknown(message) => message,
+    }
+}
+
+fn encode_move_mode(mode: MouseMoveMode) -> u16 {
+    match mode {
+        MouseMoveMode::Relative => 0x00,
+        MouseMoveMode::Absolute => 0x01,
+        MouseMoveMode::VirtualDesktop => 0x03,
+        MouseMoveMode::AttributeChange => 0x04,
+        MouseMoveMode::Unknown(flags) => flags,
+    }
+}
+
+fn encode_button_action(action: MouseButtonAction) -> (u8, u32) {
+    match action {
+        MouseButtonAction::WheelUp(lines) => (10, lines),
+        MouseButtonAction::WheelDown(lines) => (11, lines),
+        MouseButtonAction::WheelRight(lines) => (12, lines),
+        MouseButtonAction::WheelLeft(lines) => (13, lines),
+        MouseButtonAction::None => (14, 0),
+        other => {
+            let tag = BUTTON_ACTIONS
+                .iter()
+                .position(|&action| action == other)
+                .expect("every button action has a wire value");
+            (tag as u8, 0)
+        }
+    }
+}
+
+fn decode_button_action(tag: u8, lines: u32) -> Result<MouseButtonAction, RawInputError> {
+    Ok(match tag {
+        0..=9 => BUTTON_ACTIONS[usize::from(tag)],
+        10 => MouseButtonAction::WheelUp(lines),
+        11 => MouseButtonAction::WheelDown(lines),
+        12 => MouseButtonAction::WheelRight(lines),
+        13 => MouseButtonAction::WheelLeft(lines),
+        14 => MouseButtonAction::None,
+        other => return Err(invalid(format!("unknown mouse button action {other}"))),
+    })
+}
+
+fn invalid(reason: String) -> RawInputError {
+    RawInputError::Other(format!("Invalid event record: {reason}"))
+}
+
+/// Reads fixed-width little-endian fields from a record.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RawInputError> {
+        if self.bytes.len() < len {
+            return Err(invalid("truncated".to_string()));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], RawInputError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, RawInputError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, RawInputError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, RawInputError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, RawInputError> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    fn i64(&mut self) -> Result<i64, RawInputError> {
+        self.array().map(i64::from_le_bytes)
+    }
+}
+
+impl RawInputEvent {
+    /// Encodes the event in a compact, versioned binary format, e.g. to stream it to
+    /// another process over a pipe or socket.
+    ///
+    /// The record is self-delimiting and made of fixed-width little-endian fields: a
+    /// common header followed by a body that depends on the event type.
+    ///
+    /// | Offset | Size | Header field |
+    /// |---|---|---|
+    /// | 0 | 1 | Format version, [`WIRE_FORMAT_VERSION`] |
+    /// | 1 | 1 | Event type: 0 keyboard, 1 mouse, 2 HID |
+    /// | 2 | 4 | Length of the whole record in bytes, header included (`u32`) |
+    /// | 6 | 8 | Raw device handle (`i64`, 0 for injected input) |
+    /// | 14 | 8 | Timestamp in microseconds relative to the encoding epoch (`i64`) |
+    /// | 22 | 8 | System time in microseconds since the Unix epoch (`i64`, `i64::MIN` if not captured) |
+    ///
+    /// | Size | Keyboard body (17 bytes) |
+    /// |---|---|
+    /// | 2 | Scan code (`make_code`) |
+    /// | 1 | Flags: bit 0 `key_up`, bit 1 E0 prefix, bit 2 E1 prefix, bit 3 `extended`, bit 4 break flag (`flags.key_up`) |
+    /// | 4 | Window message (`WM_KEYDOWN` etc.) |
+    /// | 2 | Virtual key (`vkey`) |
+    /// | 2 | Virtual key as reported (`raw_vkey`) |
+    /// | 4 | `extra_information` |
+    /// | 2 | [`Modifiers::bits`] |
+    ///
+    /// | Size | Mouse body (45 bytes) |
+    /// |---|---|
+    /// | 2 | Movement flags (`usFlags`: 0 relative, 1 absolute, 3 virtual desktop, 4 attribute change) |
+    /// | 1 | Button action: 0 to 9 for `LeftDown` to `XButton2Up` in declaration order, 10 to 13 for `WheelUp` to `WheelLeft`, 14 for `None` |
+    /// | 4 | Lines scrolled by a wheel action (`u32`, 0 otherwise) |
+    /// | 2 | `wheel_delta` (`i16`) |
+    /// | 2 | `button_data` |
+    /// | 1 | Wheel axis: 0 none, 1 vertical, 2 horizontal |
+    /// | 4 | `raw_buttons` |
+    /// | 4 | `last_x` (`i32`) |
+    /// | 4 | `last_y` (`i32`) |
+    /// | 4 | `extra_information` |
+    /// | 1 | 1 if a previous movement follows, 0 otherwise |
+    /// | 4 | Previous `last_x` (`i32`, 0 if none) |
+    /// | 4 | Previous `last_y` (`i32`, 0 if none) |
+    /// | 8 | Previous timestamp relative to the encoding epoch (`i64`, 0 if none) |
+    ///
+    /// | Size | HID body (12 bytes and the reports) |
+    /// |---|---|
+    /// | 4 | `report_size` |
+    /// | 4 | `report_count` |
+    /// | 4 | Length of the report data |
+    /// | n | Report data |
+    ///
+    /// [`Instant`]s only have a meaning within a process, so the timestamps are encoded
+    /// relative to `epoch`, typically the time the stream started; the receiving side
+    /// passes its own epoch to [`from_bytes`](Self::from_bytes), which keeps the intervals
+    /// between events (to the microsecond). The verbatim Win32 fields of
+    /// [`include_raw`](crate::ListenerBuilder::include_raw), the
+    /// [`message_time`](Self::message_time) and the
+    /// [`foreground_process`](Self::foreground_process) are not encoded.
+    ///
+    /// # Example
+    /// ```
+    /// use mkb_raw_input::{RawHidEvent, RawInputEvent};
+    /// use std::time::Instant;
+    ///
+    /// let epoch = Instant::now();
+    /// let event = RawInputEvent::Hid(RawHidEvent {
+    ///     report_size: 2,
+    ///     report_count: 1,
+    ///     data: vec![0x01, 0x7F],
+    ///     device: Default::default(),
+    ///     timestamp: epoch,
+    ///     system_time: None,
+    ///     message_time: 0,
+    ///     foreground_process: None,
+    /// });
+    /// let bytes = event.to_bytes(epoch);
+    ///
+    /// let (decoded, len) = RawInputEvent::from_bytes(&bytes, Instant::now()).unwrap();
+    /// assert_eq!(len, bytes.len());
+    /// assert!(matches!(decoded, RawInputEvent::Hid(hid) if hid.data == [0x01, 0x7F]));
+    /// ```
+    pub fn to_bytes(&self, epoch: Instant) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 45);
+        let event_type = match self {
+            Self::Keyboard(_) => TYPE_KEYBOARD,
+            Self::Mouse(_) => TYPE_MOUSE,
+            Self::Hid(_) => TYPE_HID,
+        };
+        bytes.push(WIRE_FORMAT_VERSION);
+        bytes.push(event_type);
+        bytes.extend_from_slice(&[0; 4]); // Length, filled in below
+        bytes.extend_from_slice(&(self.device().as_raw() as i64).to_le_bytes());
+        bytes.extend_from_slice(&micros_since(self.timestamp(), epoch).to_le_bytes());
+        bytes.extend_from_slice(&encode_system_time(self.system_time()).to_le_bytes());
+
+        match self {
+            Self::Keyboard(kbd) => {
+                let flags = u8::from(kbd.key_up)
+                    | u8::from(kbd.flags.e0_prefix) << 1
+                    | u8::from(kbd.flags.e1_prefix) << 2
+                    | u8::from(kbd.extended) << 3
+                    | u8::from(kbd.flags.key_up) << 4;
+                bytes.extend_from_slice(&kbd.make_code.to_le_bytes());
+                bytes.push(flags);
+                bytes.extend_from_slice(&encode_message(kbd.message).to_le_bytes());
+                bytes.extend_from_slice(&u16::from(kbd.vkey).to_le_bytes());
+                bytes.extend_from_slice(&kbd.raw_vkey.to_le_bytes());
+                bytes.extend_from_slice(&kbd.extra_information.to_le_bytes());
+                bytes.extend_from_slice(&kbd.modifiers.bits().to_le_bytes());
+            }
+            Self::Mouse(mouse) => {
+                let (action, lines) = encode_button_action(mouse.button_action);
+                let axis = match mouse.wheel_axis {
+                    None => 0u8,
+                    Some(WheelAxis::Vertical) => 1,
+                    Some(WheelAxis::Horizontal) => 2,
+                };
+                bytes.extend_from_slice(&encode_move_mode(mouse.move_mode).to_le_bytes());
+                bytes.push(action);
+                bytes.extend_from_slice(&lines.to_le_bytes());
+                bytes.extend_from_slice(&mouse.wheel_delta.to_le_bytes());
+                bytes.extend_from_slice(&mouse.button_data.to_le_bytes());
+                bytes.push(axis);
+                bytes.extend_from_slice(&mouse.raw_buttons.to_le_bytes());
+                bytes.extend_from_slice(&mouse.last_x.to_le_bytes());
+                bytes.extend_from_slice(&mouse.last_y.to_le_bytes());
+                bytes.extend_from_slice(&mouse.extra_information.to_le_bytes());
+                let previous = mouse.previous_move;
+                bytes.push(u8::from(previous.is_some()));
+                bytes.extend_from_slice(&previous.map_or(0, |p| p.last_x).to_le_bytes());
+                bytes.extend_from_slice(&previous.map_or(0, |p| p.last_y).to_le_bytes());
+                let time = previous.map_or(0, |p| micros_since(p.timestamp, epoch));
+                bytes.extend_from_slice(&time.to_le_bytes());
+            }
+            Self::Hid(hid) => {
+                bytes.extend_from_slice(&hid.report_size.to_le_bytes());
+                bytes.extend_from_slice(&hid.report_count.to_le_bytes());
+                bytes.extend_from_slice(&(hid.data.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(&hid.data);
+            }
+        }
+
+        let len = bytes.len() as u32;
+        bytes[2..6].copy_from_slice(&len.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes the record at the start of `bytes`, as written by
+    /// [`to_bytes`](Self::to_bytes), placing its timestamps relative to `epoch`.
+    ///
+    /// Records are self-delimiting, so a stream of them can be decoded by advancing past
+    /// the returned length. When reading from a pipe, read the 6 bytes up to the length
+    /// field first to know how many bytes the record has.
+    ///
+    /// # Returns
+    /// * `Ok((RawInputEvent, usize))` - The event and the length of its record in bytes
+    /// * `Err(RawInputError)` - If the record is truncated, of another format version or
+    ///   malformed
+    pub fn from_bytes(bytes: &[u8], epoch: Instant) -> Result<(Self, usize), RawInputError> {
+        let mut header = Reader { bytes };
+        let version = header.u8()?;
+        if version != WIRE_FORMAT_VERSION {
+            return Err(invalid(format!(
+                "unsupported format version {version} (expected {WIRE_FORMAT_VERSION})"
+            )));
+        }
+        let event_type = header.u8()?;
+        let len = header.u32()? as usize;
+        if len < HEADER_SIZE {
+            return Err(invalid(format!("record length {len} is too short")));
+        }
+        let record = bytes
+            .get(..len)
+            .ok_or_else(|| invalid("truncated".to_string()))?;
+        let mut reader = Reader {
+            bytes: &record[6..],
+        };
+        let device = RawInputDeviceHandle::from_raw(reader.i64()? as isize);
+        let timestamp = instant_at(epoch, reader.i64()?);
+        let system_time = decode_system_time(reader.i64()?);
+
+        let event = match event_type {
+            TYPE_KEYBOARD => {
+                let make_code = reader.u16()?;
+                let flags = reader.u8()?;
+                let bit = |n: u8| flags & (1 << n) != 0;
+                Self::Keyboard(RawKeyboardEvent {
+                    make_code,
+                    key_up: bit(0),
+                    extended: bit(3),
+                    flags: KeyFlags {
+                        e0_prefix: bit(1),
+                        key_up: bit(4),
+                        e1_prefix: bit(2),
+                    },
+                    message: KeyEventMessage::from(reader.u32()?),
+                    vkey: VirtualKey::from(reader.u16()?),
+                    raw_vkey: reader.u16()?,
+                    extra_information: reader.u32()?,
+                    device,
+                    modifiers: Modifiers::from_bits(reader.u16()?),
+                    raw: None,
+                    timestamp,
+                    system_time,
+                    message_time: 0,
+                    foreground_process: None,
+                })
+            }
+            TYPE_MOUSE => {
+                let move_mode = MouseMoveMode::from(reader.u16()?);
+                let action = reader.u8()?;
+                let button_action = decode_button_action(action, reader.u32()?)?;
+                let wheel_delta = reader.u16()? as i16;
+                let button_data = reader.u16()?;
+                let wheel_axis = match reader.u8()? {
+                    0 => None,
+                    1 => Some(WheelAxis::Vertical),
+                    2 => Some(WheelAxis::Horizontal),
+                    other => return Err(invalid(format!("unknown wheel axis {other}"))),
+                };
+                let raw_buttons = reader.u32()?;
+                let last_x = reader.i32()?;
+                let last_y = reader.i32()?;
+                let extra_information = reader.u32()?;
+                let has_previous = reader.u8()? != 0;
+                let previous = PreviousMove {
+                    last_x: reader.i32()?,
+                    last_y: reader.i32()?,
+                    timestamp: instant_at(epoch, reader.i64()?),
+                };
+                Self::Mouse(RawMouseEvent {
+                    move_mode,
+                    button_action,
+                    wheel_delta,
+                    button_data,
+                    wheel_axis,
+                    raw_buttons,
+                    last_x,
+                    last_y,
+                    extra_information,
+                    device,
+                    previous_move: has_previous.then_some(previous),
+                    raw: None,
+                    timestamp,
+                    system_time,
+                    message_time: 0,
+                    foreground_process: None,
+                })
+            }
+            TYPE_HID => {
+                let report_size = reader.u32()?;
+                let report_count = reader.u32()?;
+                let data_len = reader.u32()? as usize;
+                Self::Hid(RawHidEvent {
+                    report_size,
+                    report_count,
+                    data: reader.take(data_len)?.to_vec(),
+                    device,
+                    timestamp,
+                    system_time,
+                    message_time: 0,
+                    foreground_process: None,
+                })
+            }
+            other => return Err(invalid(format!("unknown event type {other}"))),
+        };
+        Ok((event, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that every event type survives a round trip, including the fields that are
+    /// only set in some configurations
+    #[test]
+    fn test_round_trip() {
+        let epoch = Instant::now();
+        let at = |micros| epoch + Duration::from_micros(micros);
+        let key = RawInputEvent::Keyboard(RawKeyboardEvent {
+            make_code: 0x1C,
+            key_up: true,
+            extended: true,
+            flags: KeyFlags {
+                e0_prefix: true,
+                key_up: true,
+                e1_prefix: false,
+            },
+            message: KeyEventMessage::SysKeyUp,
+            vkey: VirtualKey::Enter,
+            raw_vkey: 0x0D,
+            extra_information: 0xFF51_5700,
+            device: RawInputDeviceHandle::from_raw(0x1234),
+            modifiers: Modifiers::ALT | Modifiers::LEFT_ALT,
+            raw: None,
+            timestamp: at(1),
+            system_time: Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_123)),
+            message_time: 0,
+            foreground_process: None,
+        });
+        let wheel = RawInputEvent::Mouse(RawMouseEvent {
+            move_mode: MouseMoveMode::VirtualDesktop,
+            button_action: MouseButtonAction::WheelLeft(3),
+            wheel_delta: -120,
+            button_data: 0xFF88,
+            wheel_axis: Some(WheelAxis::Horizontal),
+            raw_buttons: 0xFF88_0800,
+            last_x: -5,
+            last_y: 65535,
+            extra_information: 0,
+            device: RawInputDeviceHandle::from_raw(-1),
+            previous_move: Some(PreviousMove {
+                last_x: 7,
+                last_y: -8,
+                timestamp: at(500),
+            }),
+            raw: None,
+            timestamp: at(1500),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        });
+        let click = RawInputEvent::Mouse(RawMouseEvent {
+            button_action: MouseButtonAction::XButton2Up,
+            wheel_axis: None,
+            previous_move: None,
+            ..match &wheel {
+                RawInputEvent::Mouse(mouse) => mouse.clone(),
+                _ => unreachable!(),
+            }
+        });
+        let hid = RawInputEvent::Hid(RawHidEvent {
+            report_size: 2,
+            report_count: 2,
+            data: vec![1, 2, 3, 4],
+            device: Default::default(),
+            timestamp: at(2000),
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        });
+
+        let mut stream = Vec::new();
+        for event in [&key, &wheel, &click, &hid] {
+            stream.extend(event.to_bytes(epoch));
+        }
+        let mut decoded = Vec::new();
+        let mut rest = &stream[..];
+        while !rest.is_empty() {
+            let (event, len) = RawInputEvent::from_bytes(rest, epoch).unwrap();
+            decoded.push(format!("{event:?}"));
+            rest = &rest[len..];
+        }
+        let expected: Vec<_> = [key, wheel, click, hid]
+            .iter()
+            .map(|event| format!("{event:?}"))
+            .collect();
+        assert_eq!(decoded, expected);
+        assert_eq!(stream[0], WIRE_FORMAT_VERSION);
+        assert_eq!(stream.len(), 4 * HEADER_SIZE + 17 + 2 * 45 + 12 + 4);
+    }
+
+    /// Test that timestamps before the epochs are kept
+    #[test]
+    fn test_timestamps_around_the_epoch() {
+        let epoch = Instant::now() + Duration::from_secs(10);
+        let before = epoch - Duration::from_micros(2500);
+        assert_eq!(micros_since(before, epoch), -2500);
+        assert_eq!(instant_at(epoch, -2500), before);
+        // Out of range on some platforms, which must not panic
+        instant_at(epoch, i64::MIN + 1);
+        let before_unix = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(
+            decode_system_time(encode_system_time(Some(before_unix))),
+            Some(before_unix)
+        );
+    }
+
+    /// Test that truncated, unknown and newer records are rejected
+    #[test]
+    fn test_invalid_records() {
+        let epoch = Instant::now();
+        let hid = RawInputEvent::Hid(RawHidEvent {
+            report_size: 1,
+            report_count: 1,
+            data: vec![0],
+            device: Default::default(),
+            timestamp: epoch,
+            system_time: None,
+            message_time: 0,
+            foreground_process: None,
+        });
+        let bytes = hid.to_bytes(epoch);
+        assert!(RawInputEvent::from_bytes(&bytes[..bytes.len() - 1], epoch).is_err());
+        assert!(RawInputEvent::from_bytes(&[], epoch).is_err());
+
+        let mut newer = bytes.clone();
+        newer[0] = WIRE_FORMAT_VERSION + 1;
+        assert!(RawInputEvent::from_bytes(&newer, epoch).is_err());
+
+        let mut unknown = bytes.clone();
+        unknown[1] = 3;
+        assert!(RawInputEvent::from_bytes(&unknown, epoch).is_err());
+
+        // A record whose length field understates its HID data
+        let mut short = bytes;
+        short[2..6].copy_from_slice(&(HEADER_SIZE as u32 + 12).to_le_bytes());
+        assert!(RawInputEvent::from_bytes(&short, epoch).is_err());
+    }
+}